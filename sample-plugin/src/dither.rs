@@ -6,7 +6,8 @@ use vapoursynth4_rs::{
     key,
     map::{AppendMode, MapRef, Value},
     node::{
-        ActivationReason, Dependencies, Filter, FilterDependency, Node, RequestPattern, VideoNode,
+        ActivationReason, Dependencies, Filter, FilterDependency, Node, RawFilterConstructor,
+        RequestPattern, VideoNode,
     },
 };
 
@@ -22,9 +23,43 @@ impl Filter for DitherFilter {
     type FrameType = VideoFrame;
     type FilterData = ();
 
+    fn get_frame(
+        &self,
+        n: i32,
+        activation_reason: ActivationReason,
+        _frame_data: *mut *mut c_void,
+        mut ctx: FrameContext,
+        core: CoreRef,
+    ) -> Result<Option<VideoFrame>, Self::Error> {
+        use ActivationReason as r;
+
+        match activation_reason {
+            r::Initial => {
+                ctx.request_frame_filter(n, &self.node);
+            }
+            r::AllFramesReady => {
+                let src = self.node.get_frame_filter(n, &mut ctx);
+                let dst = core.copy_frame(&src);
+
+                // Do whatever frame processing here, in the new bit depth.
+
+                return Ok(Some(dst));
+            }
+            _ => {}
+        }
+
+        Ok(None)
+    }
+
+    const NAME: &'static CStr = c"Depth";
+    const ARGS: &'static CStr = c"clip:vnode;bits:int:opt;";
+    const RETURN_TYPE: &'static CStr = c"clip:vnode;";
+}
+
+impl RawFilterConstructor for DitherFilter {
     fn create(
         input: MapRef,
-        output: MapRef,
+        mut output: MapRef,
         _data: Option<Box<Self::FilterData>>,
         mut core: CoreRef,
     ) -> Result<(), Self::Error> {
@@ -36,7 +71,7 @@ impl Filter for DitherFilter {
         let bits = input.get_int_saturated(key!(c"bits"), 0).unwrap_or(16);
 
         // Use fmtconv to dither to the desired bit depth.
-        let Some(fmtc_plugin) = core.get_plugin_by_namespace(c"fmtc") else {
+        let Some(fmtc_plugin) = core.plugin_by_namespace(c"fmtc") else {
             return Err(c"Failed to find the fmtconv plugin.");
         };
         let mut args = core.create_map();
@@ -50,20 +85,25 @@ impl Filter for DitherFilter {
             .unwrap();
         args.set(key!(c"dmode"), Value::Int(8), AppendMode::Replace)
             .unwrap();
-        let ret = fmtc_plugin.invoke(c"bitdepth", &args);
+        let Ok(ret) = fmtc_plugin.invoke(c"bitdepth", &args) else {
+            return Err(c"Failed to dither the clip.");
+        };
         let Ok(dithered_node) = ret.get_video_node(key!(c"clip"), 0) else {
             return Err(c"Failed to dither the clip.");
         };
 
         // Update output info to reflect the new bit depth.
         let mut vi = node.info().clone();
-        vi.format = core.query_video_format(
+        let Ok(format) = core.try_query_video_format(
             vi.format.color_family,
             vi.format.sample_type,
             bits,
             vi.format.sub_sampling_w,
             vi.format.sub_sampling_h,
-        );
+        ) else {
+            return Err(c"Invalid output format.");
+        };
+        vi.format = format;
 
         let filter = DitherFilter {
             node: dithered_node,
@@ -75,45 +115,14 @@ impl Filter for DitherFilter {
         }];
 
         core.create_video_filter(
-            output,
+            &mut output,
             c"Depth",
             &vi,
-            Box::new(filter),
+            filter,
             Dependencies::new(&deps).unwrap(),
-        );
+        )
+        .map_err(|_| c"Failed to create the filter")?;
 
         Ok(())
     }
-
-    fn get_frame(
-        &self,
-        n: i32,
-        activation_reason: ActivationReason,
-        _frame_data: *mut *mut c_void,
-        mut ctx: FrameContext,
-        core: CoreRef,
-    ) -> Result<Option<VideoFrame>, Self::Error> {
-        use ActivationReason as r;
-
-        match activation_reason {
-            r::Initial => {
-                ctx.request_frame_filter(n, &self.node);
-            }
-            r::AllFramesReady => {
-                let src = self.node.get_frame_filter(n, &mut ctx);
-                let dst = core.copy_frame(&src);
-
-                // Do whatever frame processing here, in the new bit depth.
-
-                return Ok(Some(dst));
-            }
-            _ => {}
-        }
-
-        Ok(None)
-    }
-
-    const NAME: &'static CStr = c"Depth";
-    const ARGS: &'static CStr = c"clip:vnode;bits:int:opt;";
-    const RETURN_TYPE: &'static CStr = c"clip:vnode;";
 }
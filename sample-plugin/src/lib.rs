@@ -2,7 +2,6 @@ mod dither;
 
 use std::ffi::{CStr, c_void};
 
-use dither::DitherFilter;
 use vapoursynth4_rs::{
     SampleType,
     core::CoreRef,
@@ -11,8 +10,10 @@ use vapoursynth4_rs::{
     key,
     map::MapRef,
     node::{
-        ActivationReason, Dependencies, Filter, FilterDependency, Node, RequestPattern, VideoNode,
+        ActivationReason, Dependencies, Filter, FilterDependency, Node, RawFilterConstructor,
+        RequestPattern, VideoNode,
     },
+    signature,
 };
 
 struct DumbFilter {
@@ -25,49 +26,6 @@ impl Filter for DumbFilter {
     type FrameType = VideoFrame;
     type FilterData = ();
 
-    fn create(
-        input: MapRef,
-        output: MapRef,
-        _data: Option<Box<Self::FilterData>>,
-        mut core: CoreRef,
-    ) -> Result<(), Self::Error> {
-        let Ok(node) = input.get_video_node(key!(c"clip"), 0) else {
-            return Err(c"Failed to get clip");
-        };
-        let n = node.clone();
-        let vi = n.info();
-
-        if !vapoursynth4_rs::utils::is_constant_video_format(vi)
-            || vi.format.sample_type != SampleType::Integer
-            || vi.format.bits_per_sample != 8
-        {
-            return Err(c"Invert: only constant format 8bit integer input supported");
-        }
-
-        let filter = DumbFilter {
-            node,
-            enabled: input
-                .get_int(key!(c"enabled"), 0)
-                .map(|v| v != 0)
-                .unwrap_or(true),
-        };
-
-        let deps = [FilterDependency {
-            source: filter.node.as_ptr(),
-            request_pattern: RequestPattern::StrictSpatial,
-        }];
-
-        core.create_video_filter(
-            output,
-            c"Invert",
-            vi,
-            Box::new(filter),
-            Dependencies::new(&deps).unwrap(),
-        );
-
-        Ok(())
-    }
-
     fn get_frame(
         &self,
         n: i32,
@@ -121,8 +79,54 @@ impl Filter for DumbFilter {
     }
 
     const NAME: &'static CStr = c"Filter";
-    const ARGS: &'static CStr = c"clip:vnode;enabled:int:opt;";
-    const RETURN_TYPE: &'static CStr = c"clip:vnode;";
+    const ARGS: &'static CStr = signature!("clip:vnode;", "enabled:int:opt;");
+    const RETURN_TYPE: &'static CStr = signature!("clip:vnode;");
+}
+
+impl RawFilterConstructor for DumbFilter {
+    fn create(
+        input: MapRef,
+        mut output: MapRef,
+        _data: Option<Box<Self::FilterData>>,
+        mut core: CoreRef,
+    ) -> Result<(), Self::Error> {
+        let Ok(node) = input.get_video_node(key!(c"clip"), 0) else {
+            return Err(c"Failed to get clip");
+        };
+        let n = node.clone();
+        let vi = n.info();
+
+        if !vi.is_constant_format()
+            || vi.format.sample_type != SampleType::Integer
+            || vi.format.bits_per_sample != 8
+        {
+            return Err(c"Invert: only constant format 8bit integer input supported");
+        }
+
+        let filter = DumbFilter {
+            node,
+            enabled: input
+                .get_int(key!(c"enabled"), 0)
+                .map(|v| v != 0)
+                .unwrap_or(true),
+        };
+
+        let deps = [FilterDependency {
+            source: filter.node.as_ptr(),
+            request_pattern: RequestPattern::StrictSpatial,
+        }];
+
+        core.create_video_filter(
+            &mut output,
+            c"Invert",
+            vi,
+            filter,
+            Dependencies::new(&deps).unwrap(),
+        )
+        .map_err(|_| c"Failed to create the filter")?;
+
+        Ok(())
+    }
 }
 
 declare_plugin!(
@@ -133,20 +137,19 @@ declare_plugin!(
     vapoursynth4_rs::VAPOURSYNTH_API_VERSION,
     0,
     (DumbFilter, None),
-    (DitherFilter, None)
+    (dither::DitherFilter, None),
 );
 
 #[cfg(test)]
 mod tests {
     use testresult::TestResult;
-    use vapoursynth4_rs::sciprt::{OutputNode, Script};
+    use vapoursynth4_rs::script::Environment;
 
     #[test]
     fn test_vsscript_works() -> TestResult {
-        let vss = Script::default();
-        vss.evaluate_file(c"test.vpy")?;
-        let node = vss.get_output(0)?;
-        let OutputNode::Video(vn) = node else {
+        let env = Environment::from_file("test.vpy")?;
+        let output = env.get_output(0)?;
+        let Some(vn) = output.video() else {
             return Err("Expected video node".into());
         };
         let _info = vn.info();
@@ -0,0 +1,71 @@
+use syn::{Attribute, Expr, GenericArgument, Ident, LitStr, PathArguments, Type};
+
+/// Options from the `#[vs(...)]` attributes of a field.
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+    /// `rename = "name"`: the key to use instead of the field name.
+    pub rename: Option<LitStr>,
+    /// `default = expr`: the value to use when the key is missing.
+    pub default: Option<Expr>,
+    /// `range = expr`: a range the value must be contained in.
+    pub range: Option<Expr>,
+}
+
+impl FieldAttrs {
+    pub(crate) fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs.iter().filter(|a| is_vs_attr(a)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    parsed.rename = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    parsed.default = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("range") {
+                    parsed.range = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `default`, `range` or `rename`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+pub(crate) fn is_vs_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("vs")
+}
+
+/// The last identifier of a path type, e.g. `VideoNode` for `vapoursynth4_rs::node::VideoNode`.
+pub(crate) fn type_ident(ty: &Type) -> Option<&Ident> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    path.path.segments.last().map(|s| &s.ident)
+}
+
+/// The generic type arguments of `ty` if it is spelled `wrapper<...>`.
+pub(crate) fn type_args<'a>(ty: &'a Type, wrapper: &str) -> Vec<&'a Type> {
+    let Type::Path(path) = ty else {
+        return Vec::new();
+    };
+    match path.path.segments.last() {
+        Some(segment) if segment.ident == wrapper => match &segment.arguments {
+            PathArguments::AngleBracketed(args) => args
+                .args
+                .iter()
+                .filter_map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Returns `T` if `ty` is spelled `Option<T>`.
+pub(crate) fn option_inner(ty: &Type) -> Option<&Type> {
+    type_args(ty, "Option").first().copied()
+}
@@ -0,0 +1,270 @@
+use std::fmt::Write;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Error, FnArg, Ident, ImplItem, ImplItemFn, ItemImpl, LitStr, Pat, ReturnType, Type,
+    meta::ParseNestedMeta,
+};
+
+use crate::{
+    FieldAttrs,
+    attr::{is_vs_attr, option_inner, type_args, type_ident},
+    c_str_literal, key_name,
+};
+
+/// Options of `#[vapoursynth_filter(...)]`.
+#[derive(Default)]
+pub(crate) struct FilterOptions {
+    /// `name = "Name"`: the registered function name, defaults to the type name.
+    name: Option<LitStr>,
+    /// `source = field`: the node the frames are requested from, defaults to `node`.
+    source: Option<Ident>,
+}
+
+impl FilterOptions {
+    pub(crate) fn parse(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("name") {
+            self.name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("source") {
+            self.source = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `name` or `source`"));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn expand(options: FilterOptions, mut item: ItemImpl) -> syn::Result<TokenStream> {
+    if let Some((path, _)) = &item.trait_ {
+        return Err(Error::new_spanned(path, "expected an inherent impl block"));
+    }
+    let self_ty = item.self_ty.clone();
+    let name = match &options.name {
+        Some(name) => name.value(),
+        None => type_ident(&self_ty)
+            .ok_or_else(|| Error::new_spanned(&self_ty, "expected a named type"))?
+            .to_string(),
+    };
+    let source = options.source.unwrap_or_else(|| format_ident!("node"));
+
+    let is_audio = {
+        let process = find_fn(&mut item, "process")?;
+        let [frame, _] = result_types(return_type(process)?)?;
+        type_ident(frame).is_some_and(|i| i == "AudioFrame")
+    };
+
+    let new = find_fn(&mut item, "new")?;
+    let [_, error] = result_types(return_type(new)?)?;
+    let error = error.clone();
+    let Arguments {
+        fields,
+        call_args,
+        signature,
+    } = arguments(new)?;
+
+    let name_lit = c_str_literal(&name);
+    let args_lit = c_str_literal(&signature);
+    let (frame_ty, return_lit, create) = if is_audio {
+        (
+            quote!(::vapoursynth4_rs::frame::AudioFrame),
+            c_str_literal("clip:anode;"),
+            quote!(core.create_audio_filter(&mut output, Self::NAME, &info, filter, deps)),
+        )
+    } else {
+        (
+            quote!(::vapoursynth4_rs::frame::VideoFrame),
+            c_str_literal("clip:vnode;"),
+            quote!(core.create_video_filter(&mut output, Self::NAME, &info, filter, deps)),
+        )
+    };
+    let get_frame = get_frame(&source, &frame_ty);
+    let (impl_generics, _, where_clause) = item.generics.split_for_impl();
+
+    Ok(quote! {
+        #item
+
+        const _: () = {
+            #[derive(::vapoursynth4_rs::map::FromMap)]
+            struct Arguments {
+                #(#fields,)*
+            }
+
+            impl #impl_generics ::vapoursynth4_rs::node::Filter for #self_ty #where_clause {
+                type Error = #error;
+                type FrameType = #frame_ty;
+                type FilterData = ();
+
+                const NAME: &'static ::core::ffi::CStr = #name_lit;
+                const ARGS: &'static ::core::ffi::CStr = #args_lit;
+                const RETURN_TYPE: &'static ::core::ffi::CStr = #return_lit;
+
+                #get_frame
+            }
+
+            impl #impl_generics ::vapoursynth4_rs::node::RawFilterConstructor for #self_ty #where_clause {
+                fn create(
+                    input: ::vapoursynth4_rs::map::MapRef,
+                    mut output: ::vapoursynth4_rs::map::MapRef,
+                    _data: ::core::option::Option<::std::boxed::Box<()>>,
+                    mut core: ::vapoursynth4_rs::core::CoreRef,
+                ) -> ::core::result::Result<(), Self::Error> {
+                    let args = match <Arguments as ::vapoursynth4_rs::map::FromMap>::from_map(&input) {
+                        ::core::result::Result::Ok(args) => args,
+                        ::core::result::Result::Err(e) => {
+                            output.set_error(::core::convert::AsRef::as_ref(&e));
+                            return ::core::result::Result::Ok(());
+                        }
+                    };
+                    let filter = Self::new(#(#call_args),*)?;
+                    let info = ::core::clone::Clone::clone(filter.#source.info());
+                    let deps = [::vapoursynth4_rs::node::FilterDependency {
+                        source: ::vapoursynth4_rs::node::Node::as_ptr(&filter.#source),
+                        request_pattern: ::vapoursynth4_rs::node::RequestPattern::StrictSpatial,
+                    }];
+                    let deps = ::vapoursynth4_rs::node::Dependencies::new(&deps).unwrap();
+                    // On failure the core has already left its message in `output`
+                    let _ = #create;
+                    ::core::result::Result::Ok(())
+                }
+            }
+        };
+    })
+}
+
+/// `Filter::get_frame` requesting frame `n` of `source` and passing it to `process`.
+fn get_frame(source: &Ident, frame_ty: &TokenStream) -> TokenStream {
+    quote! {
+        fn get_frame(
+            &self,
+            n: i32,
+            activation_reason: ::vapoursynth4_rs::node::ActivationReason,
+            _frame_data: *mut *mut ::core::ffi::c_void,
+            mut frame_ctx: ::vapoursynth4_rs::frame::FrameContext,
+            core: ::vapoursynth4_rs::core::CoreRef,
+        ) -> ::core::result::Result<::core::option::Option<#frame_ty>, Self::Error> {
+            match activation_reason {
+                ::vapoursynth4_rs::node::ActivationReason::Initial => {
+                    frame_ctx.request_frame_filter(n, &self.#source);
+                }
+                ::vapoursynth4_rs::node::ActivationReason::AllFramesReady => {
+                    let src = ::vapoursynth4_rs::node::Node::get_frame_filter(
+                        &self.#source,
+                        n,
+                        &mut frame_ctx,
+                    );
+                    return Self::process(self, n, &src, core)
+                        .map(::core::option::Option::Some);
+                }
+                _ => {}
+            }
+            ::core::result::Result::Ok(::core::option::Option::None)
+        }
+    }
+}
+
+/// The parameters of `new`, turned into filter arguments.
+struct Arguments {
+    /// Fields of the generated `FromMap` struct.
+    fields: Vec<TokenStream>,
+    /// Expressions passed to `new`.
+    call_args: Vec<TokenStream>,
+    /// The argument string.
+    signature: String,
+}
+
+/// Collects the arguments from the parameters of `new`, moving their `#[vs(...)]` attributes to
+/// the generated struct.
+fn arguments(new: &mut ImplItemFn) -> syn::Result<Arguments> {
+    let mut fields = Vec::new();
+    let mut call_args = Vec::new();
+    let mut signature = String::new();
+    for input in &mut new.sig.inputs {
+        let FnArg::Typed(arg) = input else {
+            return Err(Error::new_spanned(input, "`new` must not take `self`"));
+        };
+        if type_ident(&arg.ty).is_some_and(|i| i == "CoreRef") {
+            call_args.push(quote!(core));
+            continue;
+        }
+        let Pat::Ident(pat) = &*arg.pat else {
+            return Err(Error::new_spanned(&arg.pat, "expected an identifier"));
+        };
+        let ident = &pat.ident;
+        let ty = &arg.ty;
+
+        let attrs = FieldAttrs::parse(&arg.attrs)?;
+        let vs_attrs: Vec<_> = arg
+            .attrs
+            .iter()
+            .filter(|a| is_vs_attr(a))
+            .cloned()
+            .collect();
+        arg.attrs.retain(|a| !is_vs_attr(a));
+
+        let (key, _) = key_name(ident, &attrs);
+        let optional = option_inner(ty).is_some() || attrs.default.is_some();
+        let arg_ty = vs_type(option_inner(ty).unwrap_or(ty))
+            .ok_or_else(|| Error::new_spanned(ty, "unsupported argument type"))?;
+        let _ = write!(signature, "{key}:{arg_ty}");
+        if optional {
+            signature.push_str(":opt");
+        }
+        signature.push(';');
+
+        fields.push(quote!(#(#vs_attrs)* #ident: #ty));
+        call_args.push(quote!(args.#ident));
+    }
+    Ok(Arguments {
+        fields,
+        call_args,
+        signature,
+    })
+}
+
+fn find_fn<'a>(item: &'a mut ItemImpl, name: &str) -> syn::Result<&'a mut ImplItemFn> {
+    let span = item.self_ty.clone();
+    item.items
+        .iter_mut()
+        .find_map(|i| match i {
+            ImplItem::Fn(f) if f.sig.ident == name => Some(f),
+            _ => None,
+        })
+        .ok_or_else(|| Error::new_spanned(span, format!("missing `fn {name}`")))
+}
+
+fn return_type(f: &ImplItemFn) -> syn::Result<&Type> {
+    match &f.sig.output {
+        ReturnType::Type(_, ty) => Ok(ty),
+        ReturnType::Default => Err(Error::new_spanned(
+            &f.sig,
+            format!("`{}` must return a `Result`", f.sig.ident),
+        )),
+    }
+}
+
+fn result_types(ty: &Type) -> syn::Result<[&Type; 2]> {
+    match type_args(ty, "Result")[..] {
+        [ok, err] => Ok([ok, err]),
+        _ => Err(Error::new_spanned(ty, "expected `Result<T, E>`")),
+    }
+}
+
+/// The `VapourSynth` type name of an argument, e.g. `vnode` or `int[]`.
+fn vs_type(ty: &Type) -> Option<String> {
+    if let [inner] = type_args(ty, "Vec")[..] {
+        return vs_type(inner).map(|t| t + "[]");
+    }
+    let name = match type_ident(ty)?.to_string().as_str() {
+        "i64" | "i32" | "bool" => "int",
+        "f64" | "f32" => "float",
+        "String" => "data",
+        "VideoNode" => "vnode",
+        "AudioNode" => "anode",
+        "VideoFrame" => "vframe",
+        "AudioFrame" => "aframe",
+        "Function" => "func",
+        _ => return None,
+    };
+    Some(name.to_owned())
+}
@@ -0,0 +1,88 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use syn::{DeriveInput, Error, Field};
+
+use crate::{FieldAttrs, attr::option_inner, field_ident, key_expr, key_name, named_fields};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = named_fields(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let inits = fields
+        .iter()
+        .map(expand_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #impl_generics ::vapoursynth4_rs::map::FromMap for #name #ty_generics #where_clause {
+            fn from_map(
+                map: &::vapoursynth4_rs::map::Map,
+            ) -> ::core::result::Result<Self, ::vapoursynth4_rs::map::ArgumentError> {
+                ::core::result::Result::Ok(Self {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
+
+fn expand_field(field: &Field) -> syn::Result<TokenStream> {
+    let attrs = FieldAttrs::parse(&field.attrs)?;
+    let ident = &field.ident;
+    let ty = &field.ty;
+    let (key_name, span) = key_name(field_ident(field), &attrs);
+    let key = key_expr(&key_name, span);
+    let optional = option_inner(ty).is_some();
+
+    let read = if let Some(default) = &attrs.default {
+        if optional {
+            return Err(Error::new_spanned(
+                default,
+                "`default` cannot be used on an `Option` field",
+            ));
+        }
+        quote! {
+            match <::core::option::Option<#ty> as ::vapoursynth4_rs::map::MapField>::get_field(
+                map, key,
+            )
+            .map_err(|e| ::vapoursynth4_rs::map::ArgumentError::from_property(key, e))?
+            {
+                ::core::option::Option::Some(value) => value,
+                ::core::option::Option::None => #default,
+            }
+        }
+    } else {
+        quote! {
+            ::vapoursynth4_rs::map::MapField::get_field(map, key)
+                .map_err(|e| ::vapoursynth4_rs::map::ArgumentError::from_property(key, e))?
+        }
+    };
+
+    let check = attrs.range.as_ref().map(|range| {
+        let mut shown = range.to_token_stream().to_string();
+        shown.retain(|c| !c.is_whitespace());
+        let message = format!("argument `{key_name}` must be in {shown}");
+        let out_of_range = if optional {
+            quote!(value.as_ref().is_some_and(|v| !(#range).contains(v)))
+        } else {
+            quote!(!(#range).contains(&value))
+        };
+        quote! {
+            if #out_of_range {
+                return ::core::result::Result::Err(
+                    ::vapoursynth4_rs::map::ArgumentError::new(#message),
+                );
+            }
+        }
+    });
+
+    Ok(quote! {
+        #ident: {
+            let key = #key;
+            let value: #ty = #read;
+            #check
+            value
+        }
+    })
+}
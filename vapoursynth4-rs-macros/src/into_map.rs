@@ -0,0 +1,36 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::{FieldAttrs, field_ident, key_expr, key_name, named_fields};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = named_fields(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let sets = fields
+        .iter()
+        .map(|field| {
+            let attrs = FieldAttrs::parse(&field.attrs)?;
+            let ident = &field.ident;
+            let (key_name, span) = key_name(field_ident(field), &attrs);
+            let key = key_expr(&key_name, span);
+            Ok(quote! {
+                ::vapoursynth4_rs::map::MapField::set_field(self.#ident, map, #key)?;
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #impl_generics ::vapoursynth4_rs::map::IntoMap for #name #ty_generics #where_clause {
+            fn into_map(
+                self,
+                map: &mut ::vapoursynth4_rs::map::Map,
+            ) -> ::core::result::Result<(), ::vapoursynth4_rs::map::MapPropertyError> {
+                #(#sets)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    })
+}
@@ -0,0 +1,164 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Procedural macros for `vapoursynth4-rs`.
+//!
+//! Use them through the re-exports in `vapoursynth4-rs` with the `macros` feature.
+
+mod attr;
+mod filter;
+mod from_map;
+mod into_map;
+
+use std::ffi::CString;
+
+use attr::FieldAttrs;
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, Span, TokenStream as TokenStream2};
+use quote::quote_spanned;
+use syn::{
+    Data, DeriveInput, Error, Field, Fields, Ident, ItemImpl, ext::IdentExt, parse_macro_input,
+    punctuated::Punctuated, token::Comma,
+};
+
+/// Derives `vapoursynth4_rs::map::FromMap` for a struct with named fields.
+///
+/// Every field is read from the key of the same name with `MapField::get_field`.
+///
+/// Fields accept `#[vs(...)]` attributes:
+///
+/// - `rename = "name"` reads the key `name` instead.
+/// - `default = expr` uses `expr` when the key is missing.
+/// - `range = expr` rejects values not contained in the range `expr`.
+#[proc_macro_derive(FromMap, attributes(vs))]
+pub fn derive_from_map(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_map::expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `vapoursynth4_rs::map::IntoMap` for a struct with named fields.
+///
+/// Every field is written to the key of the same name with `MapField::set_field`.
+/// `#[vs(rename = "name")]` writes the key `name` instead.
+#[proc_macro_derive(IntoMap, attributes(vs))]
+pub fn derive_into_map(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_map::expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implements `vapoursynth4_rs::node::Filter` for a filter that maps each frame of one source
+/// node to an output frame.
+///
+/// Apply it to the inherent `impl` block of the filter, which must contain:
+///
+/// - `fn new(...) -> Result<Self, E>`, whose parameters are the filter arguments. They are read
+///   like the fields of a `FromMap` struct and accept the same `#[vs(...)]` attributes. A
+///   `CoreRef` parameter receives the core instead.
+/// - `fn process(&self, n: i32, src: &VideoFrame, core: CoreRef) -> Result<VideoFrame, E>`, or
+///   the same with `AudioFrame`, which produces frame `n` from frame `n` of the source.
+///
+/// The macro generates the argument and return type strings, reads the arguments, requests the
+/// source frames, and creates the node with the source's video or audio info. Register the filter
+/// with `declare_plugin!` using `FilterData` of `None`.
+///
+/// Options:
+///
+/// - `name = "Name"` sets the function name, defaulting to the type name.
+/// - `source = field` names the source node field, defaulting to `node`.
+///
+/// ```
+/// use vapoursynth4_rs::{
+///     core::CoreRef,
+///     frame::{Frame, VideoFrame},
+///     node::{VideoNode, vapoursynth_filter},
+/// };
+///
+/// struct Brighten {
+///     node: VideoNode,
+///     amount: i64,
+/// }
+///
+/// #[vapoursynth_filter(name = "Brighten")]
+/// impl Brighten {
+///     fn new(
+///         clip: VideoNode,
+///         #[vs(default = 16, range = 0..=255)] amount: i64,
+///     ) -> Result<Self, &'static std::ffi::CStr> {
+///         Ok(Self { node: clip, amount })
+///     }
+///
+///     fn process(
+///         &self,
+///         _n: i32,
+///         src: &VideoFrame,
+///         core: CoreRef,
+///     ) -> Result<VideoFrame, &'static std::ffi::CStr> {
+///         let frame = core.copy_frame(src);
+///         // ... add `self.amount` to every pixel ...
+///         Ok(frame)
+///     }
+/// }
+///
+/// use vapoursynth4_rs::node::Filter;
+/// assert_eq!(Brighten::ARGS, c"clip:vnode;amount:int:opt;");
+/// ```
+#[proc_macro_attribute]
+pub fn vapoursynth_filter(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut options = filter::FilterOptions::default();
+    let parser = syn::meta::parser(|meta| options.parse(&meta));
+    parse_macro_input!(attr with parser);
+    let item = parse_macro_input!(item as ItemImpl);
+    filter::expand(options, item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+// MARK: Helper
+
+fn named_fields(input: &DeriveInput) -> syn::Result<&Punctuated<Field, Comma>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(&fields.named),
+            _ => Err(Error::new_spanned(
+                &input.ident,
+                "only structs with named fields are supported",
+            )),
+        },
+        _ => Err(Error::new_spanned(
+            &input.ident,
+            "only structs with named fields are supported",
+        )),
+    }
+}
+
+/// A `key!(c"...")` expression for `name`, validated at compile time by `KeyStr::from_cstr`.
+fn key_expr(name: &str, span: Span) -> TokenStream2 {
+    let lit = c_str_literal(name);
+    quote_spanned!(span=> ::vapoursynth4_rs::key!(#lit))
+}
+
+fn c_str_literal(s: &str) -> Literal {
+    Literal::c_string(&CString::new(s).unwrap_or_default())
+}
+
+/// The key name of a field or argument, honoring `#[vs(rename = "...")]`.
+fn key_name(ident: &Ident, attrs: &FieldAttrs) -> (String, Span) {
+    if let Some(rename) = &attrs.rename {
+        (rename.value(), rename.span())
+    } else {
+        (ident.unraw().to_string(), ident.span())
+    }
+}
+
+/// The identifier of a named field.
+fn field_ident(field: &Field) -> &Ident {
+    // Named fields always have an identifier
+    field.ident.as_ref().unwrap()
+}
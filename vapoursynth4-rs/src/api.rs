@@ -4,16 +4,26 @@
  file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
-use std::ops::Deref;
+use std::{
+    ffi::{CStr, c_void},
+    ops::Deref,
+    ptr::null_mut,
+    sync::atomic::{AtomicPtr, Ordering},
+};
 
-#[cfg(feature = "link-library")]
+#[cfg(any(feature = "link-library", feature = "runtime-loading"))]
 use vapoursynth4_sys::vs_make_version;
+use vapoursynth4_sys::vs_split_version;
 
-use crate::ffi;
+use crate::{ffi, plugin::PluginConfig};
 
-#[cfg(feature = "link-library")]
-use self::error::ApiNotFound;
+#[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+use self::error::{ApiNotFound, ApiVersionError};
 
+/// The function table of the `VapourSynth` library.
+///
+/// Wrappers keep the `Api` they were created with, so their calls read the function pointer from
+/// the table directly instead of looking up the [global](Api::global) API.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct Api(*const ffi::VSAPI);
@@ -23,22 +33,142 @@ impl Api {
     ///
     /// # Errors
     ///
-    /// Returns `ApiNotFound` if the requested API version is not supported by the linked `VapourSynth` library.
-    #[cfg(feature = "link-library")]
+    /// Returns `ApiNotFound` if the requested API version is not supported by the linked `VapourSynth` library,
+    /// or if the library cannot be loaded at runtime.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
     pub fn new(major: u16, minor: u16) -> Result<Self, ApiNotFound> {
-        let ptr = unsafe { ffi::getVapourSynthAPI(vs_make_version(major, minor)) };
-        if ptr.is_null() {
-            Err(ApiNotFound { major, minor })
-        } else {
-            Ok(Self(ptr))
+        get_vapoursynth_api(major, minor)
+            .ok()
+            .filter(|ptr| !ptr.is_null())
+            .map(Self)
+            .ok_or(ApiNotFound { major, minor })
+    }
+
+    /// Like [`Api::new`], but tells apart a library that is too old for the requested minor
+    /// version from one without the major version at all, so a newer API can be requested first
+    /// and an older one used as a fallback.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ApiVersionError::TooOld`] with the version the library supports, or
+    /// [`ApiVersionError::NotFound`] if it supports no API of that major version. With the
+    /// `runtime-loading` feature, return [`ApiVersionError::Library`] if the library cannot be
+    /// loaded.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+    pub fn try_new(major: u16, minor: u16) -> Result<Self, ApiVersionError> {
+        let ptr = get_vapoursynth_api(major, minor)?;
+        if !ptr.is_null() {
+            return Ok(Self(ptr));
+        }
+        match Self::new(major, 0) {
+            Ok(api) => Err(ApiVersionError::TooOld {
+                requested: (major, minor),
+                available: api.version(),
+            }),
+            Err(_) => Err(ApiVersionError::NotFound { major }),
+        }
+    }
+
+    /// Requests the API like [`Api::try_new`] and installs it as the [global](Api::global) API,
+    /// for applications that embed `VapourSynth` instead of being loaded as a plugin.
+    ///
+    /// If an API is already installed, it is returned instead.
+    ///
+    /// # Errors
+    ///
+    /// See [`Api::try_new`]. Return [`ApiVersionError::TooOld`] if the installed API does not
+    /// support the requested version.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+    pub fn init(major: u16, minor: u16) -> Result<Self, ApiVersionError> {
+        if let Some(api) = Self::global() {
+            return if api.supports(major, minor) {
+                Ok(api)
+            } else {
+                Err(ApiVersionError::TooOld {
+                    requested: (major, minor),
+                    available: api.version(),
+                })
+            };
+        }
+        Self::try_new(major, minor).map(Self::install)
+    }
+
+    /// The API installed by [`Api::init`] or [`Api::install`], if any.
+    #[must_use]
+    pub fn global() -> Option<Self> {
+        let ptr = GLOBAL.load(Ordering::Acquire);
+        (!ptr.is_null()).then_some(Self(ptr))
+    }
+
+    /// Installs `self` as the [global](Api::global) API unless one is already installed, and
+    /// returns the installed one. A plugin can pass the API it receives in its callbacks.
+    #[allow(clippy::return_self_not_must_use)]
+    pub fn install(self) -> Self {
+        match GLOBAL.compare_exchange(
+            null_mut(),
+            self.0.cast_mut(),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => self,
+            Err(installed) => Self(installed),
         }
     }
 
     pub(crate) unsafe fn from_ptr(ptr: *const ffi::VSAPI) -> Self {
         Self(ptr)
     }
+
+    /// The highest API version the library supports, as `(major, minor)`.
+    ///
+    /// This may be newer than the version the `Api` was requested with.
+    #[must_use]
+    pub fn version(&self) -> (u16, u16) {
+        vs_split_version(unsafe { (self.getAPIVersion)() })
+    }
+
+    /// Whether the library supports at least API `major.minor`.
+    #[must_use]
+    pub fn supports(&self, major: u16, minor: u16) -> bool {
+        self.version() >= (major, minor)
+    }
+
+    /// Checks that the library has the functions of API `required` before they are called, as
+    /// the table of an older library ends before them.
+    #[allow(unused)]
+    pub(crate) fn require(
+        self,
+        feature: &'static str,
+        required: (u16, u16),
+    ) -> Result<(), error::UnsupportedApi> {
+        if self.supports(required.0, required.1) {
+            Ok(())
+        } else {
+            Err(error::UnsupportedApi {
+                feature,
+                required,
+                available: self.version(),
+            })
+        }
+    }
 }
 
+#[cfg(feature = "link-library")]
+#[allow(clippy::unnecessary_wraps)]
+fn get_vapoursynth_api(major: u16, minor: u16) -> Result<*const ffi::VSAPI, ApiVersionError> {
+    Ok(unsafe { ffi::getVapourSynthAPI(vs_make_version(major, minor)) })
+}
+
+#[cfg(all(feature = "runtime-loading", not(feature = "link-library")))]
+fn get_vapoursynth_api(major: u16, minor: u16) -> Result<*const ffi::VSAPI, ApiVersionError> {
+    Ok(ffi::loader::get_vapoursynth_api(vs_make_version(
+        major, minor,
+    ))?)
+}
+
+// The API is a table of function pointers that lives as long as the library.
+static GLOBAL: AtomicPtr<ffi::VSAPI> = AtomicPtr::new(null_mut());
+
 impl Deref for Api {
     type Target = ffi::VSAPI;
 
@@ -47,43 +177,78 @@ impl Deref for Api {
     }
 }
 
-#[cfg(feature = "link-library")]
+#[cfg(any(feature = "link-library", feature = "runtime-loading"))]
 impl Default for Api {
-    /// Creates a new `Api` instance with the default version.
+    /// Returns the [global](Api::global) API if one is installed, otherwise the API with the
+    /// default version, which is requested once.
     ///
     /// # Panics
     ///
     /// Internal error indicates that something went wrong with the linked `VapourSynth` library.
     #[must_use]
     fn default() -> Self {
-        Self::new(ffi::VAPOURSYNTH_API_MAJOR, ffi::VAPOURSYNTH_API_MINOR).unwrap()
+        // Like `GLOBAL`, but not returned by `Api::global`.
+        static DEFAULT: AtomicPtr<ffi::VSAPI> = AtomicPtr::new(null_mut());
+
+        if let Some(api) = Self::global() {
+            return api;
+        }
+        let ptr = DEFAULT.load(Ordering::Acquire);
+        if !ptr.is_null() {
+            return Self(ptr);
+        }
+        let api = Self::new(ffi::VAPOURSYNTH_API_MAJOR, ffi::VAPOURSYNTH_API_MINOR).unwrap();
+        DEFAULT.store(api.0.cast_mut(), Ordering::Release);
+        api
     }
 }
 
+#[cfg(feature = "script")]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct VssApi(*const ffi::VSSCRIPTAPI);
 
+#[cfg(feature = "script")]
 impl VssApi {
     /// Creates a new `VssApi` instance with the specified major and minor version.
     ///
     /// # Errors
     ///
-    /// Returns `ApiNotFound` if the requested API version is not supported by the linked `VapourSynth` library.
-    #[cfg(feature = "link-library")]
+    /// Returns `ApiNotFound` if the requested API version is not supported by the linked `VapourSynth` library,
+    /// or if the library cannot be loaded at runtime.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
     pub fn new(major: u16, minor: u16) -> Result<Self, ApiNotFound> {
+        #[cfg(feature = "link-library")]
         let ptr = unsafe { ffi::getVSScriptAPI(vs_make_version(major, minor)) };
+        #[cfg(not(feature = "link-library"))]
+        let ptr = ffi::loader::get_vsscript_api(vs_make_version(major, minor))
+            .unwrap_or(std::ptr::null());
         (!ptr.is_null())
             .then_some(Self(ptr))
             .ok_or(ApiNotFound { major, minor })
     }
 
+    /// The highest `VSScript` API version the library supports, as `(major, minor)`.
+    ///
+    /// Functions added after 4.0 are only available if this is new enough.
+    #[must_use]
+    pub fn version(&self) -> (u16, u16) {
+        vs_split_version(unsafe { (self.getAPIVersion)() })
+    }
+
+    /// Whether the library supports at least `VSScript` API `major.minor`.
+    #[must_use]
+    pub fn supports(&self, major: u16, minor: u16) -> bool {
+        self.version() >= (major, minor)
+    }
+
     #[allow(unused)]
     pub(crate) unsafe fn from_ptr(ptr: *const ffi::VSSCRIPTAPI) -> Self {
         Self(ptr.cast_mut())
     }
 }
 
+#[cfg(feature = "script")]
 impl Deref for VssApi {
     type Target = ffi::VSSCRIPTAPI;
 
@@ -92,16 +257,146 @@ impl Deref for VssApi {
     }
 }
 
-#[cfg(feature = "link-library")]
+#[cfg(all(
+    feature = "script",
+    any(feature = "link-library", feature = "runtime-loading")
+))]
+impl VssApi {
+    /// The oldest `VSScript` API the crate supports. Functions of newer versions are checked for
+    /// with [`VssApi::supports`] before they are called.
+    pub const MINIMUM_VERSION: (u16, u16) = (4, 1);
+
+    /// Requests the [minimum](VssApi::MINIMUM_VERSION) `VSScript` API once, and returns it to
+    /// every later caller. A newer library returns its whole table for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ApiNotFound` if the `VSScript` library cannot be loaded or is older than 4.1.
+    pub fn try_default() -> Result<Self, ApiNotFound> {
+        // Like `GLOBAL`, requested once and shared by every environment.
+        static DEFAULT: AtomicPtr<ffi::VSSCRIPTAPI> = AtomicPtr::new(null_mut());
+
+        let ptr = DEFAULT.load(Ordering::Acquire);
+        if !ptr.is_null() {
+            return Ok(Self(ptr));
+        }
+        let (major, minor) = Self::MINIMUM_VERSION;
+        let api = Self::new(major, minor)?;
+        DEFAULT.store(api.0.cast_mut(), Ordering::Release);
+        Ok(api)
+    }
+}
+
+#[cfg(all(
+    feature = "script",
+    any(feature = "link-library", feature = "runtime-loading")
+))]
 impl Default for VssApi {
-    /// Creates a new `Api` instance with the default version.
+    /// Returns [`VssApi::try_default`].
     ///
     /// # Panics
     ///
-    /// Internal error indicates that something went wrong with the linked `VapourSynth` library.
+    /// Panics if the `VSScript` library cannot be loaded or is older than 4.1. Use
+    /// [`VssApi::try_default`] to handle it.
     #[must_use]
     fn default() -> Self {
-        Self::new(ffi::VSSCRIPT_API_MAJOR, ffi::VSSCRIPT_API_MINOR).unwrap()
+        match Self::try_default() {
+            Ok(api) => api,
+            Err(e) => panic!("{e}"),
+        }
+    }
+}
+
+/// The subset of the API available while a plugin is loaded, as passed to
+/// `VapourSynthPluginInit2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct PluginApi(*const ffi::VSPLUGINAPI);
+
+impl PluginApi {
+    /// # Safety
+    ///
+    /// `ptr` must be the `vspapi` passed to `VapourSynthPluginInit2`, and the wrapper must not be
+    /// used after the entry point returns.
+    #[must_use]
+    pub unsafe fn from_ptr(ptr: *const ffi::VSPLUGINAPI) -> Self {
+        Self(ptr)
+    }
+
+    #[must_use]
+    pub fn as_ptr(&self) -> *const ffi::VSPLUGINAPI {
+        self.0
+    }
+
+    /// The highest API version the library supports, as `(major, minor)`.
+    #[must_use]
+    pub fn version(&self) -> (u16, u16) {
+        vs_split_version(unsafe { (self.getAPIVersion)() })
+    }
+
+    /// Whether the library supports at least API `major.minor`.
+    #[must_use]
+    pub fn supports(&self, major: u16, minor: u16) -> bool {
+        self.version() >= (major, minor)
+    }
+
+    /// Configures `plugin`, which must happen exactly once, before any function is registered.
+    /// Returns `false` if the core rejected the configuration, e.g. for an unsupported API
+    /// version.
+    ///
+    /// # Safety
+    ///
+    /// `plugin` must be the plugin passed to `VapourSynthPluginInit2` along with this API.
+    #[must_use]
+    pub unsafe fn config_plugin(&self, plugin: *mut ffi::VSPlugin, config: &PluginConfig) -> bool {
+        unsafe {
+            (self.configPlugin)(
+                config.identifier.as_ptr(),
+                config.namespace.as_ptr(),
+                config.name.as_ptr(),
+                config.plugin_version,
+                config.api_version,
+                config.flags,
+                plugin,
+            ) != 0
+        }
+    }
+
+    /// Registers `func` as the function `name` of `plugin`, with the `args` and `returns` type
+    /// strings, e.g. `clip:vnode;`. `data` is passed to every call of `func`. Returns `false` if
+    /// the core rejected the function, e.g. for an invalid name or signature.
+    ///
+    /// # Safety
+    ///
+    /// `plugin` must be the plugin passed to `VapourSynthPluginInit2` along with this API, after
+    /// it was configured, and `data` must be what `func` expects.
+    pub unsafe fn register_function(
+        &self,
+        plugin: *mut ffi::VSPlugin,
+        name: &CStr,
+        args: &CStr,
+        returns: &CStr,
+        func: ffi::VSPublicFunction,
+        data: *mut c_void,
+    ) -> bool {
+        unsafe {
+            (self.registerFunction)(
+                name.as_ptr(),
+                args.as_ptr(),
+                returns.as_ptr(),
+                func,
+                data,
+                plugin,
+            ) != 0
+        }
+    }
+}
+
+impl Deref for PluginApi {
+    type Target = ffi::VSPLUGINAPI;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.0 }
     }
 }
 
@@ -117,4 +412,81 @@ pub mod error {
         pub major: u16,
         pub minor: u16,
     }
+
+    /// A function is missing from the API of an older library.
+    #[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[error(
+        "{feature} requires VapourSynth API {}.{}, but the library only supports {}.{}",
+        required.0, required.1, available.0, available.1
+    )]
+    pub struct UnsupportedApi {
+        pub feature: &'static str,
+        pub required: (u16, u16),
+        pub available: (u16, u16),
+    }
+
+    /// Error returned by [`Api::try_new`](super::Api::try_new).
+    #[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+    pub enum ApiVersionError {
+        /// The library supports no API of the requested major version.
+        #[error("The VapourSynth library does not support API version {major}")]
+        NotFound { major: u16 },
+        /// The library supports the major version, but only an older minor version.
+        #[error(
+            "The VapourSynth library supports API version {}.{}, but {}.{} was requested",
+            available.0, available.1, requested.0, requested.1
+        )]
+        TooOld {
+            requested: (u16, u16),
+            available: (u16, u16),
+        },
+        /// The library could not be loaded at runtime.
+        #[cfg(feature = "runtime-loading")]
+        #[error("{0}")]
+        Library(#[from] crate::ffi::loader::LoadError),
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global() {
+        let api = Api::init(ffi::VAPOURSYNTH_API_MAJOR, ffi::VAPOURSYNTH_API_MINOR).unwrap();
+        assert_eq!(Api::global(), Some(api));
+        assert_eq!(Api::init(ffi::VAPOURSYNTH_API_MAJOR, 0), Ok(api));
+        let (major, minor) = api.version();
+        assert_eq!(
+            Api::init(major, minor + 1),
+            Err(ApiVersionError::TooOld {
+                requested: (major, minor + 1),
+                available: (major, minor),
+            })
+        );
+        assert_eq!(Api::default(), api);
+    }
+
+    #[test]
+    fn version() {
+        let api = Api::default();
+        let (major, minor) = api.version();
+        assert_eq!(major, ffi::VAPOURSYNTH_API_MAJOR);
+        assert!(minor >= ffi::VAPOURSYNTH_API_MINOR);
+        assert!(api.supports(major, minor));
+        assert!(!api.supports(major, minor + 1));
+
+        assert_eq!(
+            Api::try_new(major, minor + 1),
+            Err(ApiVersionError::TooOld {
+                requested: (major, minor + 1),
+                available: (major, minor),
+            })
+        );
+        assert_eq!(
+            Api::try_new(major + 1, 0),
+            Err(ApiVersionError::NotFound { major: major + 1 })
+        );
+    }
 }
@@ -0,0 +1,346 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! The names of the `vapoursynth` crate for API 3, to ease porting plugins and tools.
+//!
+//! The types are aliases of their API 4 counterparts, and the extension traits add the methods
+//! under their old names and with `usize` indices. Methods whose names are taken by the API 4
+//! methods, such as `stride`, `get_int` or `get_frame`, keep the API 4 signature, so only their
+//! arguments have to change:
+//!
+//! ```ignore
+//! use vapoursynth4_rs::compat::prelude::*;
+//!
+//! let frame: FrameRef = node.get_frame(n as i32)?;
+//! let row = frame.plane_row::<u16>(0, 10);
+//! let matrix = frame.props().get_int(key!(c"_Matrix"), 0)?;
+//! ```
+
+use thiserror::Error;
+
+use crate::{
+    VideoInfo,
+    api::Api,
+    frame::{Frame, VideoFormat, VideoFrame},
+    map::{AppendMode, InvalidKey, Key, Map, MapPropertyError, MapRef, Value},
+    node::VideoNode,
+};
+
+pub type API = Api;
+pub type FrameRef = VideoFrame;
+pub type Format = VideoFormat;
+pub type Node = VideoNode;
+pub type OwnedMap = Map;
+
+/// Imports the aliases and extension traits.
+pub mod prelude {
+    pub use super::{
+        API, ApiExt, Format, FrameExt, FrameRef, MapExt, Node, OwnedMap, Property, VideoInfoExt,
+    };
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    InvalidKey(#[from] InvalidKey),
+    #[error(transparent)]
+    Property(#[from] MapPropertyError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub trait ApiExt: Sized {
+    /// The global API, see [`Api::global`].
+    fn get() -> Option<Self>;
+}
+
+impl ApiExt for Api {
+    fn get() -> Option<Self> {
+        Api::global()
+    }
+}
+
+/// A sample type, for [`FrameExt::plane_row`].
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value.
+pub unsafe trait Component: Copy {}
+
+unsafe impl Component for u8 {}
+unsafe impl Component for u16 {}
+unsafe impl Component for u32 {}
+unsafe impl Component for f32 {}
+
+pub trait FrameExt {
+    fn width(&self, plane: usize) -> usize;
+    fn height(&self, plane: usize) -> usize;
+    fn format(&self) -> &Format;
+
+    /// The bytes of `row` of `plane`, without padding.
+    fn data_row(&self, plane: usize, row: usize) -> &[u8];
+
+    /// The samples of `row` of `plane`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` is not as wide as the samples.
+    fn plane_row<T: Component>(&self, plane: usize, row: usize) -> &[T];
+
+    /// # Panics
+    ///
+    /// Panics if the frame has no properties, which `VapourSynth` never creates.
+    fn props(&self) -> MapRef<'_>;
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+impl FrameExt for VideoFrame {
+    fn width(&self, plane: usize) -> usize {
+        self.frame_width(plane as i32).unsigned_abs() as usize
+    }
+
+    fn height(&self, plane: usize) -> usize {
+        self.frame_height(plane as i32).unsigned_abs() as usize
+    }
+
+    fn format(&self) -> &Format {
+        self.get_video_format()
+    }
+
+    fn data_row(&self, plane: usize, row: usize) -> &[u8] {
+        assert!(row < FrameExt::height(self, plane), "row out of bounds");
+        let bytes = self.get_video_format().bytes_per_sample.unsigned_abs() as usize;
+        let stride = self.stride(plane as i32);
+        // SAFETY: The row is in the plane and holds `width` samples.
+        unsafe {
+            std::slice::from_raw_parts(
+                self.plane(plane as i32).offset(stride * row as isize),
+                FrameExt::width(self, plane) * bytes,
+            )
+        }
+    }
+
+    fn plane_row<T: Component>(&self, plane: usize, row: usize) -> &[T] {
+        assert_eq!(
+            size_of::<T>(),
+            self.get_video_format().bytes_per_sample.unsigned_abs() as usize,
+            "component type does not match the sample size"
+        );
+        let data = self.data_row(plane, row);
+        // SAFETY: Rows are aligned to the sample size, and `T` accepts every bit pattern.
+        unsafe { std::slice::from_raw_parts(data.as_ptr().cast(), data.len() / size_of::<T>()) }
+    }
+
+    fn props(&self) -> MapRef<'_> {
+        self.properties().expect("frame has no properties")
+    }
+}
+
+/// The keys and `&str` based setters of API 3 maps.
+pub trait MapExt {
+    fn key_count(&self) -> usize;
+    fn keys(&self) -> Vec<String>;
+
+    /// # Errors
+    ///
+    /// Return [`Error`] if `key` is invalid or missing.
+    fn value_count(&self, key: &str) -> Result<usize>;
+
+    /// # Errors
+    ///
+    /// Return [`Error`] if `key` is invalid or holds another type.
+    fn set_int(&mut self, key: &str, value: i64) -> Result<()>;
+
+    /// # Errors
+    ///
+    /// Return [`Error`] if `key` is invalid or holds another type.
+    fn append_int(&mut self, key: &str, value: i64) -> Result<()>;
+
+    /// # Errors
+    ///
+    /// Return [`Error`] if `key` is invalid or holds another type.
+    fn set_float(&mut self, key: &str, value: f64) -> Result<()>;
+
+    /// # Errors
+    ///
+    /// Return [`Error`] if `key` is invalid or holds another type.
+    fn append_float(&mut self, key: &str, value: f64) -> Result<()>;
+
+    /// # Errors
+    ///
+    /// Return [`Error`] if `key` is invalid or holds another type.
+    fn set_data(&mut self, key: &str, value: &[u8]) -> Result<()>;
+
+    /// # Errors
+    ///
+    /// Return [`Error`] if `key` is invalid or holds another type.
+    fn append_data(&mut self, key: &str, value: &[u8]) -> Result<()>;
+
+    /// # Errors
+    ///
+    /// Return [`Error`] if `key` is invalid.
+    fn delete(&mut self, key: &str) -> Result<()>;
+}
+
+impl Map {
+    fn set_str_key(&mut self, key: &str, value: Value, append: AppendMode) -> Result<()> {
+        self.set(&Key::new(key)?, value, append)?;
+        Ok(())
+    }
+}
+
+impl MapExt for Map {
+    fn key_count(&self) -> usize {
+        self.len().unsigned_abs() as usize
+    }
+
+    fn keys(&self) -> Vec<String> {
+        (0..self.len())
+            .map(|i| self.get_key(i).to_string())
+            .collect()
+    }
+
+    fn value_count(&self, key: &str) -> Result<usize> {
+        let count = self
+            .num_elements(&Key::new(key)?)
+            .ok_or(MapPropertyError::KeyNotFound)?;
+        Ok(count.unsigned_abs() as usize)
+    }
+
+    fn set_int(&mut self, key: &str, value: i64) -> Result<()> {
+        self.set_str_key(key, Value::Int(value), AppendMode::Replace)
+    }
+
+    fn append_int(&mut self, key: &str, value: i64) -> Result<()> {
+        self.set_str_key(key, Value::Int(value), AppendMode::Append)
+    }
+
+    fn set_float(&mut self, key: &str, value: f64) -> Result<()> {
+        self.set_str_key(key, Value::Float(value), AppendMode::Replace)
+    }
+
+    fn append_float(&mut self, key: &str, value: f64) -> Result<()> {
+        self.set_str_key(key, Value::Float(value), AppendMode::Append)
+    }
+
+    fn set_data(&mut self, key: &str, value: &[u8]) -> Result<()> {
+        self.set_str_key(key, Value::Data(value), AppendMode::Replace)
+    }
+
+    fn append_data(&mut self, key: &str, value: &[u8]) -> Result<()> {
+        self.set_str_key(key, Value::Data(value), AppendMode::Append)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        self.delete_key(&Key::new(key)?);
+        Ok(())
+    }
+}
+
+/// A clip property which is either constant or varies between frames.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Property<T> {
+    Variable,
+    Constant(T),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Framerate {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Resolution {
+    pub width: usize,
+    pub height: usize,
+}
+
+/// The fields of API 3 clip information, which are now plain values with zero for variable.
+pub trait VideoInfoExt {
+    fn format(&self) -> Property<&Format>;
+    fn framerate(&self) -> Property<Framerate>;
+    fn resolution(&self) -> Property<Resolution>;
+}
+
+impl VideoInfoExt for VideoInfo {
+    fn format(&self) -> Property<&Format> {
+        if self.format.color_family == crate::ColorFamily::Undefined {
+            Property::Variable
+        } else {
+            Property::Constant(&self.format)
+        }
+    }
+
+    fn framerate(&self) -> Property<Framerate> {
+        if self.fps_num == 0 || self.fps_den == 0 {
+            Property::Variable
+        } else {
+            Property::Constant(Framerate {
+                numerator: self.fps_num.unsigned_abs(),
+                denominator: self.fps_den.unsigned_abs(),
+            })
+        }
+    }
+
+    fn resolution(&self) -> Property<Resolution> {
+        if self.width == 0 || self.height == 0 {
+            Property::Variable
+        } else {
+            Property::Constant(Resolution {
+                width: self.width.unsigned_abs() as usize,
+                height: self.height.unsigned_abs() as usize,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorFamily, SampleType, info::video_info};
+
+    #[test]
+    fn video_info_properties() {
+        let format = VideoFormat {
+            color_family: ColorFamily::Gray,
+            sample_type: SampleType::Integer,
+            bits_per_sample: 8,
+            bytes_per_sample: 1,
+            sub_sampling_w: 0,
+            sub_sampling_h: 0,
+            num_planes: 1,
+        };
+        let mut info = video_info()
+            .format(format.clone())
+            .width(64)
+            .height(32)
+            .num_frames(10)
+            .fps(30000, 1001)
+            .build()
+            .unwrap();
+        assert_eq!(VideoInfoExt::format(&info), Property::Constant(&format));
+        assert_eq!(
+            info.framerate(),
+            Property::Constant(Framerate {
+                numerator: 30000,
+                denominator: 1001
+            })
+        );
+        assert_eq!(
+            info.resolution(),
+            Property::Constant(Resolution {
+                width: 64,
+                height: 32
+            })
+        );
+        info.width = 0;
+        info.height = 0;
+        info.fps_num = 0;
+        assert_eq!(info.resolution(), Property::Variable);
+        assert_eq!(info.framerate(), Property::Variable);
+    }
+}
@@ -4,34 +4,64 @@
  file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
+mod chain;
+mod flags;
+mod info;
+pub(crate) mod keep_alive;
+mod logging;
+mod shared;
+
+#[cfg(feature = "vs-41")]
+use std::time::Duration;
 use std::{
-    ffi::CStr,
+    borrow::Borrow,
+    ffi::{CStr, c_void},
     marker::PhantomData,
     mem::MaybeUninit,
     ops::{Deref, DerefMut},
+    panic::AssertUnwindSafe,
     ptr::{NonNull, null_mut},
 };
 
 use bon::bon;
 use core_builder::State;
 
+#[cfg(feature = "vs-41")]
+use crate::api::error::UnsupportedApi;
 use crate::{
     AudioInfo, ColorFamily, SampleType, VideoInfo,
     api::Api,
+    core::keep_alive::{CoreObject, CoreOwner, KeepAlive},
     ffi,
     frame::{
-        AudioFormat, AudioFrame, FormatName, Frame, VideoFormat, VideoFrame, internal::FrameFromPtr,
+        AudioFormat, AudioFrame, FormatError, FormatName, Frame, VideoFormat, VideoFrame,
+        internal::FrameFromPtr,
     },
     function::Function,
-    map::{Map, MapRef},
-    node::{Dependencies, Filter, internal::FilterExtern},
-    plugin::{Plugin, Plugins},
+    key,
+    map::{AppendMode, IntoMap, KeyStr, Map, MapRef},
+    node::{
+        AudioNode, Dependencies, Filter, FilterError, VideoNode,
+        internal::{FilterExtern, into_instance, panic_message},
+    },
+    plugin::{InvokeError, Plugin, Plugins},
 };
 
+pub use chain::*;
+pub use flags::*;
+pub use info::*;
+pub use logging::*;
+pub use shared::*;
+
+// Derefs to `Core` by casting the pointer, so both have the same fields in a fixed order.
+#[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CoreRef<'c> {
     handle: *const ffi::VSCore,
     api: Api,
+    flags: CoreCreationFlags,
+    // Borrowed, the owner is counted by whoever lends the core.
+    owner: Option<NonNull<CoreOwner>>,
     marker: PhantomData<&'c ()>,
 }
 
@@ -41,11 +71,29 @@ impl CoreRef<'_> {
         Self {
             handle: ptr.cast_mut(),
             api,
+            flags: CoreCreationFlags::empty(),
+            owner: None,
             marker: PhantomData,
         }
     }
 }
 
+#[cfg(feature = "script")]
+impl<'c> CoreRef<'c> {
+    /// Lends a core whose objects keep `keep_alive` alive, e.g. the core of a script environment.
+    #[must_use]
+    pub(crate) unsafe fn with_owner(
+        ptr: *const ffi::VSCore,
+        api: Api,
+        keep_alive: &'c KeepAlive,
+    ) -> Self {
+        Self {
+            owner: keep_alive.owner(),
+            ..unsafe { Self::from_ptr(ptr, api) }
+        }
+    }
+}
+
 impl AsRef<Core> for CoreRef<'_> {
     fn as_ref(&self) -> &Core {
         unsafe { &*std::ptr::from_ref(self).cast() }
@@ -66,10 +114,20 @@ impl DerefMut for CoreRef<'_> {
     }
 }
 
+/// A `VapourSynth` core.
+///
+/// The nodes, frames, functions and maps created from it keep it alive, so it is freed once it
+/// and the last of them are dropped, as `freeCore` requires. Objects created by `VapourSynth`
+/// inside a filter, e.g. from the [`CoreRef`] passed to it, do not need to: their core outlives
+/// the filter. [`Plugin`]s are owned by the core and borrow it.
+// `CoreRef` derefs to it, see there.
+#[repr(C)]
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Core {
     handle: *const ffi::VSCore,
     api: Api,
+    flags: CoreCreationFlags,
+    keep_alive: KeepAlive,
 }
 
 impl Core {
@@ -78,6 +136,15 @@ impl Core {
         self.handle.cast_mut()
     }
 
+    /// The flags the core was created with.
+    ///
+    /// The API cannot report them, so they are empty for a core that was not created by
+    /// [`Core::builder`], e.g. the [`CoreRef`] passed to a filter.
+    #[must_use]
+    pub fn flags(&self) -> CoreCreationFlags {
+        self.flags
+    }
+
     pub fn set_max_cache_size(&mut self, size: i64) {
         unsafe {
             (self.api.setMaxCacheSize)(size, self.as_ptr());
@@ -90,8 +157,7 @@ impl Core {
         }
     }
 
-    #[must_use]
-    pub fn get_info(&self) -> ffi::VSCoreInfo {
+    fn raw_info(&self) -> ffi::VSCoreInfo {
         unsafe {
             let mut info = MaybeUninit::uninit();
             (self.api.getCoreInfo)(self.as_ptr(), info.as_mut_ptr());
@@ -99,60 +165,298 @@ impl Core {
         }
     }
 
+    #[must_use]
+    pub fn get_info(&self) -> CoreInfo {
+        unsafe { CoreInfo::from_ffi(&self.raw_info()) }
+    }
+
+    /// A snapshot of the framebuffer cache usage, the memory part of [`Core::get_info`].
+    #[must_use]
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats::from_ffi(&self.raw_info())
+    }
+
+    /// Current size of the framebuffer cache, in bytes.
+    #[must_use]
+    pub fn used_framebuffer_size(&self) -> u64 {
+        self.memory_stats().used_framebuffer_size
+    }
+
+    /// Size the framebuffer cache may grow to before memory is aggressively reclaimed, in bytes.
+    /// Change it with [`Core::set_max_cache_size`].
+    #[must_use]
+    pub fn max_framebuffer_size(&self) -> u64 {
+        self.memory_stats().max_framebuffer_size
+    }
+
+    /// Drops the frames cached by every node of the core.
+    ///
+    /// # Errors
+    ///
+    /// Return [`UnsupportedApi`] if the core is older than API 4.1.
+    #[cfg(feature = "vs-41")]
+    pub fn clear_caches(&self) -> Result<(), UnsupportedApi> {
+        self.api.require("Clearing the core caches", (4, 1))?;
+        unsafe { (self.api.clearCoreCaches)(self.as_ptr()) };
+        Ok(())
+    }
+
+    /// Whether the nodes count their [processing time](crate::node::Node::processing_time).
+    ///
+    /// # Errors
+    ///
+    /// Return [`UnsupportedApi`] if the core is older than API 4.1.
+    #[cfg(feature = "vs-41")]
+    pub fn node_timing(&self) -> Result<bool, UnsupportedApi> {
+        self.api.require("Node timing", (4, 1))?;
+        Ok(unsafe { (self.api.getCoreNodeTiming)(self.as_ptr()) } != 0)
+    }
+
+    /// Starts or stops counting the processing time of the nodes. Stopping keeps the counters.
+    ///
+    /// # Errors
+    ///
+    /// Return [`UnsupportedApi`] if the core is older than API 4.1.
+    #[cfg(feature = "vs-41")]
+    pub fn set_node_timing(&mut self, enable: bool) -> Result<(), UnsupportedApi> {
+        self.api.require("Node timing", (4, 1))?;
+        unsafe { (self.api.setCoreNodeTiming)(self.as_ptr(), enable.into()) };
+        Ok(())
+    }
+
+    /// Time spent producing frames by the nodes already freed, optionally resetting the counter.
+    ///
+    /// # Errors
+    ///
+    /// Return [`UnsupportedApi`] if the core is older than API 4.1.
+    #[cfg(feature = "vs-41")]
+    pub fn freed_node_processing_time(&self, reset: bool) -> Result<Duration, UnsupportedApi> {
+        self.api.require("Node timing", (4, 1))?;
+        let nanos = unsafe { (self.api.getFreedNodeProcessingTime)(self.as_ptr(), reset.into()) };
+        Ok(Duration::from_nanos(
+            u64::try_from(nanos).unwrap_or_default(),
+        ))
+    }
+
+    /// Creates the node of `filter` and appends it to `out` under `clip`.
+    ///
+    /// The filter is moved to the heap as the instance data of the node, except a zero-sized
+    /// filter, e.g. a pure function of its dependencies, which costs no allocation.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FilterError::Creation`] if the core rejected the filter. The message is also left
+    /// in `out`.
+    ///
     /// # Panics
     ///
     /// Panic if the `dependencies` has more item than [`i32::MAX`]
     pub fn create_video_filter<F: Filter>(
         &mut self,
-        out: MapRef,
+        out: &mut MapRef,
+        name: &CStr,
+        info: &VideoInfo,
+        filter: F,
+        dependencies: &Dependencies,
+    ) -> Result<(), FilterError> {
+        self.create_video_filter_with_key(out, key!(c"clip"), name, info, filter, dependencies)
+    }
+
+    /// Like [`Core::create_video_filter`], but appends the node under `key`, e.g. for a function
+    /// returning several clips.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FilterError::Creation`] if the core rejected the filter. The message is also left
+    /// in `out`. Return [`FilterError::Output`] if `key` holds values of another type in `out`.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the `dependencies` has more item than [`i32::MAX`]
+    pub fn create_video_filter_with_key<F: Filter>(
+        &mut self,
+        out: &mut MapRef,
+        key: &KeyStr,
         name: &CStr,
         info: &VideoInfo,
-        filter: Box<F>,
+        filter: F,
         dependencies: &Dependencies,
-    ) {
+    ) -> Result<(), FilterError> {
         debug_assert!(!out.as_ptr().is_null());
+        let (instance, created) = self.new_video_filter(name, info, filter, dependencies);
+        self.append_created(instance, &created, out, key)
+    }
+
+    /// Creates the node of `filter` and appends it to `out` under `clip`.
+    ///
+    /// The filter is moved to the heap as the instance data of the node, except a zero-sized
+    /// filter, e.g. a pure function of its dependencies, which costs no allocation.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FilterError::Creation`] if the core rejected the filter. The message is also left
+    /// in `out`.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the `dependencies` has more item than [`i32::MAX`]
+    pub fn create_audio_filter<F: Filter>(
+        &mut self,
+        out: &mut MapRef,
+        name: &CStr,
+        info: &AudioInfo,
+        filter: F,
+        dependencies: &Dependencies,
+    ) -> Result<(), FilterError> {
+        self.create_audio_filter_with_key(out, key!(c"clip"), name, info, filter, dependencies)
+    }
+
+    /// Like [`Core::create_audio_filter`], but appends the node under `key`, e.g. for a function
+    /// returning several clips.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FilterError::Creation`] if the core rejected the filter. The message is also left
+    /// in `out`. Return [`FilterError::Output`] if `key` holds values of another type in `out`.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the `dependencies` has more item than [`i32::MAX`]
+    pub fn create_audio_filter_with_key<F: Filter>(
+        &mut self,
+        out: &mut MapRef,
+        key: &KeyStr,
+        name: &CStr,
+        info: &AudioInfo,
+        filter: F,
+        dependencies: &Dependencies,
+    ) -> Result<(), FilterError> {
+        let (instance, created) = self.new_audio_filter(name, info, filter, dependencies);
+        self.append_created(instance, &created, out, key)
+    }
+
+    /// Moves the node the core created in `created` to `out`, running
+    /// [`Filter::on_node_created`] first.
+    fn append_created<F: Filter>(
+        &self,
+        instance: *mut F,
+        created: &Map,
+        out: &mut Map,
+        key: &KeyStr,
+    ) -> Result<(), FilterError> {
+        if let Some(msg) = created.get_error() {
+            out.set_error(msg);
+        }
+        FilterError::from_map(created)?;
+        unsafe { F::notify_node_created_in_map(instance, created, key!(c"clip"), self.api) };
+        out.set(key, created.get(key!(c"clip"), 0)?, AppendMode::Append)?;
+        Ok(())
+    }
+
+    /// Creates the node of `filter` in a map of its own, as the core always appends it under
+    /// `clip`.
+    fn new_video_filter<F: Filter>(
+        &mut self,
+        name: &CStr,
+        info: &VideoInfo,
+        filter: F,
+        dependencies: &Dependencies,
+    ) -> (*mut F, Map) {
+        let created = self.create_map();
+        let mode = filter.filter_mode();
+        let instance = into_instance(filter);
         unsafe {
             (self.api.createVideoFilter)(
-                out.as_ptr(),
+                created.as_ptr(),
                 name.as_ptr(),
                 info,
                 F::filter_get_frame,
                 Some(F::filter_free),
-                F::FILTER_MODE,
+                mode,
                 dependencies.as_ptr(),
                 dependencies.len().try_into().unwrap(),
-                Box::into_raw(filter).cast(),
+                instance.cast(),
                 self.as_ptr(),
             );
         }
+        (instance, created)
     }
 
-    /// # Panics
-    ///
-    /// Panic if the `dependencies` has more item than [`i32::MAX`]
-    pub fn create_audio_filter<F: Filter>(
+    /// Creates the node of `filter` in a map of its own, as the core always appends it under
+    /// `clip`.
+    fn new_audio_filter<F: Filter>(
         &mut self,
-        out: &mut MapRef,
         name: &CStr,
         info: &AudioInfo,
         filter: F,
         dependencies: &Dependencies,
-    ) {
-        let filter = Box::new(filter);
+    ) -> (*mut F, Map) {
+        let created = self.create_map();
+        let mode = filter.filter_mode();
+        let instance = into_instance(filter);
         unsafe {
             (self.api.createAudioFilter)(
-                out.as_ptr(),
+                created.as_ptr(),
                 name.as_ptr(),
                 info,
                 F::filter_get_frame,
                 Some(F::filter_free),
-                F::FILTER_MODE,
+                mode,
                 dependencies.as_ptr(),
                 dependencies.len().try_into().unwrap(),
-                Box::into_raw(filter).cast(),
+                instance.cast(),
                 self.as_ptr(),
             );
         }
+        (instance, created)
+    }
+
+    /// Like [`Core::create_video_filter`], but returns the new node directly instead of appending
+    /// it to an output map.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FilterError::Creation`] with the message of the core if it rejected the filter.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the `dependencies` has more item than [`i32::MAX`]
+    pub fn create_video_filter_node<F: Filter<FrameType = VideoFrame>>(
+        &mut self,
+        name: &CStr,
+        info: &VideoInfo,
+        filter: F,
+        dependencies: &Dependencies,
+    ) -> Result<VideoNode, FilterError> {
+        let (instance, created) = self.new_video_filter(name, info, filter, dependencies);
+        FilterError::from_map(&created)?;
+        let mut node = created.get_video_node(key!(c"clip"), 0)?;
+        unsafe { (*instance).on_node_created(&mut node) };
+        Ok(node)
+    }
+
+    /// Like [`Core::create_audio_filter`], but returns the new node directly instead of appending
+    /// it to an output map.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FilterError::Creation`] with the message of the core if it rejected the filter.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the `dependencies` has more item than [`i32::MAX`]
+    pub fn create_audio_filter_node<F: Filter<FrameType = AudioFrame>>(
+        &mut self,
+        name: &CStr,
+        info: &AudioInfo,
+        filter: F,
+        dependencies: &Dependencies,
+    ) -> Result<AudioNode, FilterError> {
+        let (instance, created) = self.new_audio_filter(name, info, filter, dependencies);
+        FilterError::from_map(&created)?;
+        let mut node = created.get_audio_node(key!(c"clip"), 0)?;
+        unsafe { (*instance).on_node_created(&mut node) };
+        Ok(node)
     }
 
     #[must_use]
@@ -171,7 +475,7 @@ impl Core {
                 prop_src.map_or(null_mut(), |f| f.as_ptr().cast()),
                 self.as_ptr(),
             );
-            VideoFrame::from_ptr(ptr, self.api)
+            VideoFrame::from_ptr(ptr, self.api).kept_by(&self.keep_alive)
         }
     }
 
@@ -195,7 +499,7 @@ impl Core {
                 prop_src.map_or(null_mut(), |f| f.as_ptr().cast()),
                 self.as_ptr(),
             );
-            VideoFrame::from_ptr(ptr, self.api)
+            VideoFrame::from_ptr(ptr, self.api).kept_by(&self.keep_alive)
         }
     }
 
@@ -213,7 +517,7 @@ impl Core {
                 prop_src.map_or(null_mut(), |f| f.as_ptr().cast()),
                 self.as_ptr(),
             );
-            AudioFrame::from_ptr(ptr, self.api)
+            AudioFrame::from_ptr(ptr, self.api).kept_by(&self.keep_alive)
         }
     }
 
@@ -235,7 +539,7 @@ impl Core {
                 prop_src.map_or(null_mut(), |f| f.as_ptr().cast()),
                 self.as_ptr(),
             );
-            AudioFrame::from_ptr(ptr, self.api)
+            AudioFrame::from_ptr(ptr, self.api).kept_by(&self.keep_alive)
         }
     }
 
@@ -246,9 +550,12 @@ impl Core {
                 (self.api.copyFrame)(frame.as_ptr(), self.as_ptr()),
                 self.api,
             )
+            .kept_by(&self.keep_alive)
         }
     }
 
+    /// Fills in a [`VideoFormat`] from its parts. An invalid combination gives a zero-filled
+    /// format, use [`try_query_video_format`](Self::try_query_video_format) to detect it.
     #[must_use]
     pub fn query_video_format(
         &self,
@@ -273,22 +580,64 @@ impl Core {
         }
     }
 
+    /// Fills in a [`VideoFormat`] from its parts.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FormatError::Video`] if the core rejects the combination, e.g. subsampling on
+    /// an RGB format or 7 bits per sample.
+    pub fn try_query_video_format(
+        &self,
+        color_family: ColorFamily,
+        sample_type: SampleType,
+        bits_per_sample: i32,
+        subsampling_w: i32,
+        subsampling_h: i32,
+    ) -> Result<VideoFormat, FormatError> {
+        let mut format = MaybeUninit::uninit();
+        let ok = unsafe {
+            (self.api.queryVideoFormat)(
+                format.as_mut_ptr(),
+                color_family,
+                sample_type,
+                bits_per_sample,
+                subsampling_w,
+                subsampling_h,
+                self.as_ptr(),
+            )
+        };
+        if ok == 0 {
+            return Err(FormatError::Video {
+                color_family,
+                sample_type,
+                bits_per_sample,
+                subsampling_w,
+                subsampling_h,
+            });
+        }
+        Ok(unsafe { format.assume_init() })
+    }
+
+    /// The name of `format`, e.g. `YUV420P10`. Falls back to the [`Display`](std::fmt::Display)
+    /// implementation of [`VideoFormat`] if the core rejects the format.
     #[must_use]
-    pub fn get_video_format_name(&self, format: &VideoFormat) -> Option<String> {
+    pub fn get_video_format_name(&self, format: &VideoFormat) -> String {
         let mut buffer = FormatName::new();
         if 0 == unsafe { (self.api.getVideoFormatName)(format, buffer.as_mut_ptr().cast()) } {
-            None
+            format.to_string()
         } else {
-            Some(buffer.to_string())
+            buffer.to_string()
         }
     }
 
+    /// Fills in an [`AudioFormat`] from its parts. An invalid combination gives a zero-filled
+    /// format, use [`try_query_audio_format`](Self::try_query_audio_format) to detect it.
     #[must_use]
     pub fn query_audio_format(
         &self,
         sample_type: SampleType,
         bits_per_sample: i32,
-        channel_layout: u64,
+        channel_layout: impl Into<u64>,
     ) -> AudioFormat {
         unsafe {
             let mut format = MaybeUninit::uninit();
@@ -296,20 +645,56 @@ impl Core {
                 format.as_mut_ptr(),
                 sample_type,
                 bits_per_sample,
-                channel_layout,
+                channel_layout.into(),
                 self.as_ptr(),
             );
             format.assume_init()
         }
     }
 
+    /// Fills in an [`AudioFormat`] from its parts.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FormatError::Audio`] if the core rejects the combination, e.g. an empty channel
+    /// layout or 8 bits per sample.
+    pub fn try_query_audio_format(
+        &self,
+        sample_type: SampleType,
+        bits_per_sample: i32,
+        channel_layout: impl Into<u64>,
+    ) -> Result<AudioFormat, FormatError> {
+        let channel_layout = channel_layout.into();
+        let mut format = MaybeUninit::uninit();
+        let ok = unsafe {
+            (self.api.queryAudioFormat)(
+                format.as_mut_ptr(),
+                sample_type,
+                bits_per_sample,
+                channel_layout,
+                self.as_ptr(),
+            )
+        };
+        if ok == 0 {
+            return Err(FormatError::Audio {
+                sample_type,
+                bits_per_sample,
+                channel_layout,
+            });
+        }
+        Ok(unsafe { format.assume_init() })
+    }
+
+    /// The name of `format`, e.g. `Audio16 (2 CH)`. Falls back to the
+    /// [`Display`](std::fmt::Display) implementation of [`AudioFormat`] if the core rejects the
+    /// format.
     #[must_use]
-    pub fn get_audio_format_name(&self, format: &AudioFormat) -> Option<String> {
+    pub fn get_audio_format_name(&self, format: &AudioFormat) -> String {
         let mut buffer = FormatName::new();
         if 0 == unsafe { (self.api.getAudioFormatName)(format, buffer.as_mut_ptr().cast()) } {
-            None
+            format.to_string()
         } else {
-            Some(buffer.to_string())
+            buffer.to_string()
         }
     }
 
@@ -334,61 +719,122 @@ impl Core {
         }
     }
 
+    /// Looks up a format by its ID, which may also be a
+    /// [`PresetVideoFormat`](crate::PresetVideoFormat).
     #[must_use]
-    pub fn get_video_format_by_id(&self, id: u32) -> VideoFormat {
+    pub fn get_video_format_by_id(&self, id: impl Into<u32>) -> VideoFormat {
         unsafe {
             let mut format = MaybeUninit::uninit();
-            (self.api.getVideoFormatByID)(format.as_mut_ptr(), id, self.as_ptr());
+            (self.api.getVideoFormatByID)(format.as_mut_ptr(), id.into(), self.as_ptr());
             format.assume_init()
         }
     }
 
-    pub fn create_function<T>(
-        &mut self,
-        func: ffi::VSPublicFunction,
-        data: Box<T>,
-        free: ffi::VSFreeFunctionData,
-    ) -> Function {
+    /// Wraps `func` as a [`Function`], e.g. to pass a callback to another plugin. It receives
+    /// the arguments and the output map, where it stores its results or an error message.
+    ///
+    /// A panic inside `func` is reported as an error of the call.
+    pub fn create_function<F>(&self, func: F) -> Function
+    where
+        F: Fn(&Map, &mut Map, CoreRef) + Send + Sync + 'static,
+    {
+        unsafe extern "system-unwind" fn call<F: Fn(&Map, &mut Map, CoreRef)>(
+            in_: *const ffi::VSMap,
+            out: *mut ffi::VSMap,
+            user_data: *mut c_void,
+            core: *mut ffi::VSCore,
+            vsapi: *const ffi::VSAPI,
+        ) {
+            unsafe {
+                let api = Api::from_ptr(vsapi);
+                let func = &*user_data.cast::<F>();
+                let input = MapRef::from_ptr(in_, api);
+                let mut output = MapRef::from_ptr(out, api);
+                let core = CoreRef::from_ptr(core, api);
+                if let Err(p) = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                    func(&input, &mut output, core);
+                })) {
+                    output.set_error(&panic_message(p));
+                }
+            }
+        }
+
+        unsafe extern "system-unwind" fn free<F>(user_data: *mut c_void) {
+            drop(unsafe { Box::from_raw(user_data.cast::<F>()) });
+        }
+
+        let data = Box::into_raw(Box::new(func));
         unsafe {
             Function::from_ptr(
-                (self.api.createFunction)(func, Box::into_raw(data).cast(), free, self.as_ptr()),
+                (self.api.createFunction)(call::<F>, data.cast(), Some(free::<F>), self.as_ptr()),
                 self.api,
             )
+            .kept_by(&self.keep_alive)
         }
     }
 
+    /// Looks up a loaded plugin by its identifier, e.g. `com.vapoursynth.std`.
     #[must_use]
-    pub fn get_plugin_by_id(&self, id: &CStr) -> Option<Plugin> {
+    pub fn plugin_by_id(&self, id: &CStr) -> Option<Plugin<'_>> {
         unsafe {
             NonNull::new((self.api.getPluginByID)(id.as_ptr(), self.as_ptr()))
-                .map(|p| Plugin::new(p, self.api))
+                .map(|p| Plugin::new(p, self.api).kept_by(&self.keep_alive))
         }
     }
 
+    /// Looks up a loaded plugin by its namespace, e.g. `resize`.
     #[must_use]
-    pub fn get_plugin_by_namespace(&self, ns: &CStr) -> Option<Plugin> {
+    pub fn plugin_by_namespace(&self, ns: &CStr) -> Option<Plugin<'_>> {
         unsafe {
             NonNull::new((self.api.getPluginByNamespace)(ns.as_ptr(), self.as_ptr()))
-                .map(|p| Plugin::new(p, self.api))
+                .map(|p| Plugin::new(p, self.api).kept_by(&self.keep_alive))
         }
     }
 
+    /// Iterates over every plugin loaded in the core, including the built-in ones.
     #[must_use]
     pub fn plugins(&self) -> Plugins<'_> {
         Plugins::new(self)
     }
 
-    pub fn log(&mut self, level: ffi::VSMessageType, msg: &CStr) {
-        unsafe {
-            (self.api.logMessage)(level, msg.as_ptr(), self.as_ptr());
-        }
+    /// Calls `namespace.function` with `args`, e.g. `std.Crop`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::PluginNotFound`] if no plugin has the namespace, otherwise see
+    /// [`Plugin::invoke`].
+    pub fn invoke(
+        &self,
+        namespace: &CStr,
+        function: &CStr,
+        args: impl Borrow<Map>,
+    ) -> Result<Map, InvokeError> {
+        self.plugin_by_namespace(namespace)
+            .ok_or_else(|| InvokeError::PluginNotFound(namespace.to_string_lossy().into_owned()))?
+            .invoke(function, args)
+    }
+
+    /// Like [`invoke`](Self::invoke), but with the arguments written by `args`, usually the
+    /// output of [`vsmap!`](crate::vsmap).
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::Arguments`] if `args` could not be written, otherwise see
+    /// [`invoke`](Self::invoke).
+    pub fn invoke_with(
+        &self,
+        namespace: &CStr,
+        function: &CStr,
+        args: impl IntoMap,
+    ) -> Result<Map, InvokeError> {
+        let mut map = self.create_map();
+        args.into_map(&mut map)?;
+        self.invoke(namespace, function, map)
     }
-}
 
-impl Drop for Core {
-    fn drop(&mut self) {
+    pub fn log(&self, level: MessageType, msg: &CStr) {
         unsafe {
-            (self.api.freeCore)(self.handle.cast_mut());
+            (self.api.logMessage)(level, msg.as_ptr(), self.as_ptr());
         }
     }
 }
@@ -396,9 +842,33 @@ impl Drop for Core {
 // MARK: Helper
 
 impl Core {
-    unsafe fn new_with(flags: i32, api: Api) -> Self {
-        let core = unsafe { (api.createCore)(flags) };
-        Self { handle: core, api }
+    unsafe fn new_with(flags: CoreCreationFlags, api: Api) -> Self {
+        let core = unsafe { (api.createCore)(flags.bits()) };
+        Self {
+            handle: core,
+            api,
+            flags,
+            keep_alive: KeepAlive::new(move || unsafe { (api.freeCore)(core) }),
+        }
+    }
+
+    /// The owner the objects created from this core keep alive.
+    pub(crate) fn keep_alive(&self) -> &KeepAlive {
+        &self.keep_alive
+    }
+
+    /// Hands the core over to `VapourSynth`, e.g. to `createScript`, which frees it itself. The
+    /// release of the returned keepalive must be replaced by whatever frees the new owner.
+    #[cfg(feature = "script")]
+    pub(crate) fn into_keep_alive(self) -> KeepAlive {
+        let Self { keep_alive, .. } = self;
+        keep_alive
+    }
+
+    /// Wraps the core in a [`SharedCore`] to use it from several threads.
+    #[must_use]
+    pub fn into_shared(self) -> SharedCore {
+        SharedCore::new(self)
     }
 
     #[must_use]
@@ -410,7 +880,7 @@ impl Core {
     pub fn create_map(&self) -> Map {
         unsafe {
             let ptr = (self.api.createMap)();
-            Map::from_ptr(ptr, self.api)
+            Map::from_ptr(ptr, self.api).kept_by(&self.keep_alive)
         }
     }
 }
@@ -421,13 +891,13 @@ impl Core {
 impl Core {
     #[builder]
     pub fn new(
-        #[builder(field)] flags: i32,
+        #[builder(field)] flags: CoreCreationFlags,
         max_cache_size: Option<i64>,
         thread_count: Option<i32>,
-        #[cfg(feature = "link-library")]
+        #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
         #[builder(default)]
         api: Api,
-        #[cfg(not(feature = "link-library"))] api: Api,
+        #[cfg(not(any(feature = "link-library", feature = "runtime-loading")))] api: Api,
     ) -> Self {
         let mut core = unsafe { Core::new_with(flags, api) };
         if let Some(size) = max_cache_size {
@@ -442,19 +912,22 @@ impl Core {
 }
 
 impl<S: State> CoreBuilder<S> {
-    pub fn enable_graph_inspection(mut self) -> Self {
-        self.flags |= ffi::VSCoreCreationFlags::EnableGraphInspection as i32;
+    /// Adds `flags` to the creation flags.
+    pub fn flags(mut self, flags: CoreCreationFlags) -> Self {
+        self.flags |= flags;
         self
     }
 
-    pub fn disable_auto_loading(mut self) -> Self {
-        self.flags |= ffi::VSCoreCreationFlags::DisableAutoLoading as i32;
-        self
+    pub fn enable_graph_inspection(self) -> Self {
+        self.flags(CoreCreationFlags::ENABLE_GRAPH_INSPECTION)
     }
 
-    pub fn disable_library_unloading(mut self) -> Self {
-        self.flags |= ffi::VSCoreCreationFlags::DisableLibraryUnloading as i32;
-        self
+    pub fn disable_auto_loading(self) -> Self {
+        self.flags(CoreCreationFlags::DISABLE_AUTO_LOADING)
+    }
+
+    pub fn disable_library_unloading(self) -> Self {
+        self.flags(CoreCreationFlags::DISABLE_LIBRARY_UNLOADING)
     }
 }
 
@@ -462,6 +935,124 @@ impl<S: State> CoreBuilder<S> {
 #[cfg(feature = "link-library")]
 mod tests {
     use super::*;
+    use crate::{frame::FrameContext, key, vsmap};
+
+    struct Blank;
+
+    impl Filter for Blank {
+        type Error = &'static CStr;
+        type FrameType = VideoFrame;
+        type FilterData = ();
+
+        const NAME: &'static CStr = c"Blank";
+        const ARGS: &'static CStr = c"";
+        const RETURN_TYPE: &'static CStr = c"clip:vnode;";
+
+        fn get_frame(
+            &self,
+            _n: i32,
+            _activation_reason: ffi::VSActivationReason,
+            _frame_data: *mut *mut c_void,
+            _frame_ctx: FrameContext,
+            _core: CoreRef,
+        ) -> Result<Option<VideoFrame>, Self::Error> {
+            Ok(None)
+        }
+    }
+
+    struct Silence;
+
+    impl Filter for Silence {
+        type Error = &'static CStr;
+        type FrameType = AudioFrame;
+        type FilterData = ();
+
+        const NAME: &'static CStr = c"Silence";
+        const ARGS: &'static CStr = c"";
+        const RETURN_TYPE: &'static CStr = c"clip:anode;";
+
+        fn get_frame(
+            &self,
+            _n: i32,
+            _activation_reason: ffi::VSActivationReason,
+            _frame_data: *mut *mut c_void,
+            _frame_ctx: FrameContext,
+            _core: CoreRef,
+        ) -> Result<Option<AudioFrame>, Self::Error> {
+            Ok(None)
+        }
+    }
+
+    struct Debuggable {
+        debug: bool,
+    }
+
+    impl Filter for Debuggable {
+        type Error = &'static CStr;
+        type FrameType = VideoFrame;
+        type FilterData = ();
+
+        const NAME: &'static CStr = c"Debuggable";
+        const ARGS: &'static CStr = c"";
+        const RETURN_TYPE: &'static CStr = c"clip:vnode;";
+
+        fn get_frame(
+            &self,
+            _n: i32,
+            _activation_reason: ffi::VSActivationReason,
+            _frame_data: *mut *mut c_void,
+            _frame_ctx: FrameContext,
+            _core: CoreRef,
+        ) -> Result<Option<VideoFrame>, Self::Error> {
+            Ok(None)
+        }
+
+        fn filter_mode(&self) -> crate::node::FilterMode {
+            if self.debug {
+                crate::node::FilterMode::Unordered
+            } else {
+                Self::FILTER_MODE
+            }
+        }
+    }
+
+    struct Recorder {
+        created: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl Filter for Recorder {
+        type Error = &'static CStr;
+        type FrameType = VideoFrame;
+        type FilterData = ();
+
+        const NAME: &'static CStr = c"Recorder";
+        const ARGS: &'static CStr = c"";
+        const RETURN_TYPE: &'static CStr = c"clip:vnode;";
+
+        fn get_frame(
+            &self,
+            _n: i32,
+            _activation_reason: ffi::VSActivationReason,
+            _frame_data: *mut *mut c_void,
+            _frame_ctx: FrameContext,
+            _core: CoreRef,
+        ) -> Result<Option<VideoFrame>, Self::Error> {
+            Ok(None)
+        }
+
+        fn on_node_created(&mut self, node: &mut VideoNode) {
+            assert_eq!(node.info().num_frames, 1);
+            self.created
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    fn blank_clip(core: &Core) -> VideoNode {
+        core.invoke_with(c"std", c"BlankClip", vsmap! { length: 1 })
+            .unwrap()
+            .get_video_node(key!(c"clip"), 0)
+            .unwrap()
+    }
 
     #[test]
     fn builder() {
@@ -476,5 +1067,268 @@ mod tests {
             .build();
         assert_eq!(core.get_info().max_framebuffer_size, 1024);
         assert_eq!(core.get_info().num_threads, 4);
+        assert_eq!(core.flags(), CoreCreationFlags::ALL);
+    }
+
+    #[test]
+    fn plugins() {
+        let core = Core::builder().build();
+        assert!(core.plugins().any(|p| p.id() == c"com.vapoursynth.std"));
+    }
+
+    #[test]
+    fn plugin_lookup() {
+        let core = Core::builder().build();
+        let std = core.plugin_by_id(c"com.vapoursynth.std").unwrap();
+        assert_eq!(std.namespace(), c"std");
+        assert_eq!(core.plugin_by_namespace(c"std"), Some(std));
+        assert!(core.plugin_by_namespace(c"does_not_exist").is_none());
+    }
+
+    #[test]
+    fn invoke() {
+        use crate::vsmap;
+
+        let core = Core::builder().build();
+        let out = core
+            .invoke_with(
+                c"std",
+                c"BlankClip",
+                vsmap! { width: 64, height: 32, length: 5 },
+            )
+            .unwrap();
+        let clip = out.get_video_node(crate::key!(c"clip"), 0).unwrap();
+        assert_eq!(clip.info().width, 64);
+        assert_eq!(clip.info().num_frames, 5);
+
+        assert_eq!(
+            core.invoke(c"nope", c"Crop", core.create_map()),
+            Err(InvokeError::PluginNotFound("nope".to_owned()))
+        );
+        assert!(matches!(
+            core.invoke_with(c"std", c"BlankClip", vsmap! { width: -1 }),
+            Err(InvokeError::Failed(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "vs-41")]
+    fn node_timing() {
+        use crate::{node::Node, vsmap};
+
+        let mut core = Core::builder().build();
+        core.set_node_timing(true).unwrap();
+        assert!(core.node_timing().unwrap());
+
+        let out = core
+            .invoke_with(c"std", c"BlankClip", vsmap! { length: 5 })
+            .unwrap();
+        let clip = out.get_video_node(crate::key!(c"clip"), 0).unwrap();
+        clip.get_frame(0).unwrap();
+        assert_eq!(clip.name().unwrap(), "BlankClip");
+        assert!(clip.dependencies().unwrap().is_empty());
+        clip.clear_cache().unwrap();
+        core.clear_caches().unwrap();
+    }
+
+    #[test]
+    fn preset_format() {
+        let core = Core::builder().build();
+        let format = core.get_video_format_by_id(crate::PresetVideoFormat::YUV420P10);
+        assert_eq!(format.color_family, ColorFamily::YUV);
+        assert_eq!(format.bits_per_sample, 10);
+        assert_eq!(format.sub_sampling_w, 1);
+        assert_eq!(core.get_video_format_name(&format), "YUV420P10");
+        assert_eq!(format.to_string(), "YUV420P10");
+    }
+
+    #[test]
+    fn query_format() {
+        let core = Core::builder().build();
+        let format = core
+            .try_query_video_format(ColorFamily::YUV, SampleType::Integer, 10, 1, 1)
+            .unwrap();
+        assert_eq!(format.to_string(), "YUV420P10");
+        assert!(matches!(
+            core.try_query_video_format(ColorFamily::RGB, SampleType::Integer, 8, 1, 1),
+            Err(FormatError::Video { .. })
+        ));
+        assert!(matches!(
+            core.try_query_audio_format(SampleType::Integer, 16, 0u64),
+            Err(FormatError::Audio { .. })
+        ));
+    }
+
+    #[test]
+    fn function() {
+        use crate::{key, map::MapField};
+
+        let core = Core::builder().build();
+        let double = core.create_function(|args, out, _| {
+            let x = args.get_int(key!(c"x"), 0).unwrap();
+            (x * 2).set_field(out, key!(c"x")).unwrap();
+        });
+        let mut args = core.create_map();
+        21_i64.set_field(&mut args, key!(c"x")).unwrap();
+        let out = double.call(args).unwrap();
+        assert_eq!(out.get_int(key!(c"x"), 0), Ok(42));
+
+        let fail = core.create_function(|_, _, _| panic!("boom"));
+        assert_eq!(
+            fail.call(core.create_map()),
+            Err(crate::function::CallError("boom".to_owned()))
+        );
+    }
+
+    #[test]
+    fn log_handler() {
+        let core = Core::builder().build();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = core.add_log_handler(move |ty, msg| {
+            let _ = tx.send((ty, msg.to_owned()));
+        });
+        core.log(MessageType::Warning, c"hello");
+        drop(handle);
+        core.log(MessageType::Warning, c"ignored");
+
+        let received: Vec<_> = rx.try_iter().collect();
+        assert!(received.contains(&(MessageType::Warning, "hello".to_owned())));
+        assert!(!received.iter().any(|(_, msg)| msg == "ignored"));
+    }
+
+    #[test]
+    fn filter_error() {
+        let mut core = Core::builder().build();
+        let mut info = blank_clip(&core).info().clone();
+        info.width = -1;
+
+        let out = core.create_map();
+        let res = core.create_video_filter(
+            &mut unsafe { MapRef::from_ptr(out.as_ptr(), core.api()) },
+            c"Blank",
+            &info,
+            Blank,
+            Dependencies::new(&[]).unwrap(),
+        );
+        let Err(err @ FilterError::Creation(msg)) = &res else {
+            panic!("Invalid video info is accepted: {res:?}");
+        };
+        assert_eq!(out.get_error().unwrap().to_str(), Ok(msg.as_str()));
+        assert!(std::error::Error::source(err).is_none());
+
+        let res =
+            core.create_video_filter_node(c"Blank", &info, Blank, Dependencies::new(&[]).unwrap());
+        let Err(FilterError::Creation(node_msg)) = &res else {
+            panic!("Invalid video info is accepted: {res:?}");
+        };
+        assert_eq!(node_msg, msg);
+    }
+
+    #[test]
+    fn filter_node() {
+        use crate::node::Node;
+
+        let mut core = Core::builder().build();
+        let deps = Dependencies::new(&[]).unwrap();
+
+        let info = blank_clip(&core).info().clone();
+        let video = core
+            .create_video_filter_node(c"Blank", &info, Blank, deps)
+            .unwrap();
+        assert_eq!(video.info(), &info);
+        #[cfg(feature = "vs-41")]
+        assert_eq!(video.name().unwrap(), "Blank");
+
+        let audio = core
+            .invoke_with(c"std", c"BlankAudio", vsmap! { length: 4800 })
+            .unwrap()
+            .get_audio_node(key!(c"clip"), 0)
+            .unwrap();
+        let info = audio.info().clone();
+        let audio = core
+            .create_audio_filter_node(c"Silence", &info, Silence, deps)
+            .unwrap();
+        assert_eq!(audio.info(), &info);
+    }
+
+    #[test]
+    #[cfg(feature = "vs-41")]
+    fn filter_mode() {
+        use crate::node::{FilterMode, Node};
+
+        let mut core = Core::builder().build();
+        let info = blank_clip(&core).info().clone();
+        let deps = Dependencies::new(&[]).unwrap();
+
+        let parallel = core
+            .create_video_filter_node(c"Debuggable", &info, Debuggable { debug: false }, deps)
+            .unwrap();
+        assert_eq!(parallel.filter_mode().unwrap(), FilterMode::Parallel);
+
+        let serial = core
+            .create_video_filter_node(c"Debuggable", &info, Debuggable { debug: true }, deps)
+            .unwrap();
+        assert_eq!(serial.filter_mode().unwrap(), FilterMode::Unordered);
+    }
+
+    #[test]
+    fn node_created() {
+        use std::sync::{Arc, atomic::Ordering};
+
+        let mut core = Core::builder().build();
+        let info = blank_clip(&core).info().clone();
+        let deps = Dependencies::new(&[]).unwrap();
+        let created = Arc::default();
+        let recorder = || Recorder {
+            created: Arc::clone(&created),
+        };
+
+        core.create_video_filter_node(c"Recorder", &info, recorder(), deps)
+            .unwrap();
+        assert_eq!(created.load(Ordering::Relaxed), 1);
+
+        let out = core.create_map();
+        let mut out_ref = unsafe { MapRef::from_ptr(out.as_ptr(), core.api()) };
+        core.create_video_filter(&mut out_ref, c"Recorder", &info, recorder(), deps)
+            .unwrap();
+        core.create_video_filter_with_key(
+            &mut out_ref,
+            key!(c"second"),
+            c"Recorder",
+            &info,
+            recorder(),
+            deps,
+        )
+        .unwrap();
+        assert_eq!(created.load(Ordering::Relaxed), 3);
+        assert_eq!(out.num_elements(key!(c"clip")), Some(1));
+        assert_eq!(
+            out.get_video_node(key!(c"second"), 0).unwrap().info(),
+            &info
+        );
+    }
+
+    #[test]
+    fn objects_outlive_core() {
+        use crate::{map::Value, node::Node};
+
+        let core = Core::builder().build();
+        let clip = blank_clip(&core);
+        let mut map = core.create_map();
+        map.set(
+            key!(c"clip"),
+            Value::VideoNode(clip.clone()),
+            AppendMode::Replace,
+        )
+        .unwrap();
+        drop(core);
+
+        let frame = clip.get_frame(0).unwrap();
+        drop(clip);
+        assert_eq!(frame.frame_width(0), 640);
+
+        let clip = map.get_video_node(key!(c"clip"), 0).unwrap();
+        drop(map);
+        assert!(clip.get_frame(0).is_ok());
     }
 }
@@ -0,0 +1,139 @@
+use std::ffi::CStr;
+
+use thiserror::Error;
+
+use crate::{
+    key,
+    map::{AppendMode, IntoMap, MapValue},
+    plugin::InvokeError,
+};
+
+use super::Core;
+
+/// Builds a graph by invoking one function after another, passing the output clip of each step
+/// as the `clip` argument of the next.
+///
+/// ```no_run
+/// # fn f(
+/// #     core: &vapoursynth4_rs::core::Core,
+/// #     clip: vapoursynth4_rs::node::VideoNode,
+/// # ) -> Result<(), vapoursynth4_rs::core::ChainError> {
+/// use vapoursynth4_rs::{core::Chain, vsmap};
+///
+/// let clip = Chain::new(core, clip)
+///     .invoke(c"std", c"Crop", vsmap! { left: 8, right: 8 })?
+///     .invoke(c"resize", c"Bicubic", vsmap! { width: 1280, height: 720 })?
+///     .into_node();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Chain<'c, N> {
+    core: &'c Core,
+    node: N,
+    step: usize,
+}
+
+impl<'c, N: MapValue + Clone> Chain<'c, N> {
+    #[must_use]
+    pub fn new(core: &'c Core, node: N) -> Self {
+        Self {
+            core,
+            node,
+            step: 0,
+        }
+    }
+
+    /// Calls `namespace.function` with the current clip and `args`, usually the output of
+    /// [`vsmap!`](crate::vsmap), and continues with the clip it returns.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ChainError`] with the failed step if the function could not be invoked or did
+    /// not return a clip of the same kind.
+    pub fn invoke(
+        mut self,
+        namespace: &CStr,
+        function: &CStr,
+        args: impl IntoMap,
+    ) -> Result<Self, ChainError> {
+        self.step += 1;
+        let error = |source| ChainError {
+            step: self.step,
+            function: format!(
+                "{}.{}",
+                namespace.to_string_lossy(),
+                function.to_string_lossy()
+            ),
+            source,
+        };
+
+        let mut map = self.core.create_map();
+        self.node
+            .clone()
+            .set_value(&mut map, key!(c"clip"), AppendMode::Replace)
+            .and_then(|()| args.into_map(&mut map))
+            .map_err(|e| error(e.into()))?;
+        let out = self.core.invoke(namespace, function, map).map_err(error)?;
+        self.node = N::get_value(&out, key!(c"clip"), 0)
+            .map_err(|e| error(InvokeError::Failed(e.to_string())))?;
+        Ok(self)
+    }
+
+    /// The current clip.
+    #[must_use]
+    pub fn node(&self) -> &N {
+        &self.node
+    }
+
+    #[must_use]
+    pub fn into_node(self) -> N {
+        self.node
+    }
+}
+
+/// Error returned by [`Chain::invoke`].
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+#[error("Step {step} ({function}) failed: {source}")]
+pub struct ChainError {
+    /// The failed step, counting from 1.
+    pub step: usize,
+    /// The function of the step, as `namespace.function`.
+    pub function: String,
+    pub source: InvokeError,
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use super::*;
+    use crate::{node::VideoNode, vsmap};
+
+    #[test]
+    fn chain() {
+        let core = Core::builder().build();
+        let clip: VideoNode = core
+            .invoke_with(
+                c"std",
+                c"BlankClip",
+                vsmap! { width: 64, height: 48, length: 10 },
+            )
+            .unwrap()
+            .get_video_node(key!(c"clip"), 0)
+            .unwrap();
+
+        let chain = Chain::new(&core, clip)
+            .invoke(c"std", c"Crop", vsmap! { left: 8 })
+            .unwrap()
+            .invoke(c"std", c"Trim", vsmap! { length: 2 })
+            .unwrap();
+        assert_eq!(chain.node().info().width, 56);
+        assert_eq!(chain.node().info().num_frames, 2);
+
+        let e = chain
+            .invoke(c"std", c"Crop", vsmap! { left: 100 })
+            .unwrap_err();
+        assert_eq!(e.step, 3);
+        assert_eq!(e.function, "std.Crop");
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{ffi, utils::bit_flags};
+
+/// The options a [`Core`](super::Core) is created with, see
+/// [`CoreBuilder::flags`](super::CoreBuilder::flags).
+///
+/// ```
+/// use vapoursynth4_rs::core::CoreCreationFlags;
+///
+/// let flags = CoreCreationFlags::ENABLE_GRAPH_INSPECTION | CoreCreationFlags::DISABLE_AUTO_LOADING;
+/// assert_eq!(flags.bits(), 3);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CoreCreationFlags(i32);
+
+impl CoreCreationFlags {
+    /// See [`VSCoreCreationFlags::EnableGraphInspection`](ffi::VSCoreCreationFlags).
+    pub const ENABLE_GRAPH_INSPECTION: Self =
+        Self::from_flag(ffi::VSCoreCreationFlags::EnableGraphInspection);
+    /// See [`VSCoreCreationFlags::DisableAutoLoading`](ffi::VSCoreCreationFlags).
+    pub const DISABLE_AUTO_LOADING: Self =
+        Self::from_flag(ffi::VSCoreCreationFlags::DisableAutoLoading);
+    /// See [`VSCoreCreationFlags::DisableLibraryUnloading`](ffi::VSCoreCreationFlags).
+    pub const DISABLE_LIBRARY_UNLOADING: Self =
+        Self::from_flag(ffi::VSCoreCreationFlags::DisableLibraryUnloading);
+}
+
+bit_flags! {
+    CoreCreationFlags(i32): ffi::VSCoreCreationFlags;
+    fn from_flag(flag) { flag as i32 }
+    const FLAGS = [
+        ffi::VSCoreCreationFlags::EnableGraphInspection,
+        ffi::VSCoreCreationFlags::DisableAutoLoading,
+        ffi::VSCoreCreationFlags::DisableLibraryUnloading,
+    ];
+}
@@ -0,0 +1,139 @@
+use std::{ffi::CStr, fmt::Display};
+
+use crate::ffi;
+
+/// Information about a [`Core`](super::Core), see [`Core::get_info`](super::Core::get_info).
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CoreInfo {
+    /// Name of the library, copyright notice, core and API versions.
+    pub version_string: String,
+    /// Version of the core, e.g. `70` for R70.
+    pub core_version: i32,
+    /// Version of the API as `(major, minor)`.
+    pub api_version: (u16, u16),
+    /// Number of worker threads.
+    pub num_threads: usize,
+    /// The framebuffer cache will be allowed to grow up to this size (bytes)
+    /// before memory is aggressively reclaimed.
+    pub max_framebuffer_size: u64,
+    /// Current size of the framebuffer cache, in bytes.
+    pub used_framebuffer_size: u64,
+}
+
+impl CoreInfo {
+    /// # Safety
+    ///
+    /// `info.version_string` must be a valid C string.
+    pub(crate) unsafe fn from_ffi(info: &ffi::VSCoreInfo) -> Self {
+        let version_string = if info.version_string.is_null() {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(info.version_string) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let api_version = ((info.api >> 16) as u16, (info.api & 0xffff) as u16);
+
+        let stats = MemoryStats::from_ffi(info);
+
+        Self {
+            version_string,
+            core_version: info.core,
+            api_version,
+            num_threads: usize::try_from(info.num_threads).unwrap_or_default(),
+            max_framebuffer_size: stats.max_framebuffer_size,
+            used_framebuffer_size: stats.used_framebuffer_size,
+        }
+    }
+
+    /// The framebuffer part of this information.
+    #[must_use]
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            used_framebuffer_size: self.used_framebuffer_size,
+            max_framebuffer_size: self.max_framebuffer_size,
+        }
+    }
+}
+
+/// Framebuffer cache usage of a core at one point in time, see
+/// [`Core::memory_stats`](super::Core::memory_stats).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MemoryStats {
+    /// Current size of the framebuffer cache, in bytes.
+    pub used_framebuffer_size: u64,
+    /// Size the framebuffer cache may grow to before memory is aggressively reclaimed, in bytes.
+    pub max_framebuffer_size: u64,
+}
+
+impl MemoryStats {
+    pub(crate) fn from_ffi(info: &ffi::VSCoreInfo) -> Self {
+        Self {
+            used_framebuffer_size: u64::try_from(info.used_framebuffer_size).unwrap_or_default(),
+            max_framebuffer_size: u64::try_from(info.max_framebuffer_size).unwrap_or_default(),
+        }
+    }
+
+    /// Used part of the cache, usually between 0 and 1. Above 1 the core reclaims memory
+    /// aggressively.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn usage(&self) -> f64 {
+        if self.max_framebuffer_size == 0 {
+            0.0
+        } else {
+            self.used_framebuffer_size as f64 / self.max_framebuffer_size as f64
+        }
+    }
+
+    /// Whether the cache has grown past its limit.
+    #[must_use]
+    pub fn is_over_limit(&self) -> bool {
+        self.used_framebuffer_size > self.max_framebuffer_size
+    }
+}
+
+impl Display for CoreInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "VapourSynth R{} (API {}.{}), {} threads, framebuffer {} / {} bytes",
+            self.core_version,
+            self.api_version.0,
+            self.api_version.1,
+            self.num_threads,
+            self.used_framebuffer_size,
+            self.max_framebuffer_size,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_ffi() {
+        let raw = ffi::VSCoreInfo {
+            version_string: c"VapourSynth Video Processing Library".as_ptr(),
+            core: 70,
+            api: ffi::vs_make_version(4, 1),
+            num_threads: 8,
+            max_framebuffer_size: 4096,
+            used_framebuffer_size: 1024,
+        };
+        let info = unsafe { CoreInfo::from_ffi(&raw) };
+        assert_eq!(info.version_string, "VapourSynth Video Processing Library");
+        assert_eq!(info.api_version, (4, 1));
+        assert_eq!(
+            info.to_string(),
+            "VapourSynth R70 (API 4.1), 8 threads, framebuffer 1024 / 4096 bytes"
+        );
+
+        let stats = info.memory_stats();
+        assert_eq!(stats, MemoryStats::from_ffi(&raw));
+        assert!((stats.usage() - 0.25).abs() < f64::EPSILON);
+        assert!(!stats.is_over_limit());
+    }
+}
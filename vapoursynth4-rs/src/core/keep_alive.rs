@@ -0,0 +1,171 @@
+use std::{
+    hash::{Hash, Hasher},
+    ptr::NonNull,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// Frees a core, or the script environment owning it, once nothing uses it anymore.
+pub struct CoreOwner {
+    release: Mutex<Option<Box<dyn FnOnce()>>>,
+    // Dropped after `release` ran, e.g. the cores of the nodes a script holds in its variables.
+    #[cfg(feature = "script")]
+    dependencies: Mutex<Vec<KeepAlive>>,
+}
+
+// SAFETY: The release functions only call `freeCore` or `freeScript`, which may be called from any
+// thread.
+unsafe impl Send for CoreOwner {}
+unsafe impl Sync for CoreOwner {}
+
+impl Drop for CoreOwner {
+    fn drop(&mut self) {
+        let release = self
+            .release
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(release) = release.take() {
+            release();
+        }
+    }
+}
+
+/// A counted reference to the [`CoreOwner`] of the core an object was created from.
+///
+/// Objects created by `VapourSynth` itself, e.g. inside a filter, hold none, as their core is
+/// alive for as long as the filter.
+#[repr(transparent)]
+pub struct KeepAlive(Option<NonNull<CoreOwner>>);
+
+// SAFETY: `CoreOwner` is `Send` and `Sync`, and the count is atomic.
+unsafe impl Send for KeepAlive {}
+unsafe impl Sync for KeepAlive {}
+
+impl KeepAlive {
+    pub(crate) const NONE: Self = Self(None);
+
+    /// Creates the owner of a core, which runs `release` once the last reference is dropped.
+    pub(crate) fn new(release: impl FnOnce() + 'static) -> Self {
+        let owner = Arc::new(CoreOwner {
+            release: Mutex::new(Some(Box::new(release))),
+            #[cfg(feature = "script")]
+            dependencies: Mutex::new(Vec::new()),
+        });
+        Self(Some(unsafe {
+            NonNull::new_unchecked(Arc::into_raw(owner).cast_mut())
+        }))
+    }
+
+    /// Counts another reference to the owner of a borrowed core.
+    ///
+    /// # Safety
+    ///
+    /// `owner` must be counted by a `KeepAlive` that is still alive.
+    pub(crate) unsafe fn from_owner(owner: Option<NonNull<CoreOwner>>) -> Self {
+        if let Some(owner) = owner {
+            unsafe { Arc::increment_strong_count(owner.as_ptr()) };
+        }
+        Self(owner)
+    }
+
+    #[cfg(feature = "script")]
+    pub(crate) fn owner(&self) -> Option<NonNull<CoreOwner>> {
+        self.0
+    }
+
+    #[cfg(feature = "script")]
+    fn get(&self) -> Option<&CoreOwner> {
+        // SAFETY: The reference keeps the owner alive.
+        self.0.map(|owner| unsafe { owner.as_ref() })
+    }
+
+    /// Takes the owner of `other` if there is none yet, e.g. for a map a node is stored in.
+    pub(crate) fn adopt(&mut self, other: &Self) {
+        if self.0.is_none() {
+            *self = other.clone();
+        }
+    }
+
+    /// Replaces what the owner runs once the last reference is dropped, e.g. when a script
+    /// environment takes over the core. `None` if `VapourSynth` already freed it, e.g. after
+    /// `createScript` failed.
+    #[cfg(feature = "script")]
+    pub(crate) fn replace_release(&self, release: Option<Box<dyn FnOnce()>>) {
+        if let Some(owner) = self.get() {
+            *owner.release.lock().unwrap_or_else(PoisonError::into_inner) = release;
+        }
+    }
+
+    /// Keeps the core of `other` alive until this core is freed.
+    #[cfg(feature = "script")]
+    pub(crate) fn depend_on(&self, other: &Self) {
+        if let Some(owner) = self.get()
+            && other.0.is_some()
+            && other.0 != self.0
+        {
+            let mut dependencies = owner
+                .dependencies
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if !dependencies.iter().any(|d| d.0 == other.0) {
+                dependencies.push(other.clone());
+            }
+        }
+    }
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl Clone for KeepAlive {
+    fn clone(&self) -> Self {
+        // SAFETY: `self` is alive.
+        unsafe { Self::from_owner(self.0) }
+    }
+}
+
+impl Drop for KeepAlive {
+    fn drop(&mut self) {
+        if let Some(owner) = self.0 {
+            drop(unsafe { Arc::from_raw(owner.as_ptr()) });
+        }
+    }
+}
+
+// The owner is not part of the identity of an object.
+impl PartialEq for KeepAlive {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for KeepAlive {}
+
+impl Hash for KeepAlive {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl std::fmt::Debug for KeepAlive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("KeepAlive").field(&self.0).finish()
+    }
+}
+
+/// An object that keeps the core it was created from alive.
+pub trait CoreObject {
+    fn keep_alive(&self) -> &KeepAlive;
+
+    fn keep_alive_mut(&mut self) -> &mut KeepAlive;
+
+    /// Makes the object keep the core of `keep_alive` alive.
+    #[must_use]
+    fn kept_by(mut self, keep_alive: &KeepAlive) -> Self
+    where
+        Self: Sized,
+    {
+        *self.keep_alive_mut() = keep_alive.clone();
+        self
+    }
+}
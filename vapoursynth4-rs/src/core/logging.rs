@@ -0,0 +1,137 @@
+use std::{
+    ffi::{CStr, c_char, c_int, c_void},
+    panic::AssertUnwindSafe,
+};
+
+use crate::ffi;
+
+use super::Core;
+
+pub type MessageType = ffi::VSMessageType;
+
+fn message_type(value: c_int) -> MessageType {
+    match value {
+        0 => MessageType::Debug,
+        1 => MessageType::Information,
+        2 => MessageType::Warning,
+        3 => MessageType::Critical,
+        _ => MessageType::Fatal,
+    }
+}
+
+/// A log handler added by [`Core::add_log_handler`]. Dropping it removes the handler.
+#[derive(Debug)]
+#[must_use = "dropping the handle removes the log handler"]
+pub struct LogHandle<'c> {
+    handle: *mut ffi::VSLogHandle,
+    core: &'c Core,
+}
+
+impl LogHandle<'_> {
+    /// Keeps the handler until the core is freed.
+    pub fn detach(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for LogHandle<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            (self.core.api.removeLogHandler)(self.handle, self.core.as_ptr());
+        }
+    }
+}
+
+impl Core {
+    /// Adds `handler` to receive every message logged to the core, until the returned
+    /// [`LogHandle`] is dropped.
+    ///
+    /// The handler may be called from any thread. Panics inside it are caught and the message is
+    /// dropped. After a [`MessageType::Fatal`] message, `VapourSynth` aborts the process.
+    pub fn add_log_handler<F>(&self, handler: F) -> LogHandle<'_>
+    where
+        F: Fn(MessageType, &str) + Send + Sync + 'static,
+    {
+        unsafe extern "system-unwind" fn handle<F: Fn(MessageType, &str)>(
+            msg_type: c_int,
+            msg: *const c_char,
+            user_data: *mut c_void,
+        ) {
+            unsafe {
+                let handler = &*user_data.cast::<F>();
+                let msg = CStr::from_ptr(msg).to_string_lossy();
+                let _ = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                    handler(message_type(msg_type), &msg);
+                }));
+            }
+        }
+
+        unsafe extern "system-unwind" fn free<F>(user_data: *mut c_void) {
+            drop(unsafe { Box::from_raw(user_data.cast::<F>()) });
+        }
+
+        let data = Box::into_raw(Box::new(handler));
+        let handle = unsafe {
+            (self.api.addLogHandler)(
+                Some(handle::<F>),
+                Some(free::<F>),
+                data.cast(),
+                self.as_ptr(),
+            )
+        };
+        LogHandle { handle, core: self }
+    }
+
+    /// Forwards every core message to the [`log`] crate with the target `vapoursynth`, until
+    /// the returned [`LogHandle`] is dropped.
+    ///
+    /// Critical and fatal messages are logged as [`log::Level::Error`].
+    #[cfg(feature = "log")]
+    pub fn install_log_bridge(&self) -> LogHandle<'_> {
+        self.add_log_handler(|ty, msg| {
+            log::log!(target: "vapoursynth", log_level(ty), "{msg}");
+        })
+    }
+
+    /// Forwards every core message to [`tracing`] as an event with the target `vapoursynth`,
+    /// until the returned [`LogHandle`] is dropped.
+    ///
+    /// Events carry the `core_id` field, which tells messages of different cores apart, and the
+    /// original `message_type`. Critical and fatal messages are [`tracing::Level::ERROR`].
+    #[cfg(feature = "tracing")]
+    pub fn install_tracing_bridge(&self) -> LogHandle<'_> {
+        let core_id = self.as_ptr() as usize;
+        self.add_log_handler(move |ty, msg| {
+            macro_rules! emit {
+                ($level:expr) => {
+                    tracing::event!(
+                        target: "vapoursynth",
+                        $level,
+                        core_id,
+                        message_type = ?ty,
+                        "{msg}"
+                    )
+                };
+            }
+
+            match ty {
+                MessageType::Debug => emit!(tracing::Level::DEBUG),
+                MessageType::Information => emit!(tracing::Level::INFO),
+                MessageType::Warning => emit!(tracing::Level::WARN),
+                // Critical, fatal and any later types.
+                _ => emit!(tracing::Level::ERROR),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "log")]
+fn log_level(ty: MessageType) -> log::Level {
+    match ty {
+        MessageType::Debug => log::Level::Debug,
+        MessageType::Information => log::Level::Info,
+        MessageType::Warning => log::Level::Warn,
+        // Critical, fatal and any later types.
+        _ => log::Level::Error,
+    }
+}
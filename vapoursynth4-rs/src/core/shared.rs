@@ -0,0 +1,115 @@
+use std::{ops::Deref, sync::Arc};
+
+use super::Core;
+
+/// A reference counted [`Core`] that can be cloned and sent to other threads, e.g. to request
+/// frames or invoke functions in parallel.
+///
+/// It derefs to `&Core`, which gives the methods that take `&self`. The core API is thread safe
+/// for all of them. The methods that take `&mut self` change the whole core, like
+/// [`Core::set_thread_count`] or the `create_*_filter` methods, and are only reachable through
+/// [`get_mut`](Self::get_mut) or [`try_unwrap`](Self::try_unwrap) while no other clone exists.
+///
+/// The nodes, frames, functions and maps created from it keep the core alive, as for [`Core`]
+/// itself, so it is freed once the last clone and the last of them are dropped.
+///
+/// ```no_run
+/// # fn f(core: vapoursynth4_rs::core::Core) {
+/// use vapoursynth4_rs::core::SharedCore;
+///
+/// let core = SharedCore::new(core);
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let core = core.clone();
+///         std::thread::spawn(move || core.get_info().num_threads)
+///     })
+///     .collect();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedCore(Arc<Core>);
+
+// SAFETY: Only `&Core` is shared between threads, and the core API functions behind its `&self`
+// methods may be called concurrently.
+unsafe impl Send for SharedCore {}
+unsafe impl Sync for SharedCore {}
+
+impl SharedCore {
+    #[must_use]
+    #[allow(clippy::arc_with_non_send_sync)] // `SharedCore` is `Send` and `Sync`, see above.
+    pub fn new(core: Core) -> Self {
+        Self(Arc::new(core))
+    }
+
+    /// Exclusive access to the core if this is the only clone.
+    #[must_use]
+    pub fn get_mut(&mut self) -> Option<&mut Core> {
+        Arc::get_mut(&mut self.0)
+    }
+
+    /// Takes the core back if this is the only clone.
+    ///
+    /// # Errors
+    ///
+    /// Return `self` if other clones still exist.
+    pub fn try_unwrap(self) -> Result<Core, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+
+    /// Whether both are clones of the same core.
+    #[must_use]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for SharedCore {
+    type Target = Core;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<Core> for SharedCore {
+    fn as_ref(&self) -> &Core {
+        &self.0
+    }
+}
+
+impl From<Core> for SharedCore {
+    fn from(core: Core) -> Self {
+        Self::new(core)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use super::*;
+    use crate::vsmap;
+
+    #[test]
+    fn shared() {
+        let mut core = SharedCore::new(Core::builder().build());
+        core.get_mut().unwrap().set_thread_count(2);
+
+        let other = core.clone();
+        assert!(core.ptr_eq(&other));
+        assert!(core.get_mut().is_none());
+
+        let width = std::thread::spawn(move || {
+            other
+                .invoke_with(c"std", c"BlankClip", vsmap! { width: 64, height: 32 })
+                .unwrap()
+                .get_video_node(crate::key!(c"clip"), 0)
+                .unwrap()
+                .info()
+                .width
+        })
+        .join()
+        .unwrap();
+        assert_eq!(width, 64);
+        assert!(core.try_unwrap().is_ok());
+    }
+}
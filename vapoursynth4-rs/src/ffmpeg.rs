@@ -0,0 +1,400 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Converting frames to [`ffmpeg_next`] frames, so encoders can consume clips directly.
+//!
+//! The converted frames share the planes of the `VapourSynth` frames, which are kept alive by
+//! their buffers. Only 24 bit audio and audio with more than 8 channels are copied, because FFmpeg
+//! stores 24 bit samples in the high bits and needs its own buffers for more channels.
+//!
+//! Requires FFmpeg 5.1 or newer, and samples wider than 8 bits are only shared on little endian
+//! targets, which FFmpeg pixel formats are chosen for.
+
+use std::{
+    ffi::{c_int, c_void},
+    ptr,
+};
+
+use ffmpeg_next::{
+    chroma::Location,
+    color::{Primaries, Range, Space, TransferCharacteristic},
+    ffi as av,
+    frame::{Audio, Video},
+    util::format::{Pixel, Sample, sample::Type::Planar},
+};
+use thiserror::Error;
+
+use crate::{
+    ColorFamily, SampleType, ffi,
+    frame::{AudioFormat, AudioFrame, Frame, VideoFormat, VideoFrame},
+    key,
+    map::{KeyStr, Map},
+};
+
+#[derive(Debug, Error)]
+pub enum FfmpegError {
+    /// FFmpeg has no format with the same layout as the frame.
+    #[error("{0}")]
+    UnsupportedFormat(String),
+    #[error(transparent)]
+    Ffmpeg(#[from] ffmpeg_next::Error),
+}
+
+/// The FFmpeg pixel format with the same planes as `format`, if there is one. FFmpeg stores RGB
+/// as GBR, which only changes the order of the planes.
+#[must_use]
+pub fn pixel_format(format: &VideoFormat) -> Option<Pixel> {
+    use av::AVPixelFormat as F;
+
+    let bits = format.bits_per_sample;
+    if bits > 8 && cfg!(target_endian = "big") {
+        return None;
+    }
+    let subsampling = (format.sub_sampling_w, format.sub_sampling_h);
+    let pixel = match (format.color_family, format.sample_type) {
+        (ColorFamily::Gray, SampleType::Integer) => match bits {
+            8 => F::AV_PIX_FMT_GRAY8,
+            9 => F::AV_PIX_FMT_GRAY9LE,
+            10 => F::AV_PIX_FMT_GRAY10LE,
+            12 => F::AV_PIX_FMT_GRAY12LE,
+            14 => F::AV_PIX_FMT_GRAY14LE,
+            16 => F::AV_PIX_FMT_GRAY16LE,
+            _ => return None,
+        },
+        (ColorFamily::Gray, SampleType::Float) if bits == 32 => F::AV_PIX_FMT_GRAYF32LE,
+        (ColorFamily::YUV, SampleType::Integer) => match (bits, subsampling) {
+            (8, (1, 1)) => F::AV_PIX_FMT_YUV420P,
+            (8, (1, 0)) => F::AV_PIX_FMT_YUV422P,
+            (8, (0, 0)) => F::AV_PIX_FMT_YUV444P,
+            (8, (2, 2)) => F::AV_PIX_FMT_YUV410P,
+            (8, (2, 0)) => F::AV_PIX_FMT_YUV411P,
+            (8, (0, 1)) => F::AV_PIX_FMT_YUV440P,
+            (9, (1, 1)) => F::AV_PIX_FMT_YUV420P9LE,
+            (9, (1, 0)) => F::AV_PIX_FMT_YUV422P9LE,
+            (9, (0, 0)) => F::AV_PIX_FMT_YUV444P9LE,
+            (10, (1, 1)) => F::AV_PIX_FMT_YUV420P10LE,
+            (10, (1, 0)) => F::AV_PIX_FMT_YUV422P10LE,
+            (10, (0, 0)) => F::AV_PIX_FMT_YUV444P10LE,
+            (10, (0, 1)) => F::AV_PIX_FMT_YUV440P10LE,
+            (12, (1, 1)) => F::AV_PIX_FMT_YUV420P12LE,
+            (12, (1, 0)) => F::AV_PIX_FMT_YUV422P12LE,
+            (12, (0, 0)) => F::AV_PIX_FMT_YUV444P12LE,
+            (12, (0, 1)) => F::AV_PIX_FMT_YUV440P12LE,
+            (14, (1, 1)) => F::AV_PIX_FMT_YUV420P14LE,
+            (14, (1, 0)) => F::AV_PIX_FMT_YUV422P14LE,
+            (14, (0, 0)) => F::AV_PIX_FMT_YUV444P14LE,
+            (16, (1, 1)) => F::AV_PIX_FMT_YUV420P16LE,
+            (16, (1, 0)) => F::AV_PIX_FMT_YUV422P16LE,
+            (16, (0, 0)) => F::AV_PIX_FMT_YUV444P16LE,
+            _ => return None,
+        },
+        (ColorFamily::RGB, SampleType::Integer) => match bits {
+            8 => F::AV_PIX_FMT_GBRP,
+            9 => F::AV_PIX_FMT_GBRP9LE,
+            10 => F::AV_PIX_FMT_GBRP10LE,
+            12 => F::AV_PIX_FMT_GBRP12LE,
+            14 => F::AV_PIX_FMT_GBRP14LE,
+            16 => F::AV_PIX_FMT_GBRP16LE,
+            _ => return None,
+        },
+        (ColorFamily::RGB, SampleType::Float) if bits == 32 => F::AV_PIX_FMT_GBRPF32LE,
+        _ => return None,
+    };
+    Some(pixel.into())
+}
+
+/// The planar FFmpeg sample format for `format`, if there is one. 24 bit samples are converted
+/// to 32 bits.
+#[must_use]
+pub fn sample_format(format: &AudioFormat) -> Option<Sample> {
+    match (format.sample_type, format.bits_per_sample) {
+        (SampleType::Integer, 16) => Some(Sample::I16(Planar)),
+        (SampleType::Integer, 17..=32) => Some(Sample::I32(Planar)),
+        (SampleType::Float, 32) => Some(Sample::F32(Planar)),
+        _ => None,
+    }
+}
+
+impl VideoFrame {
+    /// Converts the frame to an FFmpeg frame that shares its planes, with the color metadata of
+    /// its properties.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FfmpegError::UnsupportedFormat`] if FFmpeg has no [`pixel_format`] for the frame,
+    /// or the FFmpeg error if a buffer cannot be allocated.
+    pub fn to_ffmpeg(&self) -> Result<Video, FfmpegError> {
+        let format = self.get_video_format();
+        let pixel = pixel_format(format).ok_or_else(|| {
+            FfmpegError::UnsupportedFormat("FFmpeg has no pixel format for the frame".to_owned())
+        })?;
+
+        let mut video = Video::empty();
+        video.set_format(pixel);
+        video.set_width(self.frame_width(0).try_into().unwrap_or_default());
+        video.set_height(self.frame_height(0).try_into().unwrap_or_default());
+        let rgb = format.color_family == ColorFamily::RGB;
+        unsafe {
+            let frame = video.as_mut_ptr();
+            for (index, plane) in (0..format.num_planes).enumerate() {
+                // FFmpeg stores RGB as GBR.
+                let plane = if rgb { (plane + 1) % 3 } else { plane };
+                let stride = self.stride(plane);
+                let len = stride.unsigned_abs() * self.frame_height(plane).unsigned_abs() as usize;
+                let data = self.plane(plane);
+                (*frame).buf[index] = share(self, data, len)?;
+                (*frame).data[index] = data.cast_mut();
+                (*frame).linesize[index] = stride.try_into().unwrap_or_default();
+            }
+            (*frame).extended_data = (*frame).data.as_mut_ptr();
+        }
+        if let Some(props) = self.properties() {
+            set_color(&mut video, &props, rgb);
+        } else if rgb {
+            video.set_color_space(Space::RGB);
+        }
+        Ok(video)
+    }
+}
+
+impl AudioFrame {
+    /// Converts the frame to an FFmpeg frame with the planar [`sample_format`], which shares the
+    /// samples unless they are 24 bit or there are more than 8 channels.
+    ///
+    /// # Errors
+    ///
+    /// Return [`FfmpegError::UnsupportedFormat`] if FFmpeg has no sample format for the frame,
+    /// or the FFmpeg error if a buffer cannot be allocated.
+    pub fn to_ffmpeg(&self, sample_rate: i32) -> Result<Audio, FfmpegError> {
+        let format = self.get_audio_format();
+        let sample = sample_format(format).ok_or_else(|| {
+            FfmpegError::UnsupportedFormat("FFmpeg has no sample format for the frame".to_owned())
+        })?;
+        let samples = self.frame_length().unsigned_abs() as usize;
+        let bytes = format.bytes_per_sample.unsigned_abs() as usize;
+        let shift = match format.sample_type {
+            SampleType::Integer => format.bytes_per_sample * 8 - format.bits_per_sample,
+            SampleType::Float => 0,
+        };
+
+        let mut audio = Audio::empty();
+        audio.set_format(sample);
+        audio.set_samples(samples);
+        audio.set_rate(sample_rate.unsigned_abs());
+        unsafe {
+            let frame = audio.as_mut_ptr();
+            check(av::av_channel_layout_from_mask(
+                &raw mut (*frame).ch_layout,
+                format.channel_layout,
+            ))?;
+            if shift == 0 && format.num_channels.unsigned_abs() as usize <= (*frame).data.len() {
+                let len = samples * bytes;
+                for (index, channel) in (0..format.num_channels).enumerate() {
+                    let data = self.channel(channel);
+                    (*frame).buf[index] = share(self, data, len)?;
+                    (*frame).data[index] = data.cast_mut();
+                }
+                (*frame).linesize[0] = (samples * bytes).try_into().unwrap_or_default();
+                (*frame).extended_data = (*frame).data.as_mut_ptr();
+            } else {
+                check(av::av_frame_get_buffer(frame, 0))?;
+                for (index, channel) in (0..format.num_channels).enumerate() {
+                    let src = self.channel(channel);
+                    let dst = *(*frame).extended_data.add(index);
+                    if shift == 0 {
+                        ptr::copy_nonoverlapping(src, dst, samples * bytes);
+                    } else {
+                        let src = std::slice::from_raw_parts(src.cast::<i32>(), samples);
+                        let dst = std::slice::from_raw_parts_mut(dst.cast::<i32>(), samples);
+                        for (dst, src) in dst.iter_mut().zip(src) {
+                            *dst = src << shift;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(audio)
+    }
+}
+
+fn check(ret: c_int) -> Result<(), ffmpeg_next::Error> {
+    if ret < 0 { Err(ret.into()) } else { Ok(()) }
+}
+
+/// Wraps `len` bytes at `data` in a read-only buffer that holds a reference to `frame`.
+unsafe fn share<F: Frame + Clone>(
+    frame: &F,
+    data: *const u8,
+    len: usize,
+) -> Result<*mut av::AVBufferRef, ffmpeg_next::Error> {
+    unsafe extern "C" fn free<F>(opaque: *mut c_void, _: *mut u8) {
+        drop(unsafe { Box::from_raw(opaque.cast::<F>()) });
+    }
+
+    let opaque = Box::into_raw(Box::new(frame.clone())).cast::<c_void>();
+    let buf = unsafe {
+        av::av_buffer_create(
+            data.cast_mut(),
+            len,
+            Some(free::<F>),
+            opaque,
+            av::AV_BUFFER_FLAG_READONLY as c_int,
+        )
+    };
+    if buf.is_null() {
+        unsafe { free::<F>(opaque, ptr::null_mut()) };
+        return Err(ffmpeg_next::Error::Other {
+            errno: ffmpeg_next::error::ENOMEM,
+        });
+    }
+    Ok(buf)
+}
+
+fn prop<T: TryFrom<i32>>(props: &Map, key: &KeyStr) -> Option<T> {
+    let value = props.get_int(key, 0).ok()?;
+    T::try_from(i32::try_from(value).ok()?).ok()
+}
+
+fn set_color(video: &mut Video, props: &Map, rgb: bool) {
+    match prop(props, key!(ffi::props::MATRIX)).and_then(color_space) {
+        Some(space) => video.set_color_space(space),
+        None if rgb => video.set_color_space(Space::RGB),
+        None => {}
+    }
+    if let Some(transfer) = prop(props, key!(ffi::props::TRANSFER)).and_then(transfer) {
+        video.set_color_transfer_characteristic(transfer);
+    }
+    if let Some(primaries) = prop(props, key!(ffi::props::PRIMARIES)).and_then(primaries) {
+        video.set_color_primaries(primaries);
+    }
+    if let Some(range) = prop(props, key!(ffi::props::COLOR_RANGE)) {
+        video.set_color_range(match range {
+            ffi::VSColorRange::VSC_RANGE_FULL => Range::JPEG,
+            _ => Range::MPEG,
+        });
+    }
+    let location = prop(props, key!(ffi::props::CHROMA_LOCATION)).and_then(chroma_location);
+    let sar = (
+        props.get_int(key!(ffi::props::SAR_NUM), 0),
+        props.get_int(key!(ffi::props::SAR_DEN), 0),
+    );
+    unsafe {
+        let frame = video.as_mut_ptr();
+        if let Some(location) = location {
+            (*frame).chroma_location = location.into();
+        }
+        if let (Ok(num), Ok(den)) = sar
+            && let (Ok(num), Ok(den)) = (num.try_into(), den.try_into())
+        {
+            (*frame).sample_aspect_ratio = av::AVRational { num, den };
+        }
+    }
+}
+
+fn color_space(matrix: ffi::VSMatrixCoefficients) -> Option<Space> {
+    use ffi::VSMatrixCoefficients as M;
+
+    Some(match matrix {
+        M::VSC_MATRIX_RGB => Space::RGB,
+        M::VSC_MATRIX_BT709 => Space::BT709,
+        M::VSC_MATRIX_FCC => Space::FCC,
+        M::VSC_MATRIX_BT470_BG => Space::BT470BG,
+        M::VSC_MATRIX_ST170_M => Space::SMPTE170M,
+        M::VSC_MATRIX_ST240_M => Space::SMPTE240M,
+        M::VSC_MATRIX_YCGCO => Space::YCGCO,
+        M::VSC_MATRIX_BT2020_NCL => Space::BT2020NCL,
+        M::VSC_MATRIX_BT2020_CL => Space::BT2020CL,
+        M::VSC_MATRIX_CHROMATICITY_DERIVED_NCL => Space::ChromaDerivedNCL,
+        M::VSC_MATRIX_CHROMATICITY_DERIVED_CL => Space::ChromaDerivedCL,
+        M::VSC_MATRIX_ICTCP => Space::ICTCP,
+        _ => return None,
+    })
+}
+
+fn transfer(transfer: ffi::VSTransferCharacteristics) -> Option<TransferCharacteristic> {
+    use ffi::VSTransferCharacteristics as T;
+
+    Some(match transfer {
+        T::VSC_TRANSFER_BT709 => TransferCharacteristic::BT709,
+        T::VSC_TRANSFER_BT470_M => TransferCharacteristic::GAMMA22,
+        T::VSC_TRANSFER_BT470_BG => TransferCharacteristic::GAMMA28,
+        T::VSC_TRANSFER_BT601 => TransferCharacteristic::SMPTE170M,
+        T::VSC_TRANSFER_ST240_M => TransferCharacteristic::SMPTE240M,
+        T::VSC_TRANSFER_LINEAR => TransferCharacteristic::Linear,
+        T::VSC_TRANSFER_LOG_100 => TransferCharacteristic::Log,
+        T::VSC_TRANSFER_LOG_316 => TransferCharacteristic::LogSqrt,
+        T::VSC_TRANSFER_IEC_61966_2_4 => TransferCharacteristic::IEC61966_2_4,
+        T::VSC_TRANSFER_IEC_61966_2_1 => TransferCharacteristic::IEC61966_2_1,
+        T::VSC_TRANSFER_BT2020_10 => TransferCharacteristic::BT2020_10,
+        T::VSC_TRANSFER_BT2020_12 => TransferCharacteristic::BT2020_12,
+        T::VSC_TRANSFER_ST2084 => TransferCharacteristic::SMPTE2084,
+        T::VSC_TRANSFER_ARIB_B67 => TransferCharacteristic::ARIB_STD_B67,
+        _ => return None,
+    })
+}
+
+fn primaries(primaries: ffi::VSColorPrimaries) -> Option<Primaries> {
+    use ffi::VSColorPrimaries as P;
+
+    Some(match primaries {
+        P::VSC_PRIMARIES_BT709 => Primaries::BT709,
+        P::VSC_PRIMARIES_BT470_M => Primaries::BT470M,
+        P::VSC_PRIMARIES_BT470_BG => Primaries::BT470BG,
+        P::VSC_PRIMARIES_ST170_M => Primaries::SMPTE170M,
+        P::VSC_PRIMARIES_ST240_M => Primaries::SMPTE240M,
+        P::VSC_PRIMARIES_FILM => Primaries::Film,
+        P::VSC_PRIMARIES_BT2020 => Primaries::BT2020,
+        P::VSC_PRIMARIES_ST428 => Primaries::SMPTE428,
+        P::VSC_PRIMARIES_ST431_2 => Primaries::SMPTE431,
+        P::VSC_PRIMARIES_ST432_1 => Primaries::SMPTE432,
+        P::VSC_PRIMARIES_EBU3213_E => Primaries::JEDEC_P22,
+        _ => return None,
+    })
+}
+
+fn chroma_location(location: ffi::VSChromaLocation) -> Option<Location> {
+    use ffi::VSChromaLocation as L;
+
+    Some(match location {
+        L::VSC_CHROMA_LEFT => Location::Left,
+        L::VSC_CHROMA_CENTER => Location::Center,
+        L::VSC_CHROMA_TOP_LEFT => Location::TopLeft,
+        L::VSC_CHROMA_TOP => Location::Top,
+        L::VSC_CHROMA_BOTTOM_LEFT => Location::BottomLeft,
+        L::VSC_CHROMA_BOTTOM => Location::Bottom,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let mut format = VideoFormat {
+            color_family: ColorFamily::YUV,
+            sample_type: SampleType::Integer,
+            bits_per_sample: 10,
+            bytes_per_sample: 2,
+            sub_sampling_w: 1,
+            sub_sampling_h: 1,
+            num_planes: 3,
+        };
+        assert_eq!(pixel_format(&format), Some(Pixel::YUV420P10LE));
+        format.sub_sampling_w = 2;
+        assert_eq!(pixel_format(&format), None);
+
+        let audio = AudioFormat {
+            sample_type: SampleType::Integer,
+            bits_per_sample: 24,
+            bytes_per_sample: 4,
+            num_channels: 2,
+            channel_layout: 0b11,
+        };
+        assert_eq!(sample_format(&audio), Some(Sample::I32(Planar)));
+    }
+}
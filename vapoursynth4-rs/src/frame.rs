@@ -4,15 +4,32 @@
  file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
-use crate::{api::Api, ffi, map::MapRef};
-
+use crate::{
+    AudioInfo, VideoInfo,
+    api::Api,
+    core::keep_alive::{CoreObject, KeepAlive},
+    ffi,
+    map::MapRef,
+    node::{
+        AudioNode, Node, VideoNode,
+        internal::{FilterInfo, NodeFromPtr},
+    },
+};
+
+mod channels;
 mod context;
 mod format;
 
+pub use channels::*;
 pub use context::*;
 pub use format::*;
 
-pub trait Frame: Sized + Send + internal::FrameFromPtr {
+pub trait Frame: Sized + Send + internal::FrameFromPtr + CoreObject {
+    /// The kind of node producing this kind of frame.
+    type Node: Node<FrameType = Self> + NodeFromPtr;
+    /// The clip information of that node.
+    type Info: FilterInfo;
+
     fn api(&self) -> Api;
 
     #[must_use]
@@ -38,7 +55,7 @@ pub trait Frame: Sized + Send + internal::FrameFromPtr {
 }
 
 pub(crate) mod internal {
-    use super::{Api, AudioFrame, VideoFrame, ffi};
+    use super::{Api, AudioFrame, KeepAlive, VideoFrame, ffi};
 
     pub trait FrameFromPtr {
         unsafe fn from_ptr(ptr: *const ffi::VSFrame, api: Api) -> Self;
@@ -50,13 +67,18 @@ pub(crate) mod internal {
             VideoFrame {
                 handle: ptr.cast_mut(),
                 api,
+                keep_alive: KeepAlive::NONE,
             }
         }
     }
 
     impl FrameFromPtr for AudioFrame {
         unsafe fn from_ptr(ptr: *const ffi::VSFrame, api: Api) -> Self {
-            AudioFrame { handle: ptr, api }
+            AudioFrame {
+                handle: ptr,
+                api,
+                keep_alive: KeepAlive::NONE,
+            }
         }
     }
 }
@@ -66,11 +88,25 @@ use internal::FrameFromPtr;
 pub struct VideoFrame {
     handle: *const ffi::VSFrame,
     api: Api,
+    keep_alive: KeepAlive,
+}
+
+impl CoreObject for VideoFrame {
+    fn keep_alive(&self) -> &KeepAlive {
+        &self.keep_alive
+    }
+
+    fn keep_alive_mut(&mut self) -> &mut KeepAlive {
+        &mut self.keep_alive
+    }
 }
 
 unsafe impl Send for VideoFrame {}
 
 impl Frame for VideoFrame {
+    type Node = VideoNode;
+    type Info = VideoInfo;
+
     #[inline]
     fn api(&self) -> Api {
         self.api
@@ -129,6 +165,7 @@ impl VideoFrame {
 impl Clone for VideoFrame {
     fn clone(&self) -> Self {
         unsafe { Self::from_ptr((self.api.addFrameRef)(self.handle), self.api) }
+            .kept_by(&self.keep_alive)
     }
 }
 
@@ -142,11 +179,25 @@ impl Drop for VideoFrame {
 pub struct AudioFrame {
     handle: *const ffi::VSFrame,
     api: Api,
+    keep_alive: KeepAlive,
+}
+
+impl CoreObject for AudioFrame {
+    fn keep_alive(&self) -> &KeepAlive {
+        &self.keep_alive
+    }
+
+    fn keep_alive_mut(&mut self) -> &mut KeepAlive {
+        &mut self.keep_alive
+    }
 }
 
 unsafe impl Send for AudioFrame {}
 
 impl Frame for AudioFrame {
+    type Node = AudioNode;
+    type Info = AudioInfo;
+
     fn api(&self) -> Api {
         self.api
     }
@@ -167,6 +218,12 @@ impl AudioFrame {
         unsafe { (self.api.getWritePtr)(self.as_ptr(), channel) }
     }
 
+    #[must_use]
+    pub fn get_audio_format(&self) -> &AudioFormat {
+        // safety: `af` is valid if the node is an audio node
+        unsafe { &*(self.api.getAudioFrameFormat)(self.as_ptr()) }
+    }
+
     #[must_use]
     pub fn frame_length(&self) -> i32 {
         unsafe { (self.api.getFrameLength)(self.as_ptr()) }
@@ -176,6 +233,7 @@ impl AudioFrame {
 impl Clone for AudioFrame {
     fn clone(&self) -> Self {
         unsafe { Self::from_ptr((self.api.addFrameRef)(self.handle), self.api) }
+            .kept_by(&self.keep_alive)
     }
 }
 
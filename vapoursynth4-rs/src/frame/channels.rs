@@ -0,0 +1,134 @@
+use thiserror::Error;
+
+use crate::{ffi, utils::bit_flags};
+
+/// A set of audio channel positions, as used for [`AudioFormat::channel_layout`].
+///
+/// ```
+/// use vapoursynth4_rs::frame::AudioChannels;
+///
+/// let layout = AudioChannels::STEREO | AudioChannels::LOW_FREQUENCY;
+/// assert_eq!(layout.len(), 3);
+/// assert_eq!(u64::from(layout), 0b1011);
+/// ```
+///
+/// [`AudioFormat::channel_layout`]: crate::frame::AudioFormat
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u64", into = "u64")
+)]
+pub struct AudioChannels(u64);
+
+/// Error returned when a channel layout has a bit that is not a known channel.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Error)]
+#[error("Unknown channels in the audio channel layout {0:#x}")]
+pub struct UnknownChannelsError(pub u64);
+
+macro_rules! channels {
+    ($($name:ident => $channel:ident),* $(,)?) => {
+        impl AudioChannels {
+            $(
+                pub const $name: Self = Self::from_channel(ffi::VSAudioChannels::$channel);
+            )*
+        }
+    };
+}
+
+channels!(
+    FRONT_LEFT => FrontLeft,
+    FRONT_RIGHT => FrontRight,
+    FRONT_CENTER => FrontCenter,
+    LOW_FREQUENCY => LowFrequency,
+    BACK_LEFT => BackLeft,
+    BACK_RIGHT => BackRight,
+    FRONT_LEFT_OF_CENTER => FrontLeftOFCenter,
+    FRONT_RIGHT_OF_CENTER => FrontRightOFCenter,
+    BACK_CENTER => BackCenter,
+    SIDE_LEFT => SideLeft,
+    SIDE_RIGHT => SideRight,
+    TOP_CENTER => TopCenter,
+    TOP_FRONT_LEFT => TopFrontLeft,
+    TOP_FRONT_CENTER => TopFrontCenter,
+    TOP_FRONT_RIGHT => TopFrontRight,
+    TOP_BACK_LEFT => TopBackLeft,
+    TOP_BACK_CENTER => TopBackCenter,
+    TOP_BACK_RIGHT => TopBackRight,
+    STEREO_LEFT => StereoLeft,
+    STEREO_RIGHT => StereoRight,
+    WIDE_LEFT => WideLeft,
+    WIDE_RIGHT => WideRight,
+    SURROUND_DIRECT_LEFT => SurroundDirectLeft,
+    SURROUND_DIRECT_RIGHT => SurroundDirectRight,
+    LOW_FREQUENCY2 => LowFrequency2,
+);
+
+impl AudioChannels {
+    pub const MONO: Self = Self::FRONT_CENTER;
+    pub const STEREO: Self = Self(Self::FRONT_LEFT.0 | Self::FRONT_RIGHT.0);
+    pub const SURROUND_5_1: Self = Self(
+        Self::STEREO.0
+            | Self::FRONT_CENTER.0
+            | Self::LOW_FREQUENCY.0
+            | Self::BACK_LEFT.0
+            | Self::BACK_RIGHT.0,
+    );
+    pub const SURROUND_7_1: Self =
+        Self(Self::SURROUND_5_1.0 | Self::SIDE_LEFT.0 | Self::SIDE_RIGHT.0);
+}
+
+bit_flags! {
+    AudioChannels(u64): ffi::VSAudioChannels;
+    fn from_channel(channel) { 1 << channel as u64 }
+    const FLAGS = ffi::VSAudioChannels::ALL;
+}
+
+impl TryFrom<u64> for AudioChannels {
+    type Error = UnknownChannelsError;
+
+    fn try_from(bits: u64) -> Result<Self, Self::Error> {
+        Self::from_bits(bits).ok_or(UnknownChannelsError(bits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels() {
+        use ffi::VSAudioChannels as c;
+
+        let layout: AudioChannels = [c::FrontLeft, c::FrontRight].into_iter().collect();
+        assert_eq!(layout, AudioChannels::STEREO);
+        assert_eq!(AudioChannels::SURROUND_5_1.bits(), 0x3f);
+        assert_eq!(AudioChannels::SURROUND_7_1.len(), 8);
+        assert!(AudioChannels::SURROUND_5_1.contains(AudioChannels::STEREO));
+        assert_eq!(
+            (AudioChannels::SURROUND_5_1 - AudioChannels::LOW_FREQUENCY).len(),
+            5
+        );
+        assert_eq!(AudioChannels::from_bits(1 << 20), None);
+        assert_eq!(
+            AudioChannels::from_bits_truncate((1 << 20) | 1),
+            AudioChannels::FRONT_LEFT
+        );
+        assert_eq!(
+            format!("{:?}", AudioChannels::STEREO),
+            "{FrontLeft, FrontRight}"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn deserialize() {
+        use serde::{Deserialize, de::value::U64Deserializer};
+
+        let de = |bits| {
+            AudioChannels::deserialize(U64Deserializer::<serde::de::value::Error>::new(bits))
+        };
+        assert_eq!(de(0b11), Ok(AudioChannels::STEREO));
+        assert!(de(1 << 40).is_err());
+    }
+}
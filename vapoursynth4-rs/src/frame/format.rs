@@ -4,11 +4,41 @@ use std::{
     str,
 };
 
-use crate::ffi;
+use thiserror::Error;
+
+use crate::{ColorFamily, SampleType, ffi};
 
 pub type VideoFormat = ffi::VSVideoFormat;
 pub type AudioFormat = ffi::VSAudioFormat;
 
+/// Error returned by [`Core::try_query_video_format`] and [`Core::try_query_audio_format`].
+///
+/// [`Core::try_query_video_format`]: crate::core::Core::try_query_video_format
+/// [`Core::try_query_audio_format`]: crate::core::Core::try_query_audio_format
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Error)]
+pub enum FormatError {
+    #[error(
+        "Invalid video format: {color_family:?} {sample_type:?} with {bits_per_sample} bits and \
+         subsampling {subsampling_w}x{subsampling_h}"
+    )]
+    Video {
+        color_family: ColorFamily,
+        sample_type: SampleType,
+        bits_per_sample: i32,
+        subsampling_w: i32,
+        subsampling_h: i32,
+    },
+    #[error(
+        "Invalid audio format: {sample_type:?} with {bits_per_sample} bits and channel layout \
+         {channel_layout:#x}"
+    )]
+    Audio {
+        sample_type: SampleType,
+        bits_per_sample: i32,
+        channel_layout: u64,
+    },
+}
+
 pub(crate) struct FormatName {
     pub buffer: [u8; 32],
 }
@@ -1,16 +1,30 @@
-use crate::{api::Api, ffi, map::Map};
+use std::borrow::Borrow;
+
+use thiserror::Error;
+
+use crate::{
+    api::Api,
+    core::keep_alive::{CoreObject, KeepAlive},
+    ffi,
+    map::Map,
+};
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Function {
     handle: *const ffi::VSFunction,
     api: Api,
+    keep_alive: KeepAlive,
 }
 
 unsafe impl Send for Function {}
 
 impl Function {
     pub(crate) unsafe fn from_ptr(ptr: *mut ffi::VSFunction, api: Api) -> Self {
-        Self { handle: ptr, api }
+        Self {
+            handle: ptr,
+            api,
+            keep_alive: KeepAlive::NONE,
+        }
     }
 
     #[must_use]
@@ -18,13 +32,29 @@ impl Function {
         self.handle.cast_mut()
     }
 
-    pub fn call(&mut self, in_: &Map, out: &mut Map) {
-        unsafe {
-            (self.api.callFunction)(self.as_ptr(), in_.as_ptr(), out.as_ptr());
+    /// Calls the function with `args` and returns its output map.
+    ///
+    /// # Errors
+    ///
+    /// Return [`CallError`] with the message the function left in its output map.
+    pub fn call(&self, args: impl Borrow<Map>) -> Result<Map, CallError> {
+        let out = unsafe {
+            let out = Map::from_ptr((self.api.createMap)(), self.api).kept_by(&self.keep_alive);
+            (self.api.callFunction)(self.as_ptr(), args.borrow().as_ptr(), out.as_ptr());
+            out
+        };
+        match out.get_error() {
+            Some(msg) => Err(CallError(msg.to_string_lossy().into_owned())),
+            None => Ok(out),
         }
     }
 }
 
+/// Error returned by [`Function::call`].
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+#[error("{0}")]
+pub struct CallError(pub String);
+
 impl Drop for Function {
     fn drop(&mut self) {
         unsafe { (self.api.freeFunction)(self.as_ptr()) }
@@ -34,5 +64,16 @@ impl Drop for Function {
 impl Clone for Function {
     fn clone(&self) -> Self {
         unsafe { Self::from_ptr((self.api.addFunctionRef)(self.as_ptr()), self.api) }
+            .kept_by(&self.keep_alive)
+    }
+}
+
+impl CoreObject for Function {
+    fn keep_alive(&self) -> &KeepAlive {
+        &self.keep_alive
+    }
+
+    fn keep_alive_mut(&mut self) -> &mut KeepAlive {
+        &mut self.keep_alive
     }
 }
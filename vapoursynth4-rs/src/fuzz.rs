@@ -0,0 +1,173 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Helpers for fuzzing the argument handling of filters, e.g. with `cargo fuzz`.
+//!
+//! The maps are built from the arguments of a signature, so the fuzzer mostly produces maps the
+//! filter can get from `VapourSynth`, with missing arguments, wrong types and extra keys mixed in.
+//!
+//! ```ignore
+//! #![no_main]
+//!
+//! libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+//!     vapoursynth4_rs::fuzz::fuzz_from_map::<BlurArgs>(data, Blur::ARGS, &[]);
+//! });
+//! ```
+
+use std::ffi::CStr;
+
+use arbitrary::{Result, Unstructured};
+
+use crate::{
+    core::{Core, CoreRef},
+    map::{AppendMode, FromMap, Key, KeyStr, Map, Value},
+    node::{FilterConstructor, VideoNode},
+    plugin::{Type, validate_signature},
+};
+
+/// Builds arguments for a function with `signature` from fuzzer input.
+///
+/// Each argument is left unset, or set to one or more values of its type or, sometimes, of
+/// another type. Video nodes are taken from `clips`, while audio nodes, frames and functions are
+/// left unset.
+///
+/// # Errors
+///
+/// Return [`arbitrary::Error`] if `u` can not provide the values.
+///
+/// # Panics
+///
+/// Panics if `signature` is not a valid argument string.
+pub fn arbitrary_map(u: &mut Unstructured, signature: &CStr, clips: &[VideoNode]) -> Result<Map> {
+    let mut map = Map::default();
+    for (key, ty, optional) in arguments(signature) {
+        let unset = if optional {
+            u.ratio(1, 2)?
+        } else {
+            u.ratio(1, 16)?
+        };
+        if !unset {
+            set_arbitrary(u, &mut map, &key, &ty, clips)?;
+        }
+    }
+    if u.ratio(1, 8)? {
+        let key = Key::new(format!("unknown{}", u.arbitrary::<u8>()?)).expect("valid key");
+        set_arbitrary(u, &mut map, &key, &Type::Int, clips)?;
+    }
+    Ok(map)
+}
+
+/// Reads `T` from a map built by [`arbitrary_map`] from `data`, so panics in its argument
+/// handling are found. Errors are ignored.
+///
+/// # Panics
+///
+/// Panics if `signature` is not a valid argument string, or if [`FromMap::from_map`] panics.
+pub fn fuzz_from_map<T: FromMap>(data: &[u8], signature: &CStr, clips: &[VideoNode]) {
+    if let Ok(map) = arbitrary_map(&mut Unstructured::new(data), signature, clips) {
+        _ = T::from_map(&map);
+    }
+}
+
+/// Calls [`FilterConstructor::create`] of `F` with a map built by [`arbitrary_map`] from `data`
+/// and [`Filter::ARGS`](crate::node::Filter::ARGS). Errors and created filters are dropped.
+///
+/// # Panics
+///
+/// Panics if the signature of `F` is not valid, or if the constructor panics.
+pub fn fuzz_constructor<F: FilterConstructor>(core: &Core, data: &[u8], clips: &[VideoNode]) {
+    if let Ok(map) = arbitrary_map(&mut Unstructured::new(data), F::ARGS, clips) {
+        // SAFETY: The core outlives the call.
+        let core = unsafe { CoreRef::from_ptr(core.as_ptr(), core.api()) };
+        _ = <F as FilterConstructor>::create(&map, core);
+    }
+}
+
+/// The name, type and whether it is optional of each argument of `signature`.
+fn arguments(signature: &CStr) -> Vec<(Key, Type, bool)> {
+    let signature = validate_signature(signature)
+        .to_str()
+        .expect("signatures are ASCII");
+    signature
+        .split(';')
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| {
+            let mut parts = arg.split(':');
+            let key = Key::new(parts.next().unwrap_or_default()).expect("validated name");
+            let ty = Type::from_args(parts.next().unwrap_or_default()).expect("validated type");
+            (key, ty, parts.any(|flag| flag == "opt"))
+        })
+        .collect()
+}
+
+fn set_arbitrary(
+    u: &mut Unstructured,
+    map: &mut Map,
+    key: &KeyStr,
+    ty: &Type,
+    clips: &[VideoNode],
+) -> Result<()> {
+    static OTHER_TYPES: [Type; 3] = [Type::Int, Type::Float, Type::Data];
+
+    let ty = if u.ratio(1, 8)? {
+        u.choose(&OTHER_TYPES)?
+    } else {
+        ty
+    };
+    let (element, count) = match ty {
+        Type::Array(element) => (&**element, u.int_in_range(0..=4)?),
+        ty => (ty, if u.ratio(1, 8)? { 2 } else { 1 }),
+    };
+    for _ in 0..count {
+        let value = match element {
+            Type::Int => Value::Int(u.arbitrary()?),
+            Type::Float => Value::Float(u.arbitrary()?),
+            Type::Data => {
+                let len = u.arbitrary_len::<u8>()?;
+                Value::Data(u.bytes(len)?)
+            }
+            Type::VNode if !clips.is_empty() => Value::VideoNode(u.choose(clips)?.clone()),
+            _ => return Ok(()),
+        };
+        // Appending a value of another type fails, which leaves the first type.
+        _ = map.set(key, value, AppendMode::Append);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_arguments() {
+        let args = arguments(c"clip:vnode;planes:int[]:opt;sigma:float:empty;");
+        assert_eq!(
+            args,
+            [
+                (Key::new("clip").unwrap(), Type::VNode, false),
+                (
+                    Key::new("planes").unwrap(),
+                    Type::Array(Box::new(Type::Int)),
+                    true
+                ),
+                (Key::new("sigma").unwrap(), Type::Float, false),
+            ]
+        );
+    }
+
+    #[cfg(feature = "link-library")]
+    #[test]
+    fn maps_from_bytes() {
+        use crate::key;
+
+        let data: Vec<u8> = (0..=255).collect();
+        let map =
+            arbitrary_map(&mut Unstructured::new(&data), c"a:int;b:data[]:opt;", &[]).unwrap();
+        assert!(map.len() <= 3);
+        _ = map.get(key!(c"a"), 0);
+    }
+}
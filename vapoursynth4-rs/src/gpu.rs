@@ -0,0 +1,122 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Uploading frame planes to `wgpu` textures.
+//!
+//! Every plane is uploaded to its own single channel texture of the [`texture_format`], without
+//! repacking, by passing the fields of a [`PlaneUpload`] to `Queue::write_texture`:
+//!
+//! ```ignore
+//! for (plane, upload) in frame.gpu_uploads()?.iter().enumerate() {
+//!     queue.write_texture(
+//!         textures[plane].as_image_copy(),
+//!         upload.data,
+//!         upload.layout,
+//!         upload.size,
+//!     );
+//! }
+//! ```
+
+use thiserror::Error;
+use wgpu_types::{Extent3d, TexelCopyBufferLayout, TextureFormat};
+
+use crate::{
+    SampleType,
+    frame::{VideoFormat, VideoFrame},
+};
+
+#[derive(Debug, Error)]
+#[error("No texture format can store the samples of the frame")]
+pub struct UnsupportedFormat;
+
+/// The data of one plane and how it is laid out, for `Queue::write_texture`.
+#[derive(Clone, Copy, Debug)]
+pub struct PlaneUpload<'a> {
+    /// The rows of the plane, including the padding at the end of each row.
+    pub data: &'a [u8],
+    /// The stride of the plane, which `write_texture` does not require to be aligned.
+    pub layout: TexelCopyBufferLayout,
+    /// The dimensions of the plane.
+    pub size: Extent3d,
+    /// The texture format the plane must be uploaded to.
+    pub format: TextureFormat,
+}
+
+/// The single channel texture format with the samples of `format`, if there is one.
+///
+/// Integer samples of 9 to 16 bits use [`TextureFormat::R16Unorm`], which needs
+/// `Features::TEXTURE_FORMAT_16BIT_NORM` and is normalized to 16 bits, so shaders have to scale
+/// samples of fewer bits by `65535 / ((1 << bits) - 1)`.
+#[must_use]
+pub fn texture_format(format: &VideoFormat) -> Option<TextureFormat> {
+    match (format.sample_type, format.bytes_per_sample) {
+        (SampleType::Integer, 1) => Some(TextureFormat::R8Unorm),
+        (SampleType::Integer, 2) => Some(TextureFormat::R16Unorm),
+        (SampleType::Float, 2) => Some(TextureFormat::R16Float),
+        (SampleType::Float, 4) => Some(TextureFormat::R32Float),
+        _ => None,
+    }
+}
+
+impl VideoFrame {
+    /// The uploads of the planes of the frame, which borrow its data.
+    ///
+    /// # Errors
+    ///
+    /// Return [`UnsupportedFormat`] if there is no [`texture_format`] for the samples.
+    pub fn gpu_uploads(&self) -> Result<Vec<PlaneUpload<'_>>, UnsupportedFormat> {
+        let format = self.get_video_format();
+        let texture = texture_format(format).ok_or(UnsupportedFormat)?;
+        Ok((0..format.num_planes)
+            .map(|plane| {
+                let stride = self.stride(plane).unsigned_abs();
+                let height = self.frame_height(plane).unsigned_abs();
+                // safety: a plane has `height` rows of `stride` bytes
+                let data = unsafe {
+                    std::slice::from_raw_parts(self.plane(plane), stride * height as usize)
+                };
+                PlaneUpload {
+                    data,
+                    layout: TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: u32::try_from(stride).ok(),
+                        rows_per_image: Some(height),
+                    },
+                    size: Extent3d {
+                        width: self.frame_width(plane).unsigned_abs(),
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    format: texture,
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorFamily;
+
+    #[test]
+    fn formats() {
+        let mut format = VideoFormat {
+            color_family: ColorFamily::YUV,
+            sample_type: SampleType::Integer,
+            bits_per_sample: 10,
+            bytes_per_sample: 2,
+            sub_sampling_w: 1,
+            sub_sampling_h: 1,
+            num_planes: 3,
+        };
+        assert_eq!(texture_format(&format), Some(TextureFormat::R16Unorm));
+        format.sample_type = SampleType::Float;
+        assert_eq!(texture_format(&format), Some(TextureFormat::R16Float));
+        format.bytes_per_sample = 8;
+        assert_eq!(texture_format(&format), None);
+    }
+}
@@ -0,0 +1,262 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Validated construction of [`VideoInfo`] and [`AudioInfo`].
+
+use bon::builder;
+use thiserror::Error;
+
+use crate::{
+    AudioInfo, ColorFamily, SampleType, VideoInfo,
+    frame::{AudioChannels, AudioFormat, VideoFormat},
+    rational::Rational,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Error)]
+pub enum InfoError {
+    #[error("Width and height must both be positive, or both 0 for a variable size")]
+    InvalidSize,
+    #[error("The size {width}x{height} is not a multiple of the subsampling")]
+    Subsampling { width: i32, height: i32 },
+    #[error("Invalid frame rate {num}/{den}")]
+    InvalidFps { num: i64, den: i64 },
+    #[error("The clip must have at least one frame")]
+    NoFrames,
+    #[error("Unsupported audio format: {0}")]
+    InvalidAudioFormat(&'static str),
+    #[error("The sample rate must be positive, got {0}")]
+    InvalidSampleRate(i32),
+    #[error("The clip must have between 1 and {} samples", i64::from(i32::MAX) * i64::from(AudioFormat::SAMPLES_PER_FRAME))]
+    InvalidSampleCount,
+}
+
+/// Builds a [`VideoInfo`], checking that the size fits the subsampling of a constant format and
+/// reducing the frame rate.
+///
+/// Leave out `fps` for a variable frame rate, and pass a width and height of 0 for a variable
+/// size.
+///
+/// ```
+/// use vapoursynth4_rs::{
+///     ColorFamily, SampleType, frame::VideoFormat, info::video_info,
+/// };
+///
+/// let format = VideoFormat {
+///     color_family: ColorFamily::YUV,
+///     sample_type: SampleType::Integer,
+///     bits_per_sample: 8,
+///     bytes_per_sample: 1,
+///     sub_sampling_w: 1,
+///     sub_sampling_h: 1,
+///     num_planes: 3,
+/// };
+/// let vi = video_info()
+///     .format(format)
+///     .width(1920)
+///     .height(1080)
+///     .fps(48000, 2002)
+///     .num_frames(100)
+///     .build()
+///     .unwrap();
+/// assert_eq!((vi.fps_num, vi.fps_den), (24000, 1001));
+/// ```
+///
+/// # Errors
+///
+/// Return [`InfoError`] describing the first invalid field.
+#[builder(builder_type = VideoInfoBuilder, finish_fn = build)]
+pub fn video_info(
+    format: VideoFormat,
+    width: i32,
+    height: i32,
+    #[builder(with = |num: i64, den: i64| (num, den))] fps: Option<(i64, i64)>,
+    num_frames: i32,
+) -> Result<VideoInfo, InfoError> {
+    if width < 0 || height < 0 || (width == 0) != (height == 0) {
+        return Err(InfoError::InvalidSize);
+    }
+    if format.color_family != ColorFamily::Undefined && !format.are_valid_dimensions(width, height)
+    {
+        return Err(InfoError::Subsampling { width, height });
+    }
+
+    let (fps_num, fps_den) = match fps {
+        None => (0, 0),
+        Some((num, den)) if num > 0 && den > 0 => Rational::new(num, den).into(),
+        Some((num, den)) => return Err(InfoError::InvalidFps { num, den }),
+    };
+    if num_frames <= 0 {
+        return Err(InfoError::NoFrames);
+    }
+
+    Ok(VideoInfo {
+        format,
+        fps_num,
+        fps_den,
+        width,
+        height,
+        num_frames,
+    })
+}
+
+/// Builds an [`AudioInfo`], checking the format and computing the number of frames from the
+/// number of samples.
+///
+/// The format is either a complete [`AudioFormat`], or set from its parts with
+/// [`layout`](AudioInfoBuilder::layout).
+///
+/// ```
+/// use vapoursynth4_rs::{SampleType, frame::AudioChannels, info::audio_info};
+///
+/// let ai = audio_info()
+///     .layout(SampleType::Integer, 16, AudioChannels::STEREO)
+///     .sample_rate(48000)
+///     .num_samples(48000)
+///     .build()
+///     .unwrap();
+/// assert_eq!(ai.num_frames, 16);
+/// assert_eq!(ai.format.bytes_per_sample, 2);
+/// ```
+///
+/// # Errors
+///
+/// Return [`InfoError`] describing the first invalid field.
+#[builder(builder_type = AudioInfoBuilder, finish_fn = build)]
+pub fn audio_info(
+    format: AudioFormat,
+    sample_rate: i32,
+    num_samples: i64,
+) -> Result<AudioInfo, InfoError> {
+    match (format.sample_type, format.bits_per_sample) {
+        (SampleType::Integer, 16..=32) | (SampleType::Float, 32) => {}
+        _ => return Err(InfoError::InvalidAudioFormat("bits per sample")),
+    }
+    let channels = AudioChannels::from_bits(format.channel_layout)
+        .filter(|c| !c.is_empty())
+        .ok_or(InfoError::InvalidAudioFormat("channel layout"))?;
+    if i64::from(channels.len()) != i64::from(format.num_channels) {
+        return Err(InfoError::InvalidAudioFormat("number of channels"));
+    }
+    if sample_rate <= 0 {
+        return Err(InfoError::InvalidSampleRate(sample_rate));
+    }
+
+    let samples_per_frame = i64::from(AudioFormat::SAMPLES_PER_FRAME);
+    let num_frames = if num_samples > 0 {
+        i32::try_from((num_samples - 1) / samples_per_frame + 1)
+            .map_err(|_| InfoError::InvalidSampleCount)?
+    } else {
+        return Err(InfoError::InvalidSampleCount);
+    };
+
+    Ok(AudioInfo {
+        format,
+        sample_rate,
+        num_samples,
+        num_frames,
+    })
+}
+
+impl<S: audio_info_builder::State> AudioInfoBuilder<S>
+where
+    S::Format: audio_info_builder::IsUnset,
+{
+    /// Sets the format from its sample type, bit depth and channels, like
+    /// [`Core::query_audio_format`](crate::core::Core::query_audio_format).
+    pub fn layout(
+        self,
+        sample_type: SampleType,
+        bits_per_sample: i32,
+        channels: AudioChannels,
+    ) -> AudioInfoBuilder<audio_info_builder::SetFormat<S>> {
+        self.format(AudioFormat {
+            sample_type,
+            bits_per_sample,
+            bytes_per_sample: if bits_per_sample <= 16 { 2 } else { 4 },
+            num_channels: channels.len().cast_signed(),
+            channel_layout: channels.bits(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SampleType;
+
+    fn yuv420() -> VideoFormat {
+        VideoFormat {
+            color_family: ColorFamily::YUV,
+            sample_type: SampleType::Integer,
+            bits_per_sample: 8,
+            bytes_per_sample: 1,
+            sub_sampling_w: 1,
+            sub_sampling_h: 1,
+            num_planes: 3,
+        }
+    }
+
+    #[test]
+    fn video() {
+        let vi = video_info()
+            .format(yuv420())
+            .width(640)
+            .height(480)
+            .fps(60, 2)
+            .num_frames(10)
+            .build()
+            .unwrap();
+        assert_eq!((vi.fps_num, vi.fps_den), (30, 1));
+
+        let build = |width, height, fps: (i64, i64)| {
+            video_info()
+                .format(yuv420())
+                .width(width)
+                .height(height)
+                .fps(fps.0, fps.1)
+                .num_frames(1)
+                .build()
+        };
+        assert_eq!(
+            build(641, 480, (1, 1)),
+            Err(InfoError::Subsampling {
+                width: 641,
+                height: 480
+            })
+        );
+        assert_eq!(build(0, 480, (1, 1)), Err(InfoError::InvalidSize));
+        assert_eq!(
+            build(640, 480, (1, 0)),
+            Err(InfoError::InvalidFps { num: 1, den: 0 })
+        );
+    }
+
+    #[test]
+    fn audio() {
+        let build = |bits, samples| {
+            audio_info()
+                .layout(SampleType::Integer, bits, AudioChannels::SURROUND_5_1)
+                .sample_rate(44100)
+                .num_samples(samples)
+                .build()
+        };
+        let ai = build(24, 3073).unwrap();
+        assert_eq!(ai.num_frames, 2);
+        assert_eq!(ai.format.num_channels, 6);
+        assert_eq!(ai.format.bytes_per_sample, 4);
+
+        assert_eq!(
+            build(8, 1),
+            Err(InfoError::InvalidAudioFormat("bits per sample"))
+        );
+        assert_eq!(build(16, 0), Err(InfoError::InvalidSampleCount));
+
+        let max = i64::from(i32::MAX) * i64::from(AudioFormat::SAMPLES_PER_FRAME);
+        assert_eq!(build(16, max).unwrap().num_frames, i32::MAX);
+        assert_eq!(build(16, max + 1), Err(InfoError::InvalidSampleCount));
+        assert_eq!(build(16, i64::MAX), Err(InfoError::InvalidSampleCount));
+    }
+}
@@ -8,25 +8,64 @@
 //! A safe wrapper for `VapourSynth` API version 4.
 
 pub mod api;
+pub mod compat;
 pub mod core;
+#[cfg(feature = "ffmpeg")]
+pub mod ffmpeg;
 pub mod frame;
 pub mod function;
+#[cfg(all(
+    feature = "fuzz",
+    any(feature = "link-library", feature = "runtime-loading")
+))]
+pub mod fuzz;
+#[cfg(feature = "wgpu")]
+pub mod gpu;
+pub mod info;
 pub mod map;
 pub mod node;
+#[cfg(feature = "opencv")]
+pub mod opencv;
+pub mod output;
+#[cfg(feature = "playback")]
+pub mod playback;
 pub mod plugin;
-pub mod sciprt;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+#[cfg(all(feature = "preview-server", unix))]
+pub mod preview;
+#[cfg(feature = "pyo3")]
+pub mod python;
+pub mod rational;
+#[cfg(feature = "script")]
+pub mod script;
+#[cfg(feature = "shm")]
+pub mod shm;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "thumbnails")]
+pub mod thumbnails;
 pub mod utils;
 
 pub use vapoursynth4_sys as ffi;
 
+// The derive macros refer to `::vapoursynth4_rs`, also in the tests of this crate.
+#[cfg(all(test, feature = "macros"))]
+extern crate self as vapoursynth4_rs;
+
+#[cfg(feature = "script")]
+#[doc(hidden)]
+pub use script as sciprt;
+
 mod _private {
     pub trait Sealed {}
 }
 
 pub type ColorFamily = ffi::VSColorFamily;
 pub type SampleType = ffi::VSSampleType;
+pub type PresetVideoFormat = ffi::VSPresetVideoFormat;
 
 pub type VideoInfo = ffi::VSVideoInfo;
 pub type AudioInfo = ffi::VSAudioInfo;
 
-pub use ffi::VAPOURSYNTH_API_VERSION;
+pub use ffi::{VAPOURSYNTH_API_MAJOR, VAPOURSYNTH_API_MINOR, VAPOURSYNTH_API_VERSION};
@@ -1,29 +1,44 @@
 use std::{
     ffi::{CStr, c_char, c_int},
-    mem::ManuallyDrop,
+    mem::{self, ManuallyDrop},
     ops::{Deref, DerefMut},
+    ptr::NonNull,
 };
 
 use thiserror::Error;
 
 use crate::{
     api::Api,
+    core::keep_alive::{CoreObject, CoreOwner, KeepAlive},
     ffi,
     frame::{AudioFrame, Frame, VideoFrame, internal::FrameFromPtr},
     function::Function,
     node::{AudioNode, Node, VideoNode},
 };
 
+mod convert;
 mod key;
+#[cfg(feature = "serde-props")]
+mod serialized;
+pub use convert::*;
 pub use key::*;
+#[cfg(feature = "serde-props")]
+pub use serialized::*;
+#[cfg(feature = "macros")]
+pub use vapoursynth4_rs_macros::{FromMap, IntoMap};
 
 // MARK: MapRef
 
 /// A borrowed reference to a [`ffi::VSMap`].
+// Derefs to `Map` by casting the pointer, so both have the same fields in a fixed order.
+#[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct MapRef<'m> {
     handle: *const ffi::VSMap,
     api: Api,
+    // Always `None`, the map is kept alive by its owner.
+    owner: Option<NonNull<CoreOwner>>,
+    borrowed: bool,
     marker: std::marker::PhantomData<&'m ()>,
 }
 
@@ -37,6 +52,8 @@ impl MapRef<'_> {
         Self {
             handle: ptr,
             api,
+            owner: None,
+            borrowed: true,
             marker: std::marker::PhantomData,
         }
     }
@@ -65,10 +82,17 @@ impl DerefMut for MapRef<'_> {
 // MARK: Map
 
 /// An owned [`ffi::VSMap`].
+///
+/// The nodes, frames and functions stored in it keep their core alive for as long as the map.
+// `MapRef` derefs to it, see there.
+#[repr(C)]
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Map {
     handle: *const ffi::VSMap,
     api: Api,
+    keep_alive: KeepAlive,
+    // Set through `MapRef`, whose map is owned by someone else and must not take a keepalive.
+    borrowed: bool,
 }
 
 unsafe impl Send for Map {}
@@ -78,7 +102,12 @@ impl Map {
     #[must_use]
     pub(crate) unsafe fn from_ptr(ptr: *mut ffi::VSMap, api: Api) -> Self {
         debug_assert!(!ptr.is_null());
-        Self { handle: ptr, api }
+        Self {
+            handle: ptr,
+            api,
+            keep_alive: KeepAlive::NONE,
+            borrowed: false,
+        }
     }
 
     /// Returns a raw pointer to the wrapped value.
@@ -86,6 +115,23 @@ impl Map {
     pub fn as_ptr(&self) -> *mut ffi::VSMap {
         self.handle.cast_mut()
     }
+
+    /// Keeps the core of `object` alive, as the map now holds a reference to it.
+    fn adopt(&mut self, object: &impl CoreObject) {
+        if !self.borrowed {
+            self.keep_alive.adopt(object.keep_alive());
+        }
+    }
+}
+
+impl CoreObject for Map {
+    fn keep_alive(&self) -> &KeepAlive {
+        &self.keep_alive
+    }
+
+    fn keep_alive_mut(&mut self) -> &mut KeepAlive {
+        &mut self.keep_alive
+    }
 }
 
 impl Map {
@@ -226,7 +272,7 @@ impl Map {
     pub fn get_function(&self, key: &KeyStr, index: i32) -> Result<Function, MapPropertyError> {
         unsafe {
             self.get_internal(self.api.mapGetFunction, key, index)
-                .map(|p| Function::from_ptr(p, self.api))
+                .map(|p| Function::from_ptr(p, self.api).kept_by(&self.keep_alive))
         }
     }
 
@@ -236,7 +282,7 @@ impl Map {
     pub fn get_video_node(&self, key: &KeyStr, index: i32) -> Result<VideoNode, MapPropertyError> {
         unsafe {
             self.get_internal(self.api.mapGetNode, key, index)
-                .map(|p| VideoNode::from_ptr(p, self.api))
+                .map(|p| VideoNode::from_ptr(p, self.api).kept_by(&self.keep_alive))
         }
     }
 
@@ -246,7 +292,7 @@ impl Map {
     pub fn get_audio_node(&self, key: &KeyStr, index: i32) -> Result<AudioNode, MapPropertyError> {
         unsafe {
             self.get_internal(self.api.mapGetNode, key, index)
-                .map(|p| AudioNode::from_ptr(p, self.api))
+                .map(|p| AudioNode::from_ptr(p, self.api).kept_by(&self.keep_alive))
         }
     }
 
@@ -260,7 +306,7 @@ impl Map {
     ) -> Result<VideoFrame, MapPropertyError> {
         unsafe {
             self.get_internal(self.api.mapGetFrame, key, index)
-                .map(|p| VideoFrame::from_ptr(p, self.api))
+                .map(|p| VideoFrame::from_ptr(p, self.api).kept_by(&self.keep_alive))
         }
     }
 
@@ -274,7 +320,7 @@ impl Map {
     ) -> Result<AudioFrame, MapPropertyError> {
         unsafe {
             self.get_internal(self.api.mapGetFrame, key, index)
-                .map(|p| AudioFrame::from_ptr(p, self.api))
+                .map(|p| AudioFrame::from_ptr(p, self.api).kept_by(&self.keep_alive))
         }
     }
 
@@ -293,21 +339,14 @@ impl Map {
                     use ffi::VSDataTypeHint as dt;
 
                     let size = self.get_internal(self.api.mapGetDataSize, key, index)?;
+                    let ptr = self.get_internal(self.api.mapGetData, key, index)?;
                     #[allow(clippy::cast_sign_loss)]
-                    match self.get_internal(self.api.mapGetDataTypeHint, key, index)? {
-                        dt::Unknown | dt::Binary => {
-                            let ptr = self.get_internal(self.api.mapGetData, key, index)?;
-                            Ok(Value::Data(std::slice::from_raw_parts(
-                                ptr.cast(),
-                                size as _,
-                            )))
-                        }
-                        dt::Utf8 => {
-                            let ptr = self.get_internal(self.api.mapGetData, key, index)?;
-                            Ok(Value::Utf8(std::str::from_utf8_unchecked(
-                                std::slice::from_raw_parts(ptr.cast(), size as _),
-                            )))
-                        }
+                    let data = std::slice::from_raw_parts(ptr.cast(), size as _);
+                    // Binary, unknown and any later hints are returned as bytes.
+                    if self.get_internal(self.api.mapGetDataTypeHint, key, index)? == dt::Utf8 {
+                        Ok(Value::Utf8(std::str::from_utf8_unchecked(data)))
+                    } else {
+                        Ok(Value::Data(data))
                     }
                 }
                 t::Function => self.get_function(key, index).map(Value::Function),
@@ -315,6 +354,7 @@ impl Map {
                 t::AudioNode => self.get_audio_node(key, index).map(Value::AudioNode),
                 t::VideoFrame => self.get_video_frame(key, index).map(Value::VideoFrame),
                 t::AudioFrame => self.get_audio_frame(key, index).map(Value::AudioFrame),
+                _ => Err(MapPropertyError::InvalidType),
             }
         }
     }
@@ -410,11 +450,51 @@ impl Map {
         key: &KeyStr,
         val: Value,
         append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        // Owned references are handed over instead of being added and released again.
+        match val {
+            Value::VideoNode(node) => self.consume_node(key, node, append),
+            Value::AudioNode(node) => self.consume_node(key, node, append),
+            Value::VideoFrame(frame) => self.consume_frame(key, frame, append),
+            Value::AudioFrame(frame) => self.consume_frame(key, frame, append),
+            Value::Function(function) => self.consume_function(key, function, append),
+            val => self.set_value(key, &val, append),
+        }
+    }
+
+    /// Replaces the values of many keys, e.g. the properties a filter forwards for every frame.
+    ///
+    /// This is a convenience over calling [`Map::set`] for each entry: the API has no batch
+    /// setter, so every entry is still one call into the core. The values are only borrowed,
+    /// and the core takes its own references to nodes, frames and functions. An entry replaces
+    /// the value of an earlier entry with the same key. Borrowed maps get it through [`MapRef`],
+    /// e.g. the properties of a frame.
+    ///
+    /// # Errors
+    ///
+    /// Return [`MapPropertyError::InvalidType`] for the first value that could not be stored. The
+    /// entries before it are stored.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the [`Value::Data`]'s or [`Value::Utf8`]'s len is larger than [`i32::MAX`]
+    pub fn set_many(&mut self, entries: &[(&KeyStr, Value)]) -> Result<(), MapPropertyError> {
+        entries
+            .iter()
+            .try_for_each(|(key, val)| self.set_value(key, val, AppendMode::Replace))
+    }
+
+    #[inline]
+    fn set_value(
+        &mut self,
+        key: &KeyStr,
+        val: &Value,
+        append: AppendMode,
     ) -> Result<(), MapPropertyError> {
         unsafe {
             match val {
-                Value::Int(val) => self.set_internal(self.api.mapSetInt, key, val, append),
-                Value::Float(val) => self.set_internal(self.api.mapSetFloat, key, val, append),
+                Value::Int(val) => self.set_internal(self.api.mapSetInt, key, *val, append),
+                Value::Float(val) => self.set_internal(self.api.mapSetFloat, key, *val, append),
                 Value::Data(val) => handle_set_error((self.api.mapSetData)(
                     self.as_ptr(),
                     key.as_ptr(),
@@ -432,18 +512,23 @@ impl Map {
                     append,
                 )),
                 Value::VideoNode(val) => {
+                    self.adopt(val);
                     self.set_internal(self.api.mapSetNode, key, val.as_ptr(), append)
                 }
                 Value::AudioNode(val) => {
+                    self.adopt(val);
                     self.set_internal(self.api.mapSetNode, key, val.as_ptr(), append)
                 }
                 Value::VideoFrame(val) => {
+                    self.adopt(val);
                     self.set_internal(self.api.mapSetFrame, key, val.as_ptr(), append)
                 }
                 Value::AudioFrame(val) => {
+                    self.adopt(val);
                     self.set_internal(self.api.mapSetFrame, key, val.as_ptr(), append)
                 }
                 Value::Function(val) => {
+                    self.adopt(val);
                     self.set_internal(self.api.mapSetFunction, key, val.as_ptr(), append)
                 }
             }
@@ -495,6 +580,10 @@ impl Map {
         node: impl Node,
         append: AppendMode,
     ) -> Result<(), MapPropertyError> {
+        let mut node = node;
+        self.adopt(&node);
+        // The map takes the reference, but the keepalive is still ours to drop.
+        drop(mem::take(node.keep_alive_mut()));
         let node = ManuallyDrop::new(node);
         unsafe {
             handle_set_error((self.api.mapConsumeNode)(
@@ -515,6 +604,10 @@ impl Map {
         frame: impl Frame,
         append: AppendMode,
     ) -> Result<(), MapPropertyError> {
+        let mut frame = frame;
+        self.adopt(&frame);
+        // The map takes the reference, but the keepalive is still ours to drop.
+        drop(mem::take(frame.keep_alive_mut()));
         let frame = ManuallyDrop::new(frame);
         unsafe {
             handle_set_error((self.api.mapConsumeFrame)(
@@ -535,6 +628,10 @@ impl Map {
         function: Function,
         append: AppendMode,
     ) -> Result<(), MapPropertyError> {
+        let mut function = function;
+        self.adopt(&function);
+        // The map takes the reference, but the keepalive is still ours to drop.
+        drop(mem::take(function.keep_alive_mut()));
         let function = ManuallyDrop::new(function);
         unsafe {
             handle_set_error((self.api.mapConsumeFunction)(
@@ -560,12 +657,12 @@ impl Clone for Map {
         unsafe {
             let ptr = (self.api.createMap)();
             (self.api.copyMap)(self.as_ptr(), ptr);
-            Self::from_ptr(ptr, self.api)
+            Self::from_ptr(ptr, self.api).kept_by(&self.keep_alive)
         }
     }
 }
 
-#[cfg(feature = "link-library")]
+#[cfg(any(feature = "link-library", feature = "runtime-loading"))]
 impl Default for Map {
     fn default() -> Self {
         unsafe {
@@ -587,7 +684,7 @@ fn handle_get_error<T>(res: T, error: ffi::VSMapPropertyError) -> Result<T, MapP
         e::Unset => Err(pe::KeyNotFound),
         e::Type => Err(pe::InvalidType),
         e::Index => Err(pe::IndexOutOfBound),
-        e::Error => Err(pe::MapError),
+        _ => Err(pe::MapError),
     }
 }
 
@@ -688,6 +785,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn set_many() -> TestResult {
+        use crate::key;
+
+        let mut map = Map::default();
+        map.set(key!(c"b"), Value::Int(1), AppendMode::Replace)?;
+        map.set_many(&[
+            (key!(c"a"), Value::Int(42)),
+            (key!(c"b"), Value::Float(0.5)),
+            (key!(c"c"), Value::Utf8("BT.709")),
+        ])?;
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get_int(key!(c"a"), 0)?, 42);
+        assert_eq!(map.num_elements(key!(c"b")), Some(1));
+        assert_eq!(map.get_utf8(key!(c"c"), 0)?, "BT.709");
+        Ok(())
+    }
+
+    #[test]
+    fn set_many_props() -> TestResult {
+        use crate::{core::Core, frame::Frame, key};
+
+        let core = Core::builder().build();
+        let format = core.get_video_format_by_id(crate::PresetVideoFormat::Gray8);
+        let mut frame = core.new_video_frame(&format, 16, 16, None);
+        let mut props = frame.properties_mut().unwrap();
+        props.set_many(&[
+            (key!(c"_Matrix"), Value::Int(1)),
+            (key!(c"_SceneChangePrev"), Value::Int(0)),
+        ])?;
+        assert_eq!(props.get_int(key!(c"_Matrix"), 0)?, 1);
+        assert_eq!(props.num_elements(key!(c"_SceneChangePrev")), Some(1));
+        Ok(())
+    }
+
     #[test]
     fn len() -> TestResult {
         let mut map = Map::default();
@@ -783,4 +915,127 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    #[cfg(feature = "macros")]
+    fn from_map() -> TestResult {
+        use crate::key;
+
+        #[derive(FromMap, Debug, PartialEq)]
+        struct Args {
+            width: i64,
+            ratio: f64,
+            matrix: String,
+            planes: Vec<i64>,
+            crop: Option<i64>,
+        }
+
+        let mut map = Map::default();
+        640_i64.set_field(&mut map, key!(c"width"))?;
+        1.5_f64.set_field(&mut map, key!(c"ratio"))?;
+        "709".to_owned().set_field(&mut map, key!(c"matrix"))?;
+        vec![0_i64, 2].set_field(&mut map, key!(c"planes"))?;
+        assert_eq!(
+            Args::from_map(&map)?,
+            Args {
+                width: 640,
+                ratio: 1.5,
+                matrix: "709".to_owned(),
+                planes: vec![0, 2],
+                crop: None,
+            }
+        );
+
+        map.set(key!(c"width"), Value::Float(1.0), AppendMode::Replace)?;
+        assert_eq!(
+            Args::from_map(&map).unwrap_err().to_string(),
+            "argument `width` has the wrong type"
+        );
+        map.delete_key(key!(c"width"));
+        assert_eq!(
+            Args::from_map(&map).unwrap_err().to_string(),
+            "argument `width` is required"
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "macros")]
+    fn into_map() -> TestResult {
+        use crate::key;
+
+        #[derive(IntoMap, FromMap, Debug, PartialEq)]
+        struct Output {
+            frames: i64,
+            scene_changes: Option<Vec<i64>>,
+        }
+
+        let mut map = Map::default();
+        Output {
+            frames: 10,
+            scene_changes: Some(vec![3, 7]),
+        }
+        .into_map(&mut map)?;
+        assert_eq!(map.get_int_array(key!(c"scene_changes"))?, &[3, 7]);
+        assert_eq!(
+            Output::from_map(&map)?,
+            Output {
+                frames: 10,
+                scene_changes: Some(vec![3, 7]),
+            }
+        );
+
+        let mut map = Map::default();
+        Output {
+            frames: 10,
+            scene_changes: None,
+        }
+        .into_map(&mut map)?;
+        assert_eq!(map.num_elements(key!(c"scene_changes")), None);
+        assert_eq!(Output::from_map(&map)?.scene_changes, None);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "macros")]
+    fn from_map_attributes() -> TestResult {
+        use crate::key;
+
+        #[derive(FromMap, Debug, PartialEq)]
+        struct Args {
+            #[vs(default = 16, range = 0..=255)]
+            amount: i64,
+            #[vs(rename = "min", range = 0..)]
+            minimum: Option<i64>,
+        }
+
+        let mut map = Map::default();
+        assert_eq!(
+            Args::from_map(&map)?,
+            Args {
+                amount: 16,
+                minimum: None,
+            }
+        );
+
+        300_i64.set_field(&mut map, key!(c"amount"))?;
+        assert_eq!(
+            Args::from_map(&map).unwrap_err().to_string(),
+            "argument `amount` must be in 0..=255"
+        );
+
+        map.clear();
+        4_i64.set_field(&mut map, key!(c"min"))?;
+        assert_eq!(Args::from_map(&map)?.minimum, Some(4));
+        (-1_i64).set_field(&mut map, key!(c"min"))?;
+        assert_eq!(
+            Args::from_map(&map).unwrap_err().to_string(),
+            "argument `min` must be in 0.."
+        );
+
+        map.clear();
+        4_i64.set_field(&mut map, key!(c"minimum"))?;
+        assert_eq!(Args::from_map(&map)?.minimum, None);
+        Ok(())
+    }
 }
@@ -0,0 +1,428 @@
+use std::{
+    ffi::{CStr, CString},
+    fmt::{Debug, Display},
+};
+
+use crate::{
+    frame::{AudioFrame, VideoFrame},
+    function::Function,
+    node::{AudioNode, VideoNode},
+    utils::ToCString,
+};
+
+use super::{AppendMode, KeyStr, Map, MapPropertyError, Value};
+
+// MARK: Values
+
+/// A single element that can be stored in a [`Map`].
+pub trait MapValue: Sized {
+    /// # Errors
+    ///
+    /// Return [`MapPropertyError`] if the element is missing or has another type.
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError>;
+
+    /// # Errors
+    ///
+    /// Return [`MapPropertyError`] if the `key`'s type is not the same with `self`.
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError>;
+}
+
+impl MapValue for i64 {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_int(key, index)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.set(key, Value::Int(self), append)
+    }
+}
+
+impl MapValue for i32 {
+    /// Saturates values outside of the range of [`i32`].
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_int_saturated(key, index)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.set(key, Value::Int(self.into()), append)
+    }
+}
+
+impl MapValue for bool {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_int(key, index).map(|v| v != 0)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.set(key, Value::Int(self.into()), append)
+    }
+}
+
+impl MapValue for f64 {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_float(key, index)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.set(key, Value::Float(self), append)
+    }
+}
+
+impl MapValue for f32 {
+    /// Saturates values outside of the range of [`f32`].
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_float_saturated(key, index)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.set(key, Value::Float(self.into()), append)
+    }
+}
+
+impl MapValue for String {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_utf8(key, index).map(ToOwned::to_owned)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.set(key, Value::Utf8(&self), append)
+    }
+}
+
+impl MapValue for VideoNode {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_video_node(key, index)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.consume_node(key, self, append)
+    }
+}
+
+impl MapValue for AudioNode {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_audio_node(key, index)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.consume_node(key, self, append)
+    }
+}
+
+impl MapValue for VideoFrame {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_video_frame(key, index)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.consume_frame(key, self, append)
+    }
+}
+
+impl MapValue for AudioFrame {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_audio_frame(key, index)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.consume_frame(key, self, append)
+    }
+}
+
+impl MapValue for Function {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        map.get_function(key, index)
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        map.consume_function(key, self, append)
+    }
+}
+
+// MARK: Fields
+
+/// A whole [`Map`] entry, i.e. every element stored under a key.
+///
+/// Implemented for every [`MapValue`] (the first element), [`Vec`] of them (all elements),
+/// and [`Option`] of those (a missing key is [`None`]).
+pub trait MapField: Sized {
+    /// # Errors
+    ///
+    /// Return [`MapPropertyError`] if the entry is missing or has another type.
+    fn get_field(map: &Map, key: &KeyStr) -> Result<Self, MapPropertyError>;
+
+    /// Replaces the entry with `self`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`MapPropertyError`] if the value could not be stored.
+    fn set_field(self, map: &mut Map, key: &KeyStr) -> Result<(), MapPropertyError>;
+}
+
+impl<T: MapValue> MapField for T {
+    fn get_field(map: &Map, key: &KeyStr) -> Result<Self, MapPropertyError> {
+        T::get_value(map, key, 0)
+    }
+
+    fn set_field(self, map: &mut Map, key: &KeyStr) -> Result<(), MapPropertyError> {
+        self.set_value(map, key, AppendMode::Replace)
+    }
+}
+
+impl<T: MapValue> MapField for Vec<T> {
+    fn get_field(map: &Map, key: &KeyStr) -> Result<Self, MapPropertyError> {
+        let len = map.num_elements(key).ok_or(MapPropertyError::KeyNotFound)?;
+        (0..len).map(|i| T::get_value(map, key, i)).collect()
+    }
+
+    fn set_field(self, map: &mut Map, key: &KeyStr) -> Result<(), MapPropertyError> {
+        map.delete_key(key);
+        self.into_iter()
+            .try_for_each(|v| v.set_value(map, key, AppendMode::Append))
+    }
+}
+
+impl<T: MapField> MapField for Option<T> {
+    fn get_field(map: &Map, key: &KeyStr) -> Result<Self, MapPropertyError> {
+        match T::get_field(map, key) {
+            Ok(v) => Ok(Some(v)),
+            Err(MapPropertyError::KeyNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn set_field(self, map: &mut Map, key: &KeyStr) -> Result<(), MapPropertyError> {
+        match self {
+            Some(v) => v.set_field(map, key),
+            None => Ok(()),
+        }
+    }
+}
+
+// MARK: Structs
+
+/// Types that can be read from a [`Map`], usually the arguments of a filter.
+///
+/// With the `macros` feature, this can be derived for structs with named fields whose types
+/// implement [`MapField`]. Each field is read from the key of the same name, which
+/// `#[vs(...)]` attributes can adjust:
+///
+/// - `rename = "name"` reads the key `name` instead.
+/// - `default = expr` uses `expr` when the key is missing.
+/// - `range = expr` rejects values not contained in the range `expr`.
+///
+/// ```
+/// use vapoursynth4_rs::{map::FromMap, node::VideoNode};
+///
+/// #[derive(FromMap)]
+/// struct Args {
+///     clip: VideoNode,
+///     #[vs(default = 1.0, range = 0.0..=10.0)]
+///     strength: f64,
+///     #[vs(rename = "min", range = 0..)]
+///     minimum: Option<i64>,
+///     planes: Option<Vec<i64>>,
+/// }
+/// ```
+pub trait FromMap: Sized {
+    /// # Errors
+    ///
+    /// Return [`ArgumentError`] describing the first argument that could not be read.
+    fn from_map(map: &Map) -> Result<Self, ArgumentError>;
+}
+
+/// Types that can be written into a [`Map`], usually the return values of a filter or function.
+///
+/// With the `macros` feature, this can be derived for structs with named fields whose types
+/// implement [`MapField`]. Each field is written to the key of the same name, and [`None`]
+/// fields are left unset.
+///
+/// ```
+/// use vapoursynth4_rs::{map::IntoMap, node::VideoNode};
+///
+/// #[derive(IntoMap)]
+/// struct Output {
+///     clip: VideoNode,
+///     scene_changes: Option<Vec<i64>>,
+/// }
+/// ```
+pub trait IntoMap {
+    /// # Errors
+    ///
+    /// Return [`MapPropertyError`] if a value could not be stored.
+    fn into_map(self, map: &mut Map) -> Result<(), MapPropertyError>;
+}
+
+/// An [`IntoMap`] that writes its entries with a closure, usually built by [`vsmap!`].
+///
+/// [`vsmap!`]: crate::vsmap
+pub struct MapFn<F>(pub F);
+
+impl<F> IntoMap for MapFn<F>
+where
+    F: FnOnce(&mut Map) -> Result<(), MapPropertyError>,
+{
+    fn into_map(self, map: &mut Map) -> Result<(), MapPropertyError> {
+        (self.0)(map)
+    }
+}
+
+/// Copies every entry, replacing the entries of the same key.
+impl IntoMap for &Map {
+    fn into_map(self, map: &mut Map) -> Result<(), MapPropertyError> {
+        map.adopt(self);
+        unsafe { (map.api.copyMap)(self.as_ptr(), map.as_ptr()) };
+        Ok(())
+    }
+}
+
+/// Builds the arguments of a function call as an [`IntoMap`](crate::map::IntoMap), e.g. for
+/// [`Core::invoke_with`](crate::core::Core::invoke_with).
+///
+/// Each value is stored with [`MapField::set_field`](crate::map::MapField::set_field), so it may
+/// be a single value, a [`Vec`] or an [`Option`] that is left unset when [`None`]. A key without a
+/// value takes the variable of the same name.
+///
+/// ```
+/// # fn crop(core: &vapoursynth4_rs::core::Core, clip: vapoursynth4_rs::node::VideoNode) {
+/// use vapoursynth4_rs::vsmap;
+///
+/// let cropped = core.invoke_with(c"std", c"Crop", vsmap! { clip: clip, left: 8, right: 8 });
+/// # }
+/// ```
+#[macro_export]
+macro_rules! vsmap {
+    (@value $key:ident) => {
+        $key
+    };
+    (@value $key:ident $value:expr) => {
+        $value
+    };
+    ($($key:ident $(: $value:expr)?),* $(,)?) => {
+        $crate::map::MapFn(move |map: &mut $crate::map::Map| {
+            $(
+                $crate::map::MapField::set_field(
+                    $crate::vsmap!(@value $key $($value)?),
+                    map,
+                    const {
+                        match ::std::ffi::CStr::from_bytes_with_nul(
+                            concat!(stringify!($key), "\0").as_bytes(),
+                        ) {
+                            Ok(key) => $crate::map::KeyStr::from_cstr(key),
+                            Err(_) => unreachable!(),
+                        }
+                    },
+                )?;
+            )*
+            Ok::<(), $crate::map::MapPropertyError>(())
+        })
+    };
+}
+
+/// Error produced by [`FromMap`], with a message suitable for reporting to `VapourSynth`.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct ArgumentError {
+    message: CString,
+}
+
+impl ArgumentError {
+    #[must_use]
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into().into_cstring_lossy(),
+        }
+    }
+
+    /// Describes why the argument `key` could not be read.
+    #[must_use]
+    pub fn from_property(key: &KeyStr, error: MapPropertyError) -> Self {
+        use MapPropertyError as pe;
+
+        Self::new(match error {
+            pe::KeyNotFound => format!("argument `{key}` is required"),
+            pe::InvalidType => format!("argument `{key}` has the wrong type"),
+            pe::IndexOutOfBound => format!("argument `{key}` has too few elements"),
+            pe::MapError => format!("argument `{key}` could not be read: {error}"),
+        })
+    }
+}
+
+impl AsRef<CStr> for ArgumentError {
+    fn as_ref(&self) -> &CStr {
+        &self.message
+    }
+}
+
+impl Display for ArgumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message.to_string_lossy())
+    }
+}
+
+impl Debug for ArgumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ArgumentError").field(&self.message).finish()
+    }
+}
+
+impl std::error::Error for ArgumentError {}
@@ -0,0 +1,112 @@
+//! Storing Rust values in binary properties as CBOR, for filters which pass structured
+//! metadata such as motion vectors or detections to each other.
+
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+use super::{AppendMode, KeyStr, Map, MapPropertyError, MapValue, Value};
+
+#[derive(Debug, Error)]
+pub enum SerializedPropError {
+    #[error(transparent)]
+    Property(#[from] MapPropertyError),
+    #[error("Failed to serialize the property: {0}")]
+    Serialize(String),
+    #[error("Failed to deserialize the property: {0}")]
+    Deserialize(String),
+}
+
+impl Map {
+    /// Stores `value` as CBOR in a binary element of `key`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`SerializedPropError::Serialize`] if `value` can not be serialized, or
+    /// [`SerializedPropError::Property`] if `key` holds another type.
+    pub fn set_serialized<T: Serialize + ?Sized>(
+        &mut self,
+        key: &KeyStr,
+        value: &T,
+        append: AppendMode,
+    ) -> Result<(), SerializedPropError> {
+        let mut data = Vec::new();
+        ciborium::into_writer(value, &mut data)
+            .map_err(|e| SerializedPropError::Serialize(e.to_string()))?;
+        self.set(key, Value::Data(&data), append)?;
+        Ok(())
+    }
+
+    /// Reads the element `index` of `key` stored by [`Map::set_serialized`].
+    ///
+    /// # Errors
+    ///
+    /// Return [`SerializedPropError::Deserialize`] if the element is not a `T`, or
+    /// [`SerializedPropError::Property`] if it is missing or not binary.
+    pub fn get_serialized<T: DeserializeOwned>(
+        &self,
+        key: &KeyStr,
+        index: i32,
+    ) -> Result<T, SerializedPropError> {
+        let data = self.get_binary(key, index)?;
+        ciborium::from_reader(data).map_err(|e| SerializedPropError::Deserialize(e.to_string()))
+    }
+}
+
+/// A value stored with [`Map::set_serialized`], so it can be a field of `FromMap` and `IntoMap`
+/// types. Failing to (de)serialize it is reported as [`MapPropertyError::InvalidType`].
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Serialized<T>(pub T);
+
+impl<T: Serialize + DeserializeOwned> MapValue for Serialized<T> {
+    fn get_value(map: &Map, key: &KeyStr, index: i32) -> Result<Self, MapPropertyError> {
+        match map.get_serialized(key, index) {
+            Ok(value) => Ok(Self(value)),
+            Err(SerializedPropError::Property(e)) => Err(e),
+            Err(_) => Err(MapPropertyError::InvalidType),
+        }
+    }
+
+    fn set_value(
+        self,
+        map: &mut Map,
+        key: &KeyStr,
+        append: AppendMode,
+    ) -> Result<(), MapPropertyError> {
+        match map.set_serialized(key, &self.0, append) {
+            Ok(()) => Ok(()),
+            Err(SerializedPropError::Property(e)) => Err(e),
+            Err(_) => Err(MapPropertyError::InvalidType),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Motion {
+        vectors: Vec<(i16, i16)>,
+        scene_change: bool,
+    }
+
+    #[test]
+    fn round_trip() -> TestResult {
+        let mut map = Map::default();
+        let key = crate::key!(c"Motion");
+        let motion = Motion {
+            vectors: vec![(1, -2), (0, 3)],
+            scene_change: false,
+        };
+        map.set_serialized(key, &motion, AppendMode::Replace)?;
+        assert_eq!(map.get_serialized::<Motion>(key, 0)?, motion);
+        assert!(matches!(
+            map.get_serialized::<String>(key, 0),
+            Err(SerializedPropError::Deserialize(_))
+        ));
+        Ok(())
+    }
+}
@@ -9,21 +9,30 @@ mod filter;
 pub(crate) mod internal;
 
 use std::ffi::{CStr, CString, c_void};
+#[cfg(feature = "vs-41")]
+use std::time::Duration;
 
+#[cfg(feature = "vs-41")]
+use crate::api::error::UnsupportedApi;
 use crate::{
     AudioInfo, VideoInfo,
     api::Api,
-    core::Core,
+    core::{
+        Core,
+        keep_alive::{CoreObject, KeepAlive},
+    },
     ffi,
     frame::{AudioFrame, Frame, FrameContext, VideoFrame, internal::FrameFromPtr},
-    node::internal::FilterExtern,
+    node::internal::{FilterExtern, into_instance},
 };
 
 pub use dependency::*;
 pub use filter::*;
+#[cfg(feature = "macros")]
+pub use vapoursynth4_rs_macros::vapoursynth_filter;
 use vapoursynth4_sys::VSFrameDoneCallback;
 
-pub trait Node: Sized + Send + Sync + crate::_private::Sealed {
+pub trait Node: Sized + Send + Sync + crate::_private::Sealed + CoreObject {
     type FrameType: Frame;
 
     fn api(&self) -> Api;
@@ -61,7 +70,7 @@ pub trait Node: Sized + Send + Sync + crate::_private::Sealed {
             let mut buf = std::mem::ManuallyDrop::new(buf);
             Err(unsafe { CStr::from_ptr(buf.as_mut_ptr()).into() })
         } else {
-            unsafe { Ok(Self::FrameType::from_ptr(ptr, self.api())) }
+            unsafe { Ok(Self::FrameType::from_ptr(ptr, self.api()).kept_by(self.keep_alive())) }
         }
     }
 
@@ -77,12 +86,93 @@ pub trait Node: Sized + Send + Sync + crate::_private::Sealed {
             (self.api().getFrameAsync)(n, self.as_ptr(), callback, data);
         }
     }
+
+    /// Drops the frames cached by the node.
+    ///
+    /// # Errors
+    ///
+    /// Return [`UnsupportedApi`] if the core is older than API 4.1.
+    #[cfg(feature = "vs-41")]
+    fn clear_cache(&self) -> Result<(), UnsupportedApi> {
+        let api = self.api();
+        api.require("Clearing the node cache", (4, 1))?;
+        unsafe { (api.clearNodeCache)(self.as_ptr()) };
+        Ok(())
+    }
+
+    /// The name the filter was created with.
+    ///
+    /// # Errors
+    ///
+    /// Return [`UnsupportedApi`] if the core is older than API 4.1.
+    #[cfg(feature = "vs-41")]
+    fn name(&self) -> Result<String, UnsupportedApi> {
+        let api = self.api();
+        api.require("Getting the node name", (4, 1))?;
+        let name = unsafe { CStr::from_ptr((api.getNodeName)(self.as_ptr())) };
+        Ok(name.to_string_lossy().into_owned())
+    }
+
+    /// # Errors
+    ///
+    /// Return [`UnsupportedApi`] if the core is older than API 4.1.
+    #[cfg(feature = "vs-41")]
+    fn filter_mode(&self) -> Result<FilterMode, UnsupportedApi> {
+        let api = self.api();
+        api.require("Getting the filter mode", (4, 1))?;
+        Ok(unsafe { (api.getNodeFilterMode)(self.as_ptr()) })
+    }
+
+    /// The nodes the filter requests frames from.
+    ///
+    /// # Errors
+    ///
+    /// Return [`UnsupportedApi`] if the core is older than API 4.1.
+    #[cfg(feature = "vs-41")]
+    fn dependencies(&self) -> Result<&[FilterDependency], UnsupportedApi> {
+        let api = self.api();
+        api.require("Getting the node dependencies", (4, 1))?;
+        let len = unsafe { (api.getNumNodeDependencies)(self.as_ptr()) };
+        let ptr = unsafe { (api.getNodeDependencies)(self.as_ptr()) };
+        if ptr.is_null() {
+            return Ok(&[]);
+        }
+        // SAFETY: The dependencies are owned by the node.
+        Ok(unsafe { std::slice::from_raw_parts(ptr, usize::try_from(len).unwrap_or_default()) })
+    }
+
+    /// Time spent producing frames while node timing is
+    /// [enabled](crate::core::Core::set_node_timing), optionally resetting the counter.
+    ///
+    /// # Errors
+    ///
+    /// Return [`UnsupportedApi`] if the core is older than API 4.1.
+    #[cfg(feature = "vs-41")]
+    fn processing_time(&self, reset: bool) -> Result<Duration, UnsupportedApi> {
+        let api = self.api();
+        api.require("Node timing", (4, 1))?;
+        let nanos = unsafe { (api.getNodeProcessingTime)(self.as_ptr(), reset.into()) };
+        Ok(Duration::from_nanos(
+            u64::try_from(nanos).unwrap_or_default(),
+        ))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct VideoNode {
     handle: *const ffi::VSNode,
     api: Api,
+    keep_alive: KeepAlive,
+}
+
+impl CoreObject for VideoNode {
+    fn keep_alive(&self) -> &KeepAlive {
+        &self.keep_alive
+    }
+
+    fn keep_alive_mut(&mut self) -> &mut KeepAlive {
+        &mut self.keep_alive
+    }
 }
 
 impl crate::_private::Sealed for VideoNode {}
@@ -107,7 +197,7 @@ impl Node for VideoNode {
     fn get_frame_filter(&self, n: i32, ctx: &mut FrameContext) -> Self::FrameType {
         unsafe {
             VideoFrame::from_ptr(
-                (self.api.getFrameFilter)(n, self.as_ptr(), ctx.as_ptr()),
+                (ctx.api().getFrameFilter)(n, self.as_ptr(), ctx.as_ptr()),
                 self.api,
             )
         }
@@ -120,7 +210,11 @@ impl VideoNode {
     /// The caller must ensure that `ptr` is a valid pointer to a [`ffi::VSNode`] that represents a video node.
     #[must_use]
     pub unsafe fn from_ptr(ptr: *mut ffi::VSNode, api: Api) -> Self {
-        Self { handle: ptr, api }
+        Self {
+            handle: ptr,
+            api,
+            keep_alive: KeepAlive::NONE,
+        }
     }
 
     #[must_use]
@@ -132,37 +226,42 @@ impl VideoNode {
     /// # Panics
     ///
     /// Panics if the the dependency index is larger than [`i32::MAX`].
-    pub fn new<F: Filter>(
+    pub fn new<F: Filter<FrameType = VideoFrame>>(
         name: &str,
         info: &VideoInfo,
         filter: F,
         dependencies: &[ffi::VSFilterDependency],
         core: impl AsRef<Core>,
     ) -> Option<Self> {
-        let filter = Box::new(filter);
         let name = CString::new(name).ok()?;
         let core = core.as_ref();
+        let mode = filter.filter_mode();
+        let instance = into_instance(filter);
         let ptr = unsafe {
             (core.api().createVideoFilter2)(
                 name.as_ptr(),
                 info,
                 F::filter_get_frame,
                 Some(F::filter_free),
-                F::FILTER_MODE,
+                mode,
                 dependencies.as_ptr(),
                 dependencies.len().try_into().unwrap(),
-                Box::into_raw(filter).cast(),
+                instance.cast(),
                 core.as_ptr(),
             )
         };
-        ptr.is_null()
-            .then_some(unsafe { Self::from_ptr(ptr, core.api()) })
+        (!ptr.is_null()).then(|| unsafe {
+            let mut node = Self::from_ptr(ptr, core.api()).kept_by(core.keep_alive());
+            (*instance).on_node_created(&mut node);
+            node
+        })
     }
 }
 
 impl Clone for VideoNode {
     fn clone(&self) -> Self {
         unsafe { Self::from_ptr((self.api.addNodeRef)(self.as_ptr()), self.api) }
+            .kept_by(&self.keep_alive)
     }
 }
 
@@ -176,6 +275,17 @@ impl Drop for VideoNode {
 pub struct AudioNode {
     handle: *const ffi::VSNode,
     api: Api,
+    keep_alive: KeepAlive,
+}
+
+impl CoreObject for AudioNode {
+    fn keep_alive(&self) -> &KeepAlive {
+        &self.keep_alive
+    }
+
+    fn keep_alive_mut(&mut self) -> &mut KeepAlive {
+        &mut self.keep_alive
+    }
 }
 
 impl crate::_private::Sealed for AudioNode {}
@@ -199,7 +309,7 @@ impl Node for AudioNode {
     fn get_frame_filter(&self, n: i32, ctx: &mut FrameContext) -> Self::FrameType {
         unsafe {
             AudioFrame::from_ptr(
-                (self.api.getFrameFilter)(n, self.as_ptr(), ctx.as_ptr()),
+                (ctx.api().getFrameFilter)(n, self.as_ptr(), ctx.as_ptr()),
                 self.api,
             )
         }
@@ -212,7 +322,11 @@ impl AudioNode {
     /// The caller must ensure that `ptr` is a valid pointer to a [`ffi::VSNode`] that represents an audio node.
     #[must_use]
     pub unsafe fn from_ptr(ptr: *mut ffi::VSNode, api: Api) -> Self {
-        Self { handle: ptr, api }
+        Self {
+            handle: ptr,
+            api,
+            keep_alive: KeepAlive::NONE,
+        }
     }
 
     #[must_use]
@@ -224,37 +338,42 @@ impl AudioNode {
     /// # Panics
     ///
     /// Panics if the the dependency index is larger than [`i32::MAX`].
-    pub fn new<F: Filter>(
+    pub fn new<F: Filter<FrameType = AudioFrame>>(
         name: &str,
         info: &AudioInfo,
         filter: F,
         dependencies: &[ffi::VSFilterDependency],
         core: impl AsRef<Core>,
     ) -> Option<Self> {
-        let filter = Box::new(filter);
         let name = CString::new(name).ok()?;
         let core = core.as_ref();
+        let mode = filter.filter_mode();
+        let instance = into_instance(filter);
         let ptr = unsafe {
             (core.api().createAudioFilter2)(
                 name.as_ptr(),
                 info,
                 F::filter_get_frame,
                 Some(F::filter_free),
-                F::FILTER_MODE,
+                mode,
                 dependencies.as_ptr(),
                 dependencies.len().try_into().unwrap(),
-                Box::into_raw(filter).cast(),
+                instance.cast(),
                 core.as_ptr(),
             )
         };
-        ptr.is_null()
-            .then_some(unsafe { Self::from_ptr(ptr, core.api()) })
+        (!ptr.is_null()).then(|| unsafe {
+            let mut node = Self::from_ptr(ptr, core.api()).kept_by(core.keep_alive());
+            (*instance).on_node_created(&mut node);
+            node
+        })
     }
 }
 
 impl Clone for AudioNode {
     fn clone(&self) -> Self {
         unsafe { Self::from_ptr((self.api.addNodeRef)(self.as_ptr()), self.api) }
+            .kept_by(&self.keep_alive)
     }
 }
 
@@ -3,12 +3,15 @@ use std::{
     ptr::null_mut,
 };
 
+use thiserror::Error;
+
 use crate::{
+    api::PluginApi,
     core::CoreRef,
     ffi,
     frame::{Frame, FrameContext},
-    map::MapRef,
-    node::FilterMode,
+    map::{Map, MapPropertyError, MapRef},
+    node::{FilterDependency, FilterMode},
 };
 
 pub trait Filter
@@ -25,17 +28,6 @@ where
     const ARGS: &'static CStr;
     const RETURN_TYPE: &'static CStr;
 
-    /// # Errors
-    ///
-    /// Return [`Self::Error`] if anything happens during the filter creation.
-    /// The error message will be passed to `VapourSynth`.
-    fn create(
-        input: MapRef,
-        output: MapRef,
-        data: Option<Box<Self::FilterData>>,
-        core: CoreRef,
-    ) -> Result<(), Self::Error>;
-
     /// # Errors
     ///
     /// Return [`Self::Error`] if anything happens during the filter creation.
@@ -48,12 +40,130 @@ where
         frame_ctx: FrameContext,
         core: CoreRef,
     ) -> Result<Option<Self::FrameType>, Self::Error>;
+    /// The [`FilterMode`] of this instance. Defaults to [`Self::FILTER_MODE`].
+    ///
+    /// Override this when the mode depends on the arguments, e.g. to force
+    /// [`FilterMode::Unordered`] for a debug option.
+    fn filter_mode(&self) -> FilterMode {
+        Self::FILTER_MODE
+    }
+
+    /// Called once the core has created the node for this instance, before it is returned to the
+    /// caller. Use it to tune the node's cache, or to keep a reference to it.
+    ///
+    /// Keeping an owned reference to the node inside the filter creates a cycle, so the filter
+    /// will never be freed unless the reference is released.
+    fn on_node_created(&mut self, node: &mut <Self::FrameType as Frame>::Node) {
+        let _ = node;
+    }
+
     /// Free the filter
     fn free(self, core: CoreRef) {
         let _ = core;
     }
 }
 
+/// Raw constructor of a [`Filter`], which must create the node and append it to `output`.
+///
+/// Filters implementing [`FilterConstructor`] don't need this.
+pub trait RawFilterConstructor: Filter {
+    /// # Errors
+    ///
+    /// Return [`Self::Error`] if anything happens during the filter creation.
+    /// The error message will be passed to `VapourSynth`.
+    fn create(
+        input: MapRef,
+        output: MapRef,
+        data: Option<Box<Self::FilterData>>,
+        core: CoreRef,
+    ) -> Result<(), Self::Error>;
+}
+
+/// Typed constructor of a [`Filter`].
+///
+/// The crate reads nothing itself: `create` gets the arguments and returns the output clip
+/// information with the filter, and the node is then created and appended to the output map.
+/// Errors and panics are reported to `VapourSynth`.
+///
+/// ```
+/// use std::ffi::{CStr, c_void};
+///
+/// use vapoursynth4_rs::{
+///     VideoInfo,
+///     core::CoreRef,
+///     frame::{FrameContext, VideoFrame},
+///     key,
+///     map::Map,
+///     node::{
+///         ActivationReason, Filter, FilterConstructor, FilterDependency, Node, RequestPattern,
+///         VideoNode,
+///     },
+/// };
+///
+/// struct Passthrough {
+///     node: VideoNode,
+/// }
+///
+/// impl Filter for Passthrough {
+///     type Error = &'static CStr;
+///     type FrameType = VideoFrame;
+///     type FilterData = ();
+///
+///     const NAME: &'static CStr = c"Passthrough";
+///     const ARGS: &'static CStr = c"clip:vnode;";
+///     const RETURN_TYPE: &'static CStr = c"clip:vnode;";
+///
+///     fn get_frame(
+///         &self,
+///         n: i32,
+///         activation_reason: ActivationReason,
+///         _frame_data: *mut *mut c_void,
+///         mut ctx: FrameContext,
+///         _core: CoreRef,
+///     ) -> Result<Option<VideoFrame>, Self::Error> {
+///         match activation_reason {
+///             ActivationReason::Initial => ctx.request_frame_filter(n, &self.node),
+///             ActivationReason::AllFramesReady => {
+///                 return Ok(Some(self.node.get_frame_filter(n, &mut ctx)));
+///             }
+///             _ => {}
+///         }
+///         Ok(None)
+///     }
+/// }
+///
+/// impl FilterConstructor for Passthrough {
+///     fn create(args: &Map, _core: CoreRef) -> Result<(VideoInfo, Self), Self::Error> {
+///         let node = args
+///             .get_video_node(key!(c"clip"), 0)
+///             .map_err(|_| c"clip is required")?;
+///         Ok((node.info().clone(), Self { node }))
+///     }
+///
+///     fn dependencies(&self) -> Vec<FilterDependency> {
+///         vec![FilterDependency {
+///             source: self.node.as_ptr(),
+///             request_pattern: RequestPattern::StrictSpatial,
+///         }]
+///     }
+/// }
+/// ```
+pub trait FilterConstructor: Filter {
+    /// # Errors
+    ///
+    /// Return [`Self::Error`] if the arguments are invalid. The error message will be passed to
+    /// `VapourSynth`.
+    fn create(
+        args: &Map,
+        core: CoreRef,
+    ) -> Result<(<Self::FrameType as Frame>::Info, Self), Self::Error>;
+
+    /// The nodes the filter requests frames from. Defaults to none.
+    fn dependencies(&self) -> Vec<FilterDependency> {
+        Vec::new()
+    }
+}
+
 pub struct FilterRegister<F: Filter> {
     data: Option<F::FilterData>,
 }
@@ -62,23 +172,87 @@ impl<F: Filter> FilterRegister<F> {
     pub fn new(data: Option<F::FilterData>) -> Self {
         Self { data }
     }
+}
 
+impl<F: RawFilterConstructor> FilterRegister<F> {
+    /// Registers `F` with [`RawFilterConstructor::create`] as its constructor.
+    ///
+    /// Returns whether the core accepted the filter. It rejects e.g. an invalid signature, and
+    /// the data is dropped then.
+    ///
     /// # Safety
-    pub unsafe fn register(self, plugin: *mut ffi::VSPlugin, vspapi: *const ffi::VSPLUGINAPI) {
-        use super::internal::FilterExtern;
+    pub unsafe fn register(
+        self,
+        plugin: *mut ffi::VSPlugin,
+        vspapi: *const ffi::VSPLUGINAPI,
+    ) -> bool {
+        use super::internal::RawFilterConstructorExtern;
 
-        unsafe {
-            ((*vspapi).registerFunction)(
-                F::NAME.as_ptr(),
-                F::ARGS.as_ptr(),
-                F::RETURN_TYPE.as_ptr(),
-                F::filter_create,
-                self.data
-                    .map_or(null_mut(), |d| Box::into_raw(Box::new(d)).cast()),
+        let data = self.data.map_or(null_mut(), |d| Box::into_raw(Box::new(d)));
+        let registered = unsafe {
+            PluginApi::from_ptr(vspapi).register_function(
                 plugin,
+                F::NAME,
+                F::ARGS,
+                F::RETURN_TYPE,
+                F::filter_create,
+                data.cast(),
             )
         };
+        if !registered && !data.is_null() {
+            drop(unsafe { Box::from_raw(data) });
+        }
+        registered
+    }
+}
+
+impl<F: FilterConstructor> FilterRegister<F> {
+    /// Registers `F` with [`FilterConstructor::create`] as its constructor.
+    ///
+    /// Returns whether the core accepted the filter. It rejects e.g. an invalid signature.
+    ///
+    /// # Safety
+    ///
+    /// `plugin` and `vspapi` must be the pointers passed to `VapourSynthPluginInit2`.
+    pub unsafe fn register_constructor(
+        self,
+        plugin: *mut ffi::VSPlugin,
+        vspapi: *const ffi::VSPLUGINAPI,
+    ) -> bool {
+        use super::internal::FilterConstructorExtern;
+
+        unsafe {
+            PluginApi::from_ptr(vspapi).register_function(
+                plugin,
+                F::NAME,
+                F::ARGS,
+                F::RETURN_TYPE,
+                F::filter_construct,
+                null_mut(),
+            )
+        }
     }
 }
 
 pub type ActivationReason = ffi::VSActivationReason;
+
+/// Error returned when the core fails to create a filter instance.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Error)]
+pub enum FilterError {
+    /// The core rejected the filter, e.g. because of an invalid [`VideoInfo`](crate::VideoInfo)
+    /// or [`AudioInfo`](crate::AudioInfo).
+    #[error("Failed to create the filter: {0}")]
+    Creation(String),
+    /// The node could not be appended to the output map.
+    #[error("Failed to append the filter node: {0}")]
+    Output(#[from] MapPropertyError),
+}
+
+impl FilterError {
+    pub(crate) fn from_map(out: &Map) -> Result<(), Self> {
+        match out.get_error() {
+            Some(msg) => Err(Self::Creation(msg.to_string_lossy().into_owned())),
+            None => Ok(()),
+        }
+    }
+}
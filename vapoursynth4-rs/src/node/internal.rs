@@ -1,55 +1,90 @@
 use std::{
-    ffi::{c_int, c_void},
-    mem::ManuallyDrop,
+    any::Any,
+    ffi::{CString, c_int, c_void},
+    mem::{self, ManuallyDrop},
     panic::AssertUnwindSafe,
     ptr::null,
 };
 
 use crate::{
+    AudioInfo, VideoInfo,
     api::Api,
-    core::CoreRef,
+    core::{Core, CoreRef, keep_alive::CoreObject},
     frame::{Frame, FrameContext},
-    map::MapRef,
+    map::{KeyStr, Map, MapRef},
     utils::ToCString,
 };
 
-use super::{Filter, ffi};
+use super::{
+    AudioNode, Dependencies, Filter, FilterConstructor, FilterError, RawFilterConstructor,
+    VideoNode, ffi,
+};
 
-pub trait FilterExtern: Filter {
-    unsafe extern "system-unwind" fn filter_create(
-        in_: *const ffi::VSMap,
-        out: *mut ffi::VSMap,
-        user_data: *mut c_void,
-        core: *mut ffi::VSCore,
-        vsapi: *const ffi::VSAPI,
-    ) {
-        unsafe {
-            let api = Api::from_ptr(vsapi);
+pub trait NodeFromPtr {
+    unsafe fn from_ptr(ptr: *mut ffi::VSNode, api: Api) -> Self;
+}
 
-            let input = MapRef::from_ptr(in_, api);
-            let mut output = MapRef::from_ptr(out, api);
-            let core = CoreRef::from_ptr(core, api);
-            let data = if user_data.is_null() {
-                None
-            } else {
-                Some(Box::from_raw(user_data.cast()))
-            };
+impl NodeFromPtr for VideoNode {
+    #[inline]
+    unsafe fn from_ptr(ptr: *mut ffi::VSNode, api: Api) -> Self {
+        unsafe { VideoNode::from_ptr(ptr, api) }
+    }
+}
 
-            match std::panic::catch_unwind(AssertUnwindSafe(|| {
-                Self::create(input, output, data, core)
-            })) {
-                Ok(Err(e)) => {
-                    output.set_error(e.as_ref());
-                }
-                Err(p) => {
-                    let e = p.downcast::<&str>().unwrap_unchecked();
-                    output.set_error(&e.into_cstring_lossy());
-                }
-                _ => {}
-            }
-        }
+impl NodeFromPtr for AudioNode {
+    #[inline]
+    unsafe fn from_ptr(ptr: *mut ffi::VSNode, api: Api) -> Self {
+        unsafe { AudioNode::from_ptr(ptr, api) }
     }
+}
+
+/// Hands `filter` over as the instance data of a node, to be released by
+/// [`FilterExtern::filter_free`].
+///
+/// Zero-sized filters, e.g. pure functions of their dependencies, cost no allocation: a `Box` of
+/// a zero-sized type is a dangling, well-aligned sentinel pointer, which `filter_free` turns back
+/// into a `Box` without deallocating.
+pub(crate) fn into_instance<F: Filter>(filter: F) -> *mut F {
+    Box::into_raw(Box::new(filter))
+}
 
+/// The clip information of a kind of node, which knows how to create such a node.
+pub trait FilterInfo {
+    /// Creates the node of `filter` and appends it to `out`.
+    fn create_filter<F: Filter>(
+        &self,
+        core: &mut Core,
+        out: &mut MapRef,
+        filter: F,
+        dependencies: &Dependencies,
+    ) -> Result<(), FilterError>;
+}
+
+impl FilterInfo for VideoInfo {
+    fn create_filter<F: Filter>(
+        &self,
+        core: &mut Core,
+        out: &mut MapRef,
+        filter: F,
+        dependencies: &Dependencies,
+    ) -> Result<(), FilterError> {
+        core.create_video_filter(out, F::NAME, self, filter, dependencies)
+    }
+}
+
+impl FilterInfo for AudioInfo {
+    fn create_filter<F: Filter>(
+        &self,
+        core: &mut Core,
+        out: &mut MapRef,
+        filter: F,
+        dependencies: &Dependencies,
+    ) -> Result<(), FilterError> {
+        core.create_audio_filter(out, F::NAME, self, filter, dependencies)
+    }
+}
+
+pub trait FilterExtern: Filter {
     unsafe extern "system-unwind" fn filter_get_frame(
         n: c_int,
         activation_reason: ffi::VSActivationReason,
@@ -69,8 +104,9 @@ pub trait FilterExtern: Filter {
                 filter.get_frame(n, activation_reason, frame_data, *ctx, core)
             });
             match frame {
-                Ok(Ok(Some(frame))) => {
-                    // Transfer the ownership to VapourSynth
+                Ok(Ok(Some(mut frame))) => {
+                    // Transfer the ownership to VapourSynth, whose core outlives the filter
+                    drop(mem::take(frame.keep_alive_mut()));
                     let frame = ManuallyDrop::new(frame);
                     return frame.as_ptr();
                 }
@@ -78,8 +114,7 @@ pub trait FilterExtern: Filter {
                     ctx.set_filter_error(e.as_ref());
                 }
                 Err(p) => {
-                    let e = p.downcast::<&str>().unwrap_unchecked();
-                    ctx.set_filter_error(&e.into_cstring_lossy());
+                    ctx.set_filter_error(&panic_message(p));
                 }
                 _ => {}
             }
@@ -96,11 +131,234 @@ pub trait FilterExtern: Filter {
         unsafe {
             let api = Api::from_ptr(vsapi);
             let filter = Box::from_raw(instance_data.cast::<Self>());
-            let core = CoreRef::from_ptr(core, api);
+            let core_ref = CoreRef::from_ptr(core, api);
+
+            // There is no error channel here, so report the panic to the core log instead of
+            // unwinding into `VapourSynth`.
+            if let Err(p) = std::panic::catch_unwind(AssertUnwindSafe(|| filter.free(core_ref))) {
+                (api.logMessage)(ffi::VSMessageType::Warning, panic_message(p).as_ptr(), core);
+            }
+        }
+    }
 
-            filter.free(core);
+    /// Runs [`Filter::on_node_created`] with the node the core just appended to `out` under `key`.
+    ///
+    /// # Safety
+    ///
+    /// `instance` must be the instance data of that node.
+    unsafe fn notify_node_created_in_map(instance: *mut Self, out: &Map, key: &KeyStr, api: Api) {
+        let Some(index) = out.num_elements(key) else {
+            return;
+        };
+        unsafe {
+            let mut error = ffi::VSMapPropertyError::Success;
+            let ptr = (api.mapGetNode)(out.as_ptr(), key.as_ptr(), index - 1, &raw mut error);
+            if !ptr.is_null() {
+                let mut node = <Self::FrameType as Frame>::Node::from_ptr(ptr, api);
+                (*instance).on_node_created(&mut node);
+            }
         }
     }
 }
 
 impl<F> FilterExtern for F where F: Filter {}
+
+pub trait RawFilterConstructorExtern: RawFilterConstructor {
+    unsafe extern "system-unwind" fn filter_create(
+        in_: *const ffi::VSMap,
+        out: *mut ffi::VSMap,
+        user_data: *mut c_void,
+        core: *mut ffi::VSCore,
+        vsapi: *const ffi::VSAPI,
+    ) {
+        unsafe {
+            let api = Api::from_ptr(vsapi);
+
+            let input = MapRef::from_ptr(in_, api);
+            let mut output = MapRef::from_ptr(out, api);
+            let core = CoreRef::from_ptr(core, api);
+            let data = if user_data.is_null() {
+                None
+            } else {
+                Some(Box::from_raw(user_data.cast()))
+            };
+
+            match std::panic::catch_unwind(AssertUnwindSafe(|| {
+                <Self as RawFilterConstructor>::create(input, output, data, core)
+            })) {
+                Ok(Err(e)) => {
+                    output.set_error(e.as_ref());
+                }
+                Err(p) => {
+                    output.set_error(&panic_message(p));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<F> RawFilterConstructorExtern for F where F: RawFilterConstructor {}
+
+pub trait FilterConstructorExtern: FilterConstructor {
+    unsafe extern "system-unwind" fn filter_construct(
+        in_: *const ffi::VSMap,
+        out: *mut ffi::VSMap,
+        _user_data: *mut c_void,
+        core: *mut ffi::VSCore,
+        vsapi: *const ffi::VSAPI,
+    ) {
+        unsafe {
+            let api = Api::from_ptr(vsapi);
+
+            let input = MapRef::from_ptr(in_, api);
+            let mut output = MapRef::from_ptr(out, api);
+            let mut core = CoreRef::from_ptr(core, api);
+
+            match std::panic::catch_unwind(AssertUnwindSafe(|| -> Result<(), Self::Error> {
+                let (info, filter) = <Self as FilterConstructor>::create(&input, core)?;
+                let dependencies = filter.dependencies();
+                let Some(dependencies) = Dependencies::new(&dependencies) else {
+                    output.set_error(c"Too many filter dependencies");
+                    return Ok(());
+                };
+                // On failure the core has already left its message in `output`
+                let _ = info.create_filter(&mut core, &mut output, filter, dependencies);
+                Ok(())
+            })) {
+                Ok(Err(e)) => {
+                    output.set_error(e.as_ref());
+                }
+                Err(p) => {
+                    output.set_error(&panic_message(p));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<F> FilterConstructorExtern for F where F: FilterConstructor {}
+
+/// Turns a panic payload into a message that can be handed to `VapourSynth`.
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> CString {
+    match payload.downcast::<String>() {
+        Ok(msg) => msg.into_cstring_lossy(),
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(msg) => msg.into_cstring_lossy(),
+            Err(_) => c"Rust panic with a non-string payload".into(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CStr, ptr::NonNull};
+
+    use super::*;
+    use crate::frame::VideoFrame;
+
+    struct Zst;
+
+    impl Filter for Zst {
+        type Error = &'static CStr;
+        type FrameType = VideoFrame;
+        type FilterData = ();
+
+        const NAME: &'static CStr = c"Zst";
+        const ARGS: &'static CStr = c"";
+        const RETURN_TYPE: &'static CStr = c"clip:vnode;";
+
+        fn get_frame(
+            &self,
+            _n: i32,
+            _activation_reason: ffi::VSActivationReason,
+            _frame_data: *mut *mut c_void,
+            _frame_ctx: FrameContext,
+            _core: CoreRef,
+        ) -> Result<Option<VideoFrame>, Self::Error> {
+            Ok(None)
+        }
+    }
+
+    #[cfg(feature = "link-library")]
+    struct PanicOnFree;
+
+    #[cfg(feature = "link-library")]
+    impl Filter for PanicOnFree {
+        type Error = &'static CStr;
+        type FrameType = VideoFrame;
+        type FilterData = ();
+
+        const NAME: &'static CStr = c"PanicOnFree";
+        const ARGS: &'static CStr = c"";
+        const RETURN_TYPE: &'static CStr = c"clip:vnode;";
+
+        fn get_frame(
+            &self,
+            _n: i32,
+            _activation_reason: ffi::VSActivationReason,
+            _frame_data: *mut *mut c_void,
+            _frame_ctx: FrameContext,
+            _core: CoreRef,
+        ) -> Result<Option<VideoFrame>, Self::Error> {
+            Ok(None)
+        }
+
+        fn free(self, _core: CoreRef) {
+            panic!("freed");
+        }
+    }
+
+    #[test]
+    fn zero_sized_instance() {
+        let instance = into_instance(Zst);
+        assert_eq!(instance, NonNull::dangling().as_ptr());
+        drop(unsafe { Box::from_raw(instance) });
+    }
+
+    #[test]
+    fn panic_payloads() {
+        assert_eq!(panic_message(Box::new("static")).as_c_str(), c"static");
+        assert_eq!(
+            panic_message(Box::new(String::from("owned"))).as_c_str(),
+            c"owned"
+        );
+        assert_eq!(
+            panic_message(Box::new(42)).as_c_str(),
+            c"Rust panic with a non-string payload"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "link-library")]
+    fn free_panic_is_logged() {
+        use crate::{core::MessageType, key, vsmap};
+
+        let mut core = Core::builder().build();
+        let clip = core
+            .invoke_with(c"std", c"BlankClip", vsmap! { length: 1 })
+            .unwrap()
+            .get_video_node(key!(c"clip"), 0)
+            .unwrap();
+        let node = core
+            .create_video_filter_node(
+                c"PanicOnFree",
+                clip.info(),
+                PanicOnFree,
+                Dependencies::new(&[]).unwrap(),
+            )
+            .unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let _handle = core.add_log_handler(move |ty, msg| {
+            let _ = tx.send((ty, msg.to_owned()));
+        });
+        drop(node);
+
+        assert!(
+            rx.try_iter()
+                .any(|(ty, msg)| ty == MessageType::Warning && msg == "freed")
+        );
+    }
+}
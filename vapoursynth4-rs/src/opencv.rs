@@ -0,0 +1,163 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Viewing frame planes as [`opencv`](::opencv) matrices, so analysis can run on frames
+//! without copying them.
+//!
+//! The views borrow the frame, and a mutable view needs a frame that is writable, e.g. a new
+//! frame or one returned by `copy_frame`.
+
+use std::ffi::c_void;
+
+use ::opencv::{
+    boxed_ref::{BoxedRef, BoxedRefMut},
+    core::{
+        CV_8UC1, CV_8UC3, CV_16FC1, CV_16FC3, CV_16UC1, CV_16UC3, CV_32FC1, CV_32FC3, Mat, Scalar,
+    },
+    prelude::*,
+};
+use thiserror::Error;
+
+use crate::{
+    ColorFamily, SampleType,
+    frame::{VideoFormat, VideoFrame},
+};
+
+#[derive(Debug, Error)]
+pub enum OpencvError {
+    /// OpenCV has no matrix type for the samples of the frame.
+    #[error("{0}")]
+    UnsupportedFormat(String),
+    #[error(transparent)]
+    Opencv(#[from] ::opencv::Error),
+}
+
+/// The single channel OpenCV type of the samples of `format`, if there is one. Samples of 9 to
+/// 16 bits are all stored as `CV_16U`.
+#[must_use]
+pub fn mat_type(format: &VideoFormat) -> Option<i32> {
+    match (format.sample_type, format.bytes_per_sample) {
+        (SampleType::Integer, 1) => Some(CV_8UC1),
+        (SampleType::Integer, 2) => Some(CV_16UC1),
+        (SampleType::Float, 2) => Some(CV_16FC1),
+        (SampleType::Float, 4) => Some(CV_32FC1),
+        _ => None,
+    }
+}
+
+impl VideoFrame {
+    fn plane_type(&self) -> Result<i32, OpencvError> {
+        mat_type(self.get_video_format()).ok_or_else(|| {
+            OpencvError::UnsupportedFormat("OpenCV has no matrix type for the samples".to_owned())
+        })
+    }
+
+    /// Borrows `plane` as a single channel matrix of its [`mat_type`], which uses the stride of
+    /// the plane.
+    ///
+    /// # Errors
+    ///
+    /// Return [`OpencvError::UnsupportedFormat`] if OpenCV has no type for the samples, or the
+    /// OpenCV error if the matrix cannot be created.
+    pub fn plane_mat(&self, plane: i32) -> Result<BoxedRef<'_, Mat>, OpencvError> {
+        let typ = self.plane_type()?;
+        let mat = unsafe {
+            Mat::new_rows_cols_with_data_unsafe(
+                self.frame_height(plane),
+                self.frame_width(plane),
+                typ,
+                self.plane(plane).cast::<c_void>().cast_mut(),
+                self.stride(plane).unsigned_abs(),
+            )
+        }?;
+        Ok(mat.into())
+    }
+
+    /// Borrows `plane` as a mutable matrix, like [`plane_mat`](Self::plane_mat).
+    ///
+    /// # Errors
+    ///
+    /// Return [`OpencvError::UnsupportedFormat`] if OpenCV has no type for the samples, or the
+    /// OpenCV error if the matrix cannot be created.
+    pub fn plane_mat_mut(&mut self, plane: i32) -> Result<BoxedRefMut<'_, Mat>, OpencvError> {
+        let typ = self.plane_type()?;
+        let (rows, cols) = (self.frame_height(plane), self.frame_width(plane));
+        let step = self.stride(plane).unsigned_abs();
+        let mat = unsafe {
+            Mat::new_rows_cols_with_data_unsafe(
+                rows,
+                cols,
+                typ,
+                self.plane_mut(plane).cast::<c_void>(),
+                step,
+            )
+        }?;
+        Ok(mat.into())
+    }
+
+    /// Copies an RGB frame into a new packed BGR matrix, the channel order of OpenCV images.
+    ///
+    /// # Errors
+    ///
+    /// Return [`OpencvError::UnsupportedFormat`] if the frame is not RGB or OpenCV has no type for
+    /// the samples, or the OpenCV error if the matrix cannot be created.
+    pub fn to_bgr_mat(&self) -> Result<Mat, OpencvError> {
+        let format = self.get_video_format();
+        if format.color_family != ColorFamily::RGB {
+            return Err(OpencvError::UnsupportedFormat(
+                "Only RGB frames can be converted to BGR".to_owned(),
+            ));
+        }
+        let typ = match self.plane_type()? {
+            CV_8UC1 => CV_8UC3,
+            CV_16UC1 => CV_16UC3,
+            CV_16FC1 => CV_16FC3,
+            _ => CV_32FC3,
+        };
+        let (rows, cols) = (self.frame_height(0), self.frame_width(0));
+        let bytes = format.bytes_per_sample.unsigned_abs() as usize;
+        let width = cols.unsigned_abs() as usize;
+
+        let mut mat = Mat::new_rows_cols_with_default(rows, cols, typ, Scalar::all(0.))?;
+        for y in 0..rows {
+            let dst = mat.ptr_mut(y)?;
+            let dst = unsafe { std::slice::from_raw_parts_mut(dst, width * 3 * bytes) };
+            // VapourSynth stores R, G and B planes.
+            for (channel, plane) in [2, 1, 0].into_iter().enumerate() {
+                let src = unsafe {
+                    let offset = self.stride(plane) * y as isize;
+                    std::slice::from_raw_parts(self.plane(plane).offset(offset), width * bytes)
+                };
+                for (dst, src) in dst.chunks_exact_mut(3 * bytes).zip(src.chunks_exact(bytes)) {
+                    dst[channel * bytes..(channel + 1) * bytes].copy_from_slice(src);
+                }
+            }
+        }
+        Ok(mat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn types() {
+        let mut format = VideoFormat {
+            color_family: ColorFamily::YUV,
+            sample_type: SampleType::Integer,
+            bits_per_sample: 10,
+            bytes_per_sample: 2,
+            sub_sampling_w: 1,
+            sub_sampling_h: 1,
+            num_planes: 3,
+        };
+        assert_eq!(mat_type(&format), Some(CV_16UC1));
+        format.sample_type = SampleType::Float;
+        format.bytes_per_sample = 4;
+        assert_eq!(mat_type(&format), Some(CV_32FC1));
+    }
+}
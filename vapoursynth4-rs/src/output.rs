@@ -0,0 +1,47 @@
+//! Writing clips to files or pipes, like `vspipe`.
+
+mod render;
+mod wav;
+mod y4m;
+
+use std::io::{self, Write};
+
+use thiserror::Error;
+
+pub use render::*;
+pub use wav::*;
+pub use y4m::*;
+
+use crate::frame::VideoFrame;
+
+/// Writes the planes of `frame` without padding, as `vspipe` does for raw output.
+///
+/// # Errors
+///
+/// Return the error of `writer`.
+pub fn write_planes(writer: &mut impl Write, frame: &VideoFrame) -> io::Result<()> {
+    let format = frame.get_video_format();
+    let bytes = usize::try_from(format.bytes_per_sample).unwrap_or_default();
+    for plane in 0..format.num_planes {
+        let width = usize::try_from(frame.frame_width(plane)).unwrap_or_default() * bytes;
+        let stride = frame.stride(plane);
+        let mut ptr = frame.plane(plane);
+        for _ in 0..frame.frame_height(plane) {
+            // SAFETY: Each row of the plane holds `width` bytes.
+            writer.write_all(unsafe { std::slice::from_raw_parts(ptr, width) })?;
+            ptr = ptr.wrapping_offset(stride);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum OutputError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Failed to get frame {n}: {message}")]
+    Frame { n: i32, message: String },
+    /// The clip can not be stored in the container.
+    #[error("{0}")]
+    UnsupportedFormat(String),
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    sync::{
+        Condvar, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use crate::node::Node;
+
+use super::OutputError;
+
+/// Requests the `frames` of `node` from `requests` threads at once and passes them to `f` in
+/// order, e.g. to write them to a file.
+///
+/// At most `requests` frames are kept ahead of the one `f` is waiting for.
+///
+/// # Errors
+///
+/// Return [`OutputError::Frame`] if a frame could not be produced, or the error of `f`. No more
+/// frames are requested after an error.
+pub fn render_ordered<N, F>(
+    node: &N,
+    frames: Range<i32>,
+    requests: usize,
+    mut f: F,
+) -> Result<(), OutputError>
+where
+    N: Node,
+    F: FnMut(i32, N::FrameType) -> Result<(), OutputError>,
+{
+    let requests = requests.max(1);
+    let window = i32::try_from(requests).unwrap_or(i32::MAX);
+    let next = AtomicI32::new(frames.start);
+    let stop = AtomicBool::new(false);
+    // The frame `f` is waiting for, which limits how far ahead the threads may go.
+    let written = Mutex::new(frames.start);
+    let advanced = Condvar::new();
+
+    thread::scope(|s| {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..requests {
+            let tx = tx.clone();
+            let (next, stop, written, advanced) = (&next, &stop, &written, &advanced);
+            let end = frames.end;
+            s.spawn(move || {
+                loop {
+                    let n = next.fetch_add(1, Ordering::Relaxed);
+                    if n >= end {
+                        break;
+                    }
+                    let mut current = written.lock().unwrap_or_else(PoisonError::into_inner);
+                    while n >= current.saturating_add(window) && !stop.load(Ordering::Relaxed) {
+                        current = advanced
+                            .wait(current)
+                            .unwrap_or_else(PoisonError::into_inner);
+                    }
+                    drop(current);
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let frame = node.get_frame(n).map_err(|e| OutputError::Frame {
+                        n,
+                        message: e.to_string_lossy().into_owned(),
+                    });
+                    if tx.send((n, frame)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut pending = BTreeMap::new();
+        let mut expected = frames.start;
+        let result = (|| {
+            while expected < frames.end {
+                let Ok((n, frame)) = rx.recv() else { break };
+                pending.insert(n, frame);
+                while let Some(frame) = pending.remove(&expected) {
+                    f(expected, frame?)?;
+                    expected += 1;
+                    *written.lock().unwrap_or_else(PoisonError::into_inner) = expected;
+                    advanced.notify_all();
+                }
+            }
+            Ok(())
+        })();
+
+        stop.store(true, Ordering::Relaxed);
+        advanced.notify_all();
+        drop(rx);
+        result
+    })
+}
@@ -0,0 +1,136 @@
+use std::io::{self, Write};
+
+use crate::{AudioInfo, SampleType, frame::AudioFrame};
+
+use super::OutputError;
+
+// The subformats of `WAVE_FORMAT_EXTENSIBLE`.
+const PCM: [u8; 16] = guid(0x0001);
+const IEEE_FLOAT: [u8; 16] = guid(0x0003);
+
+const fn guid(format: u8) -> [u8; 16] {
+    [
+        format, 0, 0, 0, 0, 0, 0x10, 0, 0x80, 0, 0, 0xaa, 0, 0x38, 0x9b, 0x71,
+    ]
+}
+
+/// Writes an audio clip as a `WAVE_FORMAT_EXTENSIBLE` file, the format of `vspipe -c wav`.
+///
+/// The size fields are capped at 4 GiB, which most readers ignore for longer streams.
+#[derive(Debug)]
+pub struct WavWriter<W> {
+    writer: W,
+    channels: i32,
+    bytes_per_sample: usize,
+    container: usize,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> WavWriter<W> {
+    /// Writes the header for a clip with `info`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`OutputError::UnsupportedFormat`] for more than 18 channels.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn new(mut writer: W, info: &AudioInfo) -> Result<Self, OutputError> {
+        let format = &info.format;
+        if !(1..=18).contains(&format.num_channels) {
+            return Err(OutputError::UnsupportedFormat(
+                "WAV supports 1 to 18 channels".to_owned(),
+            ));
+        }
+        let container = container_bytes(format.bits_per_sample);
+        let channels = format.num_channels as u16;
+        let block_align = channels * container;
+        let data_size = u64::from(block_align) * info.num_samples as u64;
+        let data_size = u32::try_from(data_size).unwrap_or(u32::MAX);
+        let subformat = match format.sample_type {
+            SampleType::Integer => PCM,
+            SampleType::Float => IEEE_FLOAT,
+            _ => {
+                return Err(OutputError::UnsupportedFormat(
+                    "Unknown sample type".to_owned(),
+                ));
+            }
+        };
+
+        let mut header = Vec::with_capacity(68);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&data_size.saturating_add(60).to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&40_u32.to_le_bytes());
+        header.extend_from_slice(&0xfffe_u16.to_le_bytes());
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&(info.sample_rate as u32).to_le_bytes());
+        header.extend_from_slice(&(info.sample_rate as u32 * u32::from(block_align)).to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&(container * 8).to_le_bytes());
+        header.extend_from_slice(&22_u16.to_le_bytes());
+        header.extend_from_slice(&(format.bits_per_sample as u16).to_le_bytes());
+        // The channel bits of VapourSynth are the speaker positions of WAV.
+        header.extend_from_slice(&(format.channel_layout as u32).to_le_bytes());
+        header.extend_from_slice(&subformat);
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_size.to_le_bytes());
+        writer.write_all(&header)?;
+
+        Ok(Self::without_header(writer, info))
+    }
+
+    /// Writes only the interleaved samples, like raw `vspipe` output.
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn without_header(writer: W, info: &AudioInfo) -> Self {
+        let format = &info.format;
+        Self {
+            writer,
+            channels: format.num_channels,
+            bytes_per_sample: format.bytes_per_sample as usize,
+            container: usize::from(container_bytes(format.bits_per_sample)),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Writes the samples of `frame` interleaved, which must have the format of the clip.
+    ///
+    /// # Errors
+    ///
+    /// Return the error of the writer.
+    pub fn write_frame(&mut self, frame: &AudioFrame) -> io::Result<()> {
+        let bytes = self.bytes_per_sample;
+        let samples = usize::try_from(frame.frame_length()).unwrap_or_default();
+        let channels: Vec<_> = (0..self.channels)
+            .map(|c| {
+                // SAFETY: Each channel holds `samples` samples of `bytes` bytes.
+                unsafe { std::slice::from_raw_parts(frame.channel(c), samples * bytes) }
+            })
+            .collect();
+
+        self.buffer.clear();
+        self.buffer
+            .reserve(samples * channels.len() * self.container);
+        for i in 0..samples {
+            for channel in &channels {
+                // Samples are little endian, so 24 bit samples are the low 3 bytes of 4.
+                self.buffer
+                    .extend_from_slice(&channel[i * bytes..i * bytes + self.container]);
+            }
+        }
+        self.writer.write_all(&self.buffer)
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+// Bytes of each sample in the file, 24 bit samples are packed to 3 bytes.
+fn container_bytes(bits_per_sample: i32) -> u16 {
+    match bits_per_sample {
+        ..=16 => 2,
+        17..=24 => 3,
+        _ => 4,
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::{self, Write};
+
+use crate::{ColorFamily, SampleType, VideoInfo, frame::VideoFrame};
+
+use super::{OutputError, write_planes};
+
+/// Writes a video clip as YUV4MPEG2, the format of `vspipe -c y4m`.
+#[derive(Debug)]
+pub struct Y4mWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Writes the stream header for a clip with `info`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`OutputError::UnsupportedFormat`] if the clip does not have a constant format and
+    /// size, or is not integer gray or YUV with a subsampling Y4M knows.
+    pub fn new(mut writer: W, info: &VideoInfo) -> Result<Self, OutputError> {
+        writer.write_all(header(info)?.as_bytes())?;
+        Ok(Self { writer })
+    }
+
+    /// Writes `frame`, which must have the format of the clip.
+    ///
+    /// # Errors
+    ///
+    /// Return the error of the writer.
+    pub fn write_frame(&mut self, frame: &VideoFrame) -> io::Result<()> {
+        self.writer.write_all(b"FRAME\n")?;
+        write_planes(&mut self.writer, frame)
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn header(info: &VideoInfo) -> Result<String, OutputError> {
+    let unsupported = |reason: &str| Err(OutputError::UnsupportedFormat(reason.to_owned()));
+    if !info.is_constant_format() {
+        return unsupported("Y4M requires a constant format and size");
+    }
+    let format = &info.format;
+    if format.sample_type != SampleType::Integer {
+        return unsupported("Y4M does not support float samples");
+    }
+    let bits = format.bits_per_sample;
+    let colorspace = match format.color_family {
+        ColorFamily::Gray if bits > 8 => format!("mono{bits}"),
+        ColorFamily::Gray => "mono".to_owned(),
+        ColorFamily::YUV => {
+            let subsampling = match (format.sub_sampling_w, format.sub_sampling_h) {
+                (1, 1) => "420",
+                (1, 0) => "422",
+                (0, 0) => "444",
+                (2, 2) => "410",
+                (2, 0) => "411",
+                (0, 1) => "440",
+                _ => return unsupported("Y4M does not support the subsampling"),
+            };
+            if bits > 8 {
+                format!("{subsampling}p{bits}")
+            } else {
+                subsampling.to_owned()
+            }
+        }
+        _ => return unsupported("Y4M only supports gray and YUV"),
+    };
+    Ok(format!(
+        "YUV4MPEG2 C{colorspace} W{} H{} F{}:{} Ip A0:0 XLENGTH={}\n",
+        info.width, info.height, info.fps_num, info.fps_den, info.num_frames
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{frame::VideoFormat, info::video_info};
+
+    use super::*;
+
+    #[test]
+    fn header() {
+        let mut info = video_info()
+            .format(VideoFormat {
+                color_family: ColorFamily::YUV,
+                sample_type: SampleType::Integer,
+                bits_per_sample: 10,
+                bytes_per_sample: 2,
+                sub_sampling_w: 1,
+                sub_sampling_h: 1,
+                num_planes: 3,
+            })
+            .width(64)
+            .height(32)
+            .num_frames(10)
+            .fps(24000, 1001)
+            .build()
+            .unwrap();
+        assert_eq!(
+            super::header(&info).unwrap(),
+            "YUV4MPEG2 C420p10 W64 H32 F24000:1001 Ip A0:0 XLENGTH=10\n"
+        );
+        info.width = 0;
+        assert!(super::header(&info).is_err());
+    }
+}
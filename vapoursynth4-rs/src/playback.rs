@@ -0,0 +1,332 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Playing audio clips on the default output device with `cpal`, to audition audio filters.
+//!
+//! A thread requests the frames of the clip in order and converts them to interleaved `f32`
+//! samples ahead of the device. Playback starts once the prebuffer is full, and if the filters
+//! fall behind afterwards the device plays silence, which is counted by [`Player::underruns`].
+//!
+//! ```ignore
+//! let player = Player::new(node, Duration::from_millis(500))?;
+//! while !player.is_finished() {
+//!     std::thread::sleep(Duration::from_millis(100));
+//! }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Condvar, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use cpal::{
+    BuildStreamError, PauseStreamError, PlayStreamError, SampleRate, Stream, StreamConfig,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+use thiserror::Error;
+
+use crate::{
+    SampleType,
+    frame::{AudioFormat, AudioFrame},
+    node::{AudioNode, Node},
+};
+
+#[derive(Debug, Error)]
+pub enum PlaybackError {
+    #[error("No audio output device is available")]
+    NoDevice,
+    #[error("{0}")]
+    UnsupportedFormat(String),
+    #[error(transparent)]
+    Build(#[from] BuildStreamError),
+    #[error(transparent)]
+    Play(#[from] PlayStreamError),
+    #[error(transparent)]
+    Pause(#[from] PauseStreamError),
+}
+
+/// Plays an audio node until it ends or the player is dropped.
+pub struct Player {
+    stream: Stream,
+    shared: Arc<Shared>,
+    feeder: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    buffer: Mutex<VecDeque<f32>>,
+    /// Signals the feeder that the device took samples, or that it has to stop.
+    consumed: Condvar,
+    /// Interleaved samples kept ahead of the device.
+    capacity: usize,
+    started: AtomicBool,
+    fed: AtomicBool,
+    stop: AtomicBool,
+    played: AtomicU64,
+    underruns: AtomicU64,
+    error: Mutex<Option<String>>,
+}
+
+impl Player {
+    /// Starts playing `node` once `prebuffer` of audio has been rendered.
+    ///
+    /// # Errors
+    ///
+    /// Return [`PlaybackError::NoDevice`] if there is no output device, or the error of `cpal`,
+    /// e.g. if the device does not support the sample rate or number of channels of the clip.
+    pub fn new(node: AudioNode, prebuffer: Duration) -> Result<Self, PlaybackError> {
+        let info = node.info();
+        let channels = u16::try_from(info.format.num_channels)
+            .map_err(|_| PlaybackError::UnsupportedFormat("Too many channels".to_owned()))?;
+        let sample_rate = u32::try_from(info.sample_rate)
+            .map_err(|_| PlaybackError::UnsupportedFormat("Invalid sample rate".to_owned()))?;
+        if info.format.sample_type == SampleType::Integer && info.format.bytes_per_sample == 1 {
+            return Err(PlaybackError::UnsupportedFormat(
+                "8 bit samples are not supported".to_owned(),
+            ));
+        }
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(PlaybackError::NoDevice)?;
+        let config = StreamConfig {
+            channels,
+            sample_rate: SampleRate(sample_rate),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let capacity =
+            (prebuffer.as_secs_f64() * f64::from(sample_rate)) as usize * usize::from(channels);
+        let shared = Arc::new(Shared {
+            buffer: Mutex::new(VecDeque::with_capacity(capacity)),
+            consumed: Condvar::new(),
+            capacity: capacity.max(usize::from(channels)),
+            started: AtomicBool::new(false),
+            fed: AtomicBool::new(false),
+            stop: AtomicBool::new(false),
+            played: AtomicU64::new(0),
+            underruns: AtomicU64::new(0),
+            error: Mutex::new(None),
+        });
+
+        let callback = Arc::clone(&shared);
+        let errors = Arc::clone(&shared);
+        let stream = device.build_output_stream(
+            &config,
+            move |out: &mut [f32], _| callback.fill(out),
+            move |e| errors.fail(e.to_string()),
+            None,
+        )?;
+        let feeder = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || shared.feed(&node))
+        };
+        stream.play()?;
+        Ok(Self {
+            stream,
+            shared,
+            feeder: Some(feeder),
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Return the error of `cpal`.
+    pub fn pause(&self) -> Result<(), PlaybackError> {
+        Ok(self.stream.pause()?)
+    }
+
+    /// # Errors
+    ///
+    /// Return the error of `cpal`.
+    pub fn resume(&self) -> Result<(), PlaybackError> {
+        Ok(self.stream.play()?)
+    }
+
+    /// The number of interleaved samples played so far.
+    #[must_use]
+    pub fn position(&self) -> u64 {
+        self.shared.played.load(Ordering::Relaxed)
+    }
+
+    /// How often the device ran out of samples after playback started.
+    #[must_use]
+    pub fn underruns(&self) -> u64 {
+        self.shared.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Whether every sample has been played, or playback stopped because of an error.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.shared.fed.load(Ordering::Acquire)
+            && self
+                .shared
+                .buffer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .is_empty()
+    }
+
+    /// The error which stopped playback, from the device or getting a frame.
+    #[must_use]
+    pub fn error(&self) -> Option<String> {
+        self.shared
+            .error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.shared.stop();
+        if let Some(feeder) = self.feeder.take() {
+            _ = feeder.join();
+        }
+    }
+}
+
+impl Shared {
+    fn feed(&self, node: &AudioNode) {
+        let mut samples = Vec::new();
+        for n in 0..node.info().num_frames {
+            let frame = match node.get_frame(n) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    self.fail(format!("Failed to get frame {n}: {}", e.to_string_lossy()));
+                    break;
+                }
+            };
+            samples.clear();
+            interleave(&frame, &mut samples);
+
+            let mut buffer = self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+            while buffer.len() + samples.len() > self.capacity.max(samples.len())
+                && !self.stop.load(Ordering::Relaxed)
+            {
+                // The prebuffer is full, so the device can start.
+                self.started.store(true, Ordering::Release);
+                buffer = self
+                    .consumed
+                    .wait(buffer)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+            if self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            buffer.extend(&samples);
+        }
+        self.started.store(true, Ordering::Release);
+        self.fed.store(true, Ordering::Release);
+    }
+
+    fn fill(&self, out: &mut [f32]) {
+        if !self.started.load(Ordering::Acquire) {
+            out.fill(0.0);
+            return;
+        }
+        let mut buffer = self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+        let available = buffer.len().min(out.len());
+        for (out, sample) in out.iter_mut().zip(buffer.drain(..available)) {
+            *out = sample;
+        }
+        drop(buffer);
+        out[available..].fill(0.0);
+        if available < out.len() && !self.fed.load(Ordering::Acquire) {
+            self.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+        self.played.fetch_add(available as u64, Ordering::Relaxed);
+        self.consumed.notify_one();
+    }
+
+    fn fail(&self, message: String) {
+        self.error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_or_insert(message);
+        self.stop();
+    }
+
+    fn stop(&self) {
+        // Holding the lock ensures the feeder is either waiting or has not checked `stop` yet.
+        let _buffer = self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+        self.stop.store(true, Ordering::Relaxed);
+        self.consumed.notify_all();
+    }
+}
+
+/// Appends the samples of `frame` to `out`, interleaved and scaled to `-1.0..1.0`.
+fn interleave(frame: &AudioFrame, out: &mut Vec<f32>) {
+    let format = frame.get_audio_format();
+    let bytes = usize::try_from(format.bytes_per_sample).unwrap_or_default();
+    let samples = usize::try_from(frame.frame_length()).unwrap_or_default();
+    let channels: Vec<_> = (0..format.num_channels)
+        .map(|c| {
+            // SAFETY: Each channel holds `samples` samples of `bytes` bytes.
+            unsafe { std::slice::from_raw_parts(frame.channel(c), samples * bytes) }
+        })
+        .collect();
+    interleave_channels(format, &channels, samples, out);
+}
+
+fn interleave_channels(
+    format: &AudioFormat,
+    channels: &[&[u8]],
+    samples: usize,
+    out: &mut Vec<f32>,
+) {
+    let bytes = usize::try_from(format.bytes_per_sample).unwrap_or_default();
+    #[allow(clippy::cast_precision_loss)]
+    let scale = 1.0 / (1_u64 << (format.bits_per_sample.clamp(1, 32) - 1)) as f32;
+    out.reserve(samples * channels.len());
+    for i in 0..samples {
+        for channel in channels {
+            let sample = &channel[i * bytes..(i + 1) * bytes];
+            #[allow(clippy::cast_precision_loss)]
+            out.push(match (format.sample_type, bytes) {
+                (SampleType::Float, 4) => f32::from_ne_bytes(sample.try_into().unwrap()),
+                (SampleType::Integer, 2) => {
+                    f32::from(i16::from_ne_bytes(sample.try_into().unwrap())) * scale
+                }
+                (SampleType::Integer, 4) => {
+                    i32::from_ne_bytes(sample.try_into().unwrap()) as f32 * scale
+                }
+                _ => 0.0,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interleaving() {
+        let format = AudioFormat {
+            sample_type: SampleType::Integer,
+            bits_per_sample: 24,
+            bytes_per_sample: 4,
+            num_channels: 2,
+            channel_layout: 3,
+        };
+        let left = [0_i32, 1 << 22].map(i32::to_ne_bytes);
+        let right = [-(1 << 23), 0].map(i32::to_ne_bytes);
+        let mut out = Vec::new();
+        interleave_channels(
+            &format,
+            &[left.as_flattened(), right.as_flattened()],
+            2,
+            &mut out,
+        );
+        assert_eq!(out, [0.0, -1.0, 0.5, 0.0]);
+    }
+}
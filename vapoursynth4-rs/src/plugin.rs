@@ -1,26 +1,72 @@
+pub mod config;
 pub mod plugin_function;
+pub mod registry;
+pub mod requirements;
+pub mod signature;
 pub mod types;
 
-use std::{borrow::Borrow, ffi::CStr, ptr::NonNull};
+use std::{
+    borrow::Borrow, ffi::CStr, fmt::Display, iter::FusedIterator, marker::PhantomData, ptr::NonNull,
+};
 
-use crate::{api::Api, core::Core, ffi, map::Map};
+use thiserror::Error;
 
+use crate::{
+    api::Api,
+    core::{
+        Core,
+        keep_alive::{CoreObject, KeepAlive},
+    },
+    ffi,
+    map::{Map, MapPropertyError},
+};
+
+pub use config::*;
 pub use plugin_function::*;
+pub use registry::*;
+pub use requirements::*;
+pub use signature::*;
 pub use types::*;
 
+/// A plugin loaded in a [`Core`]. It is owned by the core, so it cannot outlive it:
+///
+/// ```compile_fail
+/// # fn f(core: vapoursynth4_rs::core::Core) {
+/// let std = core.plugin_by_namespace(c"std").unwrap();
+/// drop(core);
+/// println!("{}", std.name().to_string_lossy());
+/// # }
+/// ```
 #[derive(PartialEq, Eq, Hash, Debug)]
-pub struct Plugin {
+pub struct Plugin<'c> {
     handle: NonNull<ffi::VSPlugin>,
     api: Api,
+    keep_alive: KeepAlive,
+    marker: PhantomData<&'c Core>,
 }
 
-unsafe impl Send for Plugin {}
-unsafe impl Sync for Plugin {}
+unsafe impl Send for Plugin<'_> {}
+unsafe impl Sync for Plugin<'_> {}
+
+impl CoreObject for Plugin<'_> {
+    fn keep_alive(&self) -> &KeepAlive {
+        &self.keep_alive
+    }
 
-impl Plugin {
+    fn keep_alive_mut(&mut self) -> &mut KeepAlive {
+        &mut self.keep_alive
+    }
+}
+
+impl<'c> Plugin<'c> {
     #[must_use]
     pub fn new(handle: NonNull<ffi::VSPlugin>, api: Api) -> Self {
-        Self { handle, api }
+        Self {
+            handle,
+            api,
+            keep_alive: KeepAlive::NONE,
+            marker: PhantomData,
+        }
     }
 
     #[must_use]
@@ -52,15 +98,30 @@ impl Plugin {
         }
     }
 
-    #[must_use]
-    pub fn invoke(&self, name: &CStr, args: impl Borrow<Map>) -> Map {
-        unsafe {
+    /// Calls the function `name` of this plugin with `args`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::FunctionNotFound`] if the plugin has no such function, or
+    /// [`InvokeError::Failed`] with the message the function left in its output map.
+    pub fn invoke(&self, name: &CStr, args: impl Borrow<Map>) -> Result<Map, InvokeError> {
+        if self.get_function_by_name(name).is_none() {
+            return Err(InvokeError::FunctionNotFound {
+                namespace: self.namespace().to_string_lossy().into_owned(),
+                name: name.to_string_lossy().into_owned(),
+            });
+        }
+        let out = unsafe {
             let ptr = (self.api.invoke)(
                 self.as_ptr().cast_mut(),
                 name.as_ptr(),
                 args.borrow().as_ptr(),
             );
-            Map::from_ptr(ptr, self.api)
+            Map::from_ptr(ptr, self.api).kept_by(&self.keep_alive)
+        };
+        match out.get_error() {
+            Some(msg) => Err(InvokeError::Failed(msg.to_string_lossy().into_owned())),
+            None => Ok(out),
         }
     }
 
@@ -70,7 +131,7 @@ impl Plugin {
     }
 
     #[must_use]
-    pub fn get_function_by_name(&self, name: &CStr) -> Option<PluginFunction> {
+    pub fn get_function_by_name(&self, name: &CStr) -> Option<PluginFunction<'c>> {
         unsafe {
             NonNull::new((self.api.getPluginFunctionByName)(
                 name.as_ptr(),
@@ -94,6 +155,42 @@ impl Plugin {
     }
 }
 
+/// Formats as `namespace (identifier) name major.minor`, e.g. for listing the loaded plugins.
+impl Display for Plugin<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let version = self.version();
+        write!(
+            f,
+            "{} ({}) {} {}.{}",
+            self.namespace().to_string_lossy(),
+            self.id().to_string_lossy(),
+            self.name().to_string_lossy(),
+            version >> 16,
+            version & 0xffff,
+        )
+    }
+}
+
+/// Error returned by [`Plugin::invoke`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Error)]
+pub enum InvokeError {
+    /// No plugin has the namespace, see [`Core::invoke`].
+    #[error("No plugin with the namespace `{0}` is loaded")]
+    PluginNotFound(String),
+    /// The plugin has no function of that name.
+    #[error("The function `{namespace}.{name}` does not exist")]
+    FunctionNotFound { namespace: String, name: String },
+    /// The function reported an error, e.g. for an invalid argument.
+    #[error("{0}")]
+    Failed(String),
+    /// A typed wrapper rejected an argument before invoking the function.
+    #[error("{0}")]
+    InvalidArgument(String),
+    /// The arguments could not be written, see [`Core::invoke_with`].
+    #[error("Invalid arguments: {0}")]
+    Arguments(#[from] MapPropertyError),
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Plugins<'c> {
     cursor: *mut ffi::VSPlugin,
@@ -109,8 +206,10 @@ impl<'c> Plugins<'c> {
     }
 }
 
-impl Iterator for Plugins<'_> {
-    type Item = Plugin;
+impl FusedIterator for Plugins<'_> {}
+
+impl<'c> Iterator for Plugins<'c> {
+    type Item = Plugin<'c>;
 
     fn next(&mut self) -> Option<Self::Item> {
         unsafe {
@@ -118,38 +217,98 @@ impl Iterator for Plugins<'_> {
             let ptr = (api.getNextPlugin)(self.cursor, self.core.as_ptr());
             NonNull::new(ptr).map(|p| {
                 self.cursor = ptr;
-                Plugin::new(p, api)
+                Plugin::new(p, api).kept_by(self.core.keep_alive())
             })
         }
     }
 }
 
+/// Declares the `VapourSynthPluginInit2` entry point of a plugin.
+///
+/// The arguments are the plugin identifier, namespace and full name as C string literals,
+/// the plugin version as `(major, minor)`, the required API version, the
+/// [`VSPluginConfigFlags`](crate::ffi::VSPluginConfigFlags), and then the filters to register.
+/// A filter is either a `(Filter, data)` pair registered with [`RawFilterConstructor::create`]
+/// and its [`FilterData`](crate::node::Filter::FilterData), or the type of a
+/// [`FilterConstructor`] alone.
+///
+/// [`RawFilterConstructor::create`]: crate::node::RawFilterConstructor::create
+/// [`FilterConstructor`]: crate::node::FilterConstructor
+///
+/// Two filters with the same [`NAME`](crate::node::Filter::NAME) fail the build.
+///
+/// ```ignore
+/// declare_plugin!(
+///     c"com.example.invert",
+///     c"invert",
+///     c"VapourSynth Filter Skeleton",
+///     (1, 0),
+///     vapoursynth4_rs::VAPOURSYNTH_API_VERSION,
+///     0,
+///     (InvertFilter, None),
+///     filters::BrightenFilter,
+/// );
+/// ```
 #[macro_export]
 macro_rules! declare_plugin {
-    ($id:literal, $name:literal, $desc:literal,
+    (@names [$($names:expr),*] $(,)?) => {
+        [$($names),*]
+    };
+    (@names [$($names:expr),*], ($filter:ty, $data:expr) $(, $($rest:tt)*)?) => {
+        $crate::declare_plugin!(
+            @names [$($names,)* <$filter as $crate::node::Filter>::NAME] $(, $($rest)*)?
+        )
+    };
+    (@names [$($names:expr),*], $filter:ty $(, $($rest:tt)*)?) => {
+        $crate::declare_plugin!(
+            @names [$($names,)* <$filter as $crate::node::Filter>::NAME] $(, $($rest)*)?
+        )
+    };
+    (@register $registry:ident $(,)?) => {};
+    (@register $registry:ident, ($filter:ty, $data:expr) $(, $($rest:tt)*)?) => {
+        $registry.filter::<$filter>($data);
+        $crate::declare_plugin!(@register $registry $(, $($rest)*)?);
+    };
+    (@register $registry:ident, $filter:ty $(, $($rest:tt)*)?) => {
+        $registry.constructor::<$filter>();
+        $crate::declare_plugin!(@register $registry $(, $($rest)*)?);
+    };
+    ($id:expr, $name:expr, $desc:expr,
         $version:expr,
         $api_version:expr, $flags:expr
-        $(, ($filter:tt, $data:expr) )*
+        $(, $($filters:tt)*)?
     ) => {
         #[unsafe(no_mangle)]
         pub unsafe extern "system-unwind" fn VapourSynthPluginInit2(
             plugin: *mut $crate::ffi::VSPlugin,
             vspapi: *const $crate::ffi::VSPLUGINAPI,
         ) {
+            const _: () = $crate::plugin::assert_unique_names(
+                &$crate::declare_plugin!(@names [] $(, $($filters)*)?),
+            );
+            const ID: &::std::ffi::CStr = $id;
+            const NAMESPACE: &::std::ffi::CStr = $name;
+            const NAME: &::std::ffi::CStr = $desc;
+
+            let config = $crate::plugin::PluginConfig {
+                identifier: ID,
+                namespace: NAMESPACE,
+                name: NAME,
+                plugin_version: $crate::utils::make_version($version.0, $version.1),
+                api_version: $api_version,
+                flags: $flags,
+            };
+
             unsafe {
-                ((*vspapi).configPlugin)(
-                    $id.as_ptr(),
-                    $name.as_ptr(),
-                    $desc.as_ptr(),
-                    $crate::utils::make_version($version.0, $version.1),
-                    $crate::VAPOURSYNTH_API_VERSION,
-                    $flags,
-                    plugin,
-                );
-
-                $(
-                    $crate::node::FilterRegister::<$filter>::new($data).register(plugin, vspapi);
-                )*
+                if !config.configure(plugin, vspapi) {
+                    return;
+                }
+
+                let mut registry = $crate::plugin::PluginRegistry::new();
+                $crate::declare_plugin!(@register registry $(, $($filters)*)?);
+                // The names are unique, which is checked above when the plugin is built. A function
+                // the core rejects is left out, as there is no way to fail the plugin loading.
+                let _ = registry.register(plugin, vspapi);
             }
         }
     };
@@ -0,0 +1,138 @@
+use std::ffi::CStr;
+
+use bon::Builder;
+
+use crate::{api::PluginApi, ffi, utils::make_version};
+
+/// Identity of a plugin, passed to `configPlugin` when the plugin is loaded.
+///
+/// ```
+/// use vapoursynth4_rs::plugin::PluginConfig;
+///
+/// let config = PluginConfig::builder()
+///     .identifier(c"com.example.invert")
+///     .namespace(c"invert")
+///     .name(c"VapourSynth Filter Skeleton")
+///     .plugin_version(1, 0)
+///     .build();
+/// assert_eq!(config.flags, 0);
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Builder)]
+pub struct PluginConfig<'a> {
+    /// [`VSPluginConfigFlags`](ffi::VSPluginConfigFlags) `ORed` together, usually 0.
+    #[builder(field)]
+    pub flags: i32,
+    /// Reverse URL that uniquely identifies the plugin, e.g. `com.example.invert`.
+    pub identifier: &'a CStr,
+    /// Namespace the functions are registered under, e.g. `std`.
+    pub namespace: &'a CStr,
+    /// Human readable name of the plugin.
+    pub name: &'a CStr,
+    /// Version of the plugin, see [`make_version`].
+    #[builder(with = |major: u16, minor: u16| make_version(major, minor))]
+    pub plugin_version: i32,
+    /// Minimum API version the plugin requires.
+    #[builder(default = ffi::VAPOURSYNTH_API_VERSION)]
+    pub api_version: i32,
+}
+
+impl<S: plugin_config_builder::State> PluginConfigBuilder<'_, S> {
+    /// Allow functions to be registered after the plugin loading phase. Plugins are read-only by
+    /// default.
+    pub fn modifiable(mut self) -> Self {
+        self.flags |= ffi::VSPluginConfigFlags::Modifiable as i32;
+        self
+    }
+}
+
+impl PluginConfig<'_> {
+    /// Configures `plugin`, which must happen before any function is registered. Returns `false`
+    /// if the core rejected the configuration, e.g. for an unsupported API version.
+    ///
+    /// # Safety
+    ///
+    /// `plugin` and `vspapi` must be the pointers passed to `VapourSynthPluginInit2`.
+    #[must_use]
+    pub unsafe fn configure(
+        &self,
+        plugin: *mut ffi::VSPlugin,
+        vspapi: *const ffi::VSPLUGINAPI,
+    ) -> bool {
+        unsafe { PluginApi::from_ptr(vspapi).config_plugin(plugin, self) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::{c_char, c_int, c_void},
+        sync::Mutex,
+    };
+
+    use super::*;
+
+    static CONFIGURED: Mutex<Option<(String, i32, i32)>> = Mutex::new(None);
+
+    unsafe extern "system-unwind" fn get_api_version() -> c_int {
+        ffi::VAPOURSYNTH_API_VERSION
+    }
+
+    unsafe extern "system-unwind" fn config_plugin(
+        identifier: *const c_char,
+        _namespace: *const c_char,
+        _name: *const c_char,
+        plugin_version: c_int,
+        api_version: c_int,
+        _flags: c_int,
+        _plugin: *mut ffi::VSPlugin,
+    ) -> c_int {
+        let identifier = unsafe { CStr::from_ptr(identifier) };
+        *CONFIGURED.lock().unwrap() = Some((
+            identifier.to_string_lossy().into_owned(),
+            plugin_version,
+            api_version,
+        ));
+        1
+    }
+
+    unsafe extern "system-unwind" fn register_function(
+        _name: *const c_char,
+        _args: *const c_char,
+        _return_type: *const c_char,
+        _func: ffi::VSPublicFunction,
+        _data: *mut c_void,
+        _plugin: *mut ffi::VSPlugin,
+    ) -> c_int {
+        0
+    }
+
+    #[test]
+    fn configure() {
+        let vspapi = ffi::VSPLUGINAPI {
+            getAPIVersion: get_api_version,
+            configPlugin: config_plugin,
+            registerFunction: register_function,
+        };
+        let api = unsafe { PluginApi::from_ptr(&raw const vspapi) };
+        assert_eq!(
+            api.version(),
+            (ffi::VAPOURSYNTH_API_MAJOR, ffi::VAPOURSYNTH_API_MINOR)
+        );
+
+        let config = PluginConfig::builder()
+            .identifier(c"com.example.invert")
+            .namespace(c"invert")
+            .name(c"Invert")
+            .plugin_version(1, 2)
+            .build();
+        assert!(unsafe { config.configure(std::ptr::null_mut(), &raw const vspapi) });
+        assert_eq!(
+            CONFIGURED.lock().unwrap().take(),
+            Some((
+                "com.example.invert".to_owned(),
+                make_version(1, 2),
+                ffi::VAPOURSYNTH_API_VERSION
+            ))
+        );
+    }
+}
@@ -1,23 +1,31 @@
-use std::{ffi::CStr, ptr::NonNull};
+use std::{ffi::CStr, iter::FusedIterator, marker::PhantomData, ptr::NonNull};
 
-use crate::{api::Api, ffi};
+use thiserror::Error;
 
-use super::Plugin;
+use crate::{api::Api, core::Core, ffi};
 
+use super::{Plugin, Type};
+
+/// A function of a [`Plugin`], valid as long as the core that loaded the plugin.
 #[derive(PartialEq, Eq, Hash, Debug)]
-pub struct PluginFunction {
+pub struct PluginFunction<'c> {
     handle: NonNull<ffi::VSPluginFunction>,
     api: Api,
+    marker: PhantomData<&'c Core>,
 }
 
-impl PluginFunction {
+impl PluginFunction<'_> {
     pub(crate) fn from_ptr(ptr: NonNull<ffi::VSPluginFunction>, api: Api) -> Self {
-        Self { handle: ptr, api }
+        Self::new(ptr, api)
     }
 
     #[must_use]
     pub fn new(handle: NonNull<ffi::VSPluginFunction>, api: Api) -> Self {
-        Self { handle, api }
+        Self {
+            handle,
+            api,
+            marker: PhantomData,
+        }
     }
 
     #[must_use]
@@ -48,16 +56,111 @@ impl PluginFunction {
             CStr::from_ptr(ptr)
         }
     }
+
+    /// Parses [`arguments`](Self::arguments).
+    ///
+    /// # Errors
+    ///
+    /// Return [`SignatureParseError`] if the string is malformed, which only happens with a
+    /// newer `VapourSynth` that adds types this crate does not know.
+    pub fn parsed_arguments(&self) -> Result<ParsedSignature, SignatureParseError> {
+        ParsedSignature::parse(&self.arguments().to_string_lossy())
+    }
+
+    /// Parses [`return_type`](Self::return_type).
+    ///
+    /// # Errors
+    ///
+    /// See [`parsed_arguments`](Self::parsed_arguments).
+    pub fn parsed_return_type(&self) -> Result<ParsedSignature, SignatureParseError> {
+        ParsedSignature::parse(&self.return_type().to_string_lossy())
+    }
 }
 
+/// One `name:type[:opt][:empty];` entry of a signature string.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct FunctionArgument {
+    pub name: String,
+    pub ty: Type,
+    /// The argument may be left unset.
+    pub optional: bool,
+    /// An array argument may be set with no elements.
+    pub empty: bool,
+}
+
+impl FunctionArgument {
+    #[must_use]
+    pub fn is_array(&self) -> bool {
+        matches!(self.ty, Type::Array(_))
+    }
+}
+
+/// A signature string split into its arguments.
+///
+/// ```
+/// use vapoursynth4_rs::plugin::{ParsedSignature, Type};
+///
+/// let sig = ParsedSignature::parse("clip:vnode;planes:int[]:opt;").unwrap();
+/// assert_eq!(sig.arguments[1].name, "planes");
+/// assert_eq!(sig.arguments[1].ty, Type::Array(Box::new(Type::Int)));
+/// assert!(sig.arguments[1].optional);
+/// assert!(!sig.any);
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct ParsedSignature {
+    pub arguments: Vec<FunctionArgument>,
+    /// The signature ends with `any`, so any additional keys are accepted.
+    pub any: bool,
+}
+
+impl ParsedSignature {
+    /// # Errors
+    ///
+    /// Return [`SignatureParseError`] for an entry with a missing name, an unknown type or an
+    /// unknown flag.
+    pub fn parse(s: &str) -> Result<Self, SignatureParseError> {
+        let mut sig = Self::default();
+        for entry in s.split(';').filter(|e| !e.is_empty()) {
+            if entry == "any" {
+                sig.any = true;
+                continue;
+            }
+
+            let error = || SignatureParseError(entry.to_owned());
+            let mut parts = entry.split(':');
+            let name = parts.next().filter(|n| !n.is_empty()).ok_or_else(error)?;
+            let ty = parts.next().and_then(Type::from_args).ok_or_else(error)?;
+            let mut arg = FunctionArgument {
+                name: name.to_owned(),
+                ty,
+                optional: false,
+                empty: false,
+            };
+            for flag in parts {
+                match flag {
+                    "opt" => arg.optional = true,
+                    "empty" => arg.empty = true,
+                    _ => return Err(error()),
+                }
+            }
+            sig.arguments.push(arg);
+        }
+        Ok(sig)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+#[error("Invalid signature entry `{0}`")]
+pub struct SignatureParseError(pub String);
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Functions<'p> {
     cursor: *mut ffi::VSPluginFunction,
-    plugin: &'p Plugin,
+    plugin: &'p Plugin<'p>,
 }
 
 impl<'p> Functions<'p> {
-    pub(crate) fn new(plugin: &'p Plugin) -> Functions<'p> {
+    pub(crate) fn new(plugin: &'p Plugin<'p>) -> Functions<'p> {
         Self {
             cursor: std::ptr::null_mut(),
             plugin,
@@ -65,8 +168,10 @@ impl<'p> Functions<'p> {
     }
 }
 
-impl Iterator for Functions<'_> {
-    type Item = PluginFunction;
+impl FusedIterator for Functions<'_> {}
+
+impl<'p> Iterator for Functions<'p> {
+    type Item = PluginFunction<'p>;
 
     fn next(&mut self) -> Option<Self::Item> {
         unsafe {
@@ -81,3 +186,43 @@ impl Iterator for Functions<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let sig =
+            ParsedSignature::parse("clip:vnode;func:func:opt:empty;weights:float[]:empty;any")
+                .unwrap();
+        assert!(sig.any);
+        assert_eq!(
+            sig.arguments,
+            [
+                FunctionArgument {
+                    name: "clip".to_owned(),
+                    ty: Type::VNode,
+                    optional: false,
+                    empty: false,
+                },
+                FunctionArgument {
+                    name: "func".to_owned(),
+                    ty: Type::Func,
+                    optional: true,
+                    empty: true,
+                },
+                FunctionArgument {
+                    name: "weights".to_owned(),
+                    ty: Type::Array(Box::new(Type::Float)),
+                    optional: false,
+                    empty: true,
+                },
+            ]
+        );
+        assert_eq!(
+            ParsedSignature::parse("clip:vnod;"),
+            Err(SignatureParseError("clip:vnod".to_owned()))
+        );
+    }
+}
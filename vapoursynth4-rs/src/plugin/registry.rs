@@ -0,0 +1,256 @@
+use std::{collections::HashSet, ffi::CStr};
+
+use thiserror::Error;
+
+use crate::{
+    ffi,
+    node::{FilterConstructor, FilterRegister, RawFilterConstructor},
+};
+
+use super::{FunctionSignature, PublicFunction};
+
+type Register = Box<dyn FnOnce(*mut ffi::VSPlugin, *const ffi::VSPLUGINAPI) -> bool>;
+
+struct Entry {
+    name: &'static CStr,
+    register: Register,
+}
+
+/// Collects every function of a plugin, so they can be checked and registered together.
+///
+/// ```
+/// use vapoursynth4_rs::{
+///     core::CoreRef,
+///     map::MapRef,
+///     plugin::{FunctionSignature, PluginRegistry, RegistryError},
+/// };
+///
+/// fn version(_: MapRef, _: MapRef, _: CoreRef) -> Result<(), &'static std::ffi::CStr> {
+///     Ok(())
+/// }
+///
+/// let mut registry = PluginRegistry::new();
+/// registry.function(c"Version", FunctionSignature::new(), version);
+/// registry.function(c"Version", FunctionSignature::new(), version);
+/// assert_eq!(
+///     registry.check(),
+///     Err(RegistryError::DuplicateName("Version".to_owned()))
+/// );
+/// ```
+#[derive(Default)]
+pub struct PluginRegistry {
+    entries: Vec<Entry>,
+}
+
+impl PluginRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a [`Filter`](crate::node::Filter) constructed by [`RawFilterConstructor::create`]
+    /// with `data`.
+    pub fn filter<F: RawFilterConstructor + 'static>(
+        &mut self,
+        data: Option<F::FilterData>,
+    ) -> &mut Self
+    where
+        F::FilterData: 'static,
+    {
+        self.push(F::NAME, move |plugin, vspapi| unsafe {
+            FilterRegister::<F>::new(data).register(plugin, vspapi)
+        })
+    }
+
+    /// Adds a [`FilterConstructor`].
+    pub fn constructor<F: FilterConstructor + 'static>(&mut self) -> &mut Self {
+        self.push(F::NAME, |plugin, vspapi| unsafe {
+            FilterRegister::<F>::new(None).register_constructor(plugin, vspapi)
+        })
+    }
+
+    /// Adds a plain function, see [`FunctionSignature::register`].
+    pub fn function<E: AsRef<CStr> + 'static>(
+        &mut self,
+        name: &'static CStr,
+        signature: FunctionSignature,
+        func: PublicFunction<E>,
+    ) -> &mut Self {
+        self.push(name, move |plugin, vspapi| unsafe {
+            signature.register(name, func, plugin, vspapi)
+        })
+    }
+
+    fn push(
+        &mut self,
+        name: &'static CStr,
+        register: impl FnOnce(*mut ffi::VSPlugin, *const ffi::VSPLUGINAPI) -> bool + 'static,
+    ) -> &mut Self {
+        self.entries.push(Entry {
+            name,
+            register: Box::new(register),
+        });
+        self
+    }
+
+    /// The names of the added functions, in order.
+    pub fn names(&self) -> impl Iterator<Item = &CStr> {
+        self.entries.iter().map(|e| e.name)
+    }
+
+    /// # Errors
+    ///
+    /// Return [`RegistryError::DuplicateName`] for the first name that was added twice.
+    pub fn check(&self) -> Result<(), RegistryError> {
+        let mut seen = HashSet::new();
+        match self.names().find(|name| !seen.insert(*name)) {
+            Some(name) => Err(RegistryError::DuplicateName(
+                name.to_string_lossy().into_owned(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Registers every function, after [checking](Self::check) that the names are unique.
+    /// Nothing is registered if the check fails.
+    ///
+    /// # Errors
+    ///
+    /// Return [`RegistryError::DuplicateName`] for the first name that was added twice, or
+    /// [`RegistryError::Rejected`] for the first function the core refused, e.g. for an invalid
+    /// signature. The functions before it stay registered.
+    ///
+    /// # Safety
+    ///
+    /// `plugin` and `vspapi` must be the pointers passed to `VapourSynthPluginInit2`, after the
+    /// plugin was configured.
+    pub unsafe fn register(
+        self,
+        plugin: *mut ffi::VSPlugin,
+        vspapi: *const ffi::VSPLUGINAPI,
+    ) -> Result<(), RegistryError> {
+        self.check()?;
+        for entry in self.entries {
+            if !(entry.register)(plugin, vspapi) {
+                return Err(RegistryError::Rejected(
+                    entry.name.to_string_lossy().into_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Panics if two of `names` are equal. [`declare_plugin!`](crate::declare_plugin) evaluates it
+/// at compile time, so a duplicate filter name fails the build instead of the plugin loading.
+#[doc(hidden)]
+pub const fn assert_unique_names(names: &[&CStr]) {
+    let mut i = 0;
+    while i < names.len() {
+        let mut j = i + 1;
+        while j < names.len() {
+            assert!(
+                !bytes_eq(names[i].to_bytes(), names[j].to_bytes()),
+                "two filters of the plugin have the same name"
+            );
+            j += 1;
+        }
+        i += 1;
+    }
+}
+
+const fn bytes_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Error)]
+pub enum RegistryError {
+    #[error("The function `{0}` is registered more than once")]
+    DuplicateName(String),
+    #[error("The core refused to register the function `{0}`")]
+    Rejected(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{c_char, c_int, c_void};
+
+    use super::*;
+    use crate::{core::CoreRef, map::MapRef};
+
+    unsafe extern "system-unwind" fn get_api_version() -> c_int {
+        ffi::VAPOURSYNTH_API_VERSION
+    }
+
+    unsafe extern "system-unwind" fn config_plugin(
+        _identifier: *const c_char,
+        _namespace: *const c_char,
+        _name: *const c_char,
+        _plugin_version: c_int,
+        _api_version: c_int,
+        _flags: c_int,
+        _plugin: *mut ffi::VSPlugin,
+    ) -> c_int {
+        1
+    }
+
+    // Rejects the functions whose name starts with `Bad`, like a core rejecting their signature.
+    unsafe extern "system-unwind" fn register_function(
+        name: *const c_char,
+        _args: *const c_char,
+        _return_type: *const c_char,
+        _func: ffi::VSPublicFunction,
+        _data: *mut c_void,
+        _plugin: *mut ffi::VSPlugin,
+    ) -> c_int {
+        (!unsafe { CStr::from_ptr(name) }
+            .to_bytes()
+            .starts_with(b"Bad"))
+        .into()
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn version(_: MapRef, _: MapRef, _: CoreRef) -> Result<(), &'static CStr> {
+        Ok(())
+    }
+
+    const _: () = assert_unique_names(&[c"Invert", c"Inverse", c"Inv"]);
+
+    #[test]
+    #[should_panic(expected = "two filters of the plugin have the same name")]
+    fn duplicate_names() {
+        assert_unique_names(&[c"Invert", c"Blur", c"Invert"]);
+    }
+
+    #[test]
+    fn rejected() {
+        let vspapi = ffi::VSPLUGINAPI {
+            getAPIVersion: get_api_version,
+            configPlugin: config_plugin,
+            registerFunction: register_function,
+        };
+        let register = |names: &[&'static CStr]| {
+            let mut registry = PluginRegistry::new();
+            for name in names {
+                registry.function(name, FunctionSignature::new(), version);
+            }
+            unsafe { registry.register(std::ptr::null_mut(), &raw const vspapi) }
+        };
+
+        assert_eq!(register(&[c"Version", c"Info"]), Ok(()));
+        assert_eq!(
+            register(&[c"Version", c"BadVersion", c"Info"]),
+            Err(RegistryError::Rejected("BadVersion".to_owned()))
+        );
+    }
+}
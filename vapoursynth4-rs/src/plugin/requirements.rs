@@ -0,0 +1,149 @@
+use std::{
+    ffi::{CStr, CString},
+    fmt::Display,
+};
+
+use thiserror::Error;
+
+use crate::{core::Core, utils::make_version};
+
+/// A list of plugins that must be loaded in a core, checked all at once.
+///
+/// Checking up front gives one error listing everything that is missing, instead of failing at
+/// the first `invoke` of an absent function.
+///
+/// ```no_run
+/// # fn check(core: &vapoursynth4_rs::core::Core) {
+/// use vapoursynth4_rs::plugin::PluginRequirements;
+///
+/// let result = PluginRequirements::new()
+///     .require(c"com.vapoursynth.std")
+///     .require_version(c"com.vapoursynth.resize", 2, 0)
+///     .check(core);
+/// if let Err(e) = result {
+///     eprintln!("{e}");
+/// }
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct PluginRequirements {
+    plugins: Vec<(CString, Option<i32>)>,
+}
+
+impl PluginRequirements {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires the plugin `id` in any version.
+    #[must_use]
+    pub fn require(mut self, id: &CStr) -> Self {
+        self.plugins.push((id.to_owned(), None));
+        self
+    }
+
+    /// Requires the plugin `id` in version `major.minor` or newer.
+    #[must_use]
+    pub fn require_version(mut self, id: &CStr, major: u16, minor: u16) -> Self {
+        self.plugins
+            .push((id.to_owned(), Some(make_version(major, minor))));
+        self
+    }
+
+    /// # Errors
+    ///
+    /// Return [`MissingPlugins`] listing every requirement that `core` does not meet.
+    pub fn check(&self, core: &Core) -> Result<(), MissingPlugins> {
+        let missing: Vec<_> = self
+            .plugins
+            .iter()
+            .filter_map(|(id, min)| {
+                let id_str = id.to_string_lossy().into_owned();
+                match (core.plugin_by_id(id), min) {
+                    (None, _) => Some(MissingPlugin::NotLoaded { id: id_str }),
+                    (Some(plugin), Some(min)) if plugin.version() < *min => {
+                        Some(MissingPlugin::TooOld {
+                            id: id_str,
+                            required: *min,
+                            found: plugin.version(),
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(MissingPlugins { missing })
+        }
+    }
+}
+
+/// A single unmet [`PluginRequirements`] entry.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum MissingPlugin {
+    NotLoaded {
+        id: String,
+    },
+    /// Versions are in the [`make_version`] encoding.
+    TooOld {
+        id: String,
+        required: i32,
+        found: i32,
+    },
+}
+
+impl Display for MissingPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let version = |v: &i32| format!("{}.{}", v >> 16, v & 0xffff);
+        match self {
+            Self::NotLoaded { id } => write!(f, "`{id}` is not loaded"),
+            Self::TooOld {
+                id,
+                required,
+                found,
+            } => write!(
+                f,
+                "`{id}` {} is loaded, but {} or newer is required",
+                version(found),
+                version(required)
+            ),
+        }
+    }
+}
+
+/// Error returned by [`PluginRequirements::check`].
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+#[error("Missing plugins: {}", .missing.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+pub struct MissingPlugins {
+    pub missing: Vec<MissingPlugin>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let e = MissingPlugins {
+            missing: vec![
+                MissingPlugin::NotLoaded {
+                    id: "com.example.a".to_owned(),
+                },
+                MissingPlugin::TooOld {
+                    id: "com.example.b".to_owned(),
+                    required: make_version(2, 1),
+                    found: make_version(1, 0),
+                },
+            ],
+        };
+        assert_eq!(
+            e.to_string(),
+            "Missing plugins: `com.example.a` is not loaded; \
+             `com.example.b` 1.0 is loaded, but 2.1 or newer is required"
+        );
+    }
+}
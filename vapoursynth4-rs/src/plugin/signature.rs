@@ -0,0 +1,386 @@
+use std::{
+    ffi::{CStr, CString, c_void},
+    fmt::Write,
+    panic::AssertUnwindSafe,
+};
+
+use crate::{
+    api::{Api, PluginApi},
+    core::CoreRef,
+    ffi,
+    frame::{AudioFrame, VideoFrame},
+    function::Function,
+    map::{Key, KeyStr, MapRef},
+    node::{AudioNode, VideoNode, internal::panic_message},
+    utils::ToCString,
+};
+
+use super::Type;
+
+/// Rust types that can be used as a plugin function argument or return value.
+pub trait ArgType {
+    fn arg_type() -> Type;
+}
+
+/// Rust types that can be the elements of an array argument, i.e. every [`ArgType`] except
+/// arrays, as `VapourSynth` has no nested arrays.
+///
+/// ```compile_fail
+/// use vapoursynth4_rs::{key, plugin::FunctionSignature};
+///
+/// let _ = FunctionSignature::new().arg::<Vec<Vec<i64>>>(key!(c"matrix"));
+/// ```
+pub trait ElementType: ArgType {}
+
+macro_rules! impl_arg_type {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl ArgType for $ty {
+                fn arg_type() -> Type {
+                    Type::$variant
+                }
+            }
+
+            impl ElementType for $ty {}
+        )*
+    };
+}
+
+impl_arg_type!(
+    i64 => Int,
+    f64 => Float,
+    String => Data,
+    &str => Data,
+    VideoNode => VNode,
+    AudioNode => ANode,
+    VideoFrame => VFrame,
+    AudioFrame => AFrame,
+    Function => Func,
+);
+
+impl<T: ElementType> ArgType for Vec<T> {
+    fn arg_type() -> Type {
+        Type::Array(Box::new(T::arg_type()))
+    }
+}
+
+struct Argument {
+    name: Key,
+    ty: Type,
+    optional: bool,
+}
+
+/// Builder for the argument and return type strings of a plugin function.
+///
+/// ```
+/// use vapoursynth4_rs::{key, node::VideoNode, plugin::FunctionSignature};
+///
+/// let sig = FunctionSignature::new()
+///     .arg::<VideoNode>(key!(c"clip"))
+///     .optional::<i64>(key!(c"radius"))
+///     .returns::<VideoNode>(key!(c"clip"));
+/// assert_eq!(sig.args_string().as_c_str(), c"clip:vnode;radius:int:opt;");
+/// assert_eq!(sig.return_string().as_c_str(), c"clip:vnode;");
+/// ```
+#[derive(Default)]
+pub struct FunctionSignature {
+    args: Vec<Argument>,
+    returns: Vec<Argument>,
+    returns_any: bool,
+}
+
+/// A plugin function callback that can be registered with [`FunctionSignature::register`].
+///
+/// Errors are written to `output` and panics are caught before they reach `VapourSynth`.
+pub type PublicFunction<E> = fn(input: MapRef, output: MapRef, core: CoreRef) -> Result<(), E>;
+
+impl FunctionSignature {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a required argument.
+    #[must_use]
+    pub fn arg<T: ArgType>(mut self, name: &KeyStr) -> Self {
+        self.args.push(Argument {
+            name: name.into(),
+            ty: T::arg_type(),
+            optional: false,
+        });
+        self
+    }
+
+    /// Adds an optional argument.
+    #[must_use]
+    pub fn optional<T: ArgType>(mut self, name: &KeyStr) -> Self {
+        self.args.push(Argument {
+            name: name.into(),
+            ty: T::arg_type(),
+            optional: true,
+        });
+        self
+    }
+
+    /// Adds a return value.
+    #[must_use]
+    pub fn returns<T: ArgType>(mut self, name: &KeyStr) -> Self {
+        self.returns.push(Argument {
+            name: name.into(),
+            ty: T::arg_type(),
+            optional: false,
+        });
+        self
+    }
+
+    /// Marks the function as returning arbitrary values, i.e. a return type of `any`.
+    #[must_use]
+    pub fn returns_any(mut self) -> Self {
+        self.returns_any = true;
+        self
+    }
+
+    /// Renders the argument string, e.g. `clip:vnode;radius:int:opt;`.
+    #[must_use]
+    pub fn args_string(&self) -> CString {
+        render(&self.args).into_cstring_lossy()
+    }
+
+    /// Renders the return type string, e.g. `clip:vnode;`.
+    #[must_use]
+    pub fn return_string(&self) -> CString {
+        if self.returns_any {
+            c"any".into()
+        } else {
+            render(&self.returns).into_cstring_lossy()
+        }
+    }
+
+    /// Registers `func` as the plugin function `name`.
+    ///
+    /// Returns whether the core accepted the function. It rejects e.g. an invalid signature.
+    ///
+    /// # Safety
+    ///
+    /// `plugin` and `vspapi` must be the pointers passed to `VapourSynthPluginInit2`.
+    pub unsafe fn register<E: AsRef<CStr>>(
+        &self,
+        name: &CStr,
+        func: PublicFunction<E>,
+        plugin: *mut ffi::VSPlugin,
+        vspapi: *const ffi::VSPLUGINAPI,
+    ) -> bool {
+        let args = self.args_string();
+        let returns = self.return_string();
+        unsafe {
+            PluginApi::from_ptr(vspapi).register_function(
+                plugin,
+                name,
+                &args,
+                &returns,
+                public_function::<E>,
+                func as *mut c_void,
+            )
+        }
+    }
+}
+
+/// Checks an argument or return type string, e.g. `clip:vnode;radius:int:opt;`, so it can be
+/// used in a `const`. Prefer the [`signature!`](crate::signature) macro.
+///
+/// # Panics
+///
+/// Panic if an entry is not `name:type` followed by `:opt` or `:empty` flags and `;`, where
+/// `type` is one of the `VapourSynth` types, optionally with `[]`. The return type `any` is also
+/// accepted.
+#[must_use]
+pub const fn validate_signature(sig: &CStr) -> &CStr {
+    let s = sig.to_bytes();
+    if bytes_eq(s, 0, s.len(), b"any") {
+        return sig;
+    }
+
+    let mut i = 0;
+    while i < s.len() {
+        let start = i;
+        while i < s.len() && s[i] != b':' {
+            assert!(
+                s[i].is_ascii_alphanumeric() || s[i] == b'_',
+                "Argument names must be alphanumeric or underscore"
+            );
+            i += 1;
+        }
+        assert!(i > start, "Argument name is empty");
+        assert!(
+            !s[start].is_ascii_digit(),
+            "Argument names must not start with a digit"
+        );
+        assert!(i < s.len(), "Argument is missing its type");
+        i += 1;
+
+        let start = i;
+        while i < s.len() && s[i] != b':' && s[i] != b';' {
+            i += 1;
+        }
+        assert!(is_type(s, start, i), "Unknown argument type");
+
+        while i < s.len() && s[i] == b':' {
+            i += 1;
+            let start = i;
+            while i < s.len() && s[i] != b':' && s[i] != b';' {
+                i += 1;
+            }
+            assert!(
+                bytes_eq(s, start, i, b"opt") || bytes_eq(s, start, i, b"empty"),
+                "Unknown argument flag, expected `opt` or `empty`"
+            );
+        }
+        assert!(i < s.len(), "Argument must end with `;`");
+        i += 1;
+    }
+    sig
+}
+
+const fn bytes_eq(s: &[u8], start: usize, end: usize, other: &[u8]) -> bool {
+    if end - start != other.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < other.len() {
+        if s[start + i] != other[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn is_type(s: &[u8], start: usize, mut end: usize) -> bool {
+    const TYPES: [&[u8]; 8] = [
+        b"int", b"float", b"data", b"anode", b"vnode", b"aframe", b"vframe", b"func",
+    ];
+
+    if end - start > 2 && s[end - 2] == b'[' && s[end - 1] == b']' {
+        end -= 2;
+    }
+    let mut i = 0;
+    while i < TYPES.len() {
+        if bytes_eq(s, start, end, TYPES[i]) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Builds an argument or return type string from string literals and validates it at compile
+/// time with [`validate_signature`](crate::plugin::validate_signature).
+///
+/// ```
+/// use std::ffi::CStr;
+///
+/// use vapoursynth4_rs::signature;
+///
+/// const ARGS: &CStr = signature!("clip:vnode;", "planes:int[]:opt;");
+/// assert_eq!(ARGS, c"clip:vnode;planes:int[]:opt;");
+/// ```
+///
+/// A typo fails to compile:
+///
+/// ```compile_fail
+/// const ARGS: &std::ffi::CStr = vapoursynth4_rs::signature!("clip:vnod;");
+/// ```
+#[macro_export]
+macro_rules! signature {
+    ($($part:literal),* $(,)?) => {
+        const {
+            match ::std::ffi::CStr::from_bytes_with_nul(concat!($($part,)* "\0").as_bytes()) {
+                Ok(sig) => $crate::plugin::validate_signature(sig),
+                Err(_) => panic!("Signature must not contain NUL"),
+            }
+        }
+    };
+}
+
+fn render(args: &[Argument]) -> String {
+    args.iter().fold(String::new(), |mut s, arg| {
+        let _ = write!(s, "{}:{}", arg.name, arg.ty.to_args());
+        if arg.optional {
+            s.push_str(":opt");
+        }
+        s.push(';');
+        s
+    })
+}
+
+unsafe extern "system-unwind" fn public_function<E: AsRef<CStr>>(
+    in_: *const ffi::VSMap,
+    out: *mut ffi::VSMap,
+    user_data: *mut c_void,
+    core: *mut ffi::VSCore,
+    vsapi: *const ffi::VSAPI,
+) {
+    unsafe {
+        let api = Api::from_ptr(vsapi);
+        let func: PublicFunction<E> = std::mem::transmute(user_data);
+        let input = MapRef::from_ptr(in_, api);
+        let mut output = MapRef::from_ptr(out, api);
+        let core = CoreRef::from_ptr(core, api);
+
+        match std::panic::catch_unwind(AssertUnwindSafe(|| func(input, output, core))) {
+            Ok(Err(e)) => output.set_error(e.as_ref()),
+            Err(p) => output.set_error(&panic_message(p)),
+            Ok(Ok(())) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key;
+
+    #[test]
+    fn render_signature() {
+        let sig = FunctionSignature::new()
+            .arg::<VideoNode>(key!(c"clip"))
+            .optional::<Vec<i64>>(key!(c"planes"))
+            .optional::<f64>(key!(c"strength"))
+            .returns::<VideoNode>(key!(c"clip"));
+        assert_eq!(
+            sig.args_string().as_c_str(),
+            c"clip:vnode;planes:int[]:opt;strength:float:opt;"
+        );
+        assert_eq!(sig.return_string().as_c_str(), c"clip:vnode;");
+        assert_eq!(
+            FunctionSignature::new()
+                .returns_any()
+                .return_string()
+                .as_c_str(),
+            c"any"
+        );
+    }
+
+    #[test]
+    fn validate() {
+        for sig in [
+            c"",
+            c"any",
+            c"clip:vnode;",
+            c"clip:anode;planes:int[]:opt;func:func:opt:empty;_x1:data[]:empty;",
+        ] {
+            assert_eq!(validate_signature(sig), sig);
+        }
+        let sig = FunctionSignature::new()
+            .arg::<VideoNode>(key!(c"clip"))
+            .optional::<Vec<f64>>(key!(c"weights"));
+        let args = sig.args_string();
+        assert_eq!(validate_signature(&args), args.as_c_str());
+    }
+
+    #[test]
+    #[should_panic = "Unknown argument type"]
+    fn validate_type() {
+        let _ = validate_signature(c"clip:vnod;");
+    }
+}
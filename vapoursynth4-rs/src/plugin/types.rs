@@ -2,6 +2,7 @@ use std::{borrow::Cow, collections::HashMap};
 
 use crate::map::{Key, KeyStr};
 
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Type {
     Int,
     Float,
@@ -30,6 +31,28 @@ impl Type {
             t::Array(t) => t.to_args() + "[]",
         }
     }
+
+    /// Parses a type as written in a signature string, e.g. `int[]`.
+    #[must_use]
+    pub fn from_args(s: &str) -> Option<Self> {
+        use Type as t;
+        if let Some(inner) = s.strip_suffix("[]") {
+            return Self::from_args(inner)
+                .filter(|t| !matches!(t, t::Array(_)))
+                .map(|t| t::Array(Box::new(t)));
+        }
+        Some(match s {
+            "int" => t::Int,
+            "float" => t::Float,
+            "data" => t::Data,
+            "anode" => t::ANode,
+            "vnode" => t::VNode,
+            "aframe" => t::AFrame,
+            "vframe" => t::VFrame,
+            "func" => t::Func,
+            _ => return None,
+        })
+    }
 }
 
 pub struct TypeBuilder {
@@ -0,0 +1,55 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Typed wrappers for the functions of common plugins.
+//!
+//! The arguments are checked before the function is invoked where the rules are simple, so
+//! mistakes are reported with a message that names the argument.
+
+pub mod resize;
+pub mod source;
+pub mod std;
+pub mod stubs;
+
+use ::std::ffi::CStr;
+
+use crate::{
+    core::Core,
+    key,
+    map::IntoMap,
+    node::{AudioNode, VideoNode},
+    plugin::InvokeError,
+};
+
+fn invoke_video(
+    core: &Core,
+    namespace: &CStr,
+    function: &CStr,
+    args: impl IntoMap,
+) -> Result<VideoNode, InvokeError> {
+    core.invoke_with(namespace, function, args)?
+        .get_video_node(key!(c"clip"), 0)
+        .map_err(|e| InvokeError::Failed(e.to_string()))
+}
+
+fn invoke_audio(
+    core: &Core,
+    namespace: &CStr,
+    function: &CStr,
+    args: impl IntoMap,
+) -> Result<AudioNode, InvokeError> {
+    core.invoke_with(namespace, function, args)?
+        .get_audio_node(key!(c"clip"), 0)
+        .map_err(|e| InvokeError::Failed(e.to_string()))
+}
+
+fn ensure(condition: bool, message: impl FnOnce() -> String) -> Result<(), InvokeError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(InvokeError::InvalidArgument(message()))
+    }
+}
@@ -0,0 +1,217 @@
+//! The built-in `resize` plugin.
+
+use std::ffi::CStr;
+
+use bon::bon;
+
+use crate::{PresetVideoFormat, core::Core, ffi, node::VideoNode, plugin::InvokeError, vsmap};
+
+use super::{ensure, invoke_video};
+
+pub type Matrix = ffi::VSMatrixCoefficients;
+pub type Transfer = ffi::VSTransferCharacteristics;
+pub type Primaries = ffi::VSColorPrimaries;
+pub type ColorRange = ffi::VSColorRange;
+pub type ChromaLocation = ffi::VSChromaLocation;
+
+/// The scaling kernels of the `resize` plugin, each one is a separate function.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Kernel {
+    Point,
+    Bilinear,
+    /// `filter_param_a` and `filter_param_b` are `b` and `c`.
+    Bicubic,
+    /// `filter_param_a` is the number of taps.
+    Lanczos,
+    Spline16,
+    Spline36,
+    Spline64,
+}
+
+impl Kernel {
+    #[must_use]
+    pub fn function_name(self) -> &'static CStr {
+        match self {
+            Self::Point => c"Point",
+            Self::Bilinear => c"Bilinear",
+            Self::Bicubic => c"Bicubic",
+            Self::Lanczos => c"Lanczos",
+            Self::Spline16 => c"Spline16",
+            Self::Spline36 => c"Spline36",
+            Self::Spline64 => c"Spline64",
+        }
+    }
+}
+
+/// The functions of the `resize` plugin, see [`Core::resize`].
+///
+/// ```no_run
+/// # fn f(
+/// #     core: &vapoursynth4_rs::core::Core,
+/// #     clip: &vapoursynth4_rs::node::VideoNode,
+/// # ) -> Result<(), vapoursynth4_rs::plugin::InvokeError> {
+/// use vapoursynth4_rs::{
+///     PresetVideoFormat,
+///     plugins::resize::Matrix,
+/// };
+///
+/// let clip = core
+///     .resize()
+///     .bicubic(clip)
+///     .width(1280)
+///     .height(720)
+///     .matrix_in(Matrix::VSC_MATRIX_BT709)
+///     .format(PresetVideoFormat::YUV420P8)
+///     .call()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Resize<'c> {
+    core: &'c Core,
+}
+
+impl Core {
+    /// Typed wrappers for the built-in `resize` plugin.
+    #[must_use]
+    pub fn resize(&self) -> Resize<'_> {
+        Resize { core: self }
+    }
+}
+
+#[bon]
+impl Resize<'_> {
+    /// Converts `clip` with `kernel`. Unset arguments keep the property of the input, or are
+    /// read from the frame properties.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] for a non-positive size, otherwise see
+    /// [`Core::invoke`].
+    #[builder(builder_type = ResizeBuilder)]
+    pub fn resize(
+        self,
+        #[builder(start_fn)] kernel: Kernel,
+        #[builder(start_fn)] clip: &VideoNode,
+        width: Option<i32>,
+        height: Option<i32>,
+        format: Option<PresetVideoFormat>,
+        matrix: Option<Matrix>,
+        transfer: Option<Transfer>,
+        primaries: Option<Primaries>,
+        range: Option<ColorRange>,
+        chromaloc: Option<ChromaLocation>,
+        matrix_in: Option<Matrix>,
+        transfer_in: Option<Transfer>,
+        primaries_in: Option<Primaries>,
+        range_in: Option<ColorRange>,
+        chromaloc_in: Option<ChromaLocation>,
+        filter_param_a: Option<f64>,
+        filter_param_b: Option<f64>,
+        src_left: Option<f64>,
+        src_top: Option<f64>,
+        src_width: Option<f64>,
+        src_height: Option<f64>,
+        dither_type: Option<String>,
+    ) -> Result<VideoNode, InvokeError> {
+        ensure(
+            width.is_none_or(|w| w > 0) && height.is_none_or(|h| h > 0),
+            || format!("Resize: size must be positive, got {width:?}x{height:?}"),
+        )?;
+
+        let clip = clip.clone();
+        let format = format.map(|f| f as i32);
+        let matrix = matrix.map(|v| v as i32);
+        let transfer = transfer.map(|v| v as i32);
+        let primaries = primaries.map(|v| v as i32);
+        let range = range.map(|v| v as i32);
+        let chromaloc = chromaloc.map(|v| v as i32);
+        let matrix_in = matrix_in.map(|v| v as i32);
+        let transfer_in = transfer_in.map(|v| v as i32);
+        let primaries_in = primaries_in.map(|v| v as i32);
+        let range_in = range_in.map(|v| v as i32);
+        let chromaloc_in = chromaloc_in.map(|v| v as i32);
+        invoke_video(
+            self.core,
+            c"resize",
+            kernel.function_name(),
+            vsmap! {
+                clip,
+                width,
+                height,
+                format,
+                matrix,
+                transfer,
+                primaries,
+                range,
+                chromaloc,
+                matrix_in,
+                transfer_in,
+                primaries_in,
+                range_in,
+                chromaloc_in,
+                filter_param_a,
+                filter_param_b,
+                src_left,
+                src_top,
+                src_width,
+                src_height,
+                dither_type,
+            },
+        )
+    }
+}
+
+macro_rules! kernels {
+    ($($method:ident => $kernel:ident),* $(,)?) => {
+        impl<'c> Resize<'c> {
+            $(
+                #[doc = concat!("`resize.", stringify!($kernel), "`, see [`resize`](Self::resize).")]
+                pub fn $method(self, clip: &VideoNode) -> ResizeBuilder<'c, '_> {
+                    self.resize(Kernel::$kernel, clip)
+                }
+            )*
+        }
+    };
+}
+
+kernels!(
+    point => Point,
+    bilinear => Bilinear,
+    bicubic => Bicubic,
+    lanczos => Lanczos,
+    spline16 => Spline16,
+    spline36 => Spline36,
+    spline64 => Spline64,
+);
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize() {
+        let core = Core::builder().build();
+        let clip = core
+            .std()
+            .blank_clip()
+            .width(64)
+            .height(48)
+            .format(PresetVideoFormat::RGB24)
+            .call()
+            .unwrap();
+        let resized = core
+            .resize()
+            .bicubic(&clip)
+            .width(32)
+            .height(24)
+            .matrix(Matrix::VSC_MATRIX_BT709)
+            .format(PresetVideoFormat::YUV420P8)
+            .call()
+            .unwrap();
+        assert_eq!((resized.info().width, resized.info().height), (32, 24));
+        assert_eq!(resized.info().format.color_family, crate::ColorFamily::YUV);
+    }
+}
@@ -0,0 +1,138 @@
+//! Opening files with the common source plugins.
+
+use std::{ffi::CStr, fmt::Display, path::Path};
+
+use thiserror::Error;
+
+use crate::{core::Core, node::VideoNode, plugin::InvokeError, vsmap};
+
+use super::invoke_video;
+
+/// A third-party source plugin that [`Core::open_video`] can use.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SourcePlugin {
+    /// `bs.VideoSource`
+    BestSource,
+    /// `ffms2.Source`
+    Ffms2,
+    /// `lsmas.LWLibavSource`
+    LSmash,
+}
+
+impl SourcePlugin {
+    /// Every source plugin, in the order [`Core::open_video`] tries them.
+    pub const ALL: [Self; 3] = [Self::BestSource, Self::Ffms2, Self::LSmash];
+
+    #[must_use]
+    pub fn namespace(self) -> &'static CStr {
+        match self {
+            Self::BestSource => c"bs",
+            Self::Ffms2 => c"ffms2",
+            Self::LSmash => c"lsmas",
+        }
+    }
+
+    #[must_use]
+    pub fn function(self) -> &'static CStr {
+        match self {
+            Self::BestSource => c"VideoSource",
+            Self::Ffms2 => c"Source",
+            Self::LSmash => c"LWLibavSource",
+        }
+    }
+
+    /// Whether the plugin is loaded in `core`.
+    #[must_use]
+    pub fn is_available(self, core: &Core) -> bool {
+        core.plugin_by_namespace(self.namespace()).is_some()
+    }
+}
+
+impl Display for SourcePlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}",
+            self.namespace().to_string_lossy(),
+            self.function().to_string_lossy()
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum OpenError {
+    #[error("The path `{0}` is not valid UTF-8")]
+    InvalidPath(String),
+    #[error("No source plugin is installed, install one of bs, ffms2 or lsmas")]
+    NoSourcePlugin,
+    /// Every available plugin failed, with the error of each one.
+    #[error("Failed to open the file: {}", .0.iter().map(|(p, e)| format!("{p}: {e}")).collect::<Vec<_>>().join("; "))]
+    Failed(Vec<(SourcePlugin, InvokeError)>),
+}
+
+impl Core {
+    /// Opens the video file at `path` with the first of [`SourcePlugin::ALL`] that is loaded and
+    /// can read it.
+    ///
+    /// ```no_run
+    /// # fn f(core: &vapoursynth4_rs::core::Core) -> Result<(), vapoursynth4_rs::plugins::source::OpenError> {
+    /// let clip = core.open_video("input.mkv")?;
+    /// println!("{} frames", clip.info().num_frames);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Return [`OpenError::NoSourcePlugin`] if none of the plugins is loaded, or
+    /// [`OpenError::Failed`] if all of them failed.
+    pub fn open_video(&self, path: impl AsRef<Path>) -> Result<VideoNode, OpenError> {
+        self.open_video_with(&SourcePlugin::ALL, path)
+    }
+
+    /// Like [`open_video`](Self::open_video), but tries only `plugins`, in order.
+    ///
+    /// # Errors
+    ///
+    /// See [`open_video`](Self::open_video).
+    pub fn open_video_with(
+        &self,
+        plugins: &[SourcePlugin],
+        path: impl AsRef<Path>,
+    ) -> Result<VideoNode, OpenError> {
+        let path = path.as_ref();
+        let source = path
+            .to_str()
+            .ok_or_else(|| OpenError::InvalidPath(path.display().to_string()))?;
+
+        let mut errors = Vec::new();
+        for &plugin in plugins.iter().filter(|p| p.is_available(self)) {
+            let args = vsmap! { source: source.to_owned() };
+            match invoke_video(self, plugin.namespace(), plugin.function(), args) {
+                Ok(clip) => return Ok(clip),
+                Err(e) => errors.push((plugin, e)),
+            }
+        }
+
+        if errors.is_empty() {
+            Err(OpenError::NoSourcePlugin)
+        } else {
+            Err(OpenError::Failed(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_source_plugin() {
+        let core = Core::builder().disable_auto_loading().build();
+        assert!(matches!(
+            core.open_video("input.mkv"),
+            Err(OpenError::NoSourcePlugin)
+        ));
+    }
+}
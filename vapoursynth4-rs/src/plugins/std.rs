@@ -0,0 +1,513 @@
+//! The built-in `std` plugin.
+
+use bon::bon;
+
+use crate::{
+    ColorFamily, PresetVideoFormat, SampleType,
+    core::Core,
+    ffi,
+    map::IntoMap,
+    node::{AudioNode, VideoNode},
+    plugin::InvokeError,
+    vsmap,
+};
+
+use super::{ensure, invoke_audio, invoke_video};
+
+/// The functions of the `std` plugin, see [`Core::std`].
+///
+/// ```no_run
+/// # fn f(core: &vapoursynth4_rs::core::Core) -> Result<(), vapoursynth4_rs::plugin::InvokeError> {
+/// let clip = core.std().blank_clip().width(640).height(480).length(100).call()?;
+/// let clip = core.std().crop(&clip).left(8).right(8).call()?;
+/// let clip = core.std().trim(&clip).first(10).last(19).call()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Std<'c> {
+    core: &'c Core,
+}
+
+impl Core {
+    /// Typed wrappers for the built-in `std` plugin.
+    #[must_use]
+    pub fn std(&self) -> Std<'_> {
+        Std { core: self }
+    }
+}
+
+#[bon]
+impl Std<'_> {
+    /// `std.BlankClip`: a clip of a single color, with the properties of `clip` if given.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] for a non-positive size or frame rate, otherwise
+    /// see [`Core::invoke`].
+    #[builder]
+    pub fn blank_clip(
+        self,
+        clip: Option<&VideoNode>,
+        width: Option<i32>,
+        height: Option<i32>,
+        format: Option<PresetVideoFormat>,
+        length: Option<i32>,
+        fpsnum: Option<i64>,
+        fpsden: Option<i64>,
+        color: Option<Vec<f64>>,
+        keep: Option<bool>,
+    ) -> Result<VideoNode, InvokeError> {
+        ensure(width.is_none_or(|w| w > 0), || {
+            format!("BlankClip: width must be positive, got {width:?}")
+        })?;
+        ensure(height.is_none_or(|h| h > 0), || {
+            format!("BlankClip: height must be positive, got {height:?}")
+        })?;
+        ensure(length.is_none_or(|l| l > 0), || {
+            format!("BlankClip: length must be positive, got {length:?}")
+        })?;
+        ensure(
+            fpsnum.is_none_or(|n| n >= 0) && fpsden.is_none_or(|d| d > 0),
+            || format!("BlankClip: invalid frame rate {fpsnum:?}/{fpsden:?}"),
+        )?;
+
+        let clip = clip.cloned();
+        let format = format.map(|f| f as i32);
+        invoke_video(
+            self.core,
+            c"std",
+            c"BlankClip",
+            vsmap! { clip, width, height, format, length, fpsnum, fpsden, color, keep },
+        )
+    }
+
+    /// `std.Crop`: removes the given number of pixels from each side.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] for negative amounts or if nothing would be left,
+    /// otherwise see [`Core::invoke`].
+    #[builder]
+    pub fn crop(
+        self,
+        #[builder(start_fn)] clip: &VideoNode,
+        #[builder(default)] left: i32,
+        #[builder(default)] right: i32,
+        #[builder(default)] top: i32,
+        #[builder(default)] bottom: i32,
+    ) -> Result<VideoNode, InvokeError> {
+        ensure(left >= 0 && right >= 0 && top >= 0 && bottom >= 0, || {
+            format!("Crop: amounts must not be negative, got {left}, {right}, {top}, {bottom}")
+        })?;
+        let info = clip.info();
+        if info.width != 0 {
+            ensure(
+                left + right < info.width && top + bottom < info.height,
+                || {
+                    format!(
+                        "Crop: cannot crop {left}, {right}, {top}, {bottom} from a {}x{} clip",
+                        info.width, info.height
+                    )
+                },
+            )?;
+        }
+
+        invoke_video(
+            self.core,
+            c"std",
+            c"Crop",
+            vsmap! { clip: clip.clone(), left, right, top, bottom },
+        )
+    }
+
+    /// `std.Trim`: keeps the frames from `first` to `last`, both inclusive, or `length` frames.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] if both `last` and `length` are given or the
+    /// range is empty, otherwise see [`Core::invoke`].
+    #[builder]
+    pub fn trim(
+        self,
+        #[builder(start_fn)] clip: &VideoNode,
+        #[builder(default)] first: i32,
+        last: Option<i32>,
+        length: Option<i32>,
+    ) -> Result<VideoNode, InvokeError> {
+        ensure(last.is_none() || length.is_none(), || {
+            "Trim: last and length are mutually exclusive".to_owned()
+        })?;
+        ensure(
+            first >= 0 && last.is_none_or(|l| l >= first) && length.is_none_or(|l| l > 0),
+            || format!("Trim: invalid range, first {first}, last {last:?}, length {length:?}"),
+        )?;
+
+        invoke_video(
+            self.core,
+            c"std",
+            c"Trim",
+            vsmap! { clip: clip.clone(), first, last, length },
+        )
+    }
+
+    /// `std.Splice`: joins `clips` end to end. With `mismatch`, clips of different formats and
+    /// sizes are allowed.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] if `clips` is empty, otherwise see
+    /// [`Core::invoke`].
+    #[builder]
+    pub fn splice(
+        self,
+        #[builder(start_fn)] clips: &[VideoNode],
+        #[builder(default)] mismatch: bool,
+    ) -> Result<VideoNode, InvokeError> {
+        ensure(!clips.is_empty(), || "Splice: no clips given".to_owned())?;
+
+        invoke_video(
+            self.core,
+            c"std",
+            c"Splice",
+            vsmap! { clips: clips.to_vec(), mismatch },
+        )
+    }
+
+    /// `std.AssumeFPS`: changes the frame rate without adding or dropping frames.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] for a non-positive frame rate, otherwise see
+    /// [`Core::invoke`].
+    #[builder]
+    pub fn assume_fps(
+        self,
+        #[builder(start_fn)] clip: &VideoNode,
+        fpsnum: i64,
+        #[builder(default = 1)] fpsden: i64,
+    ) -> Result<VideoNode, InvokeError> {
+        ensure(fpsnum > 0 && fpsden > 0, || {
+            format!("AssumeFPS: invalid frame rate {fpsnum}/{fpsden}")
+        })?;
+
+        invoke_video(
+            self.core,
+            c"std",
+            c"AssumeFPS",
+            vsmap! { clip: clip.clone(), fpsnum, fpsden },
+        )
+    }
+
+    /// `std.ShufflePlanes`: builds a clip of `color_family` from `planes[i]` of `clips[i]`.
+    /// If fewer clips than planes are given, the last clip is repeated.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] if the number of planes does not match
+    /// `color_family`, otherwise see [`Core::invoke`].
+    #[builder]
+    pub fn shuffle_planes(
+        self,
+        #[builder(start_fn)] clips: &[VideoNode],
+        #[builder(start_fn)] planes: &[i64],
+        #[builder(start_fn)] color_family: ColorFamily,
+    ) -> Result<VideoNode, InvokeError> {
+        let expected = match color_family {
+            ColorFamily::Gray => 1,
+            ColorFamily::RGB | ColorFamily::YUV => 3,
+            _ => 0,
+        };
+        ensure(planes.len() == expected, || {
+            format!(
+                "ShufflePlanes: {color_family:?} needs {expected} planes, got {}",
+                planes.len()
+            )
+        })?;
+        ensure(!clips.is_empty() && clips.len() <= planes.len(), || {
+            format!(
+                "ShufflePlanes: expected 1 to {expected} clips, got {}",
+                clips.len()
+            )
+        })?;
+
+        invoke_video(
+            self.core,
+            c"std",
+            c"ShufflePlanes",
+            vsmap! {
+                clips: clips.to_vec(),
+                planes: planes.to_vec(),
+                colorfamily: color_family as i32,
+            },
+        )
+    }
+
+    /// `std.Reverse`: plays the clip backwards.
+    ///
+    /// # Errors
+    ///
+    /// See [`Core::invoke`].
+    pub fn reverse(self, clip: &VideoNode) -> Result<VideoNode, InvokeError> {
+        invoke_video(self.core, c"std", c"Reverse", vsmap! { clip: clip.clone() })
+    }
+
+    /// `std.Loop`: repeats the clip `times` times, or for as long as possible with 0.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] if `times` is negative, otherwise see
+    /// [`Core::invoke`].
+    pub fn repeat(self, clip: &VideoNode, times: i32) -> Result<VideoNode, InvokeError> {
+        ensure(times >= 0, || {
+            format!("Loop: times must not be negative, got {times}")
+        })?;
+
+        invoke_video(
+            self.core,
+            c"std",
+            c"Loop",
+            vsmap! { clip: clip.clone(), times },
+        )
+    }
+
+    /// `std.SetFrameProps`: sets the frame properties written by `props` on every frame.
+    ///
+    /// ```no_run
+    /// # fn f(core: &vapoursynth4_rs::core::Core, clip: &vapoursynth4_rs::node::VideoNode) {
+    /// use vapoursynth4_rs::vsmap;
+    ///
+    /// let clip = core.std().set_frame_props(clip, vsmap! { _Matrix: 1, _Transfer: 1 });
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`Core::invoke_with`].
+    pub fn set_frame_props(
+        self,
+        clip: &VideoNode,
+        props: impl IntoMap,
+    ) -> Result<VideoNode, InvokeError> {
+        let clip = clip.clone();
+        invoke_video(
+            self.core,
+            c"std",
+            c"SetFrameProps",
+            crate::map::MapFn(move |map: &mut crate::map::Map| {
+                crate::map::MapField::set_field(clip, map, crate::key!(c"clip"))?;
+                props.into_map(map)
+            }),
+        )
+    }
+}
+
+// MARK: Audio
+
+#[bon]
+impl Std<'_> {
+    /// `std.BlankAudio`: silence, with the properties of `clip` if given. `length` is in samples.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] for a non-positive sample rate or length,
+    /// otherwise see [`Core::invoke`].
+    #[builder]
+    pub fn blank_audio(
+        self,
+        clip: Option<&AudioNode>,
+        channels: Option<Vec<ffi::VSAudioChannels>>,
+        bits: Option<i32>,
+        sampletype: Option<SampleType>,
+        samplerate: Option<i32>,
+        length: Option<i64>,
+        keep: Option<bool>,
+    ) -> Result<AudioNode, InvokeError> {
+        ensure(samplerate.is_none_or(|r| r > 0), || {
+            format!("BlankAudio: samplerate must be positive, got {samplerate:?}")
+        })?;
+        ensure(length.is_none_or(|l| l > 0), || {
+            format!("BlankAudio: length must be positive, got {length:?}")
+        })?;
+
+        let clip = clip.cloned();
+        let channels = channels.map(|c| c.into_iter().map(|c| c as i32).collect::<Vec<_>>());
+        let sampletype = sampletype.map(|t| t as i32);
+        invoke_audio(
+            self.core,
+            c"std",
+            c"BlankAudio",
+            vsmap! { clip, channels, bits, sampletype, samplerate, length, keep },
+        )
+    }
+
+    /// `std.AudioTrim`: keeps the samples from `first` to `last`, both inclusive, or `length`
+    /// samples.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] if both `last` and `length` are given or the
+    /// range is empty, otherwise see [`Core::invoke`].
+    #[builder]
+    pub fn audio_trim(
+        self,
+        #[builder(start_fn)] clip: &AudioNode,
+        #[builder(default)] first: i64,
+        last: Option<i64>,
+        length: Option<i64>,
+    ) -> Result<AudioNode, InvokeError> {
+        ensure(last.is_none() || length.is_none(), || {
+            "AudioTrim: last and length are mutually exclusive".to_owned()
+        })?;
+        ensure(
+            first >= 0 && last.is_none_or(|l| l >= first) && length.is_none_or(|l| l > 0),
+            || format!("AudioTrim: invalid range, first {first}, last {last:?}, length {length:?}"),
+        )?;
+
+        invoke_audio(
+            self.core,
+            c"std",
+            c"AudioTrim",
+            vsmap! { clip: clip.clone(), first, last, length },
+        )
+    }
+
+    /// `std.AudioSplice`: joins `clips` end to end. All clips must have the same format.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] if `clips` is empty, otherwise see
+    /// [`Core::invoke`].
+    pub fn audio_splice(self, clips: &[AudioNode]) -> Result<AudioNode, InvokeError> {
+        ensure(!clips.is_empty(), || {
+            "AudioSplice: no clips given".to_owned()
+        })?;
+
+        invoke_audio(
+            self.core,
+            c"std",
+            c"AudioSplice",
+            vsmap! { clips: clips.to_vec() },
+        )
+    }
+
+    /// `std.AudioMix`: mixes the channels of `clips` into `channels_out`. `matrix` has one row
+    /// of weights per output channel, with one weight per input channel.
+    ///
+    /// A clip with more than one channel must be listed once per channel, see the `VapourSynth`
+    /// documentation.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] if the size of `matrix` does not match, otherwise
+    /// see [`Core::invoke`].
+    pub fn audio_mix(
+        self,
+        clips: &[AudioNode],
+        matrix: &[f64],
+        channels_out: &[ffi::VSAudioChannels],
+    ) -> Result<AudioNode, InvokeError> {
+        let inputs = clips.len();
+        ensure(inputs > 0, || "AudioMix: no clips given".to_owned())?;
+        ensure(matrix.len() == inputs * channels_out.len(), || {
+            format!(
+                "AudioMix: matrix needs {} weights for {inputs} inputs and {} outputs, got {}",
+                inputs * channels_out.len(),
+                channels_out.len(),
+                matrix.len()
+            )
+        })?;
+
+        invoke_audio(
+            self.core,
+            c"std",
+            c"AudioMix",
+            vsmap! {
+                clips: clips.to_vec(),
+                matrix: matrix.to_vec(),
+                channels_out: channels_out.iter().map(|&c| c as i32).collect::<Vec<_>>(),
+            },
+        )
+    }
+
+    /// `std.AssumeSampleRate`: changes the sample rate without resampling.
+    ///
+    /// # Errors
+    ///
+    /// Return [`InvokeError::InvalidArgument`] for a non-positive sample rate, otherwise see
+    /// [`Core::invoke`].
+    pub fn assume_sample_rate(
+        self,
+        clip: &AudioNode,
+        samplerate: i32,
+    ) -> Result<AudioNode, InvokeError> {
+        ensure(samplerate > 0, || {
+            format!("AssumeSampleRate: samplerate must be positive, got {samplerate}")
+        })?;
+
+        invoke_audio(
+            self.core,
+            c"std",
+            c"AssumeSampleRate",
+            vsmap! { clip: clip.clone(), samplerate },
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn std() {
+        let core = Core::builder().build();
+        let std = core.std();
+        let clip = std
+            .blank_clip()
+            .width(64)
+            .height(48)
+            .length(10)
+            .call()
+            .unwrap();
+        let cropped = std.crop(&clip).left(8).top(8).call().unwrap();
+        assert_eq!((cropped.info().width, cropped.info().height), (56, 40));
+        let trimmed = std.trim(&cropped).first(2).last(5).call().unwrap();
+        assert_eq!(trimmed.info().num_frames, 4);
+        let spliced = std.splice(&[trimmed.clone(), trimmed]).call().unwrap();
+        assert_eq!(spliced.info().num_frames, 8);
+
+        assert!(matches!(
+            std.crop(&clip).left(64).call(),
+            Err(InvokeError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn audio() {
+        use ffi::VSAudioChannels as c;
+
+        let core = Core::builder().build();
+        let std = core.std();
+        let stereo = std
+            .blank_audio()
+            .channels(vec![c::FrontLeft, c::FrontRight])
+            .samplerate(48000)
+            .length(48000)
+            .call()
+            .unwrap();
+        assert_eq!(stereo.info().format.num_channels, 2);
+
+        let trimmed = std.audio_trim(&stereo).length(1000).call().unwrap();
+        assert_eq!(trimmed.info().num_samples, 1000);
+        let spliced = std.audio_splice(&[trimmed.clone(), trimmed]).unwrap();
+        assert_eq!(spliced.info().num_samples, 2000);
+        let slowed = std.assume_sample_rate(&spliced, 24000).unwrap();
+        assert_eq!(slowed.info().sample_rate, 24000);
+
+        assert!(matches!(
+            std.audio_mix(&[stereo], &[0.5], &[c::FrontCenter]),
+            Err(InvokeError::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,280 @@
+//! Generates typed wrappers for the plugins installed on the build machine.
+//!
+//! Call [`generate_stubs`] from a build script and include the output:
+//!
+//! ```no_run
+//! # fn build(core: &vapoursynth4_rs::core::Core) {
+//! // build.rs, with `core` from `Core::builder().build()`
+//! use vapoursynth4_rs::plugins::stubs::generate_stubs;
+//!
+//! let code = generate_stubs(core, &[c"std", c"resize"]).unwrap();
+//! let out = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+//! std::fs::write(out.join("plugins.rs"), code).unwrap();
+//! # }
+//! ```
+//!
+//! ```ignore
+//! // lib.rs
+//! include!(concat!(env!("OUT_DIR"), "/plugins.rs"));
+//!
+//! let clip = Std::new(&core).crop(clip, Some(8), Some(8), None, None)?;
+//! ```
+//!
+//! Each plugin becomes a struct named after its namespace, with one method per function.
+//! Required arguments are taken by value and optional ones as [`Option`]. Functions returning
+//! a single clip return the node, others return the output [`Map`](crate::map::Map).
+
+use std::{ffi::CStr, fmt::Write};
+
+use thiserror::Error;
+
+use crate::{
+    core::Core,
+    plugin::{ParsedSignature, Plugin, SignatureParseError, Type},
+};
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+pub enum StubError {
+    #[error("No plugin with the namespace `{0}` is loaded")]
+    PluginNotFound(String),
+    #[error("Cannot parse the signature of `{function}`: {source}")]
+    Signature {
+        function: String,
+        source: SignatureParseError,
+    },
+}
+
+/// Generates Rust source code wrapping every function of the plugins in `namespaces`.
+///
+/// # Errors
+///
+/// Return [`StubError::PluginNotFound`] if a namespace is not loaded in `core`, or
+/// [`StubError::Signature`] if a signature uses a type this crate does not know.
+pub fn generate_stubs(core: &Core, namespaces: &[&CStr]) -> Result<String, StubError> {
+    let mut out = String::from(
+        "// @generated by vapoursynth4_rs::plugins::stubs, do not edit.\n\
+         #[allow(dead_code, unused_imports, unused_mut, clippy::all, clippy::pedantic)]\n\
+         mod vapoursynth_plugins {\n\
+         use vapoursynth4_rs::{\n\
+         core::Core,\n\
+         frame::{AudioFrame, VideoFrame},\n\
+         function::Function,\n\
+         key,\n\
+         map::{Map, MapField},\n\
+         node::{AudioNode, VideoNode},\n\
+         plugin::InvokeError,\n\
+         };\n",
+    );
+    for namespace in namespaces {
+        let plugin = core
+            .plugin_by_namespace(namespace)
+            .ok_or_else(|| StubError::PluginNotFound(namespace.to_string_lossy().into_owned()))?;
+        generate_plugin(&mut out, &plugin)?;
+    }
+    out.push_str("}\npub use vapoursynth_plugins::*;\n");
+    Ok(out)
+}
+
+fn generate_plugin(out: &mut String, plugin: &Plugin) -> Result<(), StubError> {
+    let namespace = plugin.namespace().to_string_lossy();
+    let _ = write!(
+        out,
+        "\n/// `{namespace}`: {}\n\
+         #[derive(Clone, Copy)]\n\
+         pub struct {ty}<'c> {{\n\
+         core: &'c Core,\n\
+         }}\n\
+         impl<'c> {ty}<'c> {{\n\
+         pub fn new(core: &'c Core) -> Self {{\n\
+         Self {{ core }}\n\
+         }}\n",
+        plugin.name().to_string_lossy(),
+        ty = camel_case(&namespace),
+    );
+
+    for function in plugin.functions() {
+        let name = function.name().to_string_lossy().into_owned();
+        let parse = |sig: &CStr| {
+            ParsedSignature::parse(&sig.to_string_lossy()).map_err(|source| StubError::Signature {
+                function: format!("{namespace}.{name}"),
+                source,
+            })
+        };
+        let args = parse(function.arguments())?;
+        let ret = parse(function.return_type())?;
+        generate_function(out, &namespace, &name, &args, &ret);
+    }
+    out.push_str("}\n");
+    Ok(())
+}
+
+fn generate_function(
+    out: &mut String,
+    namespace: &str,
+    name: &str,
+    args: &ParsedSignature,
+    ret: &ParsedSignature,
+) {
+    let idents: Vec<_> = args.arguments.iter().map(|a| ident(&a.name)).collect();
+
+    let _ = write!(
+        out,
+        "/// `{namespace}.{name}`\npub fn {}(&self",
+        ident(&snake_case(name))
+    );
+    for (arg, ident) in args.arguments.iter().zip(&idents) {
+        let ty = rust_type(&arg.ty);
+        if arg.optional {
+            let _ = write!(out, ", {ident}: Option<{ty}>");
+        } else {
+            let _ = write!(out, ", {ident}: {ty}");
+        }
+    }
+
+    let (ret_ty, ret_expr) = match (ret.any, ret.arguments.as_slice()) {
+        (false, [clip]) if clip.ty == Type::VNode => (
+            "VideoNode",
+            format!(
+                "out.get_video_node(key!(c\"{}\"), 0).map_err(|e| InvokeError::Failed(e.to_string()))",
+                clip.name
+            ),
+        ),
+        (false, [clip]) if clip.ty == Type::ANode => (
+            "AudioNode",
+            format!(
+                "out.get_audio_node(key!(c\"{}\"), 0).map_err(|e| InvokeError::Failed(e.to_string()))",
+                clip.name
+            ),
+        ),
+        _ => ("Map", "Ok(out)".to_owned()),
+    };
+    let _ = writeln!(out, ") -> Result<{ret_ty}, InvokeError> {{");
+
+    out.push_str("let mut args = self.core.create_map();\n");
+    for (arg, ident) in args.arguments.iter().zip(&idents) {
+        let _ = writeln!(
+            out,
+            "MapField::set_field({ident}, &mut args, key!(c\"{}\"))?;",
+            arg.name
+        );
+    }
+    let _ = write!(
+        out,
+        "let out = self.core.invoke(c\"{namespace}\", c\"{name}\", args)?;\n{ret_expr}\n}}\n"
+    );
+}
+
+fn rust_type(ty: &Type) -> String {
+    match ty {
+        Type::Int => "i64".to_owned(),
+        Type::Float => "f64".to_owned(),
+        Type::Data => "String".to_owned(),
+        Type::ANode => "AudioNode".to_owned(),
+        Type::VNode => "VideoNode".to_owned(),
+        Type::AFrame => "AudioFrame".to_owned(),
+        Type::VFrame => "VideoFrame".to_owned(),
+        Type::Func => "Function".to_owned(),
+        Type::Array(ty) => format!("Vec<{}>", rust_type(ty)),
+    }
+}
+
+/// Escapes keywords, which are valid argument names in `VapourSynth`.
+fn ident(name: &str) -> String {
+    const KEYWORDS: [&str; 39] = [
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+        "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "type",
+    ];
+    const RESERVED: [&str; 9] = [
+        "crate", "self", "Self", "super", "typeof", "unsafe", "unsized", "use", "virtual",
+    ];
+
+    if RESERVED.contains(&name) || name == "where" || name == "while" || name == "yield" {
+        format!("{name}_")
+    } else if KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else {
+        name.to_owned()
+    }
+}
+
+/// `BlankClip` to `blank_clip`, `AssumeFPS` to `assume_fps`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<_> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(char::is_ascii_lowercase);
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_lower)
+            {
+                out.push('_');
+            }
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
+
+/// `std` to `Std`, `resize2` to `Resize2`.
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let mut chars = s.chars();
+            chars.next().map_or_else(String::new, |c| {
+                c.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(snake_case("BlankClip"), "blank_clip");
+        assert_eq!(snake_case("AssumeFPS"), "assume_fps");
+        assert_eq!(snake_case("LWLibavSource"), "lw_libav_source");
+        assert_eq!(snake_case("Spline16"), "spline16");
+        assert_eq!(camel_case("std"), "Std");
+        assert_eq!(camel_case("my_plugin"), "MyPlugin");
+        assert_eq!(ident("type"), "r#type");
+        assert_eq!(ident("self"), "self_");
+    }
+
+    #[test]
+    fn function() {
+        let mut out = String::new();
+        generate_function(
+            &mut out,
+            "std",
+            "Crop",
+            &ParsedSignature::parse("clip:vnode;left:int:opt;").unwrap(),
+            &ParsedSignature::parse("clip:vnode;").unwrap(),
+        );
+        assert!(out.contains(
+            "pub fn crop(&self, clip: VideoNode, left: Option<i64>) -> Result<VideoNode, InvokeError>"
+        ));
+        assert!(out.contains("MapField::set_field(left, &mut args, key!(c\"left\"))?;"));
+        assert!(out.contains("self.core.invoke(c\"std\", c\"Crop\", args)?;"));
+    }
+
+    #[test]
+    #[cfg(feature = "link-library")]
+    fn std_stubs_compile() {
+        let core = Core::builder().build();
+        let stubs = generate_stubs(&core, &[c"std"]).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("std_stubs.rs");
+        std::fs::write(&path, stubs + "\nfn main() {}\n").unwrap();
+        // Compiles the stubs against this crate when dropped
+        trybuild::TestCases::new().pass(&path);
+    }
+}
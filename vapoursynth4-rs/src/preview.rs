@@ -0,0 +1,188 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Serving the frames of a clip to preview GUIs over a unix socket.
+//!
+//! A client sends requests of one byte, followed by the frame number for frame requests:
+//!
+//! - `b'I'`: the clip information, as the seven fields of the format, the frame rate
+//!   numerator and denominator as `i64`, and the width, height and number of frames.
+//! - `b'F'` and an `i32`: the frame, as a status byte and, if it is 0, the seven fields of the
+//!   format, the width and height, and the planes without padding. Otherwise the error message
+//!   follows as UTF-8.
+//!
+//! Every response is prefixed with its length as an `u64`, and all numbers are little endian
+//! `i32`s unless noted otherwise.
+//!
+//! ```ignore
+//! let server = PreviewServer::bind("/tmp/preview.sock", node)?;
+//! server.run()?;
+//! ```
+
+use std::{
+    io::{self, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    thread,
+};
+
+use crate::{
+    VideoInfo,
+    frame::VideoFormat,
+    node::{Node, VideoNode},
+    output::write_planes,
+};
+
+const REQUEST_INFO: u8 = b'I';
+const REQUEST_FRAME: u8 = b'F';
+
+/// Serves the frames of a video node to every client connecting to a unix socket.
+#[derive(Debug)]
+pub struct PreviewServer {
+    listener: UnixListener,
+    node: VideoNode,
+}
+
+impl PreviewServer {
+    /// Listens on a new socket at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Return the error of binding the socket, e.g. if `path` already exists.
+    pub fn bind(path: impl AsRef<Path>, node: VideoNode) -> io::Result<Self> {
+        Ok(Self {
+            listener: UnixListener::bind(path)?,
+            node,
+        })
+    }
+
+    #[must_use]
+    pub fn listener(&self) -> &UnixListener {
+        &self.listener
+    }
+
+    /// Accepts clients until accepting fails, serving each on its own thread.
+    ///
+    /// # Errors
+    ///
+    /// Return the error of accepting a client. The errors of clients only disconnect them.
+    pub fn run(&self) -> io::Result<()> {
+        thread::scope(|s| {
+            loop {
+                let (stream, _) = self.listener.accept()?;
+                s.spawn(move || self.serve(stream));
+            }
+        })
+    }
+
+    /// Answers the requests of one client until it disconnects.
+    ///
+    /// # Errors
+    ///
+    /// Return the error of the stream, or [`io::ErrorKind::InvalidData`] for an unknown request.
+    pub fn serve(&self, mut stream: UnixStream) -> io::Result<()> {
+        let mut request = [0; 1];
+        loop {
+            match stream.read_exact(&mut request) {
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                result => result?,
+            }
+            let response = match request[0] {
+                REQUEST_INFO => encode_info(self.node.info()),
+                REQUEST_FRAME => {
+                    let mut n = [0; 4];
+                    stream.read_exact(&mut n)?;
+                    self.encode_frame(i32::from_le_bytes(n))?
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Unknown preview request",
+                    ));
+                }
+            };
+            stream.write_all(&(response.len() as u64).to_le_bytes())?;
+            stream.write_all(&response)?;
+        }
+    }
+
+    fn encode_frame(&self, n: i32) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match self.node.get_frame(n) {
+            Ok(frame) => {
+                out.push(0);
+                put_format(&mut out, frame.get_video_format());
+                put(&mut out, frame.frame_width(0));
+                put(&mut out, frame.frame_height(0));
+                write_planes(&mut out, &frame)?;
+            }
+            Err(message) => {
+                out.push(1);
+                out.extend_from_slice(message.as_bytes());
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn encode_info(info: &VideoInfo) -> Vec<u8> {
+    let mut out = Vec::with_capacity(56);
+    put_format(&mut out, &info.format);
+    out.extend_from_slice(&info.fps_num.to_le_bytes());
+    out.extend_from_slice(&info.fps_den.to_le_bytes());
+    put(&mut out, info.width);
+    put(&mut out, info.height);
+    put(&mut out, info.num_frames);
+    out
+}
+
+fn put_format(out: &mut Vec<u8>, format: &VideoFormat) {
+    put(out, format.color_family as i32);
+    put(out, format.sample_type as i32);
+    put(out, format.bits_per_sample);
+    put(out, format.bytes_per_sample);
+    put(out, format.sub_sampling_w);
+    put(out, format.sub_sampling_h);
+    put(out, format.num_planes);
+}
+
+fn put(out: &mut Vec<u8>, value: i32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorFamily, SampleType, info::video_info};
+
+    #[test]
+    fn info() {
+        let info = video_info()
+            .format(VideoFormat {
+                color_family: ColorFamily::YUV,
+                sample_type: SampleType::Integer,
+                bits_per_sample: 8,
+                bytes_per_sample: 1,
+                sub_sampling_w: 1,
+                sub_sampling_h: 1,
+                num_planes: 3,
+            })
+            .width(640)
+            .height(480)
+            .num_frames(100)
+            .fps(24, 1)
+            .build()
+            .unwrap();
+        let data = encode_info(&info);
+        assert_eq!(data.len(), 56);
+        assert_eq!(&data[..4], &3i32.to_le_bytes());
+        assert_eq!(&data[28..36], &24i64.to_le_bytes());
+        assert_eq!(
+            &data[44..],
+            [640, 480, 100].map(i32::to_le_bytes).as_flattened()
+        );
+    }
+}
@@ -0,0 +1,251 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Exchanging nodes with the `vapoursynth` Python module.
+//!
+//! Python does not expose the nodes behind its `VideoNode` and `AudioNode`, but it passes them to
+//! plugin functions and wraps the nodes they return. So the conversions call bridge functions,
+//! which a plugin registers with [`PluginRegistry::python_bridge`] and which must be loaded into
+//! the core of the Python environment, e.g. with `core.std.LoadPlugin`. The `namespace` arguments
+//! are the namespace of that plugin.
+//!
+//! Both directions check that the node belongs to the expected core, so nodes of different cores
+//! or environments are never mixed.
+//!
+//! ```ignore
+//! #[pyfunction]
+//! fn invert<'py>(clip: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+//!     let py_core = clip.py().import("vapoursynth")?.getattr("core")?;
+//!     let mut core = python::core_from_python(&py_core, "invert")?;
+//!     let node = python::video_node_from_python(clip, "invert", &core)?;
+//!     let inverted = core
+//!         .create_video_filter_node(/* ... */)
+//!         .map_err(|e| PyValueError::new_err(e.to_string()))?;
+//!     python::node_into_python(&py_core, "invert", &core, inverted)
+//! }
+//! ```
+
+use std::{cell::RefCell, ffi::CStr};
+
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use thiserror::Error;
+
+use crate::{
+    core::{Core, CoreRef},
+    key,
+    map::{AppendMode, KeyStr, Map, MapRef, MapValue},
+    node::{AudioNode, Node, VideoNode},
+    plugin::{FunctionSignature, PluginRegistry},
+};
+
+const TAKE_VIDEO: &CStr = c"RsBridgeTakeVideo";
+const TAKE_AUDIO: &CStr = c"RsBridgeTakeAudio";
+const GIVE: &CStr = c"RsBridgeGive";
+const CORE: &CStr = c"RsBridgeCore";
+
+const CLIP: &KeyStr = key!(c"clip");
+
+thread_local! {
+    // Python calls plugin functions on the calling thread, so the bridge functions hand the
+    // values over through this slot: the core of the call, and the node if there is one.
+    static SLOT: RefCell<Option<(CoreRef<'static>, Option<Map>)>> = const { RefCell::new(None) };
+}
+
+/// Error returned when a node cannot be exchanged with Python.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Error)]
+pub enum BridgeError {
+    /// The node belongs to a different core than the one it is used with.
+    #[error("The node belongs to a different core")]
+    DifferentCore,
+    /// The bridge function did not run, e.g. because the plugin is not loaded into the core.
+    #[error("The bridge function of the plugin was not called")]
+    NotCalled,
+}
+
+impl From<BridgeError> for PyErr {
+    fn from(err: BridgeError) -> Self {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+impl PluginRegistry {
+    /// Adds the bridge functions used by the [`python`](crate::python) conversions.
+    pub fn python_bridge(&mut self) -> &mut Self {
+        self.function(
+            TAKE_VIDEO,
+            FunctionSignature::new().arg::<VideoNode>(CLIP),
+            take::<VideoNode>,
+        )
+        .function(
+            TAKE_AUDIO,
+            FunctionSignature::new().arg::<AudioNode>(CLIP),
+            take::<AudioNode>,
+        )
+        .function(GIVE, FunctionSignature::new().returns_any(), give)
+        .function(CORE, FunctionSignature::new(), core)
+    }
+}
+
+fn store(core: &Core, node: Option<Map>) {
+    let core = unsafe { CoreRef::from_ptr(core.as_ptr(), core.api()) };
+    SLOT.set(Some((core, node)));
+}
+
+fn take<N: MapValue>(input: MapRef, _: MapRef, core: CoreRef) -> Result<(), &'static CStr> {
+    let node = N::get_value(&input, CLIP, 0).map_err(|_| c"Expected a clip")?;
+    let mut map = core.create_map();
+    node.set_value(&mut map, CLIP, AppendMode::Replace)
+        .map_err(|_| c"Failed to store the clip")?;
+    store(&core, Some(map));
+    Ok(())
+}
+
+fn give(_: MapRef, mut output: MapRef, core: CoreRef) -> Result<(), &'static CStr> {
+    let Some((expected, Some(map))) = SLOT.take() else {
+        return Err(c"No node to return");
+    };
+    if expected.as_ptr() != core.as_ptr() {
+        return Err(c"The node belongs to a different core");
+    }
+    let node = map
+        .get_video_node(CLIP, 0)
+        .map(|node| node.set_value(&mut output, CLIP, AppendMode::Replace))
+        .or_else(|_| {
+            map.get_audio_node(CLIP, 0)
+                .map(|node| node.set_value(&mut output, CLIP, AppendMode::Replace))
+        });
+    match node {
+        Ok(Ok(())) => Ok(()),
+        _ => Err(c"Failed to return the node"),
+    }
+}
+
+#[allow(clippy::unnecessary_wraps)] // A `PublicFunction`.
+fn core(_: MapRef, _: MapRef, core: CoreRef) -> Result<(), &'static CStr> {
+    store(&core, None);
+    Ok(())
+}
+
+/// Calls the bridge function `name` of `target` and returns what it stored.
+fn call_bridge(
+    target: &Bound<'_, PyAny>,
+    namespace: &str,
+    name: &CStr,
+) -> PyResult<(CoreRef<'static>, Option<Map>)> {
+    SLOT.set(None);
+    target
+        .getattr(namespace)?
+        .getattr(name.to_string_lossy())?
+        .call0()?;
+    Ok(SLOT.take().ok_or(BridgeError::NotCalled)?)
+}
+
+/// The core of the Python `vapoursynth.Core` object `py_core`.
+///
+/// # Errors
+///
+/// Return the Python error if the bridge function cannot be called, e.g. if the plugin is not
+/// loaded into the core.
+pub fn core_from_python<'a>(
+    py_core: &'a Bound<'_, PyAny>,
+    namespace: &str,
+) -> PyResult<CoreRef<'a>> {
+    let (core, _) = call_bridge(py_core, namespace, CORE)?;
+    Ok(core)
+}
+
+fn node_from_python<N: MapValue>(
+    obj: &Bound<'_, PyAny>,
+    namespace: &str,
+    name: &CStr,
+    core: &Core,
+) -> PyResult<N> {
+    // Accessing the namespace through the node passes it as the first argument.
+    let (node_core, map) = call_bridge(obj, namespace, name)?;
+    if node_core.as_ptr() != core.as_ptr() {
+        return Err(BridgeError::DifferentCore.into());
+    }
+    let map = map.ok_or(BridgeError::NotCalled)?;
+    N::get_value(&map, CLIP, 0).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Converts a Python `vapoursynth.VideoNode` into a [`VideoNode`] of `core`.
+///
+/// # Errors
+///
+/// Return [`BridgeError::DifferentCore`] if the node belongs to another core, or the Python error
+/// if the bridge function cannot be called, e.g. because `obj` is not a `VideoNode`.
+pub fn video_node_from_python(
+    obj: &Bound<'_, PyAny>,
+    namespace: &str,
+    core: &Core,
+) -> PyResult<VideoNode> {
+    node_from_python(obj, namespace, TAKE_VIDEO, core)
+}
+
+/// Converts a Python `vapoursynth.AudioNode` into an [`AudioNode`] of `core`.
+///
+/// # Errors
+///
+/// Return [`BridgeError::DifferentCore`] if the node belongs to another core, or the Python error
+/// if the bridge function cannot be called, e.g. because `obj` is not an `AudioNode`.
+pub fn audio_node_from_python(
+    obj: &Bound<'_, PyAny>,
+    namespace: &str,
+    core: &Core,
+) -> PyResult<AudioNode> {
+    node_from_python(obj, namespace, TAKE_AUDIO, core)
+}
+
+/// Converts `node` of `core` into a Python `vapoursynth.VideoNode` or `AudioNode` of the Python
+/// core `py_core`.
+///
+/// # Errors
+///
+/// Return a Python `vapoursynth.Error` if `py_core` is not `core`, or the Python error if the
+/// bridge function cannot be called.
+pub fn node_into_python<'py, N: Node + MapValue>(
+    py_core: &Bound<'py, PyAny>,
+    namespace: &str,
+    core: &Core,
+    node: N,
+) -> PyResult<Bound<'py, PyAny>> {
+    let mut map = core.create_map();
+    node.set_value(&mut map, CLIP, AppendMode::Replace)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    store(core, Some(map));
+
+    let ret = py_core
+        .getattr(namespace)
+        .and_then(|plugin| plugin.getattr(GIVE.to_string_lossy()))
+        .and_then(|func| func.call0());
+    // Drop the node if the bridge function did not run.
+    SLOT.set(None);
+    let ret = ret?;
+    // A single `clip` is returned as is, other results as a `dict`.
+    match ret.cast::<PyDict>() {
+        Ok(dict) => dict
+            .get_item("clip")?
+            .ok_or_else(|| BridgeError::NotCalled.into()),
+        Err(_) => Ok(ret),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bridge_functions() {
+        let mut registry = PluginRegistry::new();
+        registry.python_bridge();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            [TAKE_VIDEO, TAKE_AUDIO, GIVE, CORE]
+        );
+        assert_eq!(registry.check(), Ok(()));
+    }
+}
@@ -0,0 +1,201 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Rational numbers for frame rates, sample aspect ratios and frame durations.
+
+use std::{
+    fmt::Display,
+    ops::{Add, Div, Mul},
+};
+
+use crate::ffi::helper::{add_rational, muldiv_rational, reduce_rational};
+
+/// A rational number `num / den`, always kept in lowest terms.
+///
+/// A zero denominator marks an unknown value, such as the frame rate of a variable frame rate
+/// clip. Arithmetic on an unknown value leaves it unchanged, like the `VSHelper` functions.
+///
+/// With the `serde` feature it is serialized as a `(num, den)` pair, and reduced again when
+/// deserialized.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "(i64, i64)", into = "(i64, i64)")
+)]
+pub struct Rational {
+    num: i64,
+    den: i64,
+}
+
+impl Rational {
+    /// The unknown value `0/0`.
+    pub const UNKNOWN: Self = Self { num: 0, den: 0 };
+
+    /// Creates `num / den` in lowest terms, with the sign on the numerator.
+    ///
+    /// Any value with a zero denominator is [`UNKNOWN`](Self::UNKNOWN).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `den` is negative and `num` or `den` is [`i64::MIN`], whose negation does not
+    /// fit.
+    #[must_use]
+    pub fn new(mut num: i64, mut den: i64) -> Self {
+        if den == 0 {
+            return Self::UNKNOWN;
+        }
+        if den < 0 {
+            let (Some(neg_num), Some(neg_den)) = (num.checked_neg(), den.checked_neg()) else {
+                panic!("the sign of {num}/{den} cannot be moved to the numerator");
+            };
+            (num, den) = (neg_num, neg_den);
+        }
+        reduce_rational(&mut num, &mut den);
+        Self { num, den }
+    }
+
+    #[must_use]
+    pub const fn num(self) -> i64 {
+        self.num
+    }
+
+    #[must_use]
+    pub const fn den(self) -> i64 {
+        self.den
+    }
+
+    /// Whether the value is known, i.e. the denominator is not zero.
+    #[must_use]
+    pub const fn is_known(self) -> bool {
+        self.den != 0
+    }
+
+    /// Returns `self * mul / div`, e.g. the frame rate after changing the number of frames.
+    ///
+    /// Dividing by zero gives [`UNKNOWN`](Self::UNKNOWN).
+    #[must_use]
+    pub fn muldiv(self, mul: i64, div: i64) -> Self {
+        if div == 0 {
+            return Self::UNKNOWN;
+        }
+        let (mut num, mut den) = (self.num, self.den);
+        muldiv_rational(&mut num, &mut den, mul, div);
+        Self::new(num, den)
+    }
+
+    /// Returns the reciprocal, e.g. the frame duration of a frame rate.
+    #[must_use]
+    pub fn recip(self) -> Self {
+        if self.num == 0 {
+            return Self::UNKNOWN;
+        }
+        Self::new(self.den, self.num)
+    }
+
+    /// The value as a float, or `NaN` if unknown.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn to_f64(self) -> f64 {
+        if self.is_known() {
+            self.num as f64 / self.den as f64
+        } else {
+            f64::NAN
+        }
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        if !rhs.is_known() {
+            return Self::UNKNOWN;
+        }
+        let (mut num, mut den) = (self.num, self.den);
+        add_rational(&mut num, &mut den, rhs.num, rhs.den);
+        Self::new(num, den)
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        if !rhs.is_known() {
+            return Self::UNKNOWN;
+        }
+        self.muldiv(rhs.num, rhs.den)
+    }
+}
+
+impl Div for Rational {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        if !rhs.is_known() {
+            return Self::UNKNOWN;
+        }
+        self.muldiv(rhs.den, rhs.num)
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(value: i64) -> Self {
+        Self { num: value, den: 1 }
+    }
+}
+
+impl From<(i64, i64)> for Rational {
+    fn from((num, den): (i64, i64)) -> Self {
+        Self::new(num, den)
+    }
+}
+
+impl From<Rational> for (i64, i64) {
+    fn from(value: Rational) -> Self {
+        (value.num, value.den)
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.num, self.den)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let ntsc = Rational::new(48000, 2002);
+        assert_eq!(ntsc, Rational::new(24000, 1001));
+        assert_eq!(Rational::new(1, -2), Rational::new(-1, 2));
+
+        // Doubling the frame count of a clip doubles its frame rate.
+        assert_eq!(ntsc.muldiv(2, 1), Rational::new(48000, 1001));
+        assert_eq!(ntsc.recip(), Rational::new(1001, 24000));
+        assert_eq!(
+            Rational::new(1, 2) + Rational::new(1, 3),
+            Rational::new(5, 6)
+        );
+        assert_eq!(ntsc / Rational::from(2), Rational::new(12000, 1001));
+
+        assert!(!(Rational::UNKNOWN + ntsc).is_known());
+        assert!(!(ntsc * Rational::UNKNOWN).is_known());
+        assert!(!(Rational::from(0) / Rational::from(0)).is_known());
+        assert!(!Rational::new(5, 0).is_known());
+        assert_eq!(ntsc.to_string(), "24000/1001");
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be moved to the numerator")]
+    fn negate_min() {
+        let _ = Rational::new(i64::MIN, -1);
+    }
+}
@@ -0,0 +1,977 @@
+//! Evaluating `.vpy` scripts with `VSScript`.
+
+mod pool;
+#[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+mod render;
+#[cfg(feature = "watch")]
+mod watch;
+
+use std::{
+    collections::BTreeMap,
+    ffi::{CStr, CString, c_int},
+    mem,
+    path::{Path, PathBuf},
+    ptr::{NonNull, null_mut},
+};
+
+use bon::bon;
+use thiserror::Error;
+
+use crate::{
+    api::{Api, VssApi, error::ApiNotFound},
+    core::{
+        Core, CoreCreationFlags, CoreRef,
+        keep_alive::{CoreObject, KeepAlive},
+    },
+    map::{AppendMode, IntoMap, Key, KeyStr, Map, MapField, MapPropertyError, Value},
+    node::{AudioNode, VideoNode},
+};
+
+use super::ffi;
+
+pub use pool::*;
+#[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+pub use render::*;
+#[cfg(feature = "watch")]
+pub use watch::*;
+
+/// The script environment of earlier versions.
+///
+/// [`Environment`] replaces it: it takes over the core instead of borrowing one that
+/// `createScript` frees itself, returns the creation error instead of panicking, and keeps the
+/// environment alive from its outputs.
+#[deprecated(note = "use `Environment` instead")]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Script {
+    handle: NonNull<ffi::VSScript>,
+    vssapi: VssApi,
+    api: Api,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OutputNode {
+    Audio(AudioNode),
+    Video(VideoNode),
+}
+
+/// The outputs of an evaluated script, by index.
+pub type Outputs = BTreeMap<i32, Output>;
+
+/// An output of a script, see [`Environment::get_output`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Output {
+    pub node: OutputNode,
+    /// The alpha clip passed along with a video output.
+    pub alpha: Option<VideoNode>,
+    /// The `alt_output` argument of `set_output`, or 0 if it was not set. It has no fixed
+    /// meaning, but `vspipe` uses it to pick an alternative output format.
+    pub alt_output_mode: i32,
+}
+
+impl Output {
+    /// The video clip, or [`None`] for an audio output.
+    #[must_use]
+    pub fn video(&self) -> Option<&VideoNode> {
+        match &self.node {
+            OutputNode::Video(node) => Some(node),
+            OutputNode::Audio(_) => None,
+        }
+    }
+
+    /// The audio clip, or [`None`] for a video output.
+    #[must_use]
+    pub fn audio(&self) -> Option<&AudioNode> {
+        match &self.node {
+            OutputNode::Audio(node) => Some(node),
+            OutputNode::Video(_) => None,
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl Script {
+    /// Creates a new script instance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the script creation fails.
+    pub fn new(core: Option<&Core>, vssapi: VssApi, api: Api) -> Self {
+        unsafe {
+            let handle = NonNull::new((vssapi.createScript)(core.map_or(null_mut(), Core::as_ptr)))
+                .expect("Failed to create script");
+            Self {
+                handle,
+                vssapi,
+                api,
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn get_api(&self) -> Api {
+        self.api
+    }
+
+    /// Returns a reference to the core associated with this script.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the core could not be retrieved.
+    pub fn core(&self) -> Result<CoreRef, ScriptError> {
+        unsafe {
+            let core = (self.vssapi.getCore)(self.handle.as_ptr());
+            self.get_ptr_error(core)
+                .map(|core| CoreRef::from_ptr(core, self.api))
+        }
+    }
+
+    /// Evaluates a script buffer with the given filename.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the script evaluation fails.
+    pub fn evaluate(&self, buffer: &CStr, filename: &CStr) -> Result<(), ScriptError> {
+        unsafe {
+            let result = (self.vssapi.evaluateBuffer)(
+                self.handle.as_ptr(),
+                buffer.as_ptr(),
+                filename.as_ptr(),
+            );
+            self.get_error(result)
+        }
+    }
+
+    /// Evaluates a script from a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the script evaluation fails.
+    pub fn evaluate_file(&self, filename: &CStr) -> Result<(), ScriptError> {
+        unsafe {
+            let result = (self.vssapi.evaluateFile)(self.handle.as_ptr(), filename.as_ptr());
+            self.get_error(result)
+        }
+    }
+
+    /// Gets the output node at the specified index.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ScriptError` if the output node could not be retrieved.
+    pub fn get_output(&self, index: c_int) -> Result<OutputNode, ScriptError> {
+        unsafe {
+            let ptr =
+                self.get_ptr_error((self.vssapi.getOutputNode)(self.handle.as_ptr(), index))?;
+            if (self.api.getNodeType)(ptr) == ffi::VSMediaType::Audio {
+                Ok(OutputNode::Audio(AudioNode::from_ptr(ptr, self.api)))
+            } else {
+                Ok(OutputNode::Video(VideoNode::from_ptr(ptr, self.api)))
+            }
+        }
+    }
+}
+
+// MARK: Helper
+#[allow(deprecated)]
+impl Script {
+    fn get_error(&self, ret: c_int) -> Result<(), ScriptError> {
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(unsafe { ScriptError::from_vss(self) })
+        }
+    }
+
+    fn get_ptr_error<T>(&self, ptr: *mut T) -> Result<*mut T, ScriptError> {
+        if ptr.is_null() {
+            Err(unsafe { ScriptError::from_vss(self) })
+        } else {
+            Ok(ptr)
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl Drop for Script {
+    fn drop(&mut self) {
+        unsafe { (self.vssapi.freeScript)(self.handle.as_ptr()) };
+    }
+}
+
+#[allow(deprecated)]
+#[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+impl Default for Script {
+    fn default() -> Self {
+        Self::new(None, VssApi::default(), Api::default())
+    }
+}
+
+// MARK: Environment
+
+/// A script environment that evaluates `.vpy` scripts like `vspipe` and gives access to their
+/// outputs.
+///
+/// The outputs, and everything else created from its core, keep the environment alive like the
+/// objects of a [`Core`], so the environment and its core are freed once it and the last of them
+/// are dropped.
+///
+/// Any number of environments can exist at once. Each has its own core and its own Python
+/// globals, so scripts do not see each other's variables or outputs. `VSScript` starts Python
+/// when its API is first requested and keeps it running until the process exits, so there is
+/// nothing to initialize or finalize around them.
+///
+/// ```no_run
+/// # fn f(env: vapoursynth4_rs::script::Environment) -> Result<(), vapoursynth4_rs::script::ScriptError> {
+/// use vapoursynth4_rs::node::Node;
+///
+/// let env = env.evaluate_file("clip.vpy")?;
+/// if let Some(clip) = env.get_output(0)?.video() {
+///     println!("{} frames", clip.info().num_frames);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Environment {
+    handle: NonNull<ffi::VSScript>,
+    vssapi: VssApi,
+    api: Api,
+    // Kept for `reload`.
+    working_dir: bool,
+    variables: Map,
+    source: Option<Source>,
+    core_options: Option<CoreOptions>,
+    // Last, so the environment is freed after the variables.
+    keep_alive: KeepAlive,
+}
+
+// SAFETY: `VSScript` takes the Python GIL itself, so an environment can be used from any thread,
+// just not from several at once.
+unsafe impl Send for Environment {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct CoreOptions {
+    flags: CoreCreationFlags,
+    thread_count: i32,
+    max_cache_size: i64,
+}
+
+impl CoreOptions {
+    fn of(core: &Core) -> Self {
+        let info = core.get_info();
+        Self {
+            flags: core.flags(),
+            thread_count: i32::try_from(info.num_threads).unwrap_or(0),
+            max_cache_size: i64::try_from(info.max_framebuffer_size).unwrap_or(i64::MAX),
+        }
+    }
+
+    fn build(self, api: Api) -> Core {
+        Core::builder()
+            .api(api)
+            .flags(self.flags)
+            .thread_count(self.thread_count)
+            .max_cache_size(self.max_cache_size)
+            .build()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Source {
+    File(PathBuf),
+    Code { code: String, filename: String },
+}
+
+#[bon]
+impl Environment {
+    /// Creates an empty environment. The core is created with the default options when it is
+    /// first needed.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if the environment could not be created.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+    pub fn new() -> Result<Self, ScriptError> {
+        Self::with_apis(None, VssApi::try_default()?, Api::default())
+    }
+
+    /// Creates an empty environment that takes over `core`, e.g. to control its creation flags,
+    /// thread count and cache size, or to preload plugins. The script gets this core from
+    /// `vapoursynth.core`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if the environment could not be created. The core is freed.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+    pub fn with_core(core: Core) -> Result<Self, ScriptError> {
+        let api = core.api();
+        Self::with_apis(Some(core), VssApi::try_default()?, api)
+    }
+
+    /// Like [`with_core`](Self::with_core), but with explicit APIs.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if the environment could not be created. The core is freed.
+    pub fn with_apis(core: Option<Core>, vssapi: VssApi, api: Api) -> Result<Self, ScriptError> {
+        let core_options = core.as_ref().map(CoreOptions::of);
+        let core_ptr = core.as_ref().map_or(null_mut(), Core::as_ptr);
+        // `createScript` takes over the core even if it fails.
+        let core_keep_alive = core.map(Core::into_keep_alive);
+        let Some(handle) = NonNull::new(unsafe { (vssapi.createScript)(core_ptr) }) else {
+            if let Some(keep_alive) = &core_keep_alive {
+                keep_alive.replace_release(None);
+            }
+            return Err(ScriptError::Creation);
+        };
+        // Objects created from the core before keep it alive, so they now keep the environment.
+        let free = move || unsafe { (vssapi.freeScript)(handle.as_ptr()) };
+        let keep_alive = match core_keep_alive {
+            Some(keep_alive) => {
+                keep_alive.replace_release(Some(Box::new(free)));
+                keep_alive
+            }
+            None => KeepAlive::new(free),
+        };
+        Ok(Self {
+            handle,
+            vssapi,
+            api,
+            working_dir: false,
+            variables: unsafe { Map::from_ptr((api.createMap)(), api) },
+            source: None,
+            core_options,
+            keep_alive,
+        })
+    }
+
+    /// Creates an empty environment with options, e.g.
+    ///
+    /// ```no_run
+    /// # fn f(
+    /// #     core: vapoursynth4_rs::core::Core,
+    /// #     vssapi: vapoursynth4_rs::api::VssApi,
+    /// # ) -> Result<(), vapoursynth4_rs::script::ScriptError> {
+    /// use vapoursynth4_rs::script::Environment;
+    ///
+    /// # let api = core.api();
+    /// let env = Environment::builder()
+    ///     .core(core)
+    /// #   .vssapi(vssapi)
+    /// #   .api(api)
+    ///     .working_dir(true)
+    ///     .build()?
+    ///     .evaluate_file("scripts/encode.vpy")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// `working_dir` makes [`evaluate_file`](Self::evaluate_file) run the script from its own
+    /// directory, so relative paths resolve like under `vspipe`. The core takes precedence over
+    /// `api`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if the environment could not be created. The core is freed.
+    #[builder(start_fn = builder, finish_fn = build)]
+    pub fn with_options(
+        core: Option<Core>,
+        #[builder(default)] working_dir: bool,
+        #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+        #[builder(default)]
+        vssapi: VssApi,
+        #[cfg(not(any(feature = "link-library", feature = "runtime-loading")))] vssapi: VssApi,
+        #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+        #[builder(default)]
+        api: Api,
+        #[cfg(not(any(feature = "link-library", feature = "runtime-loading")))] api: Api,
+    ) -> Result<Self, ScriptError> {
+        let api = core.as_ref().map_or(api, Core::api);
+        let mut env = Self::with_apis(core, vssapi, api)?;
+        if working_dir {
+            env.set_working_dir(true);
+        }
+        Ok(env)
+    }
+
+    /// Evaluates the script at `path` in a new environment.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] with the Python error if the script fails.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        Self::new()?.evaluate_file(path)
+    }
+
+    /// Evaluates the script at `path` in a new environment, with `args` set as global variables
+    /// first, like `vspipe --arg`. Unlike `vspipe`, the values keep their types.
+    ///
+    /// ```no_run
+    /// use vapoursynth4_rs::{map::Value, script::Environment};
+    ///
+    /// let env = Environment::eval_with_args(
+    ///     "encode.vpy",
+    ///     &[("source", Value::Utf8("input.mkv")), ("crop", Value::Int(8))],
+    /// )?;
+    /// # Ok::<(), vapoursynth4_rs::script::ScriptError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if a name is not a valid identifier, or with the Python error if
+    /// the script fails.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+    pub fn eval_with_args(
+        path: impl AsRef<Path>,
+        args: &[(&str, Value<'_>)],
+    ) -> Result<Self, ScriptError> {
+        let mut env = Self::new()?;
+        env.set_args(args)?;
+        env.evaluate_file(path)
+    }
+
+    /// Evaluates `code` in a new environment. `virtual_filename` is shown in error messages and
+    /// set as `__file__`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] with the Python error if the script fails.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+    pub fn from_string(code: &str, virtual_filename: &str) -> Result<Self, ScriptError> {
+        Self::new()?.evaluate_string(code, virtual_filename)
+    }
+
+    /// Evaluates the script at `path`, after any earlier scripts.
+    ///
+    /// The environment is consumed on failure, since it can only report the error afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] with the Python error if the script fails.
+    pub fn evaluate_file(mut self, path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        let path = path.as_ref();
+        let c_path = path_to_cstring(path)?;
+        let ret = unsafe { (self.vssapi.evaluateFile)(self.handle.as_ptr(), c_path.as_ptr()) };
+        self.source = Some(Source::File(path.to_owned()));
+        self.check(ret)
+    }
+
+    /// Evaluates `code`, after any earlier scripts.
+    ///
+    /// The environment is consumed on failure, since it can only report the error afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] with the Python error if the script fails.
+    pub fn evaluate_string(
+        mut self,
+        code: &str,
+        virtual_filename: &str,
+    ) -> Result<Self, ScriptError> {
+        let c_code = to_cstring(code)?;
+        let c_filename = to_cstring(virtual_filename)?;
+        let ret = unsafe {
+            (self.vssapi.evaluateBuffer)(self.handle.as_ptr(), c_code.as_ptr(), c_filename.as_ptr())
+        };
+        self.source = Some(Source::Code {
+            code: code.to_owned(),
+            filename: virtual_filename.to_owned(),
+        });
+        self.check(ret)
+    }
+
+    /// Evaluates the last script again in a new environment, e.g. after it was edited. The
+    /// working directory option and the variables set so far are kept. The new core has the
+    /// creation flags, thread count and cache size of the core passed to
+    /// [`with_core`](Self::with_core), or the default options, but no manually loaded plugins.
+    ///
+    /// `self` is left untouched, so its outputs can still be shown if the script now fails.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] with the Python error if the script fails.
+    pub fn reload(&self) -> Result<Self, ScriptError> {
+        let core = self.core_options.map(|options| options.build(self.api));
+        let mut env = Self::with_apis(core, self.vssapi, self.api)?;
+        env.set_working_dir(self.working_dir);
+        env.set_variables(&self.variables)?;
+        match &self.source {
+            Some(Source::File(path)) => env.evaluate_file(path),
+            Some(Source::Code { code, filename }) => env.evaluate_string(code, filename),
+            None => Ok(env),
+        }
+    }
+
+    /// The file evaluated last, if it was not a string.
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        match &self.source {
+            Some(Source::File(path)) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Whether [`evaluate_file`](Self::evaluate_file) changes the working directory to the
+    /// directory of the script while it runs. Off by default.
+    pub fn set_working_dir(&mut self, enable: bool) {
+        self.working_dir = enable;
+        unsafe { (self.vssapi.evalSetWorkingDir)(self.handle.as_ptr(), c_int::from(enable)) };
+    }
+
+    /// The output `index` set by the script, e.g. with `clip.set_output(index, alpha)`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if there is no output at `index`.
+    pub fn get_output(&self, index: i32) -> Result<Output, ScriptError> {
+        let ptr = unsafe { (self.vssapi.getOutputNode)(self.handle.as_ptr(), index) };
+        if ptr.is_null() {
+            return Err(ScriptError::NoOutput(index));
+        }
+        let node = unsafe {
+            if (self.api.getNodeType)(ptr) == ffi::VSMediaType::Audio {
+                OutputNode::Audio(AudioNode::from_ptr(ptr, self.api).kept_by(&self.keep_alive))
+            } else {
+                OutputNode::Video(VideoNode::from_ptr(ptr, self.api).kept_by(&self.keep_alive))
+            }
+        };
+        let alpha = unsafe { (self.vssapi.getOutputAlphaNode)(self.handle.as_ptr(), index) };
+        Ok(Output {
+            node,
+            alpha: (!alpha.is_null())
+                .then(|| unsafe { VideoNode::from_ptr(alpha, self.api).kept_by(&self.keep_alive) }),
+            alt_output_mode: unsafe { (self.vssapi.getAltOutputMode)(self.handle.as_ptr(), index) },
+        })
+    }
+
+    /// The indices of every output set by the script, in ascending order.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError::Unsupported`] if the `VSScript` library is older than API 4.2,
+    /// which added the listing.
+    pub fn get_outputs(&self) -> Result<Vec<i32>, ScriptError> {
+        self.require("Listing the outputs", (4, 2))?;
+        let handle = self.handle.as_ptr();
+        let len = unsafe { (self.vssapi.getAvailableOutputNodes)(handle, 0, null_mut()) };
+        let mut indices = vec![0; usize::try_from(len).unwrap_or_default()];
+        unsafe { (self.vssapi.getAvailableOutputNodes)(handle, len, indices.as_mut_ptr()) };
+        indices.sort_unstable();
+        Ok(indices)
+    }
+
+    /// Every output set by the script, see [`get_outputs`](Self::get_outputs).
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if the outputs could not be listed.
+    pub fn outputs(&self) -> Result<Outputs, ScriptError> {
+        self.get_outputs()?
+            .into_iter()
+            .map(|index| Ok((index, self.get_output(index)?)))
+            .collect()
+    }
+
+    /// The core of the environment, created now if the script has not used it yet.
+    ///
+    /// The returned [`CoreRef`] dereferences to [`Core`], so it can be tuned with
+    /// [`Core::set_thread_count`] or [`Core::set_max_cache_size`], and used to enumerate plugins
+    /// or invoke filters on top of the script's outputs. The objects created from it keep the
+    /// environment alive, like its outputs.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if the core could not be created.
+    pub fn core(&self) -> Result<CoreRef<'_>, ScriptError> {
+        let ptr = unsafe { (self.vssapi.getCore)(self.handle.as_ptr()) };
+        if ptr.is_null() {
+            return Err(self.error());
+        }
+        Ok(unsafe { CoreRef::with_owner(ptr, self.api, &self.keep_alive) })
+    }
+
+    /// Reads the Python variable `name`, e.g. a result computed by the script. Lists are read
+    /// with a [`Vec`].
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if there is no such variable or it has another type.
+    pub fn get_variable<T: MapField>(&self, name: &KeyStr) -> Result<T, ScriptError> {
+        let map =
+            unsafe { Map::from_ptr((self.api.createMap)(), self.api) }.kept_by(&self.keep_alive);
+        let ret =
+            unsafe { (self.vssapi.getVariable)(self.handle.as_ptr(), name.as_ptr(), map.as_ptr()) };
+        if ret != 0 {
+            return Err(ScriptError::NoVariable(name.to_string()));
+        }
+        T::get_field(&map, name).map_err(|source| ScriptError::InvalidVariable {
+            name: name.to_string(),
+            source,
+        })
+    }
+
+    /// Sets every entry of `vars` as a Python variable, e.g. to pass a source path or crop
+    /// values to a script before evaluating it.
+    ///
+    /// ```no_run
+    /// # fn f(env: &mut vapoursynth4_rs::script::Environment) -> Result<(), vapoursynth4_rs::script::ScriptError> {
+    /// use vapoursynth4_rs::vsmap;
+    ///
+    /// env.set_variables(vsmap! { source: String::from("input.mkv"), crop: 8 })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if the variables could not be set.
+    pub fn set_variables(&mut self, vars: impl IntoMap) -> Result<(), ScriptError> {
+        let mut map = unsafe { Map::from_ptr((self.api.createMap)(), self.api) };
+        vars.into_map(&mut map)
+            .map_err(ScriptError::InvalidVariables)?;
+        let ret = unsafe { (self.vssapi.setVariables)(self.handle.as_ptr(), map.as_ptr()) };
+        if ret != 0 {
+            return Err(ScriptError::SetVariables);
+        }
+        // Python holds the values now, so their core must outlive the environment.
+        self.keep_alive.depend_on(map.keep_alive());
+        // Kept for `reload`.
+        (&map)
+            .into_map(&mut self.variables)
+            .map_err(|_| ScriptError::SetVariables)?;
+        // Its own core would keep the environment alive forever.
+        if self.variables.keep_alive().owner() == self.keep_alive.owner() {
+            drop(mem::take(self.variables.keep_alive_mut()));
+        }
+        Ok(())
+    }
+
+    /// Sets each `(name, value)` of `args` as a Python variable, see
+    /// [`eval_with_args`](Self::eval_with_args).
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if a name is not a valid identifier or the variables could not be
+    /// set.
+    pub fn set_args(&mut self, args: &[(&str, Value<'_>)]) -> Result<(), ScriptError> {
+        let mut map = unsafe { Map::from_ptr((self.api.createMap)(), self.api) };
+        for (name, value) in args {
+            let key = Key::new(*name).map_err(|_| ScriptError::InvalidName((*name).to_owned()))?;
+            map.set(&key, value.clone(), AppendMode::Replace)
+                .map_err(ScriptError::InvalidVariables)?;
+        }
+        self.set_variables(&map)
+    }
+
+    /// The code passed to `sys.exit` by the last evaluated script, or 0.
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        unsafe { (self.vssapi.getExitCode)(self.handle.as_ptr()) }
+    }
+
+    /// The `VSScript` library this environment uses, e.g. to check its
+    /// [`version`](VssApi::version).
+    #[must_use]
+    pub fn vssapi(&self) -> VssApi {
+        self.vssapi
+    }
+
+    fn require(&self, feature: &'static str, required: (u16, u16)) -> Result<(), ScriptError> {
+        if self.vssapi.supports(required.0, required.1) {
+            Ok(())
+        } else {
+            Err(ScriptError::Unsupported {
+                feature,
+                required,
+                available: self.vssapi.version(),
+            })
+        }
+    }
+
+    fn check(self, ret: c_int) -> Result<Self, ScriptError> {
+        if ret == 0 {
+            Ok(self)
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn error(&self) -> ScriptError {
+        unsafe { ScriptError::from_handle(self.handle.as_ptr(), &self.vssapi) }
+    }
+}
+
+fn to_cstring(s: &str) -> Result<CString, ScriptError> {
+    CString::new(s).map_err(|_| ScriptError::NulByte(s.to_owned()))
+}
+
+fn path_to_cstring(path: &Path) -> Result<CString, ScriptError> {
+    #[cfg(unix)]
+    let bytes = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes()
+    };
+    #[cfg(not(unix))]
+    let bytes = path
+        .to_str()
+        .ok_or_else(|| ScriptError::NonUtf8Path(path.to_owned()))?
+        .as_bytes();
+    CString::new(bytes).map_err(|_| ScriptError::NulByte(path.display().to_string()))
+}
+
+// MARK: ScriptError
+
+/// An error of [`Script`] or [`Environment`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+pub enum ScriptError {
+    /// The script raised an exception or exited with a non-zero code.
+    #[error("Script evaluation failed: {message}")]
+    Evaluation {
+        /// The error reported by `VSScript`, usually the Python traceback.
+        message: String,
+        /// The code passed to `sys.exit`, or the generic failure code of `VSScript`.
+        exit_code: i32,
+    },
+    #[error("Failed to create the script environment")]
+    Creation,
+    /// The `VSScript` library could not be loaded, or is older than the crate supports.
+    #[error(transparent)]
+    Library(#[from] ApiNotFound),
+    #[error("There is no output at index {0}")]
+    NoOutput(i32),
+    #[error("`{0}` is not a valid variable name")]
+    InvalidName(String),
+    #[error("There is no variable `{0}`")]
+    NoVariable(String),
+    #[error("Failed to read the variable `{name}`")]
+    InvalidVariable {
+        name: String,
+        #[source]
+        source: MapPropertyError,
+    },
+    #[error("Invalid variables")]
+    InvalidVariables(#[source] MapPropertyError),
+    #[error("Failed to set the variables")]
+    SetVariables,
+    #[error("`{0}` contains a NUL byte")]
+    NulByte(String),
+    #[error("`{}` is not valid UTF-8", .0.display())]
+    NonUtf8Path(PathBuf),
+    /// The installed `VSScript` library is too old for the operation.
+    #[error(
+        "{feature} requires VSScript API {}.{}, but the installed VapourSynth supports {}.{}",
+        required.0, required.1, available.0, available.1
+    )]
+    Unsupported {
+        feature: &'static str,
+        required: (u16, u16),
+        available: (u16, u16),
+    },
+}
+
+impl ScriptError {
+    /// The error message reported by `VSScript`, usually the Python traceback.
+    #[must_use]
+    pub fn traceback(&self) -> Option<&str> {
+        match self {
+            Self::Evaluation { message, .. } => Some(message),
+            _ => None,
+        }
+    }
+
+    /// The exit code of a failed evaluation.
+    #[must_use]
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            Self::Evaluation { exit_code, .. } => Some(*exit_code),
+            _ => None,
+        }
+    }
+
+    unsafe fn from_handle(handle: *mut ffi::VSScript, vssapi: &VssApi) -> Self {
+        let ptr = unsafe { (vssapi.getError)(handle) };
+        let message = if ptr.is_null() {
+            "Unknown VSScript error".to_owned()
+        } else {
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        Self::Evaluation {
+            message,
+            exit_code: unsafe { (vssapi.getExitCode)(handle) },
+        }
+    }
+
+    #[allow(deprecated)]
+    unsafe fn from_vss(vss: &Script) -> Self {
+        unsafe { Self::from_handle(vss.handle.as_ptr(), &vss.vssapi) }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment() {
+        let mut env = Environment::new().unwrap();
+        env.set_variables(crate::vsmap! { width: 64 }).unwrap();
+        let env = env
+            .evaluate_string(
+                "from vapoursynth import core\n\
+                 answer = [width, 42]\n\
+                 core.std.BlankClip(width=width, height=32).set_output(1)\n",
+                "test.vpy",
+            )
+            .unwrap();
+        let output = env.get_output(1).unwrap();
+        assert_eq!(output.video().unwrap().info().width, 64);
+        assert!(output.alpha.is_none());
+        assert_eq!(output.alt_output_mode, 0);
+        assert!(env.get_output(0).is_err());
+        assert_eq!(env.get_outputs(), Ok(vec![1]));
+        assert_eq!(env.outputs().unwrap().keys().collect::<Vec<_>>(), [&1]);
+        assert!(env.vssapi().supports(4, 0));
+
+        let mut core = env.core().unwrap();
+        core.set_thread_count(1);
+        assert_eq!(core.get_info().num_threads, 1);
+        assert!(core.plugin_by_namespace(c"std").is_some());
+        assert_eq!(
+            env.get_variable::<Vec<i64>>(crate::key!(c"answer")),
+            Ok(vec![64, 42])
+        );
+        assert!(env.get_variable::<i64>(crate::key!(c"missing")).is_err());
+
+        let err =
+            Environment::from_string("raise ValueError('bad clip')", "error.vpy").unwrap_err();
+        assert!(err.traceback().unwrap().contains("bad clip"));
+        assert!(err.exit_code().is_some());
+    }
+
+    #[test]
+    fn eval_with_args() {
+        let path = std::env::temp_dir().join("vapoursynth4_rs_eval_with_args.vpy");
+        std::fs::write(&path, "scaled = width * scale\n").unwrap();
+        let env = Environment::eval_with_args(
+            &path,
+            &[("width", Value::Int(64)), ("scale", Value::Float(0.5))],
+        )
+        .unwrap();
+        assert_eq!(env.get_variable::<f64>(crate::key!(c"scaled")), Ok(32.0));
+        assert!(matches!(
+            Environment::eval_with_args(&path, &[("not a name", Value::Int(1))]),
+            Err(ScriptError::InvalidName(_))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn builder() {
+        let dir = std::env::temp_dir().join("vapoursynth4_rs_builder");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("data.txt"), "42").unwrap();
+        std::fs::write(
+            dir.join("read.vpy"),
+            "answer = int(open('data.txt').read())\n",
+        )
+        .unwrap();
+        let env = Environment::builder()
+            .working_dir(true)
+            .build()
+            .unwrap()
+            .evaluate_file(dir.join("read.vpy"))
+            .unwrap();
+        assert_eq!(env.get_variable::<i64>(crate::key!(c"answer")), Ok(42));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reload() {
+        let path = std::env::temp_dir().join("vapoursynth4_rs_reload.vpy");
+        std::fs::write(&path, "answer = value\n").unwrap();
+        let mut env = Environment::new().unwrap();
+        env.set_variables(crate::vsmap! { value: 1 }).unwrap();
+        let env = env.evaluate_file(&path).unwrap();
+        assert_eq!(env.path(), Some(path.as_path()));
+
+        std::fs::write(&path, "answer = value + 1\n").unwrap();
+        let reloaded = env.reload().unwrap();
+        assert_eq!(reloaded.get_variable::<i64>(crate::key!(c"answer")), Ok(2));
+        assert_eq!(env.get_variable::<i64>(crate::key!(c"answer")), Ok(1));
+
+        std::fs::write(&path, "raise ValueError\n").unwrap();
+        assert!(env.reload().is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn with_core() {
+        let core = Core::builder().thread_count(2).max_cache_size(1024).build();
+        let env = Environment::with_core(core)
+            .unwrap()
+            .evaluate_string(
+                "from vapoursynth import core\nthreads = core.num_threads\n",
+                "core.vpy",
+            )
+            .unwrap();
+        assert_eq!(env.get_variable::<i64>(crate::key!(c"threads")), Ok(2));
+
+        let reloaded = env.reload().unwrap();
+        assert_eq!(reloaded.get_variable::<i64>(crate::key!(c"threads")), Ok(2));
+        assert_eq!(
+            reloaded.core().unwrap().get_info().max_framebuffer_size,
+            1024
+        );
+    }
+
+    #[test]
+    fn independent() {
+        let build = |threads| {
+            let core = Core::builder().thread_count(threads).build();
+            let mut env = Environment::with_core(core).unwrap();
+            env.set_variables(crate::vsmap! { threads: i64::from(threads) })
+                .unwrap();
+            env.evaluate_string(
+                "from vapoursynth import core\n\
+                 core.std.BlankClip(width=threads * 16, height=16).set_output()\n",
+                "independent.vpy",
+            )
+            .unwrap()
+        };
+        let (a, b) = (build(1), build(2));
+        for (env, threads) in [(&a, 1), (&b, 2)] {
+            assert_eq!(env.core().unwrap().get_info().num_threads, threads);
+            let width = env.get_output(0).unwrap().video().unwrap().info().width;
+            assert_eq!(width, i32::try_from(threads).unwrap() * 16);
+        }
+        drop(a);
+        assert!(b.get_output(0).is_ok());
+    }
+
+    #[test]
+    fn outputs_outlive_environment() {
+        use crate::node::Node;
+
+        let env = Environment::from_string(
+            "from vapoursynth import core\ncore.std.BlankClip(length=1).set_output()\n",
+            "outlive.vpy",
+        )
+        .unwrap();
+        let clip = env.get_output(0).unwrap().video().unwrap().clone();
+        let blank = env
+            .core()
+            .unwrap()
+            .invoke_with(c"std", c"BlankClip", crate::vsmap! { length: 1 })
+            .unwrap()
+            .get_video_node(crate::key!(c"clip"), 0)
+            .unwrap();
+        drop(env);
+
+        assert!(clip.get_frame(0).is_ok());
+        assert!(blank.get_frame(0).is_ok());
+    }
+}
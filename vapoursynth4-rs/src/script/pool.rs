@@ -0,0 +1,215 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    api::{Api, VssApi},
+    map::{KeyStr, MapField},
+};
+
+use super::{Environment, Output, Outputs, ScriptError};
+
+/// Evaluates many scripts for a multi-threaded application, e.g. a server.
+///
+/// Creating and evaluating environments, and reading their variables, is done one at a time,
+/// since all of it runs Python. The outputs of a [`PooledScript`] are plain nodes that can be
+/// rendered from any number of threads at once.
+///
+/// The pool is a cheap handle, clones share the same lock.
+///
+/// ```no_run
+/// # fn f(
+/// #     pool: &vapoursynth4_rs::script::EnvironmentPool,
+/// #     encode: impl Fn(vapoursynth4_rs::frame::VideoFrame) + Sync,
+/// # ) {
+/// use vapoursynth4_rs::{node::Node, script::Output};
+///
+/// std::thread::scope(|s| {
+///     for path in ["a.vpy", "b.vpy"] {
+///         let pool = pool.clone();
+///         let encode = &encode;
+///         s.spawn(move || -> Result<(), vapoursynth4_rs::script::ScriptError> {
+///             let script = pool.evaluate_file(path)?;
+///             let clip = script.output(0).and_then(Output::video).unwrap();
+///             for n in 0..clip.info().num_frames {
+///                 encode(clip.get_frame(n).unwrap());
+///             }
+///             Ok(())
+///         });
+///     }
+/// });
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnvironmentPool {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    lock: Mutex<()>,
+    vssapi: VssApi,
+    api: Api,
+}
+
+// SAFETY: The APIs are tables of thread safe functions.
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+impl EnvironmentPool {
+    /// Creates a pool with the default APIs.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError::Library`] if the `VSScript` library cannot be loaded.
+    #[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+    pub fn new() -> Result<Self, ScriptError> {
+        Ok(Self::with_apis(VssApi::try_default()?, Api::default()))
+    }
+
+    #[must_use]
+    pub fn with_apis(vssapi: VssApi, api: Api) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                lock: Mutex::new(()),
+                vssapi,
+                api,
+            }),
+        }
+    }
+
+    /// Evaluates the script at `path` in a new environment.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] with the Python error if the script fails, or if its outputs could
+    /// not be listed.
+    pub fn evaluate_file(&self, path: impl AsRef<Path>) -> Result<PooledScript, ScriptError> {
+        self.evaluate(|env| env.evaluate_file(path))
+    }
+
+    /// Evaluates `code` in a new environment.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] with the Python error if the script fails, or if its outputs could
+    /// not be listed.
+    pub fn evaluate_string(
+        &self,
+        code: &str,
+        virtual_filename: &str,
+    ) -> Result<PooledScript, ScriptError> {
+        self.evaluate(|env| env.evaluate_string(code, virtual_filename))
+    }
+
+    /// Evaluates a new environment with `f`, e.g. to set variables or the working directory
+    /// first. No other environment of the pool is used meanwhile.
+    ///
+    /// # Errors
+    ///
+    /// Return the error of `f`, or [`ScriptError`] if the environment could not be created or
+    /// its outputs could not be listed.
+    pub fn evaluate<F>(&self, f: F) -> Result<PooledScript, ScriptError>
+    where
+        F: FnOnce(Environment) -> Result<Environment, ScriptError>,
+    {
+        let _guard = self.shared.lock();
+        let env = f(Environment::with_apis(
+            None,
+            self.shared.vssapi,
+            self.shared.api,
+        )?)?;
+        let outputs = env.outputs()?;
+        Ok(PooledScript {
+            env: Mutex::new(env),
+            outputs,
+            pool: Arc::clone(&self.shared),
+        })
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A script evaluated by an [`EnvironmentPool`].
+///
+/// The outputs keep the environment alive, so they stay usable after the script is dropped. The
+/// environment is freed once the script and its outputs are dropped, on whichever thread drops
+/// the last of them, without the lock of the pool: `VSScript` takes the Python GIL itself.
+#[derive(Debug)]
+pub struct PooledScript {
+    env: Mutex<Environment>,
+    outputs: Outputs,
+    pool: Arc<Shared>,
+}
+
+impl PooledScript {
+    /// Every output set by the script, as of its evaluation.
+    #[must_use]
+    pub fn outputs(&self) -> &Outputs {
+        &self.outputs
+    }
+
+    /// The output `index`, if the script set it.
+    #[must_use]
+    pub fn output(&self, index: i32) -> Option<&Output> {
+        self.outputs.get(&index)
+    }
+
+    /// Reads the Python variable `name`, see [`Environment::get_variable`].
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if there is no such variable or it has another type.
+    pub fn get_variable<T: MapField>(&self, name: &KeyStr) -> Result<T, ScriptError> {
+        self.with_environment(|env| env.get_variable(name))
+    }
+
+    /// Calls `f` with the environment. No other environment of the pool is used meanwhile.
+    pub fn with_environment<R>(&self, f: impl FnOnce(&mut Environment) -> R) -> R {
+        let _guard = self.pool.lock();
+        f(&mut self.env.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use crate::node::Node;
+
+    use super::*;
+
+    #[test]
+    fn concurrent() {
+        let pool = EnvironmentPool::new().unwrap();
+        let widths: Vec<i64> = std::thread::scope(|s| {
+            let handles: Vec<_> = (1..=4)
+                .map(|i| {
+                    let pool = pool.clone();
+                    s.spawn(move || {
+                        let script = pool
+                            .evaluate_string(
+                                &format!(
+                                    "from vapoursynth import core\n\
+                                     width = {}\n\
+                                     core.std.BlankClip(width=width, height=16).set_output()\n",
+                                    i * 16
+                                ),
+                                "pool.vpy",
+                            )
+                            .unwrap();
+                        let clip = script.output(0).and_then(Output::video).unwrap().clone();
+                        clip.get_frame(0).unwrap();
+                        script.get_variable(crate::key!(c"width")).unwrap()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(widths, [16, 32, 48, 64]);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{io::Write, path::Path};
+
+use bon::Builder;
+use thiserror::Error;
+
+use crate::{
+    map::Value,
+    output::{OutputError, WavWriter, Y4mWriter, render_ordered, write_planes},
+};
+
+use super::{Environment, OutputNode, ScriptError};
+
+/// A script for [`render_to`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptSource<'a> {
+    File(&'a Path),
+    /// Code, shown as `<string>` in error messages.
+    Code(&'a str),
+}
+
+impl<'a> From<&'a Path> for ScriptSource<'a> {
+    fn from(path: &'a Path) -> Self {
+        Self::File(path)
+    }
+}
+
+/// Options of [`render_to`].
+///
+/// ```no_run
+/// use vapoursynth4_rs::script::{Progress, RenderOptions};
+///
+/// let mut progress = |p: Progress| eprint!("\r{}/{}", p.done, p.total);
+/// let options = RenderOptions::builder()
+///     .output(1)
+///     .progress(&mut progress)
+///     .build();
+/// ```
+#[derive(Builder)]
+pub struct RenderOptions<'a> {
+    /// The output index to render.
+    #[builder(default)]
+    pub output: i32,
+    /// Frames requested at once, the thread count of the core by default.
+    pub requests: Option<usize>,
+    /// Write Y4M for video and WAV for audio, otherwise the bare planes or samples.
+    #[builder(default = true)]
+    pub container: bool,
+    /// Variables set before the script is evaluated, see [`Environment::set_args`].
+    #[builder(default)]
+    pub args: &'a [(&'a str, Value<'a>)],
+    /// Called after each frame is written.
+    pub progress: Option<&'a mut dyn FnMut(Progress)>,
+}
+
+/// The frames written so far by [`render_to`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Progress {
+    pub done: i32,
+    pub total: i32,
+}
+
+/// Evaluates a script and writes one of its outputs to `writer`, like `vspipe`.
+///
+/// ```no_run
+/// use std::{fs::File, io::BufWriter, path::Path};
+///
+/// use vapoursynth4_rs::script::{RenderOptions, render_to};
+///
+/// let file = BufWriter::new(File::create("out.y4m")?);
+/// render_to(Path::new("in.vpy").into(), file, RenderOptions::builder().build())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Return [`RenderError`] if the script fails, has no such output, or it could not be written.
+pub fn render_to(
+    source: ScriptSource<'_>,
+    mut writer: impl Write,
+    options: RenderOptions<'_>,
+) -> Result<(), RenderError> {
+    let mut env = Environment::new()?;
+    env.set_args(options.args)?;
+    let env = match source {
+        ScriptSource::File(path) => env.evaluate_file(path)?,
+        ScriptSource::Code(code) => env.evaluate_string(code, "<string>")?,
+    };
+    let output = env.get_output(options.output)?;
+    let requests = match options.requests {
+        Some(requests) => requests,
+        None => env.core()?.get_info().num_threads,
+    };
+
+    let mut progress = options.progress;
+    let mut report = |n: i32, total: i32| {
+        if let Some(progress) = progress.as_mut() {
+            progress(Progress { done: n + 1, total });
+        }
+    };
+    match &output.node {
+        OutputNode::Video(node) => {
+            let total = node.info().num_frames;
+            if options.container {
+                let mut y4m = Y4mWriter::new(&mut writer, node.info())?;
+                render_ordered(node, 0..total, requests, |n, frame| {
+                    y4m.write_frame(&frame)?;
+                    report(n, total);
+                    Ok(())
+                })?;
+            } else {
+                render_ordered(node, 0..total, requests, |n, frame| {
+                    write_planes(&mut writer, &frame)?;
+                    report(n, total);
+                    Ok(())
+                })?;
+            }
+        }
+        OutputNode::Audio(node) => {
+            let total = node.info().num_frames;
+            let mut wav = if options.container {
+                WavWriter::new(&mut writer, node.info())?
+            } else {
+                WavWriter::without_header(&mut writer, node.info())
+            };
+            render_ordered(node, 0..total, requests, |n, frame| {
+                wav.write_frame(&frame)?;
+                report(n, total);
+                Ok(())
+            })?;
+        }
+    }
+    writer.flush().map_err(OutputError::from)?;
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error(transparent)]
+    Script(#[from] ScriptError),
+    #[error(transparent)]
+    Output(#[from] OutputError),
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_to() {
+        let mut out = Vec::new();
+        let mut frames = Vec::new();
+        super::render_to(
+            ScriptSource::Code(
+                "import vapoursynth as vs\n\
+                 vs.core.std.BlankClip(format=vs.YUV420P8, width=16, height=16, length=4,\
+                 fpsnum=25, fpsden=1).set_output()\n",
+            ),
+            &mut out,
+            RenderOptions::builder()
+                .requests(2)
+                .progress(&mut |p| frames.push(p.done))
+                .build(),
+        )
+        .unwrap();
+        let header = b"YUV4MPEG2 C420 W16 H16 F25:1 Ip A0:0 XLENGTH=4\n";
+        assert!(out.starts_with(header));
+        assert_eq!(out.len(), header.len() + 4 * (6 + 16 * 16 * 3 / 2));
+        assert_eq!(frames, [1, 2, 3, 4]);
+    }
+}
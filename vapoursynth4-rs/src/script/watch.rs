@@ -0,0 +1,236 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::Duration,
+};
+
+use notify::{EventKind, RecursiveMode, Watcher as _};
+use thiserror::Error;
+
+use super::{Environment, Outputs, ScriptError};
+
+/// Reloads a script whenever its file changes, for the edit-refresh loop of preview tools.
+///
+/// The outputs are swapped in one step after a successful reload, so [`outputs`](Self::outputs)
+/// always returns the outputs of a single evaluation. If the new version fails, the previous
+/// one is kept and the error is passed to the callback.
+///
+/// A single save often fires several file events, so they are collected until the file has
+/// been quiet for [`DEBOUNCE`](Self::DEBOUNCE), and the script is then reloaded once.
+///
+/// ```no_run
+/// # fn f(
+/// #     env: vapoursynth4_rs::script::Environment,
+/// #     request_repaint: impl Fn() + Send + 'static,
+/// # ) -> Result<(), Box<dyn std::error::Error>> {
+/// use vapoursynth4_rs::script::ScriptWatcher;
+///
+/// let watcher = ScriptWatcher::new(env.evaluate_file("preview.vpy")?, move |result| {
+///     if let Err(e) = result {
+///         eprintln!("{e}");
+///     }
+///     request_repaint();
+/// })?;
+/// let clip = watcher.outputs()[&0].video().cloned();
+/// # Ok(())
+/// # }
+/// ```
+pub struct ScriptWatcher {
+    state: Arc<State>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+struct State {
+    // Held while reloading, which keeps reloads in order.
+    env: Mutex<Environment>,
+    // Only held to swap or clone the `Arc`, so it never waits for a reload.
+    outputs: Mutex<Arc<Outputs>>,
+}
+
+impl ScriptWatcher {
+    /// How long the file must go without events before it is reloaded.
+    pub const DEBOUNCE: Duration = Duration::from_millis(100);
+
+    /// Watches the file `env` was evaluated from. `on_reload` is called from a background
+    /// thread after each reload.
+    ///
+    /// # Errors
+    ///
+    /// Return [`WatchError`] if `env` was not evaluated from a file, its outputs could not be
+    /// listed, or the file could not be watched.
+    pub fn new<F>(env: Environment, mut on_reload: F) -> Result<Self, WatchError>
+    where
+        F: FnMut(Result<(), ScriptError>) + Send + 'static,
+    {
+        let path = env.path().ok_or(WatchError::NotAFile)?;
+        // Events carry absolute paths.
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        let outputs = Arc::new(env.outputs()?);
+        let state = Arc::new(State {
+            env: Mutex::new(env),
+            outputs: Mutex::new(outputs),
+        });
+
+        let (tx, rx) = mpsc::channel();
+        let script = path.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else { return };
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                    && event.paths.contains(&script)
+                {
+                    _ = tx.send(());
+                }
+            })?;
+
+        // Exits once the watcher, and with it the sender, is dropped.
+        let shared = Arc::clone(&state);
+        thread::spawn(move || {
+            while rx.recv().is_ok() {
+                loop {
+                    match rx.recv_timeout(Self::DEBOUNCE) {
+                        Ok(()) => {}
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                on_reload(reload(&shared));
+            }
+        });
+        // Editors often replace the file instead of writing to it, so watch its directory.
+        let dir = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map_or_else(|| PathBuf::from("."), PathBuf::from);
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            state,
+            _watcher: watcher,
+        })
+    }
+
+    /// The outputs of the last successful evaluation.
+    #[must_use]
+    pub fn outputs(&self) -> Arc<Outputs> {
+        Arc::clone(&lock(&self.state.outputs))
+    }
+
+    /// Reloads the script now, e.g. after changing a file it imports.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ScriptError`] if the script fails. The previous outputs are kept.
+    pub fn reload(&self) -> Result<(), ScriptError> {
+        reload(&self.state)
+    }
+
+    /// Calls `f` with the current environment, e.g. to read variables or use its core. Reloads
+    /// wait until it returns.
+    pub fn with_environment<R>(&self, f: impl FnOnce(&Environment) -> R) -> R {
+        f(&lock(&self.state.env))
+    }
+}
+
+impl std::fmt::Debug for ScriptWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptWatcher")
+            .field("outputs", &self.outputs())
+            .finish_non_exhaustive()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn reload(state: &State) -> Result<(), ScriptError> {
+    let mut env = lock(&state.env);
+    let new_env = env.reload()?;
+    let outputs = new_env.outputs()?;
+    *lock(&state.outputs) = Arc::new(outputs);
+    *env = new_env;
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum WatchError {
+    #[error("The environment was not evaluated from a file")]
+    NotAFile,
+    #[error(transparent)]
+    Script(#[from] ScriptError),
+    #[error("Failed to watch the script")]
+    Notify(#[from] notify::Error),
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn blank_clip(width: i32) -> String {
+        format!(
+            "from vapoursynth import core\n\
+             core.std.BlankClip(width={width}, height=16).set_output()\n"
+        )
+    }
+
+    fn width(watcher: &ScriptWatcher) -> i32 {
+        watcher.outputs()[&0].video().unwrap().info().width
+    }
+
+    #[test]
+    fn reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watch.vpy");
+        fs::write(&path, blank_clip(16)).unwrap();
+        let env = Environment::from_file(&path).unwrap();
+        let watcher = ScriptWatcher::new(env, |_| {}).unwrap();
+        assert_eq!(width(&watcher), 16);
+
+        fs::write(&path, blank_clip(32)).unwrap();
+        watcher.reload().unwrap();
+        assert_eq!(width(&watcher), 32);
+    }
+
+    #[test]
+    fn coalesced_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watch.vpy");
+        fs::write(&path, blank_clip(16)).unwrap();
+        let env = Environment::from_file(&path).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let watcher = ScriptWatcher::new(env, move |result| tx.send(result).unwrap()).unwrap();
+
+        for width in [24, 32, 48] {
+            fs::write(&path, blank_clip(width)).unwrap();
+        }
+        rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        assert!(rx.recv_timeout(ScriptWatcher::DEBOUNCE * 5).is_err());
+        assert_eq!(width(&watcher), 48);
+    }
+
+    #[test]
+    fn failed_reload_keeps_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watch.vpy");
+        fs::write(&path, blank_clip(16)).unwrap();
+        let env = Environment::from_file(&path).unwrap();
+        let watcher = ScriptWatcher::new(env, |_| {}).unwrap();
+        let outputs = watcher.outputs();
+
+        fs::write(&path, "raise ValueError('broken')\n").unwrap();
+        assert!(matches!(
+            watcher.reload(),
+            Err(ScriptError::Evaluation { .. })
+        ));
+        assert!(Arc::ptr_eq(&watcher.outputs(), &outputs));
+        assert_eq!(width(&watcher), 16);
+    }
+}
@@ -0,0 +1,485 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Sharing frames with other processes through a memory mapped file.
+//!
+//! A [`SharedFrameWriter`] copies each frame into the file, after a header with its format, the
+//! layout of its planes and its properties, so previews or sandboxed encoders can read them with
+//! a [`SharedFrameReader`] without a pipe. On Linux, a file in `/dev/shm` never touches the disk:
+//!
+//! ```ignore
+//! let mut writer = SharedFrameWriter::create("/dev/shm/preview")?;
+//! writer.write_frame(n, &frame)?;
+//!
+//! // In the other process
+//! let mut reader = SharedFrameReader::open("/dev/shm/preview")?;
+//! if let Some(frame) = reader.read()? {
+//!     let luma = frame.plane(0);
+//! }
+//! ```
+//!
+//! The file holds only the latest frame. A sequence number in the header is odd while a frame
+//! is written, so readers retry instead of returning a torn frame. Both sides must run on the
+//! same machine, as the header is in native byte order.
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering, fence},
+};
+
+use memmap2::{Mmap, MmapMut};
+use thiserror::Error;
+
+use crate::{
+    frame::{Frame, VideoFormat, VideoFrame},
+    map::{Map, Value},
+};
+
+const MAGIC: &[u8; 8] = b"VS4RSHM1";
+const SEQUENCE: usize = 8;
+const HEADER_SIZE: usize = 144;
+/// Planes start at multiples of this, for SIMD loads.
+const ALIGNMENT: usize = 64;
+const RETRIES: usize = 1000;
+
+#[derive(Debug, Error)]
+pub enum SharedMemoryError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("The file does not hold frames written by this version")]
+    InvalidHeader,
+    #[error("The writer did not finish the frame")]
+    Busy,
+}
+
+/// A frame property which can be shared, the other kinds are skipped.
+#[derive(Clone, PartialEq, Debug)]
+pub enum SharedProp {
+    Int(i64),
+    Float(f64),
+    Utf8(String),
+    Data(Vec<u8>),
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+struct PlaneLayout {
+    offset: usize,
+    stride: usize,
+    width: i32,
+    height: i32,
+}
+
+/// Copies frames into a memory mapped file, growing it when a frame does not fit.
+#[derive(Debug)]
+pub struct SharedFrameWriter {
+    file: File,
+    map: MmapMut,
+}
+
+impl SharedFrameWriter {
+    /// Creates or truncates the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Return the error of creating or mapping the file.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Self::from_file(file)
+    }
+
+    /// Uses `file`, which must be opened for reading and writing, e.g. a `memfd` passed to a
+    /// child process. Its content is replaced.
+    ///
+    /// # Errors
+    ///
+    /// Return the error of resizing or mapping the file.
+    pub fn from_file(file: File) -> io::Result<Self> {
+        file.set_len(HEADER_SIZE as u64)?;
+        // SAFETY: The file is only resized by this writer.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map[..HEADER_SIZE].fill(0);
+        map[..MAGIC.len()].copy_from_slice(MAGIC);
+        Ok(Self { file, map })
+    }
+
+    /// Copies `frame`, the `n`th of the clip, replacing the previous frame.
+    ///
+    /// # Errors
+    ///
+    /// Return the error of growing the file.
+    pub fn write_frame(&mut self, n: i32, frame: &VideoFrame) -> io::Result<()> {
+        let format = frame.get_video_format();
+        let mut planes = [PlaneLayout::default(); 3];
+        let mut end = HEADER_SIZE;
+        for (plane, layout) in (0..format.num_planes).zip(&mut planes) {
+            let offset = end.next_multiple_of(ALIGNMENT);
+            *layout = PlaneLayout {
+                offset,
+                stride: frame.stride(plane).unsigned_abs(),
+                width: frame.frame_width(plane),
+                height: frame.frame_height(plane),
+            };
+            end = offset + layout.stride * usize::try_from(layout.height).unwrap_or_default();
+        }
+        let props = frame
+            .properties()
+            .map(|props| encode_props(&props))
+            .unwrap_or_default();
+        let total = end + props.len();
+        if total > self.map.len() {
+            self.file.set_len(total as u64)?;
+            // SAFETY: See `from_file`.
+            self.map = unsafe { MmapMut::map_mut(&self.file)? };
+        }
+
+        let sequence = self.sequence().fetch_add(1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        let mut header = Header(&mut self.map[..HEADER_SIZE]);
+        header.put(16, total as u64);
+        header.put(24, n);
+        for (i, value) in format_fields(format).into_iter().enumerate() {
+            header.put(28 + 4 * i, value);
+        }
+        for (i, layout) in planes.iter().enumerate() {
+            let at = 56 + 24 * i;
+            header.put(at, layout.offset as u64);
+            header.put(at + 8, layout.stride as u64);
+            header.put(at + 16, layout.width);
+            header.put(at + 20, layout.height);
+        }
+        header.put(128, end as u64);
+        header.put(136, props.len() as u64);
+        for (plane, layout) in (0..format.num_planes).zip(&planes) {
+            let len = layout.stride * usize::try_from(layout.height).unwrap_or_default();
+            // SAFETY: A plane has `height` rows of `stride` bytes.
+            let data = unsafe { std::slice::from_raw_parts(frame.plane(plane), len) };
+            self.map[layout.offset..layout.offset + len].copy_from_slice(data);
+        }
+        self.map[end..total].copy_from_slice(&props);
+        self.sequence().store(sequence + 2, Ordering::Release);
+        Ok(())
+    }
+
+    fn sequence(&self) -> &AtomicU64 {
+        // SAFETY: The mapping is page aligned and holds the header.
+        unsafe { &*self.map.as_ptr().add(SEQUENCE).cast() }
+    }
+}
+
+/// Reads the frames of a [`SharedFrameWriter`], possibly in another process.
+#[derive(Debug)]
+pub struct SharedFrameReader {
+    file: File,
+    map: Mmap,
+    last: u64,
+}
+
+impl SharedFrameReader {
+    /// Opens the file at `path`, which must have been created by a writer.
+    ///
+    /// # Errors
+    ///
+    /// Return [`SharedMemoryError::InvalidHeader`] if the file was not created by a writer.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SharedMemoryError> {
+        Self::from_file(File::open(path)?)
+    }
+
+    /// Uses `file`, which must have been created by a writer.
+    ///
+    /// # Errors
+    ///
+    /// Return [`SharedMemoryError::InvalidHeader`] if the file was not created by a writer.
+    pub fn from_file(file: File) -> Result<Self, SharedMemoryError> {
+        // SAFETY: The writer only grows the file.
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < HEADER_SIZE || &map[..MAGIC.len()] != MAGIC {
+            return Err(SharedMemoryError::InvalidHeader);
+        }
+        Ok(Self { file, map, last: 0 })
+    }
+
+    /// Copies the latest frame, or returns `None` if it has already been read or no frame has
+    /// been written yet.
+    ///
+    /// # Errors
+    ///
+    /// Return [`SharedMemoryError::Busy`] if the writer did not finish the frame after many
+    /// retries, e.g. because it crashed.
+    pub fn read(&mut self) -> Result<Option<SharedFrame>, SharedMemoryError> {
+        for _ in 0..RETRIES {
+            let sequence = self.sequence().load(Ordering::Acquire);
+            if sequence == self.last {
+                return Ok(None);
+            }
+            if sequence % 2 == 1 {
+                std::thread::yield_now();
+                continue;
+            }
+            let total = usize::try_from(read::<u64>(&self.map, 16)).unwrap_or(usize::MAX);
+            if total > self.map.len() {
+                // SAFETY: See `from_file`.
+                self.map = unsafe { Mmap::map(&self.file)? };
+                continue;
+            }
+            // The writer may be changing the data, which is detected below and discarded.
+            let data = self.map[..total].to_vec();
+            fence(Ordering::Acquire);
+            if self.sequence().load(Ordering::Relaxed) == sequence {
+                self.last = sequence;
+                return SharedFrame::parse(data).map(Some);
+            }
+        }
+        Err(SharedMemoryError::Busy)
+    }
+
+    fn sequence(&self) -> &AtomicU64 {
+        // SAFETY: The mapping is page aligned and holds the header.
+        unsafe { &*self.map.as_ptr().add(SEQUENCE).cast() }
+    }
+}
+
+/// A frame copied out of the shared memory.
+#[derive(Clone, Debug)]
+pub struct SharedFrame {
+    /// The number of the frame in the clip.
+    pub n: i32,
+    pub format: VideoFormat,
+    /// The properties which could be shared, with one entry per element.
+    pub props: Vec<(String, SharedProp)>,
+    planes: [PlaneLayout; 3],
+    data: Vec<u8>,
+}
+
+impl SharedFrame {
+    fn parse(data: Vec<u8>) -> Result<Self, SharedMemoryError> {
+        let field = |i: usize| read::<i32>(&data, 28 + 4 * i);
+        let invalid = |_| SharedMemoryError::InvalidHeader;
+        let format = VideoFormat {
+            color_family: field(0).try_into().map_err(invalid)?,
+            sample_type: field(1).try_into().map_err(invalid)?,
+            bits_per_sample: field(2),
+            bytes_per_sample: field(3),
+            sub_sampling_w: field(4),
+            sub_sampling_h: field(5),
+            num_planes: field(6),
+        };
+        let mut planes = [PlaneLayout::default(); 3];
+        for (i, layout) in planes.iter_mut().enumerate() {
+            let at = 56 + 24 * i;
+            *layout = PlaneLayout {
+                offset: usize::try_from(read::<u64>(&data, at)).unwrap_or(usize::MAX),
+                stride: usize::try_from(read::<u64>(&data, at + 8)).unwrap_or(usize::MAX),
+                width: read(&data, at + 16),
+                height: read(&data, at + 20),
+            };
+        }
+        let props_start = usize::try_from(read::<u64>(&data, 128)).unwrap_or(usize::MAX);
+        let props = data
+            .get(props_start..)
+            .and_then(decode_props)
+            .ok_or(SharedMemoryError::InvalidHeader)?;
+        let planes_fit = planes
+            .iter()
+            .take(usize::try_from(format.num_planes).unwrap_or_default())
+            .all(|layout| {
+                let height = usize::try_from(layout.height).unwrap_or(usize::MAX);
+                layout
+                    .stride
+                    .checked_mul(height)
+                    .and_then(|len| len.checked_add(layout.offset))
+                    .is_some_and(|end| end <= props_start)
+            });
+        if format.num_planes > 3 || !planes_fit {
+            return Err(SharedMemoryError::InvalidHeader);
+        }
+        Ok(Self {
+            n: read(&data, 24),
+            format,
+            props,
+            planes,
+            data,
+        })
+    }
+
+    /// The rows of `plane`, including the padding at the end of each row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `plane` is not a plane of the format.
+    #[must_use]
+    pub fn plane(&self, plane: i32) -> &[u8] {
+        assert!(
+            (0..self.format.num_planes).contains(&plane),
+            "invalid plane"
+        );
+        let layout = &self.planes[plane.unsigned_abs() as usize];
+        let len = layout.stride * layout.height.unsigned_abs() as usize;
+        &self.data[layout.offset..layout.offset + len]
+    }
+
+    #[must_use]
+    pub fn stride(&self, plane: i32) -> usize {
+        self.planes[plane.unsigned_abs() as usize].stride
+    }
+
+    #[must_use]
+    pub fn frame_width(&self, plane: i32) -> i32 {
+        self.planes[plane.unsigned_abs() as usize].width
+    }
+
+    #[must_use]
+    pub fn frame_height(&self, plane: i32) -> i32 {
+        self.planes[plane.unsigned_abs() as usize].height
+    }
+
+    /// The first element of the property `key`.
+    #[must_use]
+    pub fn prop(&self, key: &str) -> Option<&SharedProp> {
+        self.props.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+}
+
+fn format_fields(format: &VideoFormat) -> [i32; 7] {
+    [
+        format.color_family as i32,
+        format.sample_type as i32,
+        format.bits_per_sample,
+        format.bytes_per_sample,
+        format.sub_sampling_w,
+        format.sub_sampling_h,
+        format.num_planes,
+    ]
+}
+
+struct Header<'a>(&'a mut [u8]);
+
+impl Header<'_> {
+    fn put<T: NativeBytes>(&mut self, at: usize, value: T) {
+        let bytes = value.to_bytes();
+        self.0[at..at + bytes.as_ref().len()].copy_from_slice(bytes.as_ref());
+    }
+}
+
+trait NativeBytes: Sized {
+    type Bytes: AsRef<[u8]>;
+    fn to_bytes(self) -> Self::Bytes;
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! native_bytes {
+    ($($t:ty),*) => {$(
+        impl NativeBytes for $t {
+            type Bytes = [u8; size_of::<$t>()];
+            fn to_bytes(self) -> Self::Bytes {
+                self.to_ne_bytes()
+            }
+            fn from_bytes(bytes: &[u8]) -> Self {
+                <$t>::from_ne_bytes(bytes.try_into().unwrap())
+            }
+        }
+    )*};
+}
+
+native_bytes!(i32, u32, u64, i64, f64);
+
+/// Reads a `T` at `at`, or zero if `data` is too short.
+fn read<T: NativeBytes + Default>(data: &[u8], at: usize) -> T {
+    data.get(at..at + size_of::<T>())
+        .map_or_else(T::default, T::from_bytes)
+}
+
+// Each element is stored as the length and bytes of the key, a kind and the value.
+fn encode_props(props: &Map) -> Vec<u8> {
+    let mut out = Vec::new();
+    for i in 0..props.len() {
+        let key = props.get_key(i);
+        for index in 0..props.num_elements(key).unwrap_or_default() {
+            let (kind, value) = match props.get(key, index) {
+                Ok(Value::Int(v)) => (b'i', v.to_ne_bytes().to_vec()),
+                Ok(Value::Float(v)) => (b'f', v.to_ne_bytes().to_vec()),
+                Ok(Value::Utf8(v)) => (b's', v.as_bytes().to_vec()),
+                Ok(Value::Data(v)) => (b'd', v.to_vec()),
+                _ => continue,
+            };
+            encode_prop(&mut out, key.to_bytes(), kind, &value);
+        }
+    }
+    out
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn encode_prop(out: &mut Vec<u8>, key: &[u8], kind: u8, value: &[u8]) {
+    out.extend_from_slice(&(key.len() as u32).to_ne_bytes());
+    out.extend_from_slice(key);
+    out.push(kind);
+    out.extend_from_slice(&(value.len() as u64).to_ne_bytes());
+    out.extend_from_slice(value);
+}
+
+fn decode_props(mut data: &[u8]) -> Option<Vec<(String, SharedProp)>> {
+    fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        let (head, tail) = data.split_at_checked(len)?;
+        *data = tail;
+        Some(head)
+    }
+
+    let mut props = Vec::new();
+    while !data.is_empty() {
+        let len = u32::from_bytes(take(&mut data, 4)?);
+        let key = String::from_utf8(take(&mut data, len as usize)?.to_vec()).ok()?;
+        let kind = take(&mut data, 1)?[0];
+        let len = usize::try_from(u64::from_bytes(take(&mut data, 8)?)).ok()?;
+        let value = take(&mut data, len)?;
+        let prop = match kind {
+            b'i' if len == 8 => SharedProp::Int(i64::from_bytes(value)),
+            b'f' if len == 8 => SharedProp::Float(f64::from_bytes(value)),
+            b's' => SharedProp::Utf8(String::from_utf8(value.to_vec()).ok()?),
+            b'd' => SharedProp::Data(value.to_vec()),
+            _ => return None,
+        };
+        props.push((key, prop));
+    }
+    Some(props)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn props() {
+        let mut data = Vec::new();
+        encode_prop(&mut data, b"_Matrix", b'i', &1i64.to_ne_bytes());
+        encode_prop(&mut data, b"_AbsoluteTime", b'f', &0.5f64.to_ne_bytes());
+        encode_prop(&mut data, b"Name", b's', b"clip");
+        assert_eq!(
+            decode_props(&data),
+            Some(vec![
+                ("_Matrix".to_owned(), SharedProp::Int(1)),
+                ("_AbsoluteTime".to_owned(), SharedProp::Float(0.5)),
+                ("Name".to_owned(), SharedProp::Utf8("clip".to_owned())),
+            ])
+        );
+        assert_eq!(decode_props(&data[..data.len() - 1]), None);
+    }
+
+    #[test]
+    fn empty() -> testresult::TestResult {
+        let path = std::env::temp_dir().join(format!("vs4rs-shm-{}", std::process::id()));
+        let _writer = SharedFrameWriter::create(&path)?;
+        let mut reader = SharedFrameReader::open(&path)?;
+        assert!(reader.read()?.is_none());
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Helpers for the tests of filters written with this crate.
+
+mod clips;
+mod compare;
+mod context;
+mod deterministic;
+mod snapshot;
+#[cfg(feature = "proptest")]
+mod strategies;
+
+pub use clips::*;
+pub use compare::*;
+pub use context::*;
+pub use deterministic::*;
+pub use snapshot::*;
+#[cfg(feature = "proptest")]
+pub use strategies::*;
@@ -0,0 +1,188 @@
+use std::ffi::{CStr, c_void};
+
+use crate::{
+    SampleType, VideoInfo,
+    core::{Core, CoreRef},
+    frame::{FrameContext, VideoFormat, VideoFrame},
+    node::{ActivationReason, Dependencies, Filter, FilterError, VideoNode},
+};
+
+/// What every frame of a [`synthetic_clip`] shows.
+///
+/// Values are in the range of the samples: `0..=255` for 8 bit integers and usually `0.0..=1.0`
+/// for floats.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pattern {
+    /// Every sample of a plane has the value of the plane.
+    Solid([f64; 3]),
+    /// Every row rises from zero to the largest value of the samples.
+    Gradient,
+    /// Pseudo random samples, which only depend on the seed, the frame number and the position.
+    Noise { seed: u64 },
+    /// Every sample holds the frame number, wrapped to the range of the samples, so tests can
+    /// tell which frame they got.
+    Counter,
+}
+
+struct Synthetic {
+    format: VideoFormat,
+    width: i32,
+    height: i32,
+    pattern: Pattern,
+}
+
+/// Creates a clip with `info` which draws `pattern` in Rust, so filters can be tested without
+/// any plugin installed.
+///
+/// # Errors
+///
+/// Return [`FilterError::Creation`] if the format does not have a constant size and format, or
+/// has half precision samples, otherwise see [`Core::create_video_filter_node`].
+#[allow(clippy::missing_panics_doc)]
+pub fn synthetic_clip(
+    core: &mut Core,
+    info: &VideoInfo,
+    pattern: Pattern,
+) -> Result<VideoNode, FilterError> {
+    let format = &info.format;
+    if !info.is_constant_format() {
+        return Err(FilterError::Creation(
+            "Synthetic clips require a constant format and size".to_owned(),
+        ));
+    }
+    if format.sample_type == SampleType::Float && format.bytes_per_sample != 4 {
+        return Err(FilterError::Creation(
+            "Synthetic clips do not support half precision samples".to_owned(),
+        ));
+    }
+    let filter = Synthetic {
+        format: format.clone(),
+        width: info.width,
+        height: info.height,
+        pattern,
+    };
+    core.create_video_filter_node(c"Synthetic", info, filter, Dependencies::new(&[]).unwrap())
+}
+
+impl Filter for Synthetic {
+    type Error = &'static CStr;
+    type FrameType = VideoFrame;
+    type FilterData = ();
+
+    const NAME: &'static CStr = c"Synthetic";
+    const ARGS: &'static CStr = c"";
+    const RETURN_TYPE: &'static CStr = c"clip:vnode;";
+
+    fn get_frame(
+        &self,
+        n: i32,
+        activation_reason: ActivationReason,
+        _frame_data: *mut *mut c_void,
+        _ctx: FrameContext,
+        core: CoreRef,
+    ) -> Result<Option<VideoFrame>, Self::Error> {
+        if activation_reason != ActivationReason::Initial {
+            return Ok(None);
+        }
+        let mut frame = core.new_video_frame(&self.format, self.width, self.height, None);
+        let max = max_value(&self.format);
+        for plane in 0..self.format.num_planes {
+            let width = frame.frame_width(plane);
+            let value = |x: i32, y: i32| match self.pattern {
+                Pattern::Solid(values) => values[plane.unsigned_abs() as usize],
+                Pattern::Gradient => max * f64::from(x) / f64::from((width - 1).max(1)),
+                Pattern::Noise { seed } => {
+                    let key = [n, plane, x, y]
+                        .into_iter()
+                        .fold(seed, |hash, v| mix(hash ^ u64::from(v.unsigned_abs())));
+                    #[allow(clippy::cast_precision_loss)]
+                    let unit = (key >> 11) as f64 / (1_u64 << 53) as f64;
+                    if self.format.sample_type == SampleType::Float {
+                        unit
+                    } else {
+                        (unit * (max + 1.0)).floor()
+                    }
+                }
+                Pattern::Counter => f64::from(n) % (max + 1.0),
+            };
+            fill_plane(&mut frame, plane, value);
+        }
+        Ok(Some(frame))
+    }
+}
+
+/// The largest value of integer samples, or 1 for floats.
+fn max_value(format: &VideoFormat) -> f64 {
+    match format.sample_type {
+        SampleType::Integer => 2_f64.powi(format.bits_per_sample) - 1.0,
+        _ => 1.0,
+    }
+}
+
+/// The finalizer of `SplitMix64`.
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Rows of frames are aligned by the core.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_ptr_alignment
+)]
+fn fill_plane(frame: &mut VideoFrame, plane: i32, value: impl Fn(i32, i32) -> f64) {
+    let format = frame.get_video_format().clone();
+    let max = max_value(&format);
+    let stride = frame.stride(plane);
+    let mut row = frame.plane_mut(plane);
+    for y in 0..frame.frame_height(plane) {
+        for x in 0..frame.frame_width(plane) {
+            let v = value(x, y);
+            let x = x as usize;
+            // SAFETY: The row holds `width` samples of the format.
+            unsafe {
+                match (format.sample_type, format.bytes_per_sample) {
+                    (SampleType::Float, _) => *row.cast::<f32>().add(x) = v as f32,
+                    (_, 1) => *row.add(x) = v.round().clamp(0.0, max) as u8,
+                    (_, 2) => *row.cast::<u16>().add(x) = v.round().clamp(0.0, max) as u16,
+                    _ => *row.cast::<u32>().add(x) = v.round().clamp(0.0, max) as u32,
+                }
+            }
+        }
+        row = row.wrapping_offset(stride);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_is_spread() {
+        let values: Vec<_> = (0..4).map(|i| mix(i) >> 62).collect();
+        assert_ne!(values, [values[0]; 4]);
+        assert_eq!(mix(42), mix(42));
+    }
+
+    #[cfg(feature = "link-library")]
+    #[test]
+    fn counter() {
+        use crate::{PresetVideoFormat, node::Node};
+
+        let mut core = Core::builder().build();
+        let format = core.get_video_format_by_id(PresetVideoFormat::YUV420P8);
+        let info = crate::info::video_info()
+            .format(format)
+            .width(16)
+            .height(8)
+            .num_frames(300)
+            .build()
+            .unwrap();
+        let clip = synthetic_clip(&mut core, &info, Pattern::Counter).unwrap();
+        let frame = clip.get_frame(258).unwrap();
+        assert_eq!(unsafe { *frame.plane(2) }, 2);
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt::Display;
+
+use crate::{
+    SampleType,
+    frame::{VideoFormat, VideoFrame},
+};
+
+/// Asserts that `a` and `b` have the same format, size and samples. The padding at the end of
+/// the rows is ignored.
+///
+/// # Panics
+///
+/// Panics with the first differing plane, row and column and both samples.
+#[track_caller]
+pub fn assert_frames_equal(a: &VideoFrame, b: &VideoFrame) {
+    assert_same_shape(a, b);
+    for plane in 0..a.get_video_format().num_planes {
+        if let Some(difference) = first_difference(&view(a, plane), &view(b, plane)) {
+            panic!("frames differ at {difference}");
+        }
+    }
+}
+
+/// Asserts that `a` and `b` have the same format and size, and that the PSNR of each plane is at
+/// least `min_psnr` dB.
+///
+/// # Panics
+///
+/// Panics with the PSNR of the first plane below `min_psnr` and its first differing sample.
+#[track_caller]
+pub fn assert_frames_similar(a: &VideoFrame, b: &VideoFrame, min_psnr: f64) {
+    assert_same_shape(a, b);
+    for plane in 0..a.get_video_format().num_planes {
+        let (a, b) = (view(a, plane), view(b, plane));
+        let psnr = plane_psnr(&a, &b);
+        if psnr < min_psnr {
+            let difference = first_difference(&a, &b).expect("planes with a finite PSNR differ");
+            panic!(
+                "plane {plane} has a PSNR of {psnr:.2} dB, less than {min_psnr:.2} dB, first \
+                 difference at {difference}"
+            );
+        }
+    }
+}
+
+/// The PSNR of `plane` of `a` against `b` in dB, which is infinite for equal planes.
+///
+/// Integer samples use the largest value of their bit depth as peak, and float samples 1.
+///
+/// # Panics
+///
+/// Panics if the frames do not have the same format and size.
+#[must_use]
+#[track_caller]
+pub fn psnr(a: &VideoFrame, b: &VideoFrame, plane: i32) -> f64 {
+    assert_same_shape(a, b);
+    plane_psnr(&view(a, plane), &view(b, plane))
+}
+
+#[track_caller]
+fn assert_same_shape(a: &VideoFrame, b: &VideoFrame) {
+    assert_eq!(
+        a.get_video_format(),
+        b.get_video_format(),
+        "frames have different formats"
+    );
+    assert_eq!(
+        (a.frame_width(0), a.frame_height(0)),
+        (b.frame_width(0), b.frame_height(0)),
+        "frames have different sizes"
+    );
+}
+
+/// The samples of one plane.
+struct PlaneView<'a> {
+    format: &'a VideoFormat,
+    plane: i32,
+    data: &'a [u8],
+    stride: usize,
+    width: usize,
+    height: usize,
+}
+
+impl PlaneView<'_> {
+    fn row(&self, y: usize) -> impl Iterator<Item = f64> + '_ {
+        let bytes = self.format.bytes_per_sample.unsigned_abs() as usize;
+        let row = &self.data[y * self.stride..y * self.stride + self.width * bytes];
+        row.chunks_exact(bytes)
+            .map(move |sample| match (self.format.sample_type, bytes) {
+                (SampleType::Float, 4) => f64::from(f32::from_ne_bytes(sample.try_into().unwrap())),
+                (SampleType::Integer, 1) => f64::from(sample[0]),
+                (SampleType::Integer, 2) => {
+                    f64::from(u16::from_ne_bytes(sample.try_into().unwrap()))
+                }
+                (SampleType::Integer, 4) => {
+                    f64::from(u32::from_ne_bytes(sample.try_into().unwrap()))
+                }
+                // Half precision samples are compared by their bits.
+                _ => f64::from(u16::from_ne_bytes(sample.try_into().unwrap())),
+            })
+    }
+}
+
+fn view(frame: &VideoFrame, plane: i32) -> PlaneView<'_> {
+    let format = frame.get_video_format();
+    let stride = frame.stride(plane).unsigned_abs();
+    let height = frame.frame_height(plane).unsigned_abs() as usize;
+    PlaneView {
+        format,
+        plane,
+        // SAFETY: A plane has `height` rows of `stride` bytes.
+        data: unsafe { std::slice::from_raw_parts(frame.plane(plane), stride * height) },
+        stride,
+        width: frame.frame_width(plane).unsigned_abs() as usize,
+        height,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Difference {
+    plane: i32,
+    row: usize,
+    column: usize,
+    left: f64,
+    right: f64,
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "plane {}, row {}, column {}: {} != {}",
+            self.plane, self.row, self.column, self.left, self.right
+        )
+    }
+}
+
+fn first_difference(a: &PlaneView, b: &PlaneView) -> Option<Difference> {
+    (0..a.height).find_map(|row| {
+        a.row(row)
+            .zip(b.row(row))
+            .enumerate()
+            .find(|(_, (left, right))| left.to_bits() != right.to_bits())
+            .map(|(column, (left, right))| Difference {
+                plane: a.plane,
+                row,
+                column,
+                left,
+                right,
+            })
+    })
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn plane_psnr(a: &PlaneView, b: &PlaneView) -> f64 {
+    let peak = match a.format.sample_type {
+        SampleType::Integer => 2_f64.powi(a.format.bits_per_sample) - 1.0,
+        _ => 1.0,
+    };
+    let sum: f64 = (0..a.height)
+        .flat_map(|row| a.row(row).zip(b.row(row)))
+        .map(|(left, right)| (left - right).powi(2))
+        .sum();
+    let mse = sum / (a.width * a.height).max(1) as f64;
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (peak * peak / mse).log10()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorFamily;
+
+    const GRAY8: VideoFormat = VideoFormat {
+        color_family: ColorFamily::Gray,
+        sample_type: SampleType::Integer,
+        bits_per_sample: 8,
+        bytes_per_sample: 1,
+        sub_sampling_w: 0,
+        sub_sampling_h: 0,
+        num_planes: 1,
+    };
+
+    fn gray(data: &[u8], stride: usize) -> PlaneView<'_> {
+        PlaneView {
+            format: &GRAY8,
+            plane: 0,
+            data,
+            stride,
+            width: 2,
+            height: data.len() / stride,
+        }
+    }
+
+    #[test]
+    fn differences() {
+        // The third byte of each row is padding.
+        let a = gray(&[1, 2, 0, 3, 4, 0], 3);
+        let b = gray(&[1, 2, 9, 3, 5, 9], 3);
+        assert_eq!(
+            first_difference(&a, &b),
+            Some(Difference {
+                plane: 0,
+                row: 1,
+                column: 1,
+                left: 4.0,
+                right: 5.0
+            })
+        );
+        assert_eq!(first_difference(&a, &a), None);
+        assert!(plane_psnr(&a, &a).is_infinite());
+        let psnr = plane_psnr(&a, &b);
+        assert!((psnr - 10.0 * (255.0_f64 * 255.0 * 4.0).log10()).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString, c_char, c_int},
+    mem::ManuallyDrop,
+    ptr,
+};
+
+use crate::{
+    VAPOURSYNTH_API_MAJOR, VAPOURSYNTH_API_MINOR,
+    api::Api,
+    core::keep_alive::{CoreObject, KeepAlive},
+    ffi,
+    frame::{Frame, FrameContext},
+    node::Node,
+};
+
+/// Serves frames supplied by the test to [`Filter::get_frame`] and records what it requests, so
+/// its request and processing logic can be tested without running a graph.
+///
+/// The contexts of [`MockFrameContext::context`] answer `request_frame_filter`,
+/// `get_frame_filter`, `release_frame_early`, `cache_frame` and `set_filter_error` themselves.
+///
+/// ```ignore
+/// let mut mock = MockFrameContext::new(core.api());
+/// filter.get_frame(5, ActivationReason::Initial, ptr::null_mut(), mock.context(), core.as_ref())?;
+/// assert_eq!(mock.requested(&clip), [4, 5, 6]);
+///
+/// for n in 4..=6 {
+///     mock.supply(&clip, n, clip.get_frame(n)?);
+/// }
+/// let frame = filter.get_frame(5, ActivationReason::AllFramesReady, ptr::null_mut(), mock.context(), core.as_ref())?;
+/// ```
+///
+/// [`Filter::get_frame`]: crate::node::Filter::get_frame
+pub struct MockFrameContext {
+    // Boxed, so the contexts can point to them while the mock moves.
+    api: Box<ffi::VSAPI>,
+    state: Box<MockState>,
+}
+
+#[derive(Default)]
+struct MockState {
+    // The keepalive of each frame is dropped after the frame is freed.
+    frames: HashMap<(usize, i32), (*const ffi::VSFrame, Api, KeepAlive)>,
+    requested: Vec<(usize, i32)>,
+    released: Vec<(usize, i32)>,
+    cached: Vec<i32>,
+    error: Option<CString>,
+}
+
+impl MockFrameContext {
+    /// Creates a mock which forwards every other function to `api`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `api` is older than the API of this crate.
+    #[must_use]
+    pub fn new(api: Api) -> Self {
+        assert!(
+            api.supports(VAPOURSYNTH_API_MAJOR, VAPOURSYNTH_API_MINOR),
+            "the mock requires API {VAPOURSYNTH_API_MAJOR}.{VAPOURSYNTH_API_MINOR}"
+        );
+        // SAFETY: The table holds every function of this API version.
+        let mut table = Box::new(unsafe { ptr::read(&raw const *api) });
+        table.requestFrameFilter = request_frame_filter;
+        table.getFrameFilter = get_frame_filter;
+        table.releaseFrameEarly = release_frame_early;
+        table.cacheFrame = cache_frame;
+        table.setFilterError = set_filter_error;
+        Self {
+            api: table,
+            state: Box::default(),
+        }
+    }
+
+    /// A context for one call of `get_frame`.
+    #[must_use]
+    pub fn context(&mut self) -> FrameContext {
+        // SAFETY: Both pointers live as long as the mock, and the state is only accessed by the
+        // functions of the table.
+        unsafe {
+            FrameContext::from_ptr(
+                ptr::from_mut(&mut *self.state).cast(),
+                Api::from_ptr(&raw const *self.api),
+            )
+        }
+    }
+
+    /// Serves `frame` as frame `n` of `node`, replacing a frame supplied before.
+    pub fn supply<N: Node>(&mut self, node: &N, n: i32, frame: N::FrameType) {
+        let api = frame.api();
+        let mut frame = frame;
+        let keep_alive = std::mem::take(frame.keep_alive_mut());
+        let frame = ManuallyDrop::new(frame);
+        let key = (node.as_ptr() as usize, n);
+        if let Some((old, api, _)) = self
+            .state
+            .frames
+            .insert(key, (frame.as_ptr(), api, keep_alive))
+        {
+            unsafe { (api.freeFrame)(old) };
+        }
+    }
+
+    /// The frames of `node` requested so far, in order.
+    #[must_use]
+    pub fn requested(&self, node: &impl Node) -> Vec<i32> {
+        frames_of(&self.state.requested, node)
+    }
+
+    /// The frames of `node` released early so far, in order.
+    #[must_use]
+    pub fn released(&self, node: &impl Node) -> Vec<i32> {
+        frames_of(&self.state.released, node)
+    }
+
+    /// The numbers of the frames passed to `cache_frame` so far.
+    #[must_use]
+    pub fn cached(&self) -> &[i32] {
+        &self.state.cached
+    }
+
+    /// The error set by the filter, if any.
+    #[must_use]
+    pub fn error(&self) -> Option<&CStr> {
+        self.state.error.as_deref()
+    }
+
+    /// Forgets the requests, releases and error, but keeps the supplied frames.
+    pub fn clear(&mut self) {
+        self.state.requested.clear();
+        self.state.released.clear();
+        self.state.cached.clear();
+        self.state.error = None;
+    }
+}
+
+impl Drop for MockFrameContext {
+    fn drop(&mut self) {
+        for (frame, api, _) in self.state.frames.values() {
+            unsafe { (api.freeFrame)(*frame) };
+        }
+    }
+}
+
+fn frames_of(frames: &[(usize, i32)], node: &impl Node) -> Vec<i32> {
+    let node = node.as_ptr() as usize;
+    frames
+        .iter()
+        .filter(|(source, _)| *source == node)
+        .map(|(_, n)| *n)
+        .collect()
+}
+
+unsafe fn state<'a>(ctx: *mut ffi::VSFrameContext) -> &'a mut MockState {
+    unsafe { &mut *ctx.cast::<MockState>() }
+}
+
+unsafe extern "system-unwind" fn request_frame_filter(
+    n: c_int,
+    node: *mut ffi::VSNode,
+    ctx: *mut ffi::VSFrameContext,
+) {
+    unsafe { state(ctx) }.requested.push((node as usize, n));
+}
+
+unsafe extern "system-unwind" fn get_frame_filter(
+    n: c_int,
+    node: *mut ffi::VSNode,
+    ctx: *mut ffi::VSFrameContext,
+) -> *const ffi::VSFrame {
+    let Some(&(frame, api, _)) = unsafe { state(ctx) }.frames.get(&(node as usize, n)) else {
+        panic!("frame {n} of the node was not supplied to the mock");
+    };
+    // The caller owns the returned reference.
+    unsafe { (api.addFrameRef)(frame) }
+}
+
+unsafe extern "system-unwind" fn release_frame_early(
+    node: *mut ffi::VSNode,
+    n: c_int,
+    ctx: *mut ffi::VSFrameContext,
+) {
+    unsafe { state(ctx) }.released.push((node as usize, n));
+}
+
+unsafe extern "system-unwind" fn cache_frame(
+    _frame: *const ffi::VSFrame,
+    n: c_int,
+    ctx: *mut ffi::VSFrameContext,
+) {
+    unsafe { state(ctx) }.cached.push(n);
+}
+
+unsafe extern "system-unwind" fn set_filter_error(
+    message: *const c_char,
+    ctx: *mut ffi::VSFrameContext,
+) {
+    unsafe { state(ctx) }.error = Some(unsafe { CStr::from_ptr(message) }.to_owned());
+}
+
+#[cfg(test)]
+#[cfg(feature = "link-library")]
+mod tests {
+    use super::*;
+    use crate::{
+        PresetVideoFormat,
+        core::Core,
+        frame::VideoFrame,
+        testing::{Pattern, assert_frames_equal, synthetic_clip},
+    };
+
+    #[test]
+    fn serves_supplied_frames() {
+        let mut core = Core::builder().build();
+        let format = core.get_video_format_by_id(PresetVideoFormat::Gray8);
+        let info = crate::info::video_info()
+            .format(format)
+            .width(8)
+            .height(8)
+            .num_frames(10)
+            .build()
+            .unwrap();
+        let clip = synthetic_clip(&mut core, &info, Pattern::Counter).unwrap();
+        let mut mock = MockFrameContext::new(core.api());
+
+        let mut ctx = mock.context();
+        ctx.request_frame_filter(3, &clip);
+        ctx.set_filter_error(c"failed");
+        assert_eq!(mock.requested(&clip), [3]);
+        assert_eq!(mock.error(), Some(c"failed"));
+
+        let frame: VideoFrame = clip.get_frame(3).unwrap();
+        mock.supply(&clip, 3, frame.clone());
+        let served = clip.get_frame_filter(3, &mut mock.context());
+        assert_frames_equal(&served, &frame);
+    }
+}
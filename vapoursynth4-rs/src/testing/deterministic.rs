@@ -0,0 +1,87 @@
+use std::env;
+
+#[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+use crate::core::Core;
+use crate::node::{CacheMode, Node};
+
+use super::Pattern;
+
+/// Overrides [`DEFAULT_TEST_SEED`] for [`test_seed`], in decimal or with a `0x` prefix.
+pub const TEST_SEED: &str = "VS4RS_TEST_SEED";
+
+pub const DEFAULT_TEST_SEED: u64 = 0x5eed;
+
+/// The seed for random test data, from [`TEST_SEED`] or [`DEFAULT_TEST_SEED`], so a failure
+/// with another seed can be reproduced.
+///
+/// # Panics
+///
+/// Panics if [`TEST_SEED`] is set but not a number.
+#[must_use]
+pub fn test_seed() -> u64 {
+    parse_seed(env::var(TEST_SEED).ok().as_deref())
+}
+
+fn parse_seed(value: Option<&str>) -> u64 {
+    let Some(value) = value else {
+        return DEFAULT_TEST_SEED;
+    };
+    match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .unwrap_or_else(|_| panic!("{TEST_SEED} is not a number: {value}"))
+}
+
+impl Pattern {
+    /// Noise seeded with the [`test_seed`].
+    #[must_use]
+    pub fn test_noise() -> Self {
+        Self::Noise { seed: test_seed() }
+    }
+}
+
+/// Creates a core whose filters give the same results on every machine and run: it has one
+/// thread, so frames are produced in the order they are requested, no frame cache, and does not
+/// load the installed plugins, which may differ between machines. Load the plugins the test
+/// needs explicitly.
+#[cfg(any(feature = "link-library", feature = "runtime-loading"))]
+#[must_use]
+pub fn deterministic_core() -> Core {
+    Core::builder()
+        .thread_count(1)
+        .max_cache_size(0)
+        .disable_auto_loading()
+        .build()
+}
+
+/// Makes `node` produce every frame again instead of returning a cached one, so filters which
+/// depend on the order of requests fail every time.
+pub fn disable_cache(node: &mut impl Node) {
+    node.set_cache_mode(CacheMode::ForceDisable);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds() {
+        assert_eq!(parse_seed(None), DEFAULT_TEST_SEED);
+        assert_eq!(parse_seed(Some("42")), 42);
+        assert_eq!(parse_seed(Some("0xff")), 255);
+    }
+
+    #[test]
+    #[should_panic = "VS4RS_TEST_SEED is not a number"]
+    fn invalid_seed() {
+        _ = parse_seed(Some("seed"));
+    }
+
+    #[cfg(feature = "link-library")]
+    #[test]
+    fn single_thread() {
+        let core = deterministic_core();
+        assert_eq!(core.get_info().num_threads, 1);
+    }
+}
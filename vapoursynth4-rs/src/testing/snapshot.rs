@@ -0,0 +1,163 @@
+use std::{env, fs, path::Path, thread};
+
+use crate::{frame::VideoFrame, node::VideoNode, output::render_ordered};
+
+/// Set to `1` to overwrite snapshots which do not match, like `INSTA_UPDATE`.
+pub const UPDATE_SNAPSHOTS: &str = "VS4RS_UPDATE_SNAPSHOTS";
+
+const HEADER: &str = "# vapoursynth4-rs snapshot: frame checksum";
+
+/// Compares the checksums of every frame of `node` with the snapshot at `path`.
+///
+/// The snapshot is written if it does not exist yet, or if [`UPDATE_SNAPSHOTS`] is set to `1`,
+/// so review and commit it like the expected output of any other test.
+///
+/// # Panics
+///
+/// Panics with the differing frames if the checksums do not match the snapshot, or if a frame
+/// can not be rendered or the snapshot can not be read or written.
+#[track_caller]
+pub fn assert_snapshot(path: impl AsRef<Path>, node: &VideoNode) {
+    let path = path.as_ref();
+    let mut checksums = Vec::new();
+    let requests = thread::available_parallelism().map_or(1, usize::from);
+    render_ordered(node, 0..node.info().num_frames, requests, |_, frame| {
+        checksums.push(frame_checksum(&frame));
+        Ok(())
+    })
+    .unwrap_or_else(|e| panic!("failed to render the clip: {e}"));
+
+    let update = env::var(UPDATE_SNAPSHOTS).is_ok_and(|v| v == "1");
+    let expected = match fs::read_to_string(path) {
+        Ok(snapshot) if !update => {
+            parse(&snapshot).unwrap_or_else(|| panic!("{} is not a clip snapshot", path.display()))
+        }
+        _ => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).expect("failed to create the snapshot directory");
+            }
+            fs::write(path, to_snapshot(&checksums)).expect("failed to write the snapshot");
+            return;
+        }
+    };
+
+    let differences = mismatches(&expected, &checksums);
+    assert!(
+        differences.is_empty(),
+        "clip does not match {}, set {UPDATE_SNAPSHOTS}=1 to update it:\n{}",
+        path.display(),
+        differences.join("\n")
+    );
+}
+
+/// The FNV-1a hash of the format, size and samples of `frame`, without the padding of the
+/// rows.
+#[must_use]
+pub fn frame_checksum(frame: &VideoFrame) -> u64 {
+    let format = frame.get_video_format();
+    let bytes = format.bytes_per_sample.unsigned_abs() as usize;
+    let mut hash = fnv1a(
+        FNV_OFFSET,
+        [
+            format.color_family as i32,
+            format.sample_type as i32,
+            format.bits_per_sample,
+            format.sub_sampling_w,
+            format.sub_sampling_h,
+            frame.frame_width(0),
+            frame.frame_height(0),
+        ]
+        .map(i32::to_le_bytes)
+        .as_flattened(),
+    );
+    for plane in 0..format.num_planes {
+        let width = frame.frame_width(plane).unsigned_abs() as usize * bytes;
+        let mut row = frame.plane(plane);
+        for _ in 0..frame.frame_height(plane) {
+            // SAFETY: Each row of the plane holds `width` bytes.
+            hash = fnv1a(hash, unsafe { std::slice::from_raw_parts(row, width) });
+            row = row.wrapping_offset(frame.stride(plane));
+        }
+    }
+    hash
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn fnv1a(hash: u64, data: &[u8]) -> u64 {
+    data.iter().fold(hash, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn to_snapshot(checksums: &[u64]) -> String {
+    let lines = checksums
+        .iter()
+        .enumerate()
+        .map(|(n, checksum)| format!("{n} {checksum:016x}\n"));
+    std::iter::once(format!("{HEADER}\n"))
+        .chain(lines)
+        .collect()
+}
+
+fn parse(snapshot: &str) -> Option<Vec<u64>> {
+    let mut lines = snapshot.lines();
+    if lines.next()? != HEADER {
+        return None;
+    }
+    lines
+        .enumerate()
+        .map(|(i, line)| {
+            let (n, checksum) = line.split_once(' ')?;
+            (n.parse::<usize>().ok()? == i).then_some(())?;
+            u64::from_str_radix(checksum, 16).ok()
+        })
+        .collect()
+}
+
+fn mismatches(expected: &[u64], actual: &[u64]) -> Vec<String> {
+    let mut out = Vec::new();
+    if expected.len() != actual.len() {
+        out.push(format!(
+            "expected {} frames, got {}",
+            expected.len(),
+            actual.len()
+        ));
+    }
+    out.extend(
+        expected
+            .iter()
+            .zip(actual)
+            .enumerate()
+            .filter(|(_, (expected, actual))| expected != actual)
+            .map(|(n, (expected, actual))| {
+                format!("frame {n}: expected {expected:016x}, got {actual:016x}")
+            }),
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let checksums = [0, u64::MAX, 0x1234];
+        let snapshot = to_snapshot(&checksums);
+        assert_eq!(parse(&snapshot).as_deref(), Some(&checksums[..]));
+        assert_eq!(parse("0 1234\n"), None);
+        assert_eq!(
+            mismatches(&checksums, &[0, 1]),
+            [
+                "expected 3 frames, got 2",
+                "frame 1: expected ffffffffffffffff, got 0000000000000001"
+            ]
+        );
+    }
+
+    #[test]
+    fn fnv() {
+        assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,231 @@
+use proptest::prelude::*;
+
+use crate::{
+    AudioInfo, ColorFamily, SampleType, VideoInfo,
+    frame::{AudioChannels, AudioFormat, VideoFormat},
+    rational::Rational,
+};
+
+/// Video formats [`Core::query_video_format`] accepts: gray, RGB and YUV with 8 to 32 bit
+/// integer or 16 and 32 bit float samples and up to 4 levels of subsampling.
+///
+/// [`Core::query_video_format`]: crate::core::Core::query_video_format
+pub fn arb_video_format() -> impl Strategy<Value = VideoFormat> {
+    let color_family = prop_oneof![
+        Just(ColorFamily::Gray),
+        Just(ColorFamily::RGB),
+        Just(ColorFamily::YUV),
+    ];
+    let depth = prop_oneof![
+        (8..=32).prop_map(|bits| (SampleType::Integer, bits)),
+        Just((SampleType::Float, 16)),
+        Just((SampleType::Float, 32)),
+    ];
+    (color_family, depth, 0..=4, 0..=4).prop_map(|(color_family, (sample_type, bits), w, h)| {
+        let (sub_sampling_w, sub_sampling_h) = match color_family {
+            ColorFamily::YUV => (w, h),
+            _ => (0, 0),
+        };
+        VideoFormat {
+            color_family,
+            sample_type,
+            bits_per_sample: bits,
+            bytes_per_sample: match bits {
+                ..=8 => 1,
+                9..=16 => 2,
+                _ => 4,
+            },
+            sub_sampling_w,
+            sub_sampling_h,
+            num_planes: if color_family == ColorFamily::Gray {
+                1
+            } else {
+                3
+            },
+        }
+    })
+}
+
+/// Video formats from [`arb_video_format`] with one invalid field: the bit depth, the bytes per
+/// sample, the subsampling or the number of planes.
+pub fn near_valid_video_format() -> impl Strategy<Value = VideoFormat> {
+    (arb_video_format(), 0..4).prop_map(|(mut format, defect)| {
+        match defect {
+            0 if format.sample_type == SampleType::Float => format.bits_per_sample = 24,
+            0 => format.bits_per_sample = 7,
+            1 => format.bytes_per_sample *= 2,
+            2 if format.color_family == ColorFamily::YUV => format.sub_sampling_w = 5,
+            2 => format.sub_sampling_w = 1,
+            _ => format.num_planes = 4 - format.num_planes,
+        }
+        format
+    })
+}
+
+/// Video infos with a format from [`arb_video_format`], a size fitting its subsampling, a
+/// reduced or variable frame rate and up to 100000 frames.
+pub fn arb_video_info() -> impl Strategy<Value = VideoInfo> {
+    (
+        arb_video_format(),
+        1..=256,
+        1..=256,
+        prop::option::of((1..=240_000_i64, 1..=1001_i64)),
+        1..=100_000,
+    )
+        .prop_map(|(format, width, height, fps, num_frames)| {
+            let (fps_num, fps_den) =
+                fps.map_or((0, 0), |(num, den)| Rational::new(num, den).into());
+            VideoInfo {
+                width: width << format.sub_sampling_w,
+                height: height << format.sub_sampling_h,
+                format,
+                fps_num,
+                fps_den,
+                num_frames,
+            }
+        })
+}
+
+/// Video infos from [`arb_video_info`] which [`info::video_info`] rejects, because the size does
+/// not fit the subsampling or is half variable, the frame rate has no denominator, or the clip
+/// has no frames.
+///
+/// [`info::video_info`]: crate::info::video_info
+pub fn near_valid_video_info() -> impl Strategy<Value = VideoInfo> {
+    (arb_video_info(), 0..4).prop_map(|(mut info, defect)| {
+        match defect {
+            0 if info.format.sub_sampling_w > 0 => info.width += 1,
+            0 => info.width = -info.width,
+            1 => info.height = 0,
+            2 => (info.fps_num, info.fps_den) = (info.fps_num.max(1), 0),
+            _ => info.num_frames = 0,
+        }
+        info
+    })
+}
+
+/// Audio formats [`Core::query_audio_format`] accepts: 16 to 32 bit integer or 32 bit float
+/// samples, with common and arbitrary channel layouts.
+///
+/// [`Core::query_audio_format`]: crate::core::Core::query_audio_format
+pub fn arb_audio_format() -> impl Strategy<Value = AudioFormat> {
+    let depth = prop_oneof![
+        (16..=32).prop_map(|bits| (SampleType::Integer, bits)),
+        Just((SampleType::Float, 32)),
+    ];
+    let channels = prop_oneof![
+        Just(AudioChannels::MONO),
+        Just(AudioChannels::STEREO),
+        Just(AudioChannels::SURROUND_5_1),
+        Just(AudioChannels::SURROUND_7_1),
+        any::<u64>()
+            .prop_map(AudioChannels::from_bits_truncate)
+            .prop_filter("at least one channel", |channels| !channels.is_empty()),
+    ];
+    (depth, channels).prop_map(|((sample_type, bits_per_sample), channels)| AudioFormat {
+        sample_type,
+        bits_per_sample,
+        bytes_per_sample: if bits_per_sample <= 16 { 2 } else { 4 },
+        num_channels: channels.len().cast_signed(),
+        channel_layout: channels.bits(),
+    })
+}
+
+/// Audio formats from [`arb_audio_format`] with one invalid field: the bit depth, the number of
+/// channels or an empty channel layout.
+pub fn near_valid_audio_format() -> impl Strategy<Value = AudioFormat> {
+    (arb_audio_format(), 0..3).prop_map(|(mut format, defect)| {
+        match defect {
+            0 if format.sample_type == SampleType::Float => format.bits_per_sample = 16,
+            0 => format.bits_per_sample = 8,
+            1 => format.num_channels += 1,
+            _ => (format.channel_layout, format.num_channels) = (0, 0),
+        }
+        format
+    })
+}
+
+/// Audio infos with a format from [`arb_audio_format`], a sample rate up to 384 kHz and any
+/// number of samples that fits in the frames of a clip.
+pub fn arb_audio_info() -> impl Strategy<Value = AudioInfo> {
+    let samples_per_frame = i64::from(AudioFormat::SAMPLES_PER_FRAME);
+    // The samples of the last frame, so the count is bounded by the frames.
+    (
+        arb_audio_format(),
+        1..=384_000,
+        1..=i32::MAX,
+        1..=samples_per_frame,
+    )
+        .prop_map(
+            move |(format, sample_rate, num_frames, last_samples)| AudioInfo {
+                format,
+                sample_rate,
+                num_samples: i64::from(num_frames - 1) * samples_per_frame + last_samples,
+                num_frames,
+            },
+        )
+}
+
+/// Audio infos from [`arb_audio_info`] which [`info::audio_info`] rejects, because of a format
+/// from [`near_valid_audio_format`], a sample rate that is not positive or no samples.
+///
+/// [`info::audio_info`]: crate::info::audio_info
+pub fn near_valid_audio_info() -> impl Strategy<Value = AudioInfo> {
+    (arb_audio_info(), near_valid_audio_format(), 0..3).prop_map(|(mut info, format, defect)| {
+        match defect {
+            0 => info.format = format,
+            1 => info.sample_rate = 0,
+            _ => (info.num_samples, info.num_frames) = (0, 0),
+        }
+        info
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info::{audio_info, video_info};
+
+    fn rebuild_video(info: &VideoInfo) -> Result<VideoInfo, crate::info::InfoError> {
+        let builder = video_info()
+            .format(info.format.clone())
+            .width(info.width)
+            .height(info.height)
+            .num_frames(info.num_frames);
+        if info.fps_den == 0 && info.fps_num == 0 {
+            builder.build()
+        } else {
+            builder.fps(info.fps_num, info.fps_den).build()
+        }
+    }
+
+    fn rebuild_audio(info: &AudioInfo) -> Result<AudioInfo, crate::info::InfoError> {
+        audio_info()
+            .format(info.format.clone())
+            .sample_rate(info.sample_rate)
+            .num_samples(info.num_samples)
+            .build()
+    }
+
+    proptest! {
+        #[test]
+        fn valid_video(info in arb_video_info()) {
+            prop_assert_eq!(rebuild_video(&info), Ok(info));
+        }
+
+        #[test]
+        fn near_valid_video(info in near_valid_video_info()) {
+            prop_assert!(rebuild_video(&info).is_err());
+        }
+
+        #[test]
+        fn valid_audio(info in arb_audio_info()) {
+            prop_assert_eq!(rebuild_audio(&info), Ok(info));
+        }
+
+        #[test]
+        fn near_valid_audio(info in near_valid_audio_info()) {
+            prop_assert!(rebuild_audio(&info).is_err());
+        }
+    }
+}
@@ -0,0 +1,121 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Thumbnails of evenly spaced frames, for media managers and QC dashboards.
+
+use image::RgbImage;
+use thiserror::Error;
+
+use crate::{
+    PresetVideoFormat,
+    core::Core,
+    node::{Node, VideoNode},
+    plugin::InvokeError,
+};
+
+#[derive(Debug, Error)]
+pub enum ThumbnailError {
+    /// The clip could not be converted, see [`Core::resize`].
+    #[error(transparent)]
+    Invoke(#[from] InvokeError),
+    #[error("Failed to get frame {n}: {message}")]
+    Frame { n: i32, message: String },
+    #[error("Thumbnails require a clip with a constant size")]
+    VariableSize,
+}
+
+/// Converts `count` evenly spaced frames of `node` to RGB images which fit in `size`, keeping
+/// the aspect ratio. Each frame is taken from the middle of its part of the clip, so fades at the
+/// start and end are skipped.
+///
+/// YUV frames are converted with the matrix of their `_Matrix` property.
+///
+/// # Errors
+///
+/// Return [`ThumbnailError::VariableSize`] if the clip has no constant size, or the error of
+/// converting or getting a frame.
+pub fn generate(
+    core: &Core,
+    node: &VideoNode,
+    count: usize,
+    size: (u32, u32),
+) -> Result<Vec<RgbImage>, ThumbnailError> {
+    let info = node.info();
+    if info.width <= 0 || info.height <= 0 {
+        return Err(ThumbnailError::VariableSize);
+    }
+    let (width, height) = fit(
+        (info.width.unsigned_abs(), info.height.unsigned_abs()),
+        size,
+    );
+    let rgb = core
+        .resize()
+        .bicubic(node)
+        .width(width.cast_signed())
+        .height(height.cast_signed())
+        .format(PresetVideoFormat::RGB24)
+        .call()?;
+
+    frame_numbers(info.num_frames, count)
+        .into_iter()
+        .map(|n| {
+            let frame = rgb.get_frame(n).map_err(|e| ThumbnailError::Frame {
+                n,
+                message: e.to_string_lossy().into_owned(),
+            })?;
+            let stride = frame.stride(0).unsigned_abs();
+            let planes = [0, 1, 2].map(|plane| {
+                // SAFETY: Every plane has `height` rows of `stride` bytes.
+                unsafe { std::slice::from_raw_parts(frame.plane(plane), stride * height as usize) }
+            });
+            Ok(RgbImage::from_fn(width, height, |x, y| {
+                let i = y as usize * stride + x as usize;
+                image::Rgb(planes.map(|plane| plane[i]))
+            }))
+        })
+        .collect()
+}
+
+/// The middle frame of each of `count` equal parts of a clip with `num_frames`.
+fn frame_numbers(num_frames: i32, count: usize) -> Vec<i32> {
+    let num_frames = i64::from(num_frames.max(0));
+    let count = i64::try_from(count).unwrap_or(i64::MAX).min(num_frames);
+    (0..count)
+        .map(|i| i32::try_from((2 * i + 1) * num_frames / (2 * count)).unwrap_or_default())
+        .collect()
+}
+
+/// The largest size with the aspect ratio of `size` which fits in `bounds`, at least 1x1.
+fn fit(size: (u32, u32), bounds: (u32, u32)) -> (u32, u32) {
+    let (width, height) = (u64::from(size.0), u64::from(size.1));
+    let (max_width, max_height) = (u64::from(bounds.0), u64::from(bounds.1));
+    let (width, height) = if width * max_height > max_width * height {
+        (max_width, height * max_width / width)
+    } else {
+        (width * max_height / height, max_height)
+    };
+    let clamp = |v: u64| u32::try_from(v).unwrap_or(u32::MAX).max(1);
+    (clamp(width), clamp(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spacing() {
+        assert_eq!(frame_numbers(100, 4), [12, 37, 62, 87]);
+        assert_eq!(frame_numbers(2, 5), [0, 1]);
+        assert!(frame_numbers(0, 3).is_empty());
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(fit((1920, 1080), (320, 320)), (320, 180));
+        assert_eq!(fit((720, 1280), (320, 320)), (180, 320));
+        assert_eq!(fit((1000, 1), (100, 100)), (100, 1));
+    }
+}
@@ -17,6 +17,160 @@ impl ToCString for &str {
     }
 }
 
-pub use crate::ffi::vs_make_version as make_version;
+pub use crate::ffi::{vs_make_version as make_version, vs_split_version as split_version};
 
 pub use crate::ffi::helper::*;
+
+/// Implements the set operations of a flags newtype `$name($bits)` over the `$flag` enum.
+///
+/// `$from` maps one flag to its bit, and `$flags` lists every known flag in iteration order.
+macro_rules! bit_flags {
+    (
+        $name:ident($bits:ty): $flag:ty;
+        $(#[$from_meta:meta])*
+        fn $from:ident($arg:ident) $from_body:block
+        const FLAGS = $flags:expr;
+    ) => {
+        impl $name {
+            /// Every known flag.
+            pub const ALL: Self = {
+                let flags = $flags;
+                let mut bits = 0;
+                let mut i = 0;
+                while i < flags.len() {
+                    bits |= Self::$from(flags[i]).0;
+                    i += 1;
+                }
+                Self(bits)
+            };
+
+            #[must_use]
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            $(#[$from_meta])*
+            #[must_use]
+            pub const fn $from($arg: $flag) -> Self {
+                Self($from_body)
+            }
+
+            /// Returns [`None`] if `bits` has a bit that is not a known flag.
+            #[must_use]
+            pub const fn from_bits(bits: $bits) -> Option<Self> {
+                if bits & !Self::ALL.0 == 0 {
+                    Some(Self(bits))
+                } else {
+                    None
+                }
+            }
+
+            /// Drops the bits that are not a known flag.
+            #[must_use]
+            pub const fn from_bits_truncate(bits: $bits) -> Self {
+                Self(bits & Self::ALL.0)
+            }
+
+            #[must_use]
+            pub const fn bits(self) -> $bits {
+                self.0
+            }
+
+            #[must_use]
+            pub const fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            /// The number of flags in the set.
+            #[must_use]
+            pub const fn len(self) -> u32 {
+                self.0.count_ones()
+            }
+
+            #[must_use]
+            pub const fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            pub fn insert(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+
+            pub fn remove(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+
+            /// The flags in the set, in the order of the known flags.
+            pub fn iter(self) -> impl Iterator<Item = $flag> {
+                $flags
+                    .into_iter()
+                    .filter(move |&f| self.contains(Self::$from(f)))
+            }
+        }
+
+        impl From<$flag> for $name {
+            fn from(flag: $flag) -> Self {
+                Self::$from(flag)
+            }
+        }
+
+        impl From<$name> for $bits {
+            fn from(flags: $name) -> Self {
+                flags.0
+            }
+        }
+
+        impl FromIterator<$flag> for $name {
+            fn from_iter<T: IntoIterator<Item = $flag>>(iter: T) -> Self {
+                iter.into_iter()
+                    .fold(Self::empty(), |acc, f| acc | Self::$from(f))
+            }
+        }
+
+        impl std::ops::BitOr for $name {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self::Output {
+                Self(self.0 | rhs.0)
+            }
+        }
+
+        impl std::ops::BitOrAssign for $name {
+            fn bitor_assign(&mut self, rhs: Self) {
+                self.0 |= rhs.0;
+            }
+        }
+
+        impl std::ops::BitAnd for $name {
+            type Output = Self;
+
+            fn bitand(self, rhs: Self) -> Self::Output {
+                Self(self.0 & rhs.0)
+            }
+        }
+
+        impl std::ops::Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                Self(self.0 & !rhs.0)
+            }
+        }
+
+        impl std::ops::Not for $name {
+            type Output = Self;
+
+            fn not(self) -> Self::Output {
+                Self(!self.0 & Self::ALL.0)
+            }
+        }
+
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_set().entries(self.iter()).finish()
+            }
+        }
+    };
+}
+
+pub(crate) use bit_flags;
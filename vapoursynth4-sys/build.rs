@@ -5,17 +5,324 @@
 */
 
 fn main() {
-    #[cfg(feature = "link-library")]
-    {
-        use std::env;
+    println!("cargo::rustc-check-cfg=cfg(layout_tests)");
 
-        const LIBRARY_DIR_VARIABLE: &str = "VAPOURSYNTH_LIB_PATH";
+    #[cfg(all(feature = "link-library", not(feature = "no-link")))]
+    link::link();
 
-        // Make sure the build script is re-run if our env variable is changed.
-        println!("cargo:rerun-if-env-changed={LIBRARY_DIR_VARIABLE}");
+    #[cfg(feature = "layout-tests")]
+    layout_tests::generate();
+}
+
+/// Finds and links the libraries for the `link-library` feature, unless `no-link` is enabled.
+#[cfg(all(feature = "link-library", not(feature = "no-link")))]
+mod link {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process::Command,
+    };
+
+    const LIBRARY_DIR_VARIABLE: &str = "VAPOURSYNTH_LIB_PATH";
+
+    /// A library to link, with its name on Windows (and of its framework on macOS) and elsewhere,
+    /// its pkg-config package, the env variable with its directory, and the function it exports.
+    struct Library {
+        windows: &'static str,
+        unix: &'static str,
+        package: &'static str,
+        dir_variable: &'static str,
+        export: &'static str,
+    }
+
+    const VAPOURSYNTH: Library = Library {
+        windows: "VapourSynth",
+        unix: "vapoursynth",
+        package: "vapoursynth",
+        dir_variable: LIBRARY_DIR_VARIABLE,
+        export: "getVapourSynthAPI",
+    };
+    const VSSCRIPT: Library = Library {
+        windows: "VSScript",
+        unix: "vapoursynth-script",
+        package: "vapoursynth-script",
+        dir_variable: "VSSCRIPT_LIB_PATH",
+        export: "getVSScriptAPI",
+    };
 
-        if let Ok(dir) = env::var(LIBRARY_DIR_VARIABLE) {
+    pub(crate) fn link() {
+        let mut libraries = vec![VAPOURSYNTH];
+        if cfg!(feature = "script") {
+            libraries.push(VSSCRIPT);
+        }
+        for library in &libraries {
+            link_library(library);
+        }
+    }
+
+    /// Finds and links a library on its own, so each can be installed in a different place.
+    fn link_library(library: &Library) {
+        // The build script runs on the host, so the target is only known from the environment.
+        let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+        let windows = target_os == "windows";
+
+        // Make sure the build script is re-run if our env variables are changed.
+        println!("cargo:rerun-if-env-changed={}", library.dir_variable);
+        println!("cargo:rerun-if-env-changed={LIBRARY_DIR_VARIABLE}");
+        if let Ok(dir) = env::var(library.dir_variable).or_else(|_| env::var(LIBRARY_DIR_VARIABLE))
+        {
             println!("cargo:rustc-link-search=native={dir}");
+        } else if windows {
+            // Without an SDK, e.g. when cross-compiling, MinGW can link a generated import library.
+            if let Some(dir) = windows_lib_dir(library).or_else(|| {
+                (env::var("CARGO_CFG_TARGET_ENV").as_deref() == Ok("gnu"))
+                    .then(|| gnu_import_lib(library))
+                    .flatten()
+            }) {
+                println!("cargo:rustc-link-search=native={}", dir.display());
+            }
+        } else if probe(library.package) {
+            // pkg-config emitted the search path and library.
+            return;
+        } else if target_os == "macos" {
+            link_macos(library);
+            return;
         }
+
+        let name = if windows {
+            library.windows
+        } else {
+            library.unix
+        };
+        println!("cargo:rustc-link-lib=dylib={name}");
+    }
+
+    /// Looks up a package with pkg-config, which links it if found.
+    fn probe(package: &str) -> bool {
+        pkg_config::Config::new().probe(package).is_ok()
+    }
+
+    /// Links a library from a Homebrew or `MacPorts` prefix, or as a framework.
+    fn link_macos(library: &Library) {
+        let file = format!("lib{}.dylib", library.unix);
+        let framework = format!("{}.framework", library.windows);
+        if let Some(dir) = macos_prefixes()
+            .into_iter()
+            .map(|prefix| prefix.join("lib"))
+            .find(|dir| dir.join(&file).is_file())
+        {
+            println!("cargo:rustc-link-search=native={}", dir.display());
+        } else if let Some(dir) = macos_framework_dirs()
+            .into_iter()
+            .find(|dir| dir.join(&framework).is_dir())
+        {
+            println!("cargo:rustc-link-search=framework={}", dir.display());
+            println!("cargo:rustc-link-lib=framework={}", library.windows);
+            return;
+        }
+        println!("cargo:rustc-link-lib=dylib={}", library.unix);
+    }
+
+    /// The prefixes of Homebrew and `MacPorts`, with the one of the target architecture first.
+    fn macos_prefixes() -> Vec<PathBuf> {
+        println!("cargo:rerun-if-env-changed=HOMEBREW_PREFIX");
+        let mut prefixes: Vec<_> = env::var_os("HOMEBREW_PREFIX")
+            .map(PathBuf::from)
+            .into_iter()
+            .collect();
+        // Homebrew installs to `/opt/homebrew` on arm64 and to `/usr/local` on x86_64.
+        if env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("aarch64") {
+            prefixes.extend(["/opt/homebrew", "/usr/local"].map(PathBuf::from));
+        } else {
+            prefixes.extend(["/usr/local", "/opt/homebrew"].map(PathBuf::from));
+        }
+        prefixes.push(PathBuf::from("/opt/local"));
+        prefixes
+    }
+
+    fn macos_framework_dirs() -> Vec<PathBuf> {
+        let mut dirs = vec![PathBuf::from("/Library/Frameworks")];
+        if let Some(home) = env::var_os("HOME") {
+            dirs.push(Path::new(&home).join("Library").join("Frameworks"));
+        }
+        dirs
+    }
+
+    /// The directory with the import libraries of the SDK of a Windows installation.
+    fn windows_lib_dir(library: &Library) -> Option<PathBuf> {
+        let bits = env::var("CARGO_CFG_TARGET_POINTER_WIDTH");
+        let lib = if bits.as_deref() == Ok("32") {
+            "lib32"
+        } else {
+            "lib64"
+        };
+        windows_install_dirs()
+            .into_iter()
+            .map(|dir| dir.join("sdk").join(lib))
+            .find(|dir| dir.join(format!("{}.lib", library.windows)).is_file())
+    }
+
+    /// Generates a GNU import library for `library` with `dlltool`, returning its directory.
+    ///
+    /// The DLL only exports its API getter, so no SDK is needed to link against it.
+    fn gnu_import_lib(library: &Library) -> Option<PathBuf> {
+        let dir = PathBuf::from(env::var_os("OUT_DIR")?).join("import");
+        fs::create_dir_all(&dir).ok()?;
+
+        let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+        // 32-bit x86 uses `__stdcall`, which decorates the name with the size of the arguments.
+        let (machine, export) = match arch.as_str() {
+            "x86" => ("i386", format!("{}@4", library.export)),
+            "aarch64" => ("arm64", library.export.to_owned()),
+            _ => ("i386:x86-64", library.export.to_owned()),
+        };
+        let def = dir.join(format!("{}.def", library.windows));
+        fs::write(
+            &def,
+            format!("LIBRARY {}.dll\nEXPORTS\n    {export}\n", library.windows),
+        )
+        .ok()?;
+        let lib = dir.join(format!("lib{}.dll.a", library.windows));
+
+        println!("cargo:rerun-if-env-changed=DLLTOOL");
+        let prefix = match arch.as_str() {
+            "x86" => "i686",
+            arch => arch,
+        };
+        let tools = env::var("DLLTOOL").into_iter().chain([
+            format!("{prefix}-w64-mingw32-dlltool"),
+            "dlltool".to_owned(),
+            "llvm-dlltool".to_owned(),
+        ]);
+        for tool in tools {
+            let status = Command::new(&tool)
+                .args(["-m", machine, "-d"])
+                .arg(&def)
+                .arg("-l")
+                .arg(&lib)
+                // Import the undecorated name.
+                .args(if arch == "x86" { &["-k"][..] } else { &[] })
+                .status();
+            if status.is_ok_and(|status| status.success()) {
+                return Some(dir);
+            }
+        }
+        println!(
+            "cargo:warning=could not generate an import library for {}.dll, set DLLTOOL to a \
+             MinGW or LLVM dlltool",
+            library.windows
+        );
+        None
+    }
+
+    fn windows_install_dirs() -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        // The installer records its location for all users or the current one. It can only be
+        // read when building on Windows.
+        if cfg!(windows) {
+            for root in ["HKLM", "HKCU"] {
+                dirs.extend(registry_value(
+                    &format!(r"{root}\SOFTWARE\VapourSynth"),
+                    "Path",
+                ));
+            }
+        }
+        for (variable, dir) in [
+            ("ProgramFiles", "VapourSynth"),
+            ("ProgramFiles(x86)", "VapourSynth"),
+            ("LOCALAPPDATA", r"Programs\VapourSynth"),
+        ] {
+            if let Some(base) = env::var_os(variable) {
+                dirs.push(Path::new(&base).join(dir));
+            }
+        }
+        // Portable installs are only known from `PATH`, like their DLLs at runtime.
+        if let Some(path) = env::var_os("PATH") {
+            dirs.extend(env::split_paths(&path));
+        }
+        dirs
+    }
+
+    /// Reads a string value from the registry with `reg`, to avoid a dependency.
+    fn registry_value(key: &str, value: &str) -> Option<PathBuf> {
+        let output = Command::new("reg")
+            .args(["query", key, "/v", value])
+            .output()
+            .ok()?;
+        // The value is printed as `    Path    REG_SZ    C:\...`.
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.split_once("REG_SZ"))
+            .map(|(_, dir)| PathBuf::from(dir.trim()))
+    }
+}
+
+/// Compiles the C side of `src/abi_tests.rs` and generates bindings from the installed headers,
+/// which `src/layout_tests.rs` compares with ours.
+#[cfg(feature = "layout-tests")]
+mod layout_tests {
+    use std::{env, path::PathBuf};
+
+    const INCLUDE_DIR_VARIABLE: &str = "VAPOURSYNTH_INCLUDE_PATH";
+    const INCLUDE_DIRS: &[&str] = &[
+        "/usr/include/vapoursynth",
+        "/usr/local/include/vapoursynth",
+        "/opt/homebrew/include/vapoursynth",
+    ];
+
+    pub(crate) fn generate() {
+        println!("cargo:rerun-if-changed=src/abi_tests.c");
+        cc::Build::new()
+            .file("src/abi_tests.c")
+            .compile("vapoursynth4_abi_tests");
+
+        println!("cargo:rerun-if-env-changed={INCLUDE_DIR_VARIABLE}");
+
+        let dir = env::var_os(INCLUDE_DIR_VARIABLE)
+            .map(PathBuf::from)
+            .or_else(|| {
+                INCLUDE_DIRS
+                    .iter()
+                    .map(PathBuf::from)
+                    .find(|dir| dir.is_dir())
+            });
+        let Some(dir) = dir.filter(|dir| dir.join("VapourSynth4.h").is_file()) else {
+            println!(
+                "cargo:warning=VapourSynth4.h not found, set {INCLUDE_DIR_VARIABLE} to run the layout tests"
+            );
+            return;
+        };
+
+        let mut builder = bindgen::Builder::default()
+            .header_contents(
+                "wrapper.h",
+                "#include <VapourSynth4.h>\n\
+                 #include <VSScript4.h>\n\
+                 #include <VSConstants4.h>\n",
+            )
+            .clang_arg(format!("-I{}", dir.display()))
+            .prepend_enum_name(false)
+            .layout_tests(false)
+            .generate_comments(false);
+        // Select the same API versions as the features of the bindings.
+        if cfg!(not(feature = "vs-41")) {
+            builder = builder.clang_arg("-DVAPOURSYNTH_API_MINOR=0");
+        }
+        if cfg!(feature = "vs-graph") {
+            builder = builder.clang_arg("-DVS_GRAPH_API");
+        }
+        if cfg!(not(feature = "vsscript-42")) {
+            builder = builder.clang_arg("-DVSSCRIPT_API_MINOR=1");
+        }
+        let bindings = builder
+            .generate()
+            .expect("Failed to generate bindings from the headers");
+
+        let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("bindings.rs");
+        bindings
+            .write_to_file(out)
+            .expect("Failed to write the bindings");
+        println!("cargo:rerun-if-changed={}", dir.display());
+        println!("cargo:rustc-cfg=layout_tests");
     }
 }
@@ -0,0 +1,29 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+/* Calls through `VS_CC` function pointers, as the library does with the callbacks of plugins. */
+
+#include <stdint.h>
+
+/* The definition of VapourSynth4.h. */
+#if defined(_WIN32) && !defined(_WIN64)
+#define VS_CC __stdcall
+#else
+#define VS_CC
+#endif
+
+typedef int64_t (VS_CC *VSAbiCallback)(void *data, int a, int64_t b, double c, float d,
+                                       const char *e);
+
+int64_t vs_abi_call(VSAbiCallback callback, void *data) {
+    /* A mismatched convention leaves the stack unbalanced after the first call. */
+    int64_t first = callback(data, 1, 2, 3.0, 4.0f, "5");
+    return first + callback(data, 10, 20, 30.0, 40.0f, "50");
+}
+
+int64_t VS_CC vs_abi_callee(int a, int64_t b, double c) {
+    return a + b + (int64_t)c;
+}
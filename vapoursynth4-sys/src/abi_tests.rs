@@ -0,0 +1,56 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Checks that `"system-unwind"` matches `VS_CC` of the target, with the C code in
+//! `abi_tests.c` compiled by the `layout-tests` feature.
+
+use std::ffi::{CStr, c_char, c_double, c_float, c_int, c_void};
+
+type Callback = unsafe extern "system-unwind" fn(
+    data: *mut c_void,
+    int: c_int,
+    int64: i64,
+    double: c_double,
+    float: c_float,
+    string: *const c_char,
+) -> i64;
+
+unsafe extern "C" {
+    fn vs_abi_call(callback: Callback, data: *mut c_void) -> i64;
+}
+
+unsafe extern "system-unwind" {
+    fn vs_abi_callee(int: c_int, int64: i64, double: c_double) -> i64;
+}
+
+#[allow(clippy::cast_possible_truncation)]
+unsafe extern "system-unwind" fn callback(
+    calls: *mut c_void,
+    int: c_int,
+    int64: i64,
+    double: c_double,
+    float: c_float,
+    string: *const c_char,
+) -> i64 {
+    unsafe { *calls.cast::<i32>() += 1 };
+    let string = unsafe { CStr::from_ptr(string) }.to_str().unwrap();
+    i64::from(int) + int64 + double as i64 + float as i64 + string.parse::<i64>().unwrap()
+}
+
+#[test]
+fn callback_from_c() {
+    let mut calls = 0;
+    assert_eq!(
+        unsafe { vs_abi_call(callback, (&raw mut calls).cast()) },
+        15 + 150
+    );
+    assert_eq!(calls, 2);
+}
+
+#[test]
+fn call_into_c() {
+    assert_eq!(unsafe { vs_abi_callee(1, 2, 3.0) }, 6);
+}
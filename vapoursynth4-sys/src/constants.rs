@@ -8,15 +8,26 @@
 
 #![allow(non_camel_case_types)]
 
+use crate::enum_try_from;
+
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSColorRange {
     VSC_RANGE_FULL = 0,
     VSC_RANGE_LIMITED = 1,
 }
 
+enum_try_from!(VSColorRange {
+    VSC_RANGE_FULL,
+    VSC_RANGE_LIMITED
+});
+
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSChromaLocation {
     VSC_CHROMA_LEFT = 0,
     VSC_CHROMA_CENTER = 1,
@@ -26,16 +37,35 @@ pub enum VSChromaLocation {
     VSC_CHROMA_BOTTOM = 5,
 }
 
+enum_try_from!(VSChromaLocation {
+    VSC_CHROMA_LEFT,
+    VSC_CHROMA_CENTER,
+    VSC_CHROMA_TOP_LEFT,
+    VSC_CHROMA_TOP,
+    VSC_CHROMA_BOTTOM_LEFT,
+    VSC_CHROMA_BOTTOM
+});
+
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSFieldBased {
     VSC_FIELD_PROGRESSIVE = 0,
     VSC_FIELD_BOTTOM = 1,
     VSC_FIELD_TOP = 2,
 }
 
+enum_try_from!(VSFieldBased {
+    VSC_FIELD_PROGRESSIVE,
+    VSC_FIELD_BOTTOM,
+    VSC_FIELD_TOP
+});
+
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSMatrixCoefficients {
     VSC_MATRIX_RGB = 0,
     VSC_MATRIX_BT709 = 1,
@@ -53,8 +83,26 @@ pub enum VSMatrixCoefficients {
     VSC_MATRIX_ICTCP = 14,
 }
 
+enum_try_from!(VSMatrixCoefficients {
+    VSC_MATRIX_RGB,
+    VSC_MATRIX_BT709,
+    VSC_MATRIX_UNSPECIFIED,
+    VSC_MATRIX_FCC,
+    VSC_MATRIX_BT470_BG,
+    VSC_MATRIX_ST170_M,
+    VSC_MATRIX_ST240_M,
+    VSC_MATRIX_YCGCO,
+    VSC_MATRIX_BT2020_NCL,
+    VSC_MATRIX_BT2020_CL,
+    VSC_MATRIX_CHROMATICITY_DERIVED_NCL,
+    VSC_MATRIX_CHROMATICITY_DERIVED_CL,
+    VSC_MATRIX_ICTCP
+});
+
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSTransferCharacteristics {
     VSC_TRANSFER_BT709 = 1,
     VSC_TRANSFER_UNSPECIFIED = 2,
@@ -76,8 +124,28 @@ pub enum VSTransferCharacteristics {
     VSC_TRANSFER_ARIB_B67 = 18,
 }
 
+enum_try_from!(VSTransferCharacteristics {
+    VSC_TRANSFER_BT709,
+    VSC_TRANSFER_UNSPECIFIED,
+    VSC_TRANSFER_BT470_M,
+    VSC_TRANSFER_BT470_BG,
+    VSC_TRANSFER_BT601,
+    VSC_TRANSFER_ST240_M,
+    VSC_TRANSFER_LINEAR,
+    VSC_TRANSFER_LOG_100,
+    VSC_TRANSFER_LOG_316,
+    VSC_TRANSFER_IEC_61966_2_4,
+    VSC_TRANSFER_IEC_61966_2_1,
+    VSC_TRANSFER_BT2020_10,
+    VSC_TRANSFER_BT2020_12,
+    VSC_TRANSFER_ST2084,
+    VSC_TRANSFER_ARIB_B67
+});
+
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSColorPrimaries {
     VSC_PRIMARIES_BT709 = 1,
     VSC_PRIMARIES_UNSPECIFIED = 2,
@@ -93,3 +161,59 @@ pub enum VSColorPrimaries {
     VSC_PRIMARIES_ST432_1 = 12,
     VSC_PRIMARIES_EBU3213_E = 22,
 }
+
+enum_try_from!(VSColorPrimaries {
+    VSC_PRIMARIES_BT709,
+    VSC_PRIMARIES_UNSPECIFIED,
+    VSC_PRIMARIES_BT470_M,
+    VSC_PRIMARIES_BT470_BG,
+    VSC_PRIMARIES_ST170_M,
+    VSC_PRIMARIES_ST240_M,
+    VSC_PRIMARIES_FILM,
+    VSC_PRIMARIES_BT2020,
+    VSC_PRIMARIES_ST428,
+    VSC_PRIMARIES_ST431_2,
+    VSC_PRIMARIES_ST432_1,
+    VSC_PRIMARIES_EBU3213_E
+});
+
+/// Names of the frame properties reserved by `VapourSynth`, with the meaning given in its
+/// documentation.
+pub mod props {
+    use std::ffi::CStr;
+
+    /// Int, [`VSChromaLocation`](super::VSChromaLocation).
+    pub const CHROMA_LOCATION: &CStr = c"_ChromaLocation";
+    /// Int, [`VSColorRange`](super::VSColorRange).
+    pub const COLOR_RANGE: &CStr = c"_ColorRange";
+    /// Int, [`VSColorPrimaries`](super::VSColorPrimaries).
+    pub const PRIMARIES: &CStr = c"_Primaries";
+    /// Int, [`VSMatrixCoefficients`](super::VSMatrixCoefficients).
+    pub const MATRIX: &CStr = c"_Matrix";
+    /// Int, [`VSTransferCharacteristics`](super::VSTransferCharacteristics).
+    pub const TRANSFER: &CStr = c"_Transfer";
+    /// Int, [`VSFieldBased`](super::VSFieldBased).
+    pub const FIELD_BASED: &CStr = c"_FieldBased";
+    /// Float, the timestamp of the frame in seconds.
+    pub const ABSOLUTE_TIME: &CStr = c"_AbsoluteTime";
+    /// Int, the numerator of the frame duration in seconds.
+    pub const DURATION_NUM: &CStr = c"_DurationNum";
+    /// Int, the denominator of the frame duration in seconds.
+    pub const DURATION_DEN: &CStr = c"_DurationDen";
+    /// Int, 1 if the frame is combed.
+    pub const COMBED: &CStr = c"_Combed";
+    /// Int, the field a frame was made from by field separation: 0 is bottom, 1 is top.
+    pub const FIELD: &CStr = c"_Field";
+    /// Data, the picture type: `I`, `P` or `B`.
+    pub const PICT_TYPE: &CStr = c"_PictType";
+    /// Int, the numerator of the pixel aspect ratio.
+    pub const SAR_NUM: &CStr = c"_SARNum";
+    /// Int, the denominator of the pixel aspect ratio.
+    pub const SAR_DEN: &CStr = c"_SARDen";
+    /// Int, 1 if the next frame starts a new scene.
+    pub const SCENE_CHANGE_NEXT: &CStr = c"_SceneChangeNext";
+    /// Int, 1 if this frame starts a new scene.
+    pub const SCENE_CHANGE_PREV: &CStr = c"_SceneChangePrev";
+    /// Frame, the alpha channel of the frame.
+    pub const ALPHA: &CStr = c"_Alpha";
+}
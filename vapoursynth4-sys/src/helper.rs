@@ -5,16 +5,26 @@
 */
 
 //! VSHelper4.h
+//!
+//! `vsh_aligned_malloc` and `vsh_aligned_free` are left out, Rust code allocates with
+//! [`std::alloc`] instead.
 
 #![allow(clippy::cast_possible_truncation)]
 
-use std::ffi::{c_int, c_void};
+use std::ffi::{CStr, c_int, c_void};
 
 use crate::{
     VSAPI, VSAudioFormat, VSAudioInfo, VSColorFamily, VSCore, VSPresetVideoFormat, VSVideoFormat,
     VSVideoInfo,
 };
 
+/// Identifier of the `std` plugin.
+pub const VSH_STD_PLUGIN_ID: &CStr = c"com.vapoursynth.std";
+/// Identifier of the `resize` plugin.
+pub const VSH_RESIZE_PLUGIN_ID: &CStr = c"com.vapoursynth.resize";
+/// Identifier of the `text` plugin.
+pub const VSH_TEXT_PLUGIN_ID: &CStr = c"com.vapoursynth.text";
+
 /// Convenience function for checking if the format never changes between frames
 #[inline]
 #[must_use]
@@ -91,9 +101,8 @@ pub const fn is_same_audio_info(a1: &VSAudioInfo, a2: &VSAudioInfo) -> bool {
 
 /// Multiplies and divides a rational number,
 /// such as a frame duration, in place and reduces the result
-// TODO: use `const` when available: https://github.com/rust-lang/rust/issues/57349
 #[inline]
-pub fn muldiv_rational(num: &mut i64, den: &mut i64, mul: i64, div: i64) {
+pub const fn muldiv_rational(num: &mut i64, den: &mut i64, mul: i64, div: i64) {
     // do nothing if the rational number is invalid
     if *den == 0 {
         return;
@@ -118,13 +127,13 @@ pub fn muldiv_rational(num: &mut i64, den: &mut i64, mul: i64, div: i64) {
 
 /// Reduces a rational number
 #[inline]
-pub fn reduce_rational(num: &mut i64, den: &mut i64) {
+pub const fn reduce_rational(num: &mut i64, den: &mut i64) {
     muldiv_rational(num, den, 1, 1);
 }
 
 /// Add two rational numbers and reduces the result
 #[inline]
-pub fn add_rational(num: &mut i64, den: &mut i64, mut addnum: i64, addden: i64) {
+pub const fn add_rational(num: &mut i64, den: &mut i64, mut addnum: i64, addden: i64) {
     // Do nothing if the rational number is invalid
     if *den == 0 {
         return;
@@ -204,3 +213,76 @@ pub unsafe fn bitblt(
 pub const fn are_valid_dimensions(fi: &VSVideoFormat, width: c_int, height: c_int) -> bool {
     width % (1 << fi.sub_sampling_w) == 0 && height % (1 << fi.sub_sampling_h) == 0
 }
+
+impl VSVideoFormat {
+    /// See [`is_same_video_format`].
+    #[inline]
+    #[must_use]
+    pub const fn is_same_format(&self, other: &Self) -> bool {
+        is_same_video_format(self, other)
+    }
+
+    /// See [`are_valid_dimensions`].
+    #[inline]
+    #[must_use]
+    pub const fn are_valid_dimensions(&self, width: c_int, height: c_int) -> bool {
+        are_valid_dimensions(self, width, height)
+    }
+}
+
+impl VSVideoInfo {
+    /// See [`is_constant_video_format`].
+    #[inline]
+    #[must_use]
+    pub const fn is_constant_format(&self) -> bool {
+        is_constant_video_format(self)
+    }
+
+    /// See [`is_same_video_info`].
+    #[inline]
+    #[must_use]
+    pub const fn is_same_info(&self, other: &Self) -> bool {
+        is_same_video_info(self, other)
+    }
+}
+
+impl VSAudioFormat {
+    /// See [`is_same_audio_format`].
+    #[inline]
+    #[must_use]
+    pub const fn is_same_format(&self, other: &Self) -> bool {
+        is_same_audio_format(self, other)
+    }
+}
+
+impl VSAudioInfo {
+    /// See [`is_same_audio_info`].
+    #[inline]
+    #[must_use]
+    pub const fn is_same_info(&self, other: &Self) -> bool {
+        is_same_audio_info(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rational() {
+        const HALF: (i64, i64) = {
+            let (mut num, mut den) = (30000, 1001);
+            muldiv_rational(&mut num, &mut den, 1001, 60000);
+            (num, den)
+        };
+        assert_eq!(HALF, (1, 2));
+
+        let (mut num, mut den) = (1, 3);
+        add_rational(&mut num, &mut den, 1, 6);
+        assert_eq!((num, den), (1, 2));
+
+        let (mut num, mut den) = (4, -8);
+        reduce_rational(&mut num, &mut den);
+        assert_eq!((num, den), (1, -2));
+    }
+}
@@ -0,0 +1,478 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Compile time checks of the bindings against the installed headers.
+//!
+//! Enable the `layout-tests` feature and run `cargo test`, the headers are searched in
+//! `VAPOURSYNTH_INCLUDE_PATH` and the usual install locations.
+
+use core::mem::offset_of;
+
+#[allow(
+    non_camel_case_types,
+    non_snake_case,
+    non_upper_case_globals,
+    dead_code,
+    clippy::all,
+    clippy::pedantic
+)]
+mod c {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+/// Asserts the size and alignment of a struct and the offset of each field, with the C name of
+/// the field after the colon if it differs.
+macro_rules! assert_layout {
+    ($name:ident { $($(#[$attr:meta])* $field:ident $(: $c_field:ident)?),* $(,)? }) => {
+        const _: () = {
+            assert!(size_of::<crate::$name>() == size_of::<c::$name>());
+            assert!(align_of::<crate::$name>() == align_of::<c::$name>());
+            $(
+                $(#[$attr])*
+                assert!(assert_layout!(@offset $name, $field, $($c_field)?));
+            )*
+        };
+    };
+    (@offset $name:ident, $field:ident, ) => {
+        offset_of!(crate::$name, $field) == offset_of!(c::$name, $field)
+    };
+    (@offset $name:ident, $field:ident, $c_field:ident) => {
+        offset_of!(crate::$name, $field) == offset_of!(c::$name, $c_field)
+    };
+}
+
+/// Asserts the size of an enum and the value of each variant, with the C name of the constant
+/// after the colon if it differs.
+macro_rules! assert_values {
+    ($name:ident { $($(#[$attr:meta])* $variant:ident $(: $c_variant:ident)?),* $(,)? }) => {
+        const _: () = {
+            assert!(size_of::<crate::$name>() == size_of::<c::$name>());
+            $(
+                $(#[$attr])*
+                assert!(assert_values!(@value $name, $variant, $($c_variant)?));
+            )*
+        };
+    };
+    (@value $name:ident, $variant:ident, ) => {
+        crate::$name::$variant as i64 == c::$variant as i64
+    };
+    (@value $name:ident, $variant:ident, $c_variant:ident) => {
+        crate::$name::$variant as i64 == c::$c_variant as i64
+    };
+}
+
+assert_layout!(VSVideoFormat {
+    color_family: colorFamily,
+    sample_type: sampleType,
+    bits_per_sample: bitsPerSample,
+    bytes_per_sample: bytesPerSample,
+    sub_sampling_w: subSamplingW,
+    sub_sampling_h: subSamplingH,
+    num_planes: numPlanes,
+});
+assert_layout!(VSAudioFormat {
+    sample_type: sampleType,
+    bits_per_sample: bitsPerSample,
+    bytes_per_sample: bytesPerSample,
+    num_channels: numChannels,
+    channel_layout: channelLayout,
+});
+assert_layout!(VSVideoInfo {
+    format,
+    fps_num: fpsNum,
+    fps_den: fpsDen,
+    width,
+    height,
+    num_frames: numFrames,
+});
+assert_layout!(VSAudioInfo {
+    format,
+    sample_rate: sampleRate,
+    num_samples: numSamples,
+    num_frames: numFrames,
+});
+assert_layout!(VSCoreInfo {
+    version_string: versionString,
+    core,
+    api,
+    num_threads: numThreads,
+    max_framebuffer_size: maxFramebufferSize,
+    used_framebuffer_size: usedFramebufferSize,
+});
+assert_layout!(VSFilterDependency {
+    source,
+    request_pattern: requestPattern,
+});
+assert_layout!(VSPLUGINAPI {
+    getAPIVersion,
+    configPlugin,
+    registerFunction,
+});
+assert_layout!(VSAPI {
+    createVideoFilter,
+    createVideoFilter2,
+    createAudioFilter,
+    createAudioFilter2,
+    setLinearFilter,
+    setCacheMode,
+    setCacheOptions,
+    freeNode,
+    addNodeRef,
+    getNodeType,
+    getVideoInfo,
+    getAudioInfo,
+    newVideoFrame,
+    newVideoFrame2,
+    newAudioFrame,
+    newAudioFrame2,
+    freeFrame,
+    addFrameRef,
+    copyFrame,
+    getFramePropertiesRO,
+    getFramePropertiesRW,
+    getStride,
+    getReadPtr,
+    getWritePtr,
+    getVideoFrameFormat,
+    getAudioFrameFormat,
+    getFrameType,
+    getFrameWidth,
+    getFrameHeight,
+    getFrameLength,
+    getVideoFormatName,
+    getAudioFormatName,
+    queryVideoFormat,
+    queryAudioFormat,
+    queryVideoFormatID,
+    getVideoFormatByID,
+    getFrame,
+    getFrameAsync,
+    getFrameFilter,
+    requestFrameFilter,
+    releaseFrameEarly,
+    cacheFrame,
+    setFilterError,
+    createFunction,
+    freeFunction,
+    addFunctionRef,
+    callFunction,
+    createMap,
+    freeMap,
+    clearMap,
+    copyMap,
+    mapSetError,
+    mapGetError,
+    mapNumKeys,
+    mapGetKey,
+    mapDeleteKey,
+    mapNumElements,
+    mapGetType,
+    mapSetEmpty,
+    mapGetInt,
+    mapGetIntSaturated,
+    mapGetIntArray,
+    mapSetInt,
+    mapSetIntArray,
+    mapGetFloat,
+    mapGetFloatSaturated,
+    mapGetFloatArray,
+    mapSetFloat,
+    mapSetFloatArray,
+    mapGetData,
+    mapGetDataSize,
+    mapGetDataTypeHint,
+    mapSetData,
+    mapGetNode,
+    mapSetNode,
+    mapConsumeNode,
+    mapGetFrame,
+    mapSetFrame,
+    mapConsumeFrame,
+    mapGetFunction,
+    mapSetFunction,
+    mapConsumeFunction,
+    registerFunction,
+    getPluginByID,
+    getPluginByNamespace,
+    getNextPlugin,
+    getPluginName,
+    getPluginID,
+    getPluginNamespace,
+    getNextPluginFunction,
+    getPluginFunctionByName,
+    getPluginFunctionName,
+    getPluginFunctionArguments,
+    getPluginFunctionReturnType,
+    getPluginPath,
+    getPluginVersion,
+    invoke,
+    createCore,
+    freeCore,
+    setMaxCacheSize,
+    setThreadCount,
+    getCoreInfo,
+    getAPIVersion,
+    logMessage,
+    addLogHandler,
+    removeLogHandler,
+    #[cfg(feature = "vs-41")]
+    clearNodeCache,
+    #[cfg(feature = "vs-41")]
+    clearCoreCaches,
+    #[cfg(feature = "vs-41")]
+    getNodeName,
+    #[cfg(feature = "vs-41")]
+    getNodeFilterMode,
+    #[cfg(feature = "vs-41")]
+    getNumNodeDependencies,
+    #[cfg(feature = "vs-41")]
+    getNodeDependencies,
+    #[cfg(feature = "vs-41")]
+    getCoreNodeTiming,
+    #[cfg(feature = "vs-41")]
+    setCoreNodeTiming,
+    #[cfg(feature = "vs-41")]
+    getNodeProcessingTime,
+    #[cfg(feature = "vs-41")]
+    getFreedNodeProcessingTime,
+    #[cfg(feature = "vs-graph")]
+    getNodeCreationFunctionName,
+    #[cfg(feature = "vs-graph")]
+    getNodeCreationFunctionArguments,
+});
+assert_layout!(VSSCRIPTAPI {
+    getAPIVersion,
+    getVSAPI,
+    createScript,
+    getCore,
+    evaluateBuffer,
+    evaluateFile,
+    getError,
+    getExitCode,
+    getVariable,
+    setVariables,
+    getOutputNode,
+    getOutputAlphaNode,
+    getAltOutputMode,
+    freeScript,
+    evalSetWorkingDir,
+    #[cfg(feature = "vsscript-42")]
+    getAvailableOutputNodes,
+});
+assert_values!(VSColorFamily {
+    Undefined: cfUndefined,
+    Gray: cfGray,
+    RGB: cfRGB,
+    YUV: cfYUV,
+});
+assert_values!(VSSampleType {
+    Integer: stInteger,
+    Float: stFloat,
+});
+assert_values!(VSPresetVideoFormat {
+    None: pfNone,
+    Gray8: pfGray8,
+    Gray9: pfGray9,
+    Gray10: pfGray10,
+    Gray12: pfGray12,
+    Gray14: pfGray14,
+    Gray16: pfGray16,
+    Gray32: pfGray32,
+    GrayH: pfGrayH,
+    GrayS: pfGrayS,
+    YUV410P8: pfYUV410P8,
+    YUV411P8: pfYUV411P8,
+    YUV440P8: pfYUV440P8,
+    YUV420P8: pfYUV420P8,
+    YUV422P8: pfYUV422P8,
+    YUV444P8: pfYUV444P8,
+    YUV420P9: pfYUV420P9,
+    YUV422P9: pfYUV422P9,
+    YUV444P9: pfYUV444P9,
+    YUV420P10: pfYUV420P10,
+    YUV422P10: pfYUV422P10,
+    YUV444P10: pfYUV444P10,
+    YUV420P12: pfYUV420P12,
+    YUV422P12: pfYUV422P12,
+    YUV444P12: pfYUV444P12,
+    YUV420P14: pfYUV420P14,
+    YUV422P14: pfYUV422P14,
+    YUV444P14: pfYUV444P14,
+    YUV420P16: pfYUV420P16,
+    YUV422P16: pfYUV422P16,
+    YUV444P16: pfYUV444P16,
+    YUV420PH: pfYUV420PH,
+    YUV420PS: pfYUV420PS,
+    YUV422PH: pfYUV422PH,
+    YUV422PS: pfYUV422PS,
+    YUV444PH: pfYUV444PH,
+    YUV444PS: pfYUV444PS,
+    RGB24: pfRGB24,
+    RGB27: pfRGB27,
+    RGB30: pfRGB30,
+    RGB36: pfRGB36,
+    RGB42: pfRGB42,
+    RGB48: pfRGB48,
+    RGBH: pfRGBH,
+    RGBS: pfRGBS,
+});
+assert_values!(VSFilterMode {
+    Parallel: fmParallel,
+    ParallelRequests: fmParallelRequests,
+    Unordered: fmUnordered,
+    FrameState: fmFrameState,
+});
+assert_values!(VSMediaType {
+    Video: mtVideo,
+    Audio: mtAudio,
+});
+assert_values!(VSAudioChannels {
+    FrontLeft: acFrontLeft,
+    FrontRight: acFrontRight,
+    FrontCenter: acFrontCenter,
+    LowFrequency: acLowFrequency,
+    BackLeft: acBackLeft,
+    BackRight: acBackRight,
+    FrontLeftOFCenter: acFrontLeftOFCenter,
+    FrontRightOFCenter: acFrontRightOFCenter,
+    BackCenter: acBackCenter,
+    SideLeft: acSideLeft,
+    SideRight: acSideRight,
+    TopCenter: acTopCenter,
+    TopFrontLeft: acTopFrontLeft,
+    TopFrontCenter: acTopFrontCenter,
+    TopFrontRight: acTopFrontRight,
+    TopBackLeft: acTopBackLeft,
+    TopBackCenter: acTopBackCenter,
+    TopBackRight: acTopBackRight,
+    StereoLeft: acStereoLeft,
+    StereoRight: acStereoRight,
+    WideLeft: acWideLeft,
+    WideRight: acWideRight,
+    SurroundDirectLeft: acSurroundDirectLeft,
+    SurroundDirectRight: acSurroundDirectRight,
+    LowFrequency2: acLowFrequency2,
+});
+assert_values!(VSPropertyType {
+    Unset: ptUnset,
+    Int: ptInt,
+    Float: ptFloat,
+    Data: ptData,
+    Function: ptFunction,
+    VideoNode: ptVideoNode,
+    AudioNode: ptAudioNode,
+    VideoFrame: ptVideoFrame,
+    AudioFrame: ptAudioFrame,
+});
+assert_values!(VSMapPropertyError {
+    Success: peSuccess,
+    Unset: peUnset,
+    Type: peType,
+    Index: peIndex,
+    Error: peError,
+});
+assert_values!(VSMapAppendMode {
+    Replace: maReplace,
+    Append: maAppend,
+});
+assert_values!(VSActivationReason {
+    Initial: arInitial,
+    AllFramesReady: arAllFramesReady,
+    Error: arError,
+});
+assert_values!(VSMessageType {
+    Debug: mtDebug,
+    Information: mtInformation,
+    Warning: mtWarning,
+    Critical: mtCritical,
+    Fatal: mtFatal,
+});
+assert_values!(VSCoreCreationFlags {
+    EnableGraphInspection: ccfEnableGraphInspection,
+    DisableAutoLoading: ccfDisableAutoLoading,
+    DisableLibraryUnloading: ccfDisableLibraryUnloading,
+});
+assert_values!(VSPluginConfigFlags {
+    Modifiable: pcModifiable,
+});
+assert_values!(VSDataTypeHint {
+    Unknown: dtUnknown,
+    Binary: dtBinary,
+    Utf8: dtUtf8,
+});
+assert_values!(VSRequestPattern {
+    General: rpGeneral,
+    NoFrameReuse: rpNoFrameReuse,
+    StrictSpatial: rpStrictSpatial,
+});
+assert_values!(VSCacheMode {
+    Auto: cmAuto,
+    ForceDisable: cmForceDisable,
+    ForceEnable: cmForceEnable,
+});
+assert_values!(VSColorRange {
+    VSC_RANGE_FULL,
+    VSC_RANGE_LIMITED,
+});
+assert_values!(VSChromaLocation {
+    VSC_CHROMA_LEFT,
+    VSC_CHROMA_CENTER,
+    VSC_CHROMA_TOP_LEFT,
+    VSC_CHROMA_TOP,
+    VSC_CHROMA_BOTTOM_LEFT,
+    VSC_CHROMA_BOTTOM,
+});
+assert_values!(VSFieldBased {
+    VSC_FIELD_PROGRESSIVE,
+    VSC_FIELD_BOTTOM,
+    VSC_FIELD_TOP,
+});
+assert_values!(VSMatrixCoefficients {
+    VSC_MATRIX_RGB,
+    VSC_MATRIX_BT709,
+    VSC_MATRIX_UNSPECIFIED,
+    VSC_MATRIX_FCC,
+    VSC_MATRIX_BT470_BG,
+    VSC_MATRIX_ST170_M,
+    VSC_MATRIX_ST240_M,
+    VSC_MATRIX_YCGCO,
+    VSC_MATRIX_BT2020_NCL,
+    VSC_MATRIX_BT2020_CL,
+    VSC_MATRIX_CHROMATICITY_DERIVED_NCL,
+    VSC_MATRIX_CHROMATICITY_DERIVED_CL,
+    VSC_MATRIX_ICTCP,
+});
+assert_values!(VSTransferCharacteristics {
+    VSC_TRANSFER_BT709,
+    VSC_TRANSFER_UNSPECIFIED,
+    VSC_TRANSFER_BT470_M,
+    VSC_TRANSFER_BT470_BG,
+    VSC_TRANSFER_BT601,
+    VSC_TRANSFER_ST240_M,
+    VSC_TRANSFER_LINEAR,
+    VSC_TRANSFER_LOG_100,
+    VSC_TRANSFER_LOG_316,
+    VSC_TRANSFER_IEC_61966_2_4,
+    VSC_TRANSFER_IEC_61966_2_1,
+    VSC_TRANSFER_BT2020_10,
+    VSC_TRANSFER_BT2020_12,
+    VSC_TRANSFER_ST2084,
+    VSC_TRANSFER_ARIB_B67,
+});
+assert_values!(VSColorPrimaries {
+    VSC_PRIMARIES_BT709,
+    VSC_PRIMARIES_UNSPECIFIED,
+    VSC_PRIMARIES_BT470_M,
+    VSC_PRIMARIES_BT470_BG,
+    VSC_PRIMARIES_ST170_M,
+    VSC_PRIMARIES_ST240_M,
+    VSC_PRIMARIES_FILM,
+    VSC_PRIMARIES_BT2020,
+    VSC_PRIMARIES_ST428,
+    VSC_PRIMARIES_ST431_2,
+    VSC_PRIMARIES_ST432_1,
+    VSC_PRIMARIES_EBU3213_E,
+});
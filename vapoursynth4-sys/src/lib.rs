@@ -5,11 +5,26 @@
 */
 
 //! Raw bindings to [VapourSynth](https://github.com/vapoursynth/vapoursynth).
+//!
+//! # Calling convention
+//!
+//! Every function and callback of the API uses `VS_CC`, which is `__stdcall` on 32-bit Windows
+//! and the C convention of the target everywhere else. That is exactly the `"system"` ABI of
+//! Rust, so the bindings use `extern "system-unwind"`, and callbacks passed to the library must be
+//! declared the same way. Callbacks should still catch panics before they return to the library.
+//!
+//! The `layout-tests` feature checks this against a C compiler for the target.
 
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
+#[cfg(all(test, feature = "layout-tests"))]
+mod abi_tests;
 mod constants;
 pub mod helper;
+#[cfg(all(test, layout_tests))]
+mod layout_tests;
+#[cfg(feature = "runtime-loading")]
+pub mod loader;
 mod vs;
 mod vsscript;
 
@@ -31,9 +46,52 @@ macro_rules! opaque_struct {
 }
 pub(crate) use opaque_struct;
 
+/// Implements `TryFrom<i32>` for an enum with the listed variants, e.g. for values read from a
+/// map. Unknown values are returned back as the error.
+macro_rules! enum_try_from {
+    ($name:ident { $($variant:ident),+ $(,)? }) => {
+        impl TryFrom<i32> for $name {
+            type Error = i32;
+
+            fn try_from(value: i32) -> Result<Self, i32> {
+                $(
+                    if value == Self::$variant as i32 {
+                        return Ok(Self::$variant);
+                    }
+                )+
+                Err(value)
+            }
+        }
+    };
+}
+pub(crate) use enum_try_from;
+
 /// Used to create version numbers.
 /// The first argument is the major version and second is the minor.
 #[must_use]
 pub const fn vs_make_version(major: u16, minor: u16) -> i32 {
     ((major as i32) << 16) | minor as i32
 }
+
+/// Splits a version number created by [`vs_make_version`] into the major and minor version,
+/// e.g. the result of `getAPIVersion`.
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub const fn vs_split_version(version: i32) -> (u16, u16) {
+    ((version >> 16) as u16, version as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version() {
+        assert_eq!(
+            VAPOURSYNTH_API_VERSION,
+            vs_make_version(4, VAPOURSYNTH_API_MINOR)
+        );
+        assert_eq!(vs_make_version(4, 1), 0x0004_0001);
+        assert_eq!(vs_split_version(vs_make_version(4, 2)), (4, 2));
+    }
+}
@@ -0,0 +1,299 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Loading the `VapourSynth` and `VSScript` libraries at runtime instead of linking them.
+//!
+//! Each library is searched for once, in order:
+//!
+//! 1. the directory in the `VAPOURSYNTH_LIB_PATH` environment variable,
+//! 2. the default search path of the system loader, e.g. `LD_LIBRARY_PATH` or `PATH`,
+//! 3. the standard install locations of the platform.
+//!
+//! The result is cached, so a missing library is not searched for again.
+//!
+//! On Unix the `VSScript` library is opened with `RTLD_GLOBAL`, which Python needs to import
+//! binary extension modules, see [`vsscript`](crate::vsscript).
+
+use std::{
+    env,
+    ffi::c_int,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use libloading::Library;
+
+use crate::VSAPI;
+#[cfg(feature = "vsscript")]
+use crate::VSSCRIPTAPI;
+
+const LIBRARY_DIR_VARIABLE: &str = "VAPOURSYNTH_LIB_PATH";
+
+#[cfg(target_os = "windows")]
+const VAPOURSYNTH_NAMES: &[&str] = &["VapourSynth.dll"];
+#[cfg(target_os = "macos")]
+const VAPOURSYNTH_NAMES: &[&str] = &["libvapoursynth.dylib"];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const VAPOURSYNTH_NAMES: &[&str] = &["libvapoursynth.so", "libvapoursynth.so.4"];
+
+#[cfg(all(feature = "vsscript", target_os = "windows"))]
+const VSSCRIPT_NAMES: &[&str] = &["VSScript.dll"];
+#[cfg(all(feature = "vsscript", target_os = "macos"))]
+const VSSCRIPT_NAMES: &[&str] = &["libvapoursynth-script.dylib"];
+#[cfg(all(
+    feature = "vsscript",
+    not(any(target_os = "windows", target_os = "macos"))
+))]
+const VSSCRIPT_NAMES: &[&str] = &["libvapoursynth-script.so", "libvapoursynth-script.so.0"];
+
+/// Error returned when a library cannot be loaded.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum LoadError {
+    /// None of the candidate paths could be loaded.
+    NotFound {
+        /// The paths that were tried, in order.
+        tried: Vec<PathBuf>,
+    },
+    /// Libraries were loaded, but they all lack the entry point, so none is the expected
+    /// library. `path` is the first of them.
+    MissingSymbol { path: PathBuf, symbol: &'static str },
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound { tried } => {
+                f.write_str("Failed to load the library, tried:")?;
+                for path in tried {
+                    write!(f, " {}", path.display())?;
+                }
+                Ok(())
+            }
+            Self::MissingSymbol { path, symbol } => {
+                write!(f, "`{}` does not export `{symbol}`", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+struct Loaded<F> {
+    // Keeps `entry` valid.
+    _library: Library,
+    path: PathBuf,
+    entry: F,
+}
+
+fn standard_dirs() -> Vec<PathBuf> {
+    if cfg!(target_os = "windows") {
+        ["ProgramFiles", "ProgramFiles(x86)"]
+            .into_iter()
+            .filter_map(env::var_os)
+            .map(|dir| Path::new(&dir).join("VapourSynth").join("core"))
+            .collect()
+    } else if cfg!(target_os = "macos") {
+        ["/opt/homebrew/lib", "/usr/local/lib"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect()
+    } else {
+        [
+            "/usr/local/lib",
+            "/usr/lib",
+            "/usr/lib64",
+            "/usr/lib/x86_64-linux-gnu",
+            "/usr/lib/aarch64-linux-gnu",
+        ]
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+    }
+}
+
+fn candidates(names: &[&str]) -> Vec<PathBuf> {
+    let env_dir = env::var_os(LIBRARY_DIR_VARIABLE).map(PathBuf::from);
+    let mut paths = Vec::new();
+    for name in names {
+        if let Some(dir) = &env_dir {
+            paths.push(dir.join(name));
+        }
+    }
+    paths.extend(names.iter().map(PathBuf::from));
+    for dir in standard_dirs() {
+        paths.extend(names.iter().map(|name| dir.join(name)));
+    }
+    paths
+}
+
+/// Opens the library at `path`, making its symbols available to the libraries loaded after it if
+/// `global` is set.
+///
+/// # Safety
+///
+/// Loading runs the initializers of the library.
+unsafe fn open(path: &Path, global: bool) -> Result<Library, libloading::Error> {
+    #[cfg(unix)]
+    if global {
+        use libloading::os::unix::{Library, RTLD_GLOBAL, RTLD_NOW};
+
+        return unsafe { Library::open(Some(path), RTLD_NOW | RTLD_GLOBAL) }.map(Into::into);
+    }
+    #[cfg(not(unix))]
+    let _ = global;
+    unsafe { Library::new(path) }
+}
+
+fn load<F: Copy>(
+    names: &[&str],
+    symbol: &'static str,
+    global: bool,
+) -> Result<Loaded<F>, LoadError> {
+    let tried = candidates(names);
+    let mut missing_symbol = None;
+    for path in &tried {
+        // SAFETY: Loading runs the initializers of the library, which is trusted to be
+        // `VapourSynth` by its name and location.
+        let Ok(library) = (unsafe { open(path, global) }) else {
+            continue;
+        };
+        // SAFETY: The type of the entry point is given by the caller.
+        let Ok(entry) = (unsafe { library.get::<F>(symbol.as_bytes()) }).map(|f| *f) else {
+            // Another library of the same name, e.g. an unrelated one on the search path.
+            missing_symbol.get_or_insert_with(|| path.clone());
+            continue;
+        };
+        return Ok(Loaded {
+            _library: library,
+            path: path.clone(),
+            entry,
+        });
+    }
+    Err(match missing_symbol {
+        Some(path) => LoadError::MissingSymbol { path, symbol },
+        None => LoadError::NotFound { tried },
+    })
+}
+
+type GetVapourSynthApi = unsafe extern "system-unwind" fn(version: c_int) -> *const VSAPI;
+
+fn vapoursynth() -> Result<&'static Loaded<GetVapourSynthApi>, LoadError> {
+    static LIBRARY: OnceLock<Result<Loaded<GetVapourSynthApi>, LoadError>> = OnceLock::new();
+    LIBRARY
+        .get_or_init(|| load(VAPOURSYNTH_NAMES, "getVapourSynthAPI", false))
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// The path the `VapourSynth` library was loaded from, loading it if needed.
+///
+/// # Errors
+///
+/// Return [`LoadError`] if the library cannot be loaded.
+pub fn vapoursynth_path() -> Result<&'static Path, LoadError> {
+    vapoursynth().map(|l| l.path.as_path())
+}
+
+/// Loads the `VapourSynth` library if needed and calls its `getVapourSynthAPI`.
+///
+/// Returns `NULL` if the requested API version is not supported, see
+/// [`getVapourSynthAPI`](crate::getVapourSynthAPI).
+///
+/// # Errors
+///
+/// Return [`LoadError`] if the library cannot be loaded.
+pub fn get_vapoursynth_api(version: c_int) -> Result<*const VSAPI, LoadError> {
+    vapoursynth().map(|l| unsafe { (l.entry)(version) })
+}
+
+#[cfg(feature = "vsscript")]
+type GetVSScriptApi = unsafe extern "system-unwind" fn(version: c_int) -> *const VSSCRIPTAPI;
+
+#[cfg(feature = "vsscript")]
+fn vsscript() -> Result<&'static Loaded<GetVSScriptApi>, LoadError> {
+    static LIBRARY: OnceLock<Result<Loaded<GetVSScriptApi>, LoadError>> = OnceLock::new();
+    LIBRARY
+        // Python can only import binary modules if `VSScript` is loaded globally.
+        .get_or_init(|| load(VSSCRIPT_NAMES, "getVSScriptAPI", true))
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// The path the `VSScript` library was loaded from, loading it if needed.
+///
+/// # Errors
+///
+/// Return [`LoadError`] if the library cannot be loaded.
+#[cfg(feature = "vsscript")]
+pub fn vsscript_path() -> Result<&'static Path, LoadError> {
+    vsscript().map(|l| l.path.as_path())
+}
+
+/// Loads the `VSScript` library if needed and calls its `getVSScriptAPI`.
+///
+/// Returns `NULL` if the requested API version is not supported.
+///
+/// # Errors
+///
+/// Return [`LoadError`] if the library cannot be loaded.
+#[cfg(feature = "vsscript")]
+pub fn get_vsscript_api(version: c_int) -> Result<*const VSSCRIPTAPI, LoadError> {
+    vsscript().map(|l| unsafe { (l.entry)(version) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates_order() {
+        // The system search path is tried before the standard install locations.
+        let paths = candidates(VAPOURSYNTH_NAMES);
+        let bare = paths
+            .iter()
+            .position(|p| p == Path::new(VAPOURSYNTH_NAMES[0]))
+            .unwrap();
+        let names = VAPOURSYNTH_NAMES.len();
+        assert!(paths[bare..bare + names].iter().all(|p| p.is_relative()));
+        assert!(paths[bare + names..].iter().all(|p| p.is_absolute()));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn missing_symbol() {
+        type GetPid = unsafe extern "C" fn() -> c_int;
+
+        assert!(load::<GetPid>(&["libc.so.6"], "getpid", false).is_ok());
+        let Err(LoadError::MissingSymbol { path, symbol }) =
+            load::<GetPid>(&["libc.so.6"], "getVapourSynthAPI", false)
+        else {
+            panic!("libc was not loaded");
+        };
+        assert_eq!(
+            (path.as_path(), symbol),
+            (Path::new("libc.so.6"), "getVapourSynthAPI")
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn global_symbols() {
+        use libloading::os::unix::Library;
+
+        type ZlibVersion = unsafe extern "C" fn() -> *const std::ffi::c_char;
+
+        // Symbols of a globally loaded library can be found from the process, as Python does for
+        // the binary modules it imports.
+        let this = Library::this();
+        assert!(unsafe { this.get::<ZlibVersion>(b"zlibVersion") }.is_err());
+        let Ok(_zlib) = load::<ZlibVersion>(&["libz.so.1"], "zlibVersion", true) else {
+            // zlib is not installed
+            return;
+        };
+        assert!(unsafe { this.get::<ZlibVersion>(b"zlibVersion") }.is_ok());
+    }
+}
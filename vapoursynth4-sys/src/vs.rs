@@ -12,7 +12,7 @@
 
 use std::ffi::{c_char, c_double, c_float, c_int, c_void};
 
-use super::{opaque_struct, vs_make_version};
+use super::{enum_try_from, opaque_struct, vs_make_version};
 
 /// Major API version.
 pub const VAPOURSYNTH_API_MAJOR: u16 = 4;
@@ -121,6 +121,8 @@ opaque_struct!(
 
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSColorFamily {
     Undefined = 0,
     Gray = 1,
@@ -128,13 +130,24 @@ pub enum VSColorFamily {
     YUV = 3,
 }
 
+enum_try_from!(VSColorFamily {
+    Undefined,
+    Gray,
+    RGB,
+    YUV
+});
+
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSSampleType {
     Integer = 0,
     Float = 1,
 }
 
+enum_try_from!(VSSampleType { Integer, Float });
+
 const fn vs_make_video_id(
     color_family: VSColorFamily,
     sample_type: VSSampleType,
@@ -155,71 +168,200 @@ use VSSampleType::{Float, Integer};
 /// The presets suffixed with H and S have floating point sample type.
 /// The H and S suffixes stand for half precision and single precision, respectively.
 /// All formats are planar.
+///
+/// The variants drop the `pf` prefix of `VSPresetVideoFormat` in `VapourSynth4.h`, which is kept
+/// as a doc alias.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSPresetVideoFormat {
+    #[doc(alias = "pfNone")]
     None = 0,
 
+    #[doc(alias = "pfGray8")]
     Gray8 = vs_make_video_id(Gray, Integer, 8, 0, 0),
+    #[doc(alias = "pfGray9")]
     Gray9 = vs_make_video_id(Gray, Integer, 9, 0, 0),
+    #[doc(alias = "pfGray10")]
     Gray10 = vs_make_video_id(Gray, Integer, 10, 0, 0),
+    #[doc(alias = "pfGray12")]
     Gray12 = vs_make_video_id(Gray, Integer, 12, 0, 0),
+    #[doc(alias = "pfGray14")]
     Gray14 = vs_make_video_id(Gray, Integer, 14, 0, 0),
+    #[doc(alias = "pfGray16")]
     Gray16 = vs_make_video_id(Gray, Integer, 16, 0, 0),
+    #[doc(alias = "pfGray32")]
     Gray32 = vs_make_video_id(Gray, Integer, 32, 0, 0),
 
+    #[doc(alias = "pfGrayH")]
     GrayH = vs_make_video_id(Gray, Float, 16, 0, 0),
+    #[doc(alias = "pfGrayS")]
     GrayS = vs_make_video_id(Gray, Float, 32, 0, 0),
 
+    #[doc(alias = "pfYUV410P8")]
     YUV410P8 = vs_make_video_id(YUV, Integer, 8, 2, 2),
+    #[doc(alias = "pfYUV411P8")]
     YUV411P8 = vs_make_video_id(YUV, Integer, 8, 2, 0),
+    #[doc(alias = "pfYUV440P8")]
     YUV440P8 = vs_make_video_id(YUV, Integer, 8, 0, 1),
 
+    #[doc(alias = "pfYUV420P8")]
     YUV420P8 = vs_make_video_id(YUV, Integer, 8, 1, 1),
+    #[doc(alias = "pfYUV422P8")]
     YUV422P8 = vs_make_video_id(YUV, Integer, 8, 1, 0),
+    #[doc(alias = "pfYUV444P8")]
     YUV444P8 = vs_make_video_id(YUV, Integer, 8, 0, 0),
 
+    #[doc(alias = "pfYUV420P9")]
     YUV420P9 = vs_make_video_id(YUV, Integer, 9, 1, 1),
+    #[doc(alias = "pfYUV422P9")]
     YUV422P9 = vs_make_video_id(YUV, Integer, 9, 1, 0),
+    #[doc(alias = "pfYUV444P9")]
     YUV444P9 = vs_make_video_id(YUV, Integer, 9, 0, 0),
 
+    #[doc(alias = "pfYUV420P10")]
     YUV420P10 = vs_make_video_id(YUV, Integer, 10, 1, 1),
+    #[doc(alias = "pfYUV422P10")]
     YUV422P10 = vs_make_video_id(YUV, Integer, 10, 1, 0),
+    #[doc(alias = "pfYUV444P10")]
     YUV444P10 = vs_make_video_id(YUV, Integer, 10, 0, 0),
 
+    #[doc(alias = "pfYUV420P12")]
     YUV420P12 = vs_make_video_id(YUV, Integer, 12, 1, 1),
+    #[doc(alias = "pfYUV422P12")]
     YUV422P12 = vs_make_video_id(YUV, Integer, 12, 1, 0),
+    #[doc(alias = "pfYUV444P12")]
     YUV444P12 = vs_make_video_id(YUV, Integer, 12, 0, 0),
 
+    #[doc(alias = "pfYUV420P14")]
     YUV420P14 = vs_make_video_id(YUV, Integer, 14, 1, 1),
+    #[doc(alias = "pfYUV422P14")]
     YUV422P14 = vs_make_video_id(YUV, Integer, 14, 1, 0),
+    #[doc(alias = "pfYUV444P14")]
     YUV444P14 = vs_make_video_id(YUV, Integer, 14, 0, 0),
 
+    #[doc(alias = "pfYUV420P16")]
     YUV420P16 = vs_make_video_id(YUV, Integer, 16, 1, 1),
+    #[doc(alias = "pfYUV422P16")]
     YUV422P16 = vs_make_video_id(YUV, Integer, 16, 1, 0),
+    #[doc(alias = "pfYUV444P16")]
     YUV444P16 = vs_make_video_id(YUV, Integer, 16, 0, 0),
 
+    #[doc(alias = "pfYUV420PH")]
     YUV420PH = vs_make_video_id(YUV, Float, 16, 1, 1),
+    #[doc(alias = "pfYUV420PS")]
     YUV420PS = vs_make_video_id(YUV, Float, 32, 1, 1),
+    #[doc(alias = "pfYUV422PH")]
     YUV422PH = vs_make_video_id(YUV, Float, 16, 1, 0),
+    #[doc(alias = "pfYUV422PS")]
     YUV422PS = vs_make_video_id(YUV, Float, 32, 1, 0),
+    #[doc(alias = "pfYUV444PH")]
     YUV444PH = vs_make_video_id(YUV, Float, 16, 0, 0),
+    #[doc(alias = "pfYUV444PS")]
     YUV444PS = vs_make_video_id(YUV, Float, 32, 0, 0),
 
+    #[doc(alias = "pfRGB24")]
     RGB24 = vs_make_video_id(RGB, Integer, 8, 0, 0),
+    #[doc(alias = "pfRGB27")]
     RGB27 = vs_make_video_id(RGB, Integer, 9, 0, 0),
+    #[doc(alias = "pfRGB30")]
     RGB30 = vs_make_video_id(RGB, Integer, 10, 0, 0),
+    #[doc(alias = "pfRGB36")]
     RGB36 = vs_make_video_id(RGB, Integer, 12, 0, 0),
+    #[doc(alias = "pfRGB42")]
     RGB42 = vs_make_video_id(RGB, Integer, 14, 0, 0),
+    #[doc(alias = "pfRGB48")]
     RGB48 = vs_make_video_id(RGB, Integer, 16, 0, 0),
 
+    #[doc(alias = "pfRGBH")]
     RGBH = vs_make_video_id(RGB, Float, 16, 0, 0),
+    #[doc(alias = "pfRGBS")]
     RGBS = vs_make_video_id(RGB, Float, 32, 0, 0),
 }
 
+impl VSPresetVideoFormat {
+    /// Every preset, including [`None`](Self::None).
+    pub const ALL: [Self; 45] = [
+        Self::None,
+        Self::Gray8,
+        Self::Gray9,
+        Self::Gray10,
+        Self::Gray12,
+        Self::Gray14,
+        Self::Gray16,
+        Self::Gray32,
+        Self::GrayH,
+        Self::GrayS,
+        Self::YUV410P8,
+        Self::YUV411P8,
+        Self::YUV440P8,
+        Self::YUV420P8,
+        Self::YUV422P8,
+        Self::YUV444P8,
+        Self::YUV420P9,
+        Self::YUV422P9,
+        Self::YUV444P9,
+        Self::YUV420P10,
+        Self::YUV422P10,
+        Self::YUV444P10,
+        Self::YUV420P12,
+        Self::YUV422P12,
+        Self::YUV444P12,
+        Self::YUV420P14,
+        Self::YUV422P14,
+        Self::YUV444P14,
+        Self::YUV420P16,
+        Self::YUV422P16,
+        Self::YUV444P16,
+        Self::YUV420PH,
+        Self::YUV420PS,
+        Self::YUV422PH,
+        Self::YUV422PS,
+        Self::YUV444PH,
+        Self::YUV444PS,
+        Self::RGB24,
+        Self::RGB27,
+        Self::RGB30,
+        Self::RGB36,
+        Self::RGB42,
+        Self::RGB48,
+        Self::RGBH,
+        Self::RGBS,
+    ];
+
+    /// The format ID, as used by [`getVideoFormatByID`](VSAPI::getVideoFormatByID).
+    #[must_use]
+    pub const fn id(self) -> u32 {
+        self as u32
+    }
+
+    /// The preset with the format ID `id`, if there is one.
+    #[must_use]
+    pub fn from_id(id: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.id() == id)
+    }
+}
+
+impl From<VSPresetVideoFormat> for u32 {
+    fn from(value: VSPresetVideoFormat) -> Self {
+        value.id()
+    }
+}
+
+impl TryFrom<u32> for VSPresetVideoFormat {
+    type Error = u32;
+
+    /// Returns the ID back if it is not a preset.
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Self::from_id(value).ok_or(value)
+    }
+}
+
 /// Controls how a filter will be multithreaded, if at all.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSFilterMode {
     /// Completely parallel execution. Multiple threads will call a filter's "getFrame" function,
     /// to fetch several frames in parallel.
@@ -247,14 +389,25 @@ pub enum VSFilterMode {
     FrameState = 3,
 }
 
+enum_try_from!(VSFilterMode {
+    Parallel,
+    ParallelRequests,
+    Unordered,
+    FrameState
+});
+
 /// Used to indicate the type of a [`VSFrame`] or [`VSNode`] object.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSMediaType {
     Video = 1,
     Audio = 2,
 }
 
+enum_try_from!(VSMediaType { Video, Audio });
+
 /// Describes the format of a clip.
 ///
 /// Use [`queryVideoFormat()`](VSAPI::queryVideoFormat) to fill it in with proper error checking.
@@ -262,6 +415,7 @@ pub enum VSMediaType {
 /// since illegal combinations of values will cause undefined behavior.
 #[repr(C)]
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VSVideoFormat {
     /// See [`VSColorFamily`].
     pub color_family: VSColorFamily,
@@ -287,10 +441,41 @@ pub struct VSVideoFormat {
     pub num_planes: c_int,
 }
 
+/// Formats the name the same way as [`getVideoFormatName`](VSAPI::getVideoFormatName),
+/// e.g. `YUV420P10` or `RGBS`.
+impl std::fmt::Display for VSVideoFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let suffix = match self.sample_type {
+            VSSampleType::Float if self.bits_per_sample == 32 => "S".to_owned(),
+            VSSampleType::Float => "H".to_owned(),
+            VSSampleType::Integer if self.color_family == VSColorFamily::RGB => {
+                (self.bits_per_sample * 3).to_string()
+            }
+            VSSampleType::Integer => self.bits_per_sample.to_string(),
+        };
+        match self.color_family {
+            VSColorFamily::Undefined => f.write_str("Undefined"),
+            VSColorFamily::Gray => write!(f, "Gray{suffix}"),
+            VSColorFamily::RGB => write!(f, "RGB{suffix}"),
+            VSColorFamily::YUV => match (self.sub_sampling_w, self.sub_sampling_h) {
+                (1, 1) => write!(f, "YUV420P{suffix}"),
+                (1, 0) => write!(f, "YUV422P{suffix}"),
+                (0, 0) => write!(f, "YUV444P{suffix}"),
+                (2, 2) => write!(f, "YUV410P{suffix}"),
+                (2, 0) => write!(f, "YUV411P{suffix}"),
+                (0, 1) => write!(f, "YUV440P{suffix}"),
+                (w, h) => write!(f, "YUVssw{w}ssh{h}P{suffix}"),
+            },
+        }
+    }
+}
+
 /// Audio channel positions as an enum. Mirrors the `FFmpeg` audio channel constants
 /// in older api versions.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VSAudioChannels {
     FrontLeft = 0,
     FrontRight = 1,
@@ -319,6 +504,65 @@ pub enum VSAudioChannels {
     LowFrequency2 = 35,
 }
 
+enum_try_from!(VSAudioChannels {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    LowFrequency,
+    BackLeft,
+    BackRight,
+    FrontLeftOFCenter,
+    FrontRightOFCenter,
+    BackCenter,
+    SideLeft,
+    SideRight,
+    TopCenter,
+    TopFrontLeft,
+    TopFrontCenter,
+    TopFrontRight,
+    TopBackLeft,
+    TopBackCenter,
+    TopBackRight,
+    StereoLeft,
+    StereoRight,
+    WideLeft,
+    WideRight,
+    SurroundDirectLeft,
+    SurroundDirectRight,
+    LowFrequency2
+});
+
+impl VSAudioChannels {
+    /// Every channel, in the order of their values.
+    pub const ALL: [Self; 25] = [
+        Self::FrontLeft,
+        Self::FrontRight,
+        Self::FrontCenter,
+        Self::LowFrequency,
+        Self::BackLeft,
+        Self::BackRight,
+        Self::FrontLeftOFCenter,
+        Self::FrontRightOFCenter,
+        Self::BackCenter,
+        Self::SideLeft,
+        Self::SideRight,
+        Self::TopCenter,
+        Self::TopFrontLeft,
+        Self::TopFrontCenter,
+        Self::TopFrontRight,
+        Self::TopBackLeft,
+        Self::TopBackCenter,
+        Self::TopBackRight,
+        Self::StereoLeft,
+        Self::StereoRight,
+        Self::WideLeft,
+        Self::WideRight,
+        Self::SurroundDirectLeft,
+        Self::SurroundDirectRight,
+        Self::LowFrequency2,
+    ];
+}
+
 /// Describes the format of a clip.
 ///
 /// Use [`queryAudioFormat()`](VSAPI::queryAudioFormat) to fill it in with proper error checking.
@@ -326,6 +570,7 @@ pub enum VSAudioChannels {
 /// since illegal combinations of values will cause undefined behavior.
 #[repr(C)]
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VSAudioFormat {
     /// See [`VSSampleType`].
     pub sample_type: VSSampleType,
@@ -342,9 +587,94 @@ pub struct VSAudioFormat {
     pub channel_layout: u64,
 }
 
+impl VSAudioFormat {
+    /// Number of samples in every audio frame except the last one of a clip.
+    pub const SAMPLES_PER_FRAME: i32 = VS_AUDIO_FRAME_SAMPLES;
+
+    /// Size of one channel of a full audio frame in bytes.
+    #[must_use]
+    pub const fn bytes_per_channel_frame(&self) -> usize {
+        self.bytes_per_sample.unsigned_abs() as usize
+            * Self::SAMPLES_PER_FRAME.unsigned_abs() as usize
+    }
+
+    /// Whether `channel` is present in the layout.
+    #[must_use]
+    pub const fn has_channel(&self, channel: VSAudioChannels) -> bool {
+        self.channel_layout & (1 << channel as u64) != 0
+    }
+
+    /// The channels present in the layout, in the order of the planes of a frame.
+    pub fn channels(&self) -> impl Iterator<Item = VSAudioChannels> + use<> {
+        let layout = self.channel_layout;
+        VSAudioChannels::ALL
+            .into_iter()
+            .filter(move |&c| layout & (1 << c as u64) != 0)
+    }
+
+    /// The common name of the channel layout, e.g. `stereo` or `5.1`.
+    #[must_use]
+    pub const fn layout_name(&self) -> Option<&'static str> {
+        use VSAudioChannels::{
+            BackCenter as BC, BackLeft as BL, BackRight as BR, FrontCenter as FC, FrontLeft as FL,
+            FrontRight as FR, LowFrequency as LFE, SideLeft as SL, SideRight as SR,
+        };
+
+        const fn mask(channels: &[VSAudioChannels]) -> u64 {
+            let mut mask = 0;
+            let mut i = 0;
+            while i < channels.len() {
+                mask |= 1 << channels[i] as u64;
+                i += 1;
+            }
+            mask
+        }
+
+        const LAYOUTS: [(u64, &str); 11] = [
+            (mask(&[FC]), "mono"),
+            (mask(&[FL, FR]), "stereo"),
+            (mask(&[FL, FR, LFE]), "2.1"),
+            (mask(&[FL, FR, FC]), "3.0"),
+            (mask(&[FL, FR, FC, LFE]), "3.1"),
+            (mask(&[FL, FR, BL, BR]), "quad"),
+            (mask(&[FL, FR, FC, BL, BR]), "5.0"),
+            (mask(&[FL, FR, FC, LFE, BL, BR]), "5.1"),
+            (mask(&[FL, FR, FC, LFE, SL, SR]), "5.1(side)"),
+            (mask(&[FL, FR, FC, LFE, BC, SL, SR]), "6.1"),
+            (mask(&[FL, FR, FC, LFE, BL, BR, SL, SR]), "7.1"),
+        ];
+
+        let mut i = 0;
+        while i < LAYOUTS.len() {
+            if LAYOUTS[i].0 == self.channel_layout {
+                return Some(LAYOUTS[i].1);
+            }
+            i += 1;
+        }
+        None
+    }
+}
+
+/// Formats the name the same way as [`getAudioFormatName`](VSAPI::getAudioFormatName),
+/// e.g. `Audio16 (2 CH)` or `Audio32F (6 CH)`.
+impl std::fmt::Display for VSAudioFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let float = match self.sample_type {
+            VSSampleType::Integer => "",
+            VSSampleType::Float => "F",
+        };
+        write!(
+            f,
+            "Audio{}{float} ({} CH)",
+            self.bits_per_sample, self.num_channels
+        )
+    }
+}
+
 /// Types of properties that can be stored in a [`VSMap`].
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSPropertyType {
     Unset = 0,
     Int = 1,
@@ -357,11 +687,24 @@ pub enum VSPropertyType {
     AudioFrame = 8,
 }
 
+enum_try_from!(VSPropertyType {
+    Unset,
+    Int,
+    Float,
+    Data,
+    Function,
+    VideoNode,
+    AudioNode,
+    VideoFrame,
+    AudioFrame
+});
+
 /// When a `mapGet*` function fails, it returns one of these in the err parameter.
 ///
 /// All errors are non-zero.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSMapPropertyError {
     Success = 0,
     /// The requested key was not found in the map.
@@ -376,9 +719,18 @@ pub enum VSMapPropertyError {
     Error = 3,
 }
 
+enum_try_from!(VSMapPropertyError {
+    Success,
+    Unset,
+    Type,
+    Index,
+    Error
+});
+
 /// Controls the behaviour of [`mapSetInt()`](VSAPI::mapSetInt) and friends.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSMapAppendMode {
     /// All existing values associated with the key will be replaced with the new value.
     Replace = 0,
@@ -386,6 +738,8 @@ pub enum VSMapAppendMode {
     Append = 1,
 }
 
+enum_try_from!(VSMapAppendMode { Replace, Append });
+
 /// Contains information about a [`VSCore`] instance.
 #[repr(C)]
 #[derive(Eq, PartialEq, Hash, Debug)]
@@ -409,6 +763,7 @@ pub struct VSCoreInfo {
 /// Contains information about a clip.
 #[repr(C)]
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VSVideoInfo {
     /// Format of the clip. Will have [`VSVideoFormat::color_family`] set to
     /// [`VSColorFamily::Undefined`] if the format can vary.
@@ -430,6 +785,7 @@ pub struct VSVideoInfo {
 /// Contains information about a clip.
 #[repr(C)]
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VSAudioInfo {
     /// Format of the clip. Unlike video the audio format can never change.
     pub format: VSAudioFormat,
@@ -448,15 +804,23 @@ pub struct VSAudioInfo {
 /// See [`VSFilterGetFrame`].
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSActivationReason {
     Initial = 0,
     AllFramesReady = 1,
     Error = -1,
 }
 
+enum_try_from!(VSActivationReason {
+    Initial,
+    AllFramesReady,
+    Error
+});
+
 /// See [`addLogHandler()`](VSAPI::addLogHandler).
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSMessageType {
     Debug = 0,
     Information = 1,
@@ -466,9 +830,18 @@ pub enum VSMessageType {
     Fatal = 4,
 }
 
+enum_try_from!(VSMessageType {
+    Debug,
+    Information,
+    Warning,
+    Critical,
+    Fatal
+});
+
 /// Options when creating a core.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSCoreCreationFlags {
     /// Required to use the graph inspection api functions.
     /// Increases memory usage due to the extra information stored.
@@ -482,6 +855,12 @@ pub enum VSCoreCreationFlags {
     DisableLibraryUnloading = 4,
 }
 
+enum_try_from!(VSCoreCreationFlags {
+    EnableGraphInspection,
+    DisableAutoLoading,
+    DisableLibraryUnloading
+});
+
 impl std::ops::BitOr for VSCoreCreationFlags {
     type Output = c_int;
 
@@ -493,12 +872,15 @@ impl std::ops::BitOr for VSCoreCreationFlags {
 /// Options when loading a plugin.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSPluginConfigFlags {
     /// Allow functions to be added to the plugin object after the plugin loading phase.
     /// Mostly useful for Avisynth compatibility and other foreign plugin loaders.
     Modifiable = 1,
 }
 
+enum_try_from!(VSPluginConfigFlags { Modifiable });
+
 impl std::ops::BitOr for VSPluginConfigFlags {
     type Output = c_int;
 
@@ -513,15 +895,23 @@ impl std::ops::BitOr for VSPluginConfigFlags {
 /// as an artifact of API3 compatibility.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSDataTypeHint {
     Unknown = -1,
     Binary = 0,
     Utf8 = 1,
 }
 
+enum_try_from!(VSDataTypeHint {
+    Unknown,
+    Binary,
+    Utf8
+});
+
 /// Describes the upstream frame request pattern of a filter.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSRequestPattern {
     /// Anything goes. Note that filters that may be requesting beyond the end of a
     /// [`VSNode`] length in frames (repeating the last frame) should use
@@ -538,9 +928,16 @@ pub enum VSRequestPattern {
     StrictSpatial = 2,
 }
 
+enum_try_from!(VSRequestPattern {
+    General,
+    NoFrameReuse,
+    StrictSpatial
+});
+
 /// Describes how the output of a node is cached.
 #[repr(C)]
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[non_exhaustive]
 pub enum VSCacheMode {
     /// Cache is enabled or disabled based on the reported request patterns
     /// and number of consumers.
@@ -551,6 +948,12 @@ pub enum VSCacheMode {
     ForceEnable = 1,
 }
 
+enum_try_from!(VSCacheMode {
+    Auto,
+    ForceDisable,
+    ForceEnable
+});
+
 /// Core entry point
 pub type VSGetVapourSynthAPI = unsafe extern "system-unwind" fn(version: c_int) -> *const VSAPI;
 
@@ -727,7 +1130,7 @@ pub struct VSPLUGINAPI {
     pub getAPIVersion: unsafe extern "system-unwind" fn() -> c_int,
     /// Used to provide information about a plugin when loaded. Must be called exactly once from
     /// the `VapourSynthPluginInit2()` entry point. It is recommended to use the
-    /// [`vs_make_version]` macro when providing the `pluginVersion`.
+    /// [`vs_make_version`] function when providing the `pluginVersion`.
     /// If you don't know the specific `apiVersion` you actually require simply pass
     /// [`VAPOURSYNTH_API_VERSION`] to match the header version
     /// you're compiling against. The flags consist of values from
@@ -2174,13 +2577,17 @@ pub struct VSAPI {
 
     /* Node timing functions */
     /// non-zero when filter timing is enabled
+    #[cfg(feature = "vs-41")]
     pub getCoreNodeTiming: unsafe extern "system-unwind" fn(core: *mut VSCore) -> c_int,
     /// non-zero enables filter timing, note that disabling simply stops the counters from incrementing
+    #[cfg(feature = "vs-41")]
     pub setCoreNodeTiming: unsafe extern "system-unwind" fn(core: *mut VSCore, enable: c_int),
     /// time spent processing frames in nanoseconds, reset sets the counter to 0 again
+    #[cfg(feature = "vs-41")]
     pub getNodeProcessingTime:
         unsafe extern "system-unwind" fn(node: *mut VSNode, reset: c_int) -> i64,
     /// time spent processing frames in nanoseconds in all destroyed nodes, reset sets the counter to 0 again
+    #[cfg(feature = "vs-41")]
     pub getFreedNodeProcessingTime:
         unsafe extern "system-unwind" fn(core: *mut VSCore, reset: c_int) -> i64,
 
@@ -2205,8 +2612,8 @@ pub struct VSAPI {
         unsafe extern "system-unwind" fn(node: *mut VSNode, level: c_int) -> *const VSMap,
 }
 
+// Linked by the build script, unless the `no-link` feature is enabled.
 #[cfg(feature = "link-library")]
-#[link(name = "vapoursynth")]
 unsafe extern "system-unwind" {
     /// Returns a pointer to the global [`VSAPI`] instance.
     ///
@@ -2220,6 +2627,77 @@ unsafe extern "system-unwind" {
 mod tests {
     use super::*;
 
+    #[test]
+    fn preset_id() {
+        for preset in VSPresetVideoFormat::ALL {
+            assert_eq!(VSPresetVideoFormat::try_from(preset.id()), Ok(preset));
+        }
+        assert_eq!(VSPresetVideoFormat::YUV420P10.id(), 0x300A_0101);
+        assert_eq!(VSPresetVideoFormat::try_from(1), Err(1));
+    }
+
+    #[test]
+    fn try_from_int() {
+        assert_eq!(VSColorFamily::try_from(3), Ok(VSColorFamily::YUV));
+        assert_eq!(VSColorFamily::try_from(4), Err(4));
+        assert_eq!(
+            VSMapPropertyError::try_from(4),
+            Ok(VSMapPropertyError::Index)
+        );
+        assert_eq!(
+            VSActivationReason::try_from(-1),
+            Ok(VSActivationReason::Error)
+        );
+        assert_eq!(
+            crate::VSMatrixCoefficients::try_from(9),
+            Ok(crate::VSMatrixCoefficients::VSC_MATRIX_BT2020_NCL)
+        );
+        assert_eq!(crate::VSMatrixCoefficients::try_from(3), Err(3));
+    }
+
+    #[test]
+    fn video_format_name() {
+        let format =
+            |color_family, sample_type, bits_per_sample, sub_sampling_w, sub_sampling_h| {
+                VSVideoFormat {
+                    color_family,
+                    sample_type,
+                    bits_per_sample,
+                    bytes_per_sample: 0,
+                    sub_sampling_w,
+                    sub_sampling_h,
+                    num_planes: 0,
+                }
+                .to_string()
+            };
+        assert_eq!(format(YUV, Integer, 10, 1, 1), "YUV420P10");
+        assert_eq!(format(YUV, Float, 16, 0, 0), "YUV444PH");
+        assert_eq!(format(YUV, Integer, 8, 3, 3), "YUVssw3ssh3P8");
+        assert_eq!(format(RGB, Integer, 8, 0, 0), "RGB24");
+        assert_eq!(format(Gray, Float, 32, 0, 0), "GrayS");
+    }
+
+    #[test]
+    fn audio_format() {
+        use VSAudioChannels::{FrontLeft, FrontRight, LowFrequency};
+
+        let format = VSAudioFormat {
+            sample_type: Integer,
+            bits_per_sample: 16,
+            bytes_per_sample: 2,
+            num_channels: 2,
+            channel_layout: (1 << FrontLeft as u64) | (1 << FrontRight as u64),
+        };
+        assert_eq!(format.to_string(), "Audio16 (2 CH)");
+        assert_eq!(format.layout_name(), Some("stereo"));
+        assert_eq!(
+            format.channels().collect::<Vec<_>>(),
+            [FrontLeft, FrontRight]
+        );
+        assert!(!format.has_channel(LowFrequency));
+        assert_eq!(format.bytes_per_channel_frame(), 6144);
+    }
+
     #[test]
     fn layout() {
         assert_eq!(
@@ -30,7 +30,7 @@
 
 #![cfg(feature = "vsscript")]
 
-use std::ffi::{c_char, c_int, c_void};
+use std::ffi::{c_char, c_int};
 
 use super::{VSAPI, VSCore, VSMap, VSNode, opaque_struct, vs_make_version};
 
@@ -49,7 +49,7 @@ opaque_struct!(
 #[repr(C)]
 pub struct VSSCRIPTAPI {
     /// Returns the api version provided by vsscript.
-    pub getApiVersion: unsafe extern "system-unwind" fn() -> c_int,
+    pub getAPIVersion: unsafe extern "system-unwind" fn() -> c_int,
 
     /// Retrieves the [`VSAPI`] struct. Exists mostly as a convenience so
     /// the vapoursynth module doesn’t have to be explicitly loaded.
@@ -168,7 +168,7 @@ pub struct VSSCRIPTAPI {
     /// * `vars` - Map containing the variables to set.
     ///
     /// Returns non-zero on error.
-    pub setVariable:
+    pub setVariables:
         unsafe extern "system-unwind" fn(handle: *mut VSScript, vars: *const VSMap) -> c_int,
 
     /// Retrieves a node from the script environment. A node in the script must have been
@@ -208,12 +208,11 @@ pub struct VSSCRIPTAPI {
     ///   have been freed (frames, nodes, etc).
     ///
     /// It is safe to pass `NULL`.
-    pub freeScript: unsafe extern "system-unwind" fn(handle: *mut VSScript) -> c_int,
+    pub freeScript: unsafe extern "system-unwind" fn(handle: *mut VSScript),
 
     /// Set whether or not the working directory is temporarily changed to the same location
     /// as the script file when [`evaluateFile()`](Self::evaluateFile) is called. Off by default.
-    pub evalSetWorkingDir:
-        unsafe extern "system-unwind" fn(handle: *mut VSScript, setCWD: c_int) -> c_void,
+    pub evalSetWorkingDir: unsafe extern "system-unwind" fn(handle: *mut VSScript, setCWD: c_int),
 
     /// Write a list of set output index values to dst but at most size values.
     /// Always returns the total number of available output index values.
@@ -225,9 +224,8 @@ pub struct VSSCRIPTAPI {
     ) -> c_int,
 }
 
-#[cfg(feature = "link-library")]
-#[cfg_attr(target_os = "windows", link(name = "VSScript"))]
-#[cfg_attr(not(target_os = "windows"), link(name = "vapoursynth-script"))]
+// Linked by the build script with the `script` feature, unless the `no-link` feature is enabled.
+#[cfg(all(feature = "link-library", feature = "script"))]
 unsafe extern "system-unwind" {
     /// Returns a struct containing function pointer for the api.
     /// Will return `NULL` is the specified version isn’t supported.